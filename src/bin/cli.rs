@@ -1,5 +1,6 @@
 use chrono::NaiveDate;
 use clap::{Parser, Subcommand, ValueEnum};
+use std::collections::HashSet;
 use std::path::PathBuf;
 use std::process::ExitCode;
 use std::sync::Arc;
@@ -7,16 +8,17 @@ use tracing::{error, info, warn};
 use tracing_subscriber::EnvFilter;
 
 use bqdrift::error::{BigQueryError, BqDriftError};
-use bqdrift::executor::BqClient;
 use bqdrift::executor::PartitionWriteStats;
+use bqdrift::executor::{BqClient, DEFAULT_MAX_BACKFILL_PARTITIONS};
 use bqdrift::schema::{PartitionKey, PartitionType};
 use bqdrift::{
-    decode_sql, format_sql_diff, has_changes, AuditTableRow, DriftDetector, DriftState,
-    ImmutabilityChecker, ImmutabilityViolation, SourceAuditor, SourceStatus,
+    decode_sql, format_sql_diff, has_changes, AuditTableRow, DependencyGraph, DriftDetector,
+    DriftState, ImmutabilityChecker, ImmutabilityViolation, SourceAuditReport, SourceAuditor,
+    SourceStatus,
 };
 use bqdrift::{
     resolve_invariants_def, CheckStatus, InvariantChecker, QueryDef, QueryLoader, QueryValidator,
-    Runner, Severity,
+    Runner, Severity, ValidatorConfig,
 };
 use tabled::{settings::Style, Table};
 
@@ -32,6 +34,14 @@ struct Cli {
     #[arg(short, long, default_value = "./queries")]
     queries: PathBuf,
 
+    /// Skip query files matching these globs, relative to the queries directory
+    #[arg(long, value_delimiter = ',')]
+    ignore: Vec<String>,
+
+    /// Variables for `{% if %}` blocks in query files, as KEY=VALUE
+    #[arg(long = "var", value_parser = parse_var)]
+    vars: Vec<(String, String)>,
+
     /// GCP project ID
     #[arg(short, long, env = "GCP_PROJECT_ID")]
     project: Option<String>,
@@ -64,7 +74,16 @@ struct Cli {
 #[derive(Subcommand)]
 enum Commands {
     /// Validate all query definitions
-    Validate,
+    Validate {
+        /// Warn about dependencies produced by no query unless they match
+        /// one of these globs, e.g. 'raw_*.*'
+        #[arg(long, value_delimiter = ',', num_args = 0..)]
+        external: Option<Vec<String>>,
+
+        /// Earliest date with source data; backfill_since before it is an error
+        #[arg(long)]
+        data_available_from: Option<NaiveDate>,
+    },
 
     /// List all queries
     List {
@@ -120,6 +139,14 @@ enum Commands {
         /// Skip invariant checks
         #[arg(long)]
         skip_invariants: bool,
+
+        /// Partitions to skip (repeatable or comma-separated)
+        #[arg(long, value_delimiter = ',')]
+        exclude: Vec<String>,
+
+        /// Refuse ranges covering more partitions than this
+        #[arg(long, default_value_t = DEFAULT_MAX_BACKFILL_PARTITIONS)]
+        max_partitions: usize,
     },
 
     /// Run invariant checks only (no query execution)
@@ -150,6 +177,13 @@ enum Commands {
         version: Option<u32>,
     },
 
+    /// Print the dependency graph between queries and source tables
+    Graph {
+        /// Output format
+        #[arg(long, value_enum, default_value = "dot")]
+        format: GraphFormat,
+    },
+
     /// Initialize tracking table in BigQuery
     Init {
         /// Dataset for tracking table
@@ -198,6 +232,10 @@ enum Commands {
         #[arg(long)]
         diff: bool,
 
+        /// Exit non-zero when any source is at or above this status
+        #[arg(long)]
+        fail_on: Option<AuditFailOn>,
+
         /// Output format: table, yaml, json
         #[arg(short, long, default_value = "table")]
         output: OutputFormat,
@@ -238,6 +276,12 @@ enum ScratchAction {
     },
 }
 
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum GraphFormat {
+    Dot,
+    Json,
+}
+
 #[derive(Debug, Clone, Copy, ValueEnum)]
 enum OutputFormat {
     Table,
@@ -245,6 +289,21 @@ enum OutputFormat {
     Json,
 }
 
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum AuditFailOn {
+    NeverExecuted,
+    Modified,
+}
+
+impl From<AuditFailOn> for SourceStatus {
+    fn from(fail_on: AuditFailOn) -> Self {
+        match fail_on {
+            AuditFailOn::NeverExecuted => SourceStatus::NeverExecuted,
+            AuditFailOn::Modified => SourceStatus::Modified,
+        }
+    }
+}
+
 #[tokio::main]
 async fn main() -> ExitCode {
     let cli = Cli::parse();
@@ -297,6 +356,12 @@ fn parse_partition_key(
     PartitionKey::parse(s, partition_type).map_err(|e| e.into())
 }
 
+fn parse_var(s: &str) -> Result<(String, String), String> {
+    s.split_once('=')
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .ok_or_else(|| format!("expected KEY=VALUE, got '{}'", s))
+}
+
 fn default_partition_key(partition_type: &PartitionType) -> PartitionKey {
     PartitionKey::default_for_type(partition_type)
 }
@@ -310,11 +375,16 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
         .command
         .ok_or("No command specified. Use --help for usage or --repl for interactive mode.")?;
 
-    let loader = QueryLoader::new();
+    let loader = QueryLoader::new()
+        .with_ignore(cli.ignore.iter().cloned())
+        .with_vars(cli.vars.iter().cloned());
 
     match command {
-        Commands::Validate => {
-            cmd_validate(&loader, &cli.queries)?;
+        Commands::Validate {
+            external,
+            data_available_from,
+        } => {
+            cmd_validate(&loader, &cli.queries, external, data_available_from)?;
         }
 
         Commands::List { detailed } => {
@@ -352,6 +422,8 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
             to,
             dry_run,
             skip_invariants,
+            exclude,
+            max_partitions,
         } => {
             let project = cli
                 .project
@@ -363,8 +435,10 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                 &query,
                 from,
                 to,
+                &exclude,
                 dry_run,
                 skip_invariants,
+                max_partitions,
             )
             .await?;
         }
@@ -394,6 +468,15 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
             cmd_show(&loader, &cli.queries, &query, version)?;
         }
 
+        Commands::Graph { format } => {
+            let queries = loader.load_dir(&cli.queries)?;
+            let graph = DependencyGraph::from_queries(&queries);
+            match format {
+                GraphFormat::Dot => print!("{}", graph.to_dot()),
+                GraphFormat::Json => println!("{}", graph.to_json()),
+            }
+        }
+
         Commands::Init { dataset } => {
             let project = cli
                 .project
@@ -432,10 +515,19 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
             query,
             modified_only,
             diff,
+            fail_on,
             output,
             tracking_dataset: _,
         } => {
-            cmd_audit(&loader, &cli.queries, query, modified_only, diff, output)?;
+            cmd_audit(
+                &loader,
+                &cli.queries,
+                query,
+                modified_only,
+                diff,
+                fail_on,
+                output,
+            )?;
         }
 
         Commands::Scratch { action } => match action {
@@ -469,18 +561,26 @@ async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
 fn cmd_validate(
     loader: &QueryLoader,
     queries_path: &PathBuf,
+    external: Option<Vec<String>>,
+    data_available_from: Option<NaiveDate>,
 ) -> Result<(), Box<dyn std::error::Error>> {
     info!("Validating queries in {}", queries_path.display());
 
     let queries = loader.load_dir(queries_path)?;
+    let mut config = ValidatorConfig::new();
+    if let Some(external) = external {
+        config = config.with_external_sources(external);
+    }
+    if let Some(date) = data_available_from {
+        config = config.with_data_available_from(date);
+    }
+    let results = QueryValidator::validate_catalog(&queries, &config)?;
 
     let mut total_errors = 0;
     let mut total_warnings = 0;
     let mut failed_queries = Vec::new();
 
-    for query in &queries {
-        let result = QueryValidator::validate(&query);
-
+    for (query, result) in queries.iter().zip(results) {
         let status = if result.is_valid() {
             if result.has_warnings() {
                 "⚠"
@@ -495,7 +595,7 @@ fn cmd_validate(
 
         // Show version summary
         for version in &query.versions {
-            let schema_fields = version.schema.fields.len();
+            let schema_fields = version.schema.len();
             let revisions = version.revisions.len();
 
             if revisions > 0 {
@@ -568,10 +668,7 @@ fn cmd_list(
     for query in &queries {
         if detailed {
             println!("{}", query.name);
-            println!(
-                "  destination: {}.{}",
-                query.destination.dataset, query.destination.table
-            );
+            println!("  destination: {}", query.destination.qualified_table());
 
             if let Some(desc) = &query.description {
                 println!("  description: {}", desc);
@@ -598,8 +695,10 @@ fn cmd_list(
         } else {
             let latest = query.latest_version().map(|v| v.version).unwrap_or(0);
             println!(
-                "{:<30} v{:<3} {}.{}",
-                query.name, latest, query.destination.dataset, query.destination.table
+                "{:<30} v{:<3} {}",
+                query.name,
+                latest,
+                query.destination.qualified_table()
             );
         }
     }
@@ -644,10 +743,7 @@ async fn cmd_run(
 
             info!("Dry run for partition: {}", partition_key);
             println!("Query: {}", query.name);
-            println!(
-                "Destination: {}.{}",
-                query.destination.dataset, query.destination.table
-            );
+            println!("Destination: {}", query.destination.qualified_table());
             println!("Partition type: {:?}", partition_type);
 
             let date_for_version = partition_key.to_naive_date();
@@ -781,10 +877,18 @@ async fn cmd_run(
                 );
             }
 
+            for skipped in &report.skipped {
+                eprintln!(
+                    "\x1b[33m-\x1b[0m {} ({}): skipped, {}",
+                    skipped.query_name, skipped.partition_key, skipped.error
+                );
+            }
+
             println!(
-                "\n{} succeeded, {} failed",
+                "\n{} succeeded, {} failed, {} skipped",
                 report.stats.len(),
-                report.failures.len()
+                report.failures.len(),
+                report.skipped.len()
             );
         }
     }
@@ -831,9 +935,13 @@ fn print_scratch_invariants(report: &bqdrift::invariant::InvariantReport) {
 }
 
 fn print_stats(stats: &PartitionWriteStats, skip_invariants: bool) {
+    let version_str = match stats.sql_revision {
+        Some(rev) => format!("v{}.r{}", stats.version, rev),
+        None => format!("v{}", stats.version),
+    };
     println!(
-        "✓ {} v{} completed for {}",
-        stats.query_name, stats.version, stats.partition_key
+        "✓ {} {} completed for {}",
+        stats.query_name, version_str, stats.partition_key
     );
 
     if !skip_invariants {
@@ -891,6 +999,7 @@ fn print_stats(stats: &PartitionWriteStats, skip_invariants: bool) {
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn cmd_backfill(
     loader: &QueryLoader,
     queries_path: &PathBuf,
@@ -898,8 +1007,10 @@ async fn cmd_backfill(
     query_name: &str,
     from: String,
     to: String,
+    exclude: &[String],
     dry_run: bool,
     skip_invariants: bool,
+    max_partitions: usize,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let queries = Arc::new(loader.load_dir(queries_path)?);
 
@@ -920,6 +1031,11 @@ async fn cmd_backfill(
         .into());
     }
 
+    let exclude = exclude
+        .iter()
+        .map(|p| parse_partition_key(p, partition_type))
+        .collect::<Result<HashSet<_>, _>>()?;
+
     info!(
         "Backfilling '{}' from {} to {}",
         query_name, from_key, to_key
@@ -929,7 +1045,9 @@ async fn cmd_backfill(
         let mut current = from_key.clone();
         while current <= to_key {
             let date = current.to_naive_date();
-            if let Some(version) = query.get_version_for_date(date) {
+            if exclude.contains(&current) {
+                println!("{}: excluded", current);
+            } else if let Some(version) = query.get_version_for_date(date) {
                 println!("{}: v{} ({})", current, version.version, version.source);
             } else {
                 println!("{}: no version available", current);
@@ -944,16 +1062,20 @@ async fn cmd_backfill(
     }
 
     let client = BqClient::new(project).await?;
-    let runner = Runner::new(client, Arc::clone(&queries));
+    let runner = Runner::new(client, Arc::clone(&queries)).with_max_partitions(max_partitions);
 
     let report = runner
-        .backfill_partitions(query_name, from_key, to_key, None)
+        .backfill_partitions_excluding(query_name, from_key, to_key, None, &exclude)
         .await?;
 
     for stats in &report.stats {
         print_stats(stats, skip_invariants);
     }
 
+    for partition_key in &report.excluded {
+        println!("\x1b[90m-\x1b[0m {}: excluded", partition_key);
+    }
+
     for failure in &report.failures {
         eprintln!(
             "\x1b[31m✗\x1b[0m {}: {}",
@@ -962,9 +1084,10 @@ async fn cmd_backfill(
     }
 
     println!(
-        "\n{} succeeded, {} failed",
+        "\n{} succeeded, {} failed, {} excluded",
         report.stats.len(),
-        report.failures.len()
+        report.failures.len(),
+        report.excluded.len()
     );
 
     Ok(())
@@ -1100,10 +1223,7 @@ fn cmd_show(
         .ok_or_else(|| format!("Query '{}' not found", query_name))?;
 
     println!("Name: {}", query.name);
-    println!(
-        "Destination: {}.{}",
-        query.destination.dataset, query.destination.table
-    );
+    println!("Destination: {}", query.destination.qualified_table());
 
     if let Some(desc) = &query.description {
         println!("Description: {}", desc);
@@ -1161,7 +1281,7 @@ fn cmd_show(
             }
         }
 
-        println!("  schema: {} fields", version.schema.fields.len());
+        println!("  schema: {} fields", version.schema.len());
         for field in &version.schema.fields {
             println!("    - {}: {:?}", field.name, field.field_type);
         }
@@ -1243,6 +1363,10 @@ async fn cmd_sync(
     let detector = DriftDetector::new(&queries, &yaml_contents);
     let report = detector.detect(&stored_states, from, to)?;
 
+    for warning in &report.warnings {
+        println!("  \x1b[33m⚠\x1b[0m [{}] {}", warning.kind.as_str(), warning);
+    }
+
     let drifted: Vec<_> = report.needs_rerun();
 
     if drifted.is_empty() {
@@ -1258,9 +1382,12 @@ async fn cmd_sync(
                 DriftState::SqlChanged => "\x1b[33m◇\x1b[0m",
                 DriftState::SchemaChanged => "\x1b[31m◆\x1b[0m",
                 DriftState::VersionUpgraded => "\x1b[34m▲\x1b[0m",
+                DriftState::VersionDowngraded => "\x1b[34m▼\x1b[0m",
                 DriftState::UpstreamChanged => "\x1b[35m↺\x1b[0m",
+                DriftState::InvariantsChanged => "\x1b[34m✓\x1b[0m",
                 DriftState::NeverRun => "\x1b[36m○\x1b[0m",
                 DriftState::Failed => "\x1b[31m✗\x1b[0m",
+                DriftState::Blocked => "\x1b[90m⊘\x1b[0m",
                 DriftState::Current => "",
             };
             println!("  {} {} {}", icon, count, state.as_str());
@@ -1288,9 +1415,12 @@ async fn cmd_sync(
                     DriftState::SqlChanged => "\x1b[33msql_changed\x1b[0m",
                     DriftState::SchemaChanged => "\x1b[31mschema_changed\x1b[0m",
                     DriftState::VersionUpgraded => "\x1b[34mversion_upgraded\x1b[0m",
+                    DriftState::VersionDowngraded => "\x1b[34mversion_downgraded\x1b[0m",
                     DriftState::UpstreamChanged => "\x1b[35mupstream_changed\x1b[0m",
+                    DriftState::InvariantsChanged => "\x1b[34minvariants_changed\x1b[0m",
                     DriftState::NeverRun => "\x1b[36mnever_run\x1b[0m",
                     DriftState::Failed => "\x1b[31mfailed\x1b[0m",
+                    DriftState::Blocked => "\x1b[90mblocked\x1b[0m",
                     DriftState::Current => "current",
                 };
 
@@ -1382,6 +1512,7 @@ fn cmd_audit(
     query_filter: Option<String>,
     modified_only: bool,
     show_diff: bool,
+    fail_on: Option<AuditFailOn>,
     output: OutputFormat,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let queries = loader.load_dir(queries_path)?;
@@ -1430,7 +1561,7 @@ fn cmd_audit(
         } else {
             println!("No source entries to display");
         }
-        return Ok(());
+        return gate_audit(&report, fail_on);
     }
 
     match output {
@@ -1483,7 +1614,17 @@ fn cmd_audit(
         }
     }
 
-    Ok(())
+    gate_audit(&report, fail_on)
+}
+
+fn gate_audit(
+    report: &SourceAuditReport,
+    fail_on: Option<AuditFailOn>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    match fail_on {
+        Some(fail_on) => Ok(report.gate(fail_on.into())?),
+        None => Ok(()),
+    }
 }
 
 async fn cmd_scratch_list(project: &str) -> Result<(), Box<dyn std::error::Error>> {
@@ -1545,25 +1686,33 @@ async fn cmd_scratch_promote(
     println!("  From: {}", stats.scratch_table);
     println!("  To: {}", stats.production_table);
     println!("  Partition: {}", stats.partition_key);
+    println!("  Method: {}", stats.method);
 
     Ok(())
 }
 
 async fn run_repl(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
-    use bqdrift::repl::{AsyncJsonRpcServer, InteractiveRepl, ReplSession, ServerConfig};
+    use bqdrift::repl::{
+        AsyncJsonRpcServer, InteractiveRepl, ReplSession, ServerConfig, SharedCatalog,
+    };
 
     let is_tty = atty::is(atty::Stream::Stdin);
     let force_server = cli.server;
 
     if is_tty && !force_server {
-        let session = ReplSession::new(cli.project, cli.queries);
+        let catalog = SharedCatalog::new(cli.queries)
+            .with_ignore(cli.ignore)
+            .with_vars(cli.vars);
+        let session = ReplSession::with_catalog(cli.project, catalog);
         let mut repl = InteractiveRepl::new(session)?;
         repl.run().await?;
     } else {
         let config = ServerConfig::new(cli.project, cli.queries)
             .with_max_sessions(cli.max_sessions)
             .with_idle_timeout(cli.idle_timeout)
-            .with_max_idle_timeout(cli.max_idle_timeout);
+            .with_max_idle_timeout(cli.max_idle_timeout)
+            .with_ignore(cli.ignore)
+            .with_vars(cli.vars);
         AsyncJsonRpcServer::run(config).await?;
     }
 
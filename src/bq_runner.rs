@@ -21,8 +21,32 @@ pub struct SqlFile {
 pub struct FileLoader;
 
 impl FileLoader {
+    /// Every `*.{extension}` file under `path`, sorted by path.
     pub fn load_dir(path: impl AsRef<Path>, extension: &str) -> Result<Vec<SqlFile>> {
+        Self::load_dir_with_ignore(path, extension, &[])
+    }
+
+    /// Like `load_dir`, skipping files whose path relative to `path` matches
+    /// any of the `ignore` globs (e.g. `**/_archive/**`, `**/*.draft.yaml`).
+    pub fn load_dir_with_ignore(
+        path: impl AsRef<Path>,
+        extension: &str,
+        ignore: &[&str],
+    ) -> Result<Vec<SqlFile>> {
         let path = path.as_ref();
+        let ignore = ignore
+            .iter()
+            .map(|p| {
+                glob::Pattern::new(p).map_err(|e| {
+                    BqRunnerError::Execution(format!("Invalid ignore pattern '{}': {}", p, e))
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let match_options = glob::MatchOptions {
+            require_literal_separator: true,
+            ..Default::default()
+        };
+
         let pattern = format!("{}/**/*.{}", path.display(), extension);
         let glob_iter =
             glob::glob(&pattern).map_err(|e| BqRunnerError::Execution(e.to_string()))?;
@@ -40,6 +64,14 @@ impl FileLoader {
                 }
             };
 
+            let relative = file_path.strip_prefix(path).unwrap_or(&file_path);
+            if ignore
+                .iter()
+                .any(|p| p.matches_path_with(relative, match_options))
+            {
+                continue;
+            }
+
             match std::fs::read_to_string(&file_path) {
                 Ok(content) => files.push(SqlFile {
                     path: file_path,
@@ -56,6 +88,7 @@ impl FileLoader {
             warn!(skipped = skipped_count, "Some files could not be loaded");
         }
 
+        files.sort_by(|a, b| a.path.cmp(&b.path));
         Ok(files)
     }
 
@@ -85,3 +118,33 @@ impl SqlLoader {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_dir_sorted_by_path() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("b")).unwrap();
+        for name in ["c.sql", "b/z.sql", "a.sql", "b/a.sql"] {
+            std::fs::write(dir.path().join(name), "SELECT 1").unwrap();
+        }
+
+        let files = SqlLoader::load_dir(dir.path()).unwrap();
+        let names: Vec<_> = files
+            .iter()
+            .map(|f| f.path.strip_prefix(dir.path()).unwrap().to_path_buf())
+            .collect();
+
+        assert_eq!(
+            names,
+            vec![
+                Path::new("a.sql"),
+                Path::new("b/a.sql"),
+                Path::new("b/z.sql"),
+                Path::new("c.sql"),
+            ]
+        );
+    }
+}
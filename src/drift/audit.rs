@@ -1,6 +1,7 @@
 use super::checksum::decompress_from_base64;
 use super::state::PartitionState;
 use crate::dsl::QueryDef;
+use crate::error::BqDriftError;
 use chrono::{DateTime, Utc};
 use serde::Serialize;
 use std::collections::HashMap;
@@ -141,12 +142,14 @@ impl Serialize for SourceAuditEntry {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+/// Ordered by severity: a modified source means stored partitions were
+/// written by SQL that no longer matches the source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
 #[serde(rename_all = "snake_case")]
 pub enum SourceStatus {
     Current,
-    Modified,
     NeverExecuted,
+    Modified,
 }
 
 impl SourceStatus {
@@ -167,14 +170,14 @@ impl SourceStatus {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize)]
 pub struct SourceAuditSummary {
     pub modified: usize,
     pub current: usize,
     pub never_executed: usize,
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Serialize)]
 pub struct SourceAuditReport {
     pub entries: Vec<SourceAuditEntry>,
 }
@@ -233,6 +236,45 @@ impl SourceAuditReport {
         }
     }
 
+    /// The most severe status in the report; `Current` when empty.
+    pub fn worst_status(&self) -> SourceStatus {
+        self.entries
+            .iter()
+            .map(|e| e.status)
+            .max()
+            .unwrap_or(SourceStatus::Current)
+    }
+
+    /// Fail when any source is at or above `fail_at`, naming the offenders.
+    pub fn gate(&self, fail_at: SourceStatus) -> crate::error::Result<()> {
+        let failing: Vec<String> = self
+            .entries
+            .iter()
+            .filter(|e| e.status >= fail_at)
+            .map(|e| match e.revision {
+                Some(rev) => format!(
+                    "{} v{}.r{} ({})",
+                    e.query_name,
+                    e.version,
+                    rev,
+                    e.status.as_str()
+                ),
+                None => format!("{} v{} ({})", e.query_name, e.version, e.status.as_str()),
+            })
+            .collect();
+
+        if failing.is_empty() {
+            Ok(())
+        } else {
+            Err(BqDriftError::Validation(format!(
+                "{} source(s) at or above '{}': {}",
+                failing.len(),
+                fail_at.as_str(),
+                failing.join(", ")
+            )))
+        }
+    }
+
     pub fn by_query(&self) -> HashMap<&str, Vec<&SourceAuditEntry>> {
         let mut grouped: HashMap<&str, Vec<&SourceAuditEntry>> =
             HashMap::with_capacity(self.entries.len());
@@ -393,17 +435,22 @@ mod tests {
     fn create_test_query(name: &str, versions: Vec<VersionDef>) -> QueryDef {
         QueryDef {
             name: name.to_string(),
+            previous_names: vec![],
             destination: Destination {
+                project: None,
                 dataset: "test_dataset".to_string(),
                 table: "test_table".to_string(),
                 partition: PartitionConfig::day("date"),
                 cluster: None,
+                labels: Default::default(),
             },
             description: None,
             owner: None,
             tags: vec![],
             versions,
             cluster: None,
+            default_invariants: InvariantsDef::default(),
+            provenance: false,
         }
     }
 
@@ -416,6 +463,7 @@ mod tests {
             revisions: vec![],
             description: None,
             backfill_since: None,
+            blocked_reason: None,
             schema: Schema::default(),
             dependencies: HashSet::new(),
             invariants: InvariantsDef::default(),
@@ -439,6 +487,7 @@ mod tests {
             }],
             description: None,
             backfill_since: None,
+            blocked_reason: None,
             schema: Schema::default(),
             dependencies: HashSet::new(),
             invariants: InvariantsDef::default(),
@@ -455,14 +504,19 @@ mod tests {
         PartitionState {
             query_name: query_name.to_string(),
             partition_date,
+            partition_key: None,
             version,
             sql_revision: revision,
             effective_from: NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
             sql_checksum: "checksum".to_string(),
             schema_checksum: "schema".to_string(),
             yaml_checksum: "yaml".to_string(),
+            invariants_checksum: None,
+            cluster_checksum: None,
+            unordered_schema_checksum: None,
             executed_sql_b64: Some(compress_to_base64(executed_sql)),
             upstream_states: HashMap::new(),
+            upstream_checksums: HashMap::new(),
             executed_at: Utc::now(),
             execution_time_ms: Some(100),
             rows_written: Some(1000),
@@ -485,6 +539,44 @@ mod tests {
         assert!(!report.has_modifications());
     }
 
+    #[test]
+    fn test_gate_fails_at_or_above_threshold() {
+        let sql = "SELECT * FROM source";
+        let queries = vec![
+            create_test_query("current_query", vec![create_version(1, sql)]),
+            create_test_query("modified_query", vec![create_version(1, "SELECT 2")]),
+        ];
+        let stored = vec![
+            create_stored_state(
+                "current_query",
+                NaiveDate::from_ymd_opt(2024, 1, 15).unwrap(),
+                1,
+                None,
+                sql,
+            ),
+            create_stored_state(
+                "modified_query",
+                NaiveDate::from_ymd_opt(2024, 1, 15).unwrap(),
+                1,
+                None,
+                "SELECT 1",
+            ),
+        ];
+
+        let report = SourceAuditor::new(&queries).audit(&stored);
+        assert_eq!(report.worst_status(), SourceStatus::Modified);
+        assert!(SourceAuditReport::new()
+            .gate(SourceStatus::NeverExecuted)
+            .is_ok());
+
+        let err = report.gate(SourceStatus::NeverExecuted).unwrap_err();
+        assert!(err.to_string().contains("modified_query v1 (modified)"));
+        assert!(!err.to_string().contains("current_query"));
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["entries"][1]["status"], "modified");
+    }
+
     #[test]
     fn test_audit_current_source() {
         let sql = "SELECT * FROM source";
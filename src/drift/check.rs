@@ -0,0 +1,67 @@
+use super::detector::DriftDetector;
+use super::state::{DriftReport, PartitionState};
+use crate::dsl::QueryLoader;
+use crate::error::Result;
+use chrono::NaiveDate;
+use std::path::Path;
+
+/// Source of previously recorded partition states.
+pub trait StateStore {
+    fn load_states(&self, from: NaiveDate, to: NaiveDate) -> Result<Vec<PartitionState>>;
+}
+
+impl StateStore for [PartitionState] {
+    fn load_states(&self, from: NaiveDate, to: NaiveDate) -> Result<Vec<PartitionState>> {
+        Ok(self
+            .iter()
+            .filter(|s| s.partition_date >= from && s.partition_date <= to)
+            .cloned()
+            .collect())
+    }
+}
+
+impl StateStore for Vec<PartitionState> {
+    fn load_states(&self, from: NaiveDate, to: NaiveDate) -> Result<Vec<PartitionState>> {
+        self.as_slice().load_states(from, to)
+    }
+}
+
+#[derive(Debug)]
+pub struct DriftCheckOutcome {
+    pub report: DriftReport,
+    pub query_count: usize,
+}
+
+impl DriftCheckOutcome {
+    pub fn has_drift(&self) -> bool {
+        !self.report.needs_rerun().is_empty()
+    }
+
+    pub fn exit_code(&self) -> i32 {
+        if self.has_drift() {
+            1
+        } else {
+            0
+        }
+    }
+}
+
+/// Load the query catalog in `dir`, fetch stored states for `from..=to` and
+/// run drift detection over the whole range.
+pub fn run_drift_check<S: StateStore + ?Sized>(
+    dir: impl AsRef<Path>,
+    store: &S,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<DriftCheckOutcome> {
+    let (queries, yaml_contents) = QueryLoader::new().load_dir_with_contents(dir)?;
+    let stored_states = store.load_states(from, to)?;
+
+    let detector = DriftDetector::new(&queries, &yaml_contents);
+    let report = detector.detect(&stored_states, from, to)?;
+
+    Ok(DriftCheckOutcome {
+        report,
+        query_count: queries.len(),
+    })
+}
@@ -1,13 +1,15 @@
-use crate::dsl::VersionDef;
-use crate::schema::Schema;
+use crate::dsl::{QueryDef, VersionDef};
+use crate::invariant::InvariantsDef;
+use crate::schema::{ClusterConfig, PartitionConfig, Schema};
 use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
 use flate2::read::GzDecoder;
 use flate2::write::GzEncoder;
 use flate2::Compression;
+use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use std::io::{Read, Write};
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Checksums {
     pub sql: String,
     pub schema: String,
@@ -50,6 +52,45 @@ impl Checksums {
         Self::compute(sql, &version.schema, yaml_content)
     }
 
+    /// Like `from_version`, but the schema checksum also covers table-level
+    /// settings of `query` that change the table's shape.
+    pub fn from_query_version(
+        query: &QueryDef,
+        version: &VersionDef,
+        yaml_content: &str,
+        execution_date: chrono::NaiveDate,
+    ) -> Self {
+        let sql = version.get_sql_for_date(execution_date);
+        let schema_json = table_schema_json(&version.schema, &query.destination.partition);
+        Self::compute_with_schema_json(sql, &schema_json, yaml_content)
+    }
+
+    /// The `from_query_version` schema checksum with columns sorted by name
+    /// at every level, so reordering fields does not change it; see
+    /// `DriftDetector::with_unordered_schema`.
+    pub fn unordered_schema(query: &QueryDef, version: &VersionDef) -> String {
+        Self::sha256(&table_schema_json(
+            &version.schema.canonical_order(),
+            &query.destination.partition,
+        ))
+    }
+
+    /// Checksum of a version's resolved invariants, kept apart from the
+    /// data-affecting checksums so check-only edits don't force a rerun.
+    pub fn invariants(invariants: &InvariantsDef) -> String {
+        let json =
+            serde_json::to_string(invariants).expect("Invariant serialization should never fail");
+        Self::sha256(&json)
+    }
+
+    /// Checksum of a destination's clustering columns, in order. Kept out of
+    /// the schema checksum so states recorded before clustering was tracked
+    /// still match.
+    pub fn cluster(cluster: Option<&ClusterConfig>) -> String {
+        let fields = cluster.map(|c| c.fields.join(",")).unwrap_or_default();
+        Self::sha256(&fields)
+    }
+
     pub fn sha256(content: &str) -> String {
         let mut hasher = Sha256::new();
         hasher.update(content.as_bytes());
@@ -63,6 +104,16 @@ pub(crate) fn schema_to_json(schema: &Schema) -> String {
         .expect("Schema serialization should never fail - all field types are serializable")
 }
 
+/// Schema JSON plus partition expiration when one is set. Left identical to
+/// `schema_to_json` otherwise so existing stored checksums stay valid.
+pub(crate) fn table_schema_json(schema: &Schema, partition: &PartitionConfig) -> String {
+    let mut json = schema_to_json(schema);
+    if let Some(days) = partition.expiration_days {
+        json.push_str(&format!("|partition_expiration_days={}", days));
+    }
+    json
+}
+
 impl ExecutionArtifact {
     pub fn create(sql_content: &str, schema: &Schema, yaml_content: &str) -> Self {
         let schema_json = schema_to_json(schema);
@@ -95,6 +146,17 @@ impl ExecutionArtifact {
         Self::create(sql, &version.schema, yaml_content)
     }
 
+    pub fn from_query_version(
+        query: &QueryDef,
+        version: &VersionDef,
+        yaml_content: &str,
+        execution_date: chrono::NaiveDate,
+    ) -> Self {
+        let sql = version.get_sql_for_date(execution_date);
+        let schema_json = table_schema_json(&version.schema, &query.destination.partition);
+        Self::create_with_schema_json(sql, &schema_json, yaml_content)
+    }
+
     pub fn decompress_sql(&self) -> Option<String> {
         decompress_from_base64(&self.sql_compressed)
     }
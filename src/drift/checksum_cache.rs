@@ -0,0 +1,169 @@
+use super::checksum::Checksums;
+use crate::error::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use tracing::warn;
+
+/// Location of the on-disk checksum cache used by `DriftDetector`.
+#[derive(Debug, Clone)]
+pub struct CacheConfig {
+    pub path: PathBuf,
+}
+
+impl CacheConfig {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CachedQuery {
+    yaml_hash: String,
+    checksums: HashMap<String, Checksums>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CacheFile {
+    queries: HashMap<String, CachedQuery>,
+}
+
+/// Checksums persisted across process runs, keyed by query, version and
+/// revision. All entries for a query are dropped once its yaml hash changes.
+#[derive(Debug)]
+pub struct ChecksumCache {
+    config: CacheConfig,
+    file: Mutex<CacheFile>,
+    dirty: AtomicBool,
+}
+
+impl ChecksumCache {
+    /// Load the cache, starting empty if the file is missing or unreadable.
+    pub fn load(config: CacheConfig) -> Self {
+        let file = match std::fs::read_to_string(&config.path) {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+                warn!(path = %config.path.display(), error = %e, "Ignoring corrupt checksum cache");
+                CacheFile::default()
+            }),
+            Err(_) => CacheFile::default(),
+        };
+        Self {
+            config,
+            file: Mutex::new(file),
+            dirty: AtomicBool::new(false),
+        }
+    }
+
+    fn entry_key(version: u32, revision: Option<u32>) -> String {
+        format!("{}:{}", version, revision.unwrap_or(0))
+    }
+
+    pub fn get(
+        &self,
+        query_name: &str,
+        yaml_hash: &str,
+        version: u32,
+        revision: Option<u32>,
+    ) -> Option<Checksums> {
+        let file = self.file.lock().expect("checksum cache lock poisoned");
+        let cached = file.queries.get(query_name)?;
+        if cached.yaml_hash != yaml_hash {
+            return None;
+        }
+        cached
+            .checksums
+            .get(&Self::entry_key(version, revision))
+            .cloned()
+    }
+
+    pub fn insert(
+        &self,
+        query_name: &str,
+        yaml_hash: &str,
+        version: u32,
+        revision: Option<u32>,
+        checksums: Checksums,
+    ) {
+        let mut file = self.file.lock().expect("checksum cache lock poisoned");
+        let cached = file.queries.entry(query_name.to_string()).or_default();
+        if cached.yaml_hash != yaml_hash {
+            cached.yaml_hash = yaml_hash.to_string();
+            cached.checksums.clear();
+        }
+        let key = Self::entry_key(version, revision);
+        if cached.checksums.get(&key) != Some(&checksums) {
+            cached.checksums.insert(key, checksums);
+            self.dirty.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// Write the cache back to disk if anything changed since it was loaded.
+    pub fn save(&self) -> Result<()> {
+        if !self.dirty.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+        let file = self.file.lock().expect("checksum cache lock poisoned");
+        if let Some(parent) = self.config.path.parent() {
+            if !parent.as_os_str().is_empty() {
+                std::fs::create_dir_all(parent)?;
+            }
+        }
+        std::fs::write(&self.config.path, serde_json::to_string(&*file)?)?;
+        self.dirty.store(false, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn checksums(sql: &str) -> Checksums {
+        Checksums {
+            sql: sql.to_string(),
+            schema: "schema".to_string(),
+            yaml: "yaml".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_roundtrip_through_disk() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = CacheConfig::new(dir.path().join("cache/checksums.json"));
+
+        let cache = ChecksumCache::load(config.clone());
+        cache.insert("q", "h1", 1, None, checksums("a"));
+        cache.insert("q", "h1", 2, Some(1), checksums("b"));
+        cache.save().unwrap();
+
+        let reloaded = ChecksumCache::load(config);
+        assert_eq!(reloaded.get("q", "h1", 1, None), Some(checksums("a")));
+        assert_eq!(reloaded.get("q", "h1", 2, Some(1)), Some(checksums("b")));
+        assert_eq!(reloaded.get("q", "h1", 2, None), None);
+    }
+
+    #[test]
+    fn test_yaml_hash_change_invalidates_query() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = ChecksumCache::load(CacheConfig::new(dir.path().join("c.json")));
+
+        cache.insert("q", "h1", 1, None, checksums("a"));
+        assert_eq!(cache.get("q", "h2", 1, None), None);
+
+        cache.insert("q", "h2", 2, None, checksums("b"));
+        assert_eq!(cache.get("q", "h2", 1, None), None);
+        assert_eq!(cache.get("q", "h2", 2, None), Some(checksums("b")));
+    }
+
+    #[test]
+    fn test_corrupt_file_starts_empty() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("c.json");
+        std::fs::write(&path, "not json").unwrap();
+
+        let cache = ChecksumCache::load(CacheConfig::new(path));
+        assert_eq!(cache.get("q", "h", 1, None), None);
+    }
+}
@@ -1,107 +1,496 @@
 use super::checksum::Checksums;
-use super::state::{DriftReport, DriftState, PartitionDrift, PartitionState};
-use crate::dsl::QueryDef;
-use crate::error::{BqDriftError, Result};
-use crate::schema::PartitionKey;
-use chrono::NaiveDate;
+use super::checksum_cache::{CacheConfig, ChecksumCache};
+use super::state::{
+    DetectionWarning, DetectionWarningKind, DriftReport, DriftState, PartitionDrift, PartitionState,
+};
+use crate::dsl::{QueryDef, SqlDependencies, VersionDef};
+use crate::error::Result;
+use crate::schema::{PartitionKey, PartitionType};
+use chrono::{Datelike, NaiveDate};
 use rayon::prelude::*;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
+/// Each query may be checked over at most this many days' worth of its
+/// partitions.
 const MAX_DETECTION_DAYS: i64 = 365 * 10;
 
+type UpstreamChecksumCache<'a> = HashMap<(&'a str, NaiveDate), String>;
+
 pub struct DriftDetector<'a> {
     queries: HashMap<&'a str, &'a QueryDef>,
+    catalog: &'a [QueryDef],
     yaml_contents: &'a HashMap<String, String>,
+    transitive: bool,
+    unordered_schema: bool,
+    checksum_cache: Option<ChecksumCache>,
+    as_of: Option<NaiveDate>,
+}
+
+/// A partition whose drift state differs between two detection dates.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StateTransition {
+    pub query_name: String,
+    pub partition_key: PartitionKey,
+    pub from: DriftState,
+    pub to: DriftState,
 }
 
 impl<'a> DriftDetector<'a> {
     pub fn new(queries: &'a [QueryDef], yaml_contents: &'a HashMap<String, String>) -> Self {
+        let catalog = queries;
         let queries = queries.iter().map(|q| (q.name.as_str(), q)).collect();
         Self {
             queries,
+            catalog,
             yaml_contents,
+            transitive: false,
+            unordered_schema: false,
+            checksum_cache: None,
+            as_of: None,
         }
     }
 
+    /// Evaluate revisions (and so the current SQL) as of `date` instead of
+    /// today.
+    pub fn with_as_of(mut self, date: NaiveDate) -> Self {
+        self.as_of = Some(date);
+        self
+    }
+
+    fn as_of(&self) -> NaiveDate {
+        self.as_of
+            .unwrap_or_else(|| chrono::Utc::now().date_naive())
+    }
+
+    /// Reuse version checksums from an on-disk cache across runs. Entries
+    /// are keyed by query, version and active revision, and discarded when
+    /// the query's yaml content changes. The cache is written back after
+    /// each `detect`; write failures are logged and otherwise ignored.
+    pub fn with_checksum_cache(mut self, config: CacheConfig) -> Self {
+        self.checksum_cache = Some(ChecksumCache::load(config));
+        self
+    }
+
+    /// Compare `PartitionState::upstream_checksums` against the current
+    /// transitive checksums of managed dependencies, reporting mismatches as
+    /// `DriftState::UpstreamChanged`.
+    ///
+    /// This resolves the full upstream dependency graph for every stored
+    /// partition that would otherwise be current, so detection cost grows with
+    /// the depth and fan-in of the catalog's dependency graph.
+    pub fn with_transitive_checksums(mut self, enabled: bool) -> Self {
+        self.transitive = enabled;
+        self
+    }
+
+    /// Compare a state's `unordered_schema_checksum`, when it has one,
+    /// instead of its `schema_checksum`, so that reordering columns in the
+    /// YAML is not reported as `DriftState::SchemaChanged`.
+    ///
+    /// With this enabled, a change that only moves columns is never
+    /// detected, even though it changes `SELECT *` and other positional
+    /// reads of the table. States recorded without the unordered checksum
+    /// are still compared by `schema_checksum`, so a reorder flips them.
+    pub fn with_unordered_schema(mut self, enabled: bool) -> Self {
+        self.unordered_schema = enabled;
+        self
+    }
+
+    /// Drift for every partition overlapping `from..=to`, stepping by each
+    /// query's partition type. Stored states are matched by their
+    /// `partition_key`, or by date when they have none; an hour never
+    /// matches a state without one. Range-partitioned queries are rejected.
     pub fn detect(
         &self,
         stored_states: &[PartitionState],
         from: NaiveDate,
         to: NaiveDate,
     ) -> Result<DriftReport> {
-        let num_days = (to - from).num_days().max(0);
-        if num_days > MAX_DETECTION_DAYS {
-            return Err(BqDriftError::Partition(format!(
-                "Date range too large: {} days exceeds maximum of {} days",
-                num_days, MAX_DETECTION_DAYS
-            )));
+        self.detect_as_of(stored_states, from, to, self.as_of())
+    }
+
+    /// Stored states whose query is no longer in the catalog, under its
+    /// name or any of its `previous_names`, e.g. after a removal or an
+    /// unrecorded rename. Detection skips these; use this to find tracking
+    /// rows to clean up.
+    pub fn find_orphan_states<'s>(&self, stored: &'s [PartitionState]) -> Vec<&'s PartitionState> {
+        let known: HashSet<&str> = self.catalog.iter().flat_map(Self::tracked_names).collect();
+        stored
+            .iter()
+            .filter(|s| !known.contains(s.query_name.as_str()))
+            .collect()
+    }
+
+    /// The names `query`'s states may be stored under, current name first.
+    fn tracked_names(query: &QueryDef) -> impl Iterator<Item = &str> {
+        std::iter::once(query.name.as_str()).chain(query.previous_names.iter().map(String::as_str))
+    }
+
+    /// Release-planning preview: the partitions in `from..=to` whose state
+    /// would change if detection ran on `as_of` against the same stored
+    /// states, e.g. once a pending revision takes effect.
+    pub fn what_if(
+        &self,
+        stored_states: &[PartitionState],
+        from: NaiveDate,
+        to: NaiveDate,
+        as_of: NaiveDate,
+    ) -> Result<Vec<StateTransition>> {
+        let now = self.detect(stored_states, from, to)?;
+        let then = self.detect_as_of(stored_states, from, to, as_of)?;
+
+        Ok(now
+            .partitions
+            .into_iter()
+            .zip(then.partitions)
+            .filter(|(before, after)| before.state != after.state)
+            .map(|(before, after)| StateTransition {
+                query_name: after.query_name,
+                partition_key: after.partition_key,
+                from: before.state,
+                to: after.state,
+            })
+            .collect())
+    }
+
+    fn detect_as_of(
+        &self,
+        stored_states: &[PartitionState],
+        from: NaiveDate,
+        to: NaiveDate,
+        as_of: NaiveDate,
+    ) -> Result<DriftReport> {
+        // Queries that can't be checked over this range are skipped with a
+        // warning so the rest of the catalog is still reported.
+        let mut estimated_capacity = 0usize;
+        let mut skipped: HashMap<&str, DetectionWarningKind> = HashMap::new();
+        for query in self.catalog {
+            let partition = &query.destination.partition;
+            if partition.partition_type == PartitionType::Range {
+                skipped.insert(&query.name, DetectionWarningKind::RangePartitioned);
+                continue;
+            }
+            let (count, max) = if partition.is_partitioned() {
+                let granularity = Self::detection_granularity(query);
+                let max_to = from + chrono::Duration::days(MAX_DETECTION_DAYS - 1);
+                (
+                    Self::partition_count(&granularity, from, to),
+                    Self::partition_count(&granularity, from, max_to),
+                )
+            } else {
+                (i64::from(from <= to), 1)
+            };
+            if count > max {
+                skipped.insert(
+                    &query.name,
+                    DetectionWarningKind::TooManyPartitions {
+                        partitions: count,
+                        max,
+                    },
+                );
+                continue;
+            }
+            estimated_capacity += count.max(0) as usize;
+        }
+        if self.queries.is_empty() {
+            return Ok(DriftReport::with_capacity(0));
         }
-        let num_days = num_days as usize + 1;
-        let estimated_capacity = self.queries.len() * num_days;
 
-        let stored_map: HashMap<(&str, NaiveDate), &PartitionState> = {
+        // Keyed by the partition written; states recorded without one by
+        // their day.
+        let stored_map: HashMap<(&str, PartitionKey), &PartitionState> = {
             let mut map = HashMap::with_capacity(stored_states.len());
             for s in stored_states {
-                map.insert((s.query_name.as_str(), s.partition_date), s);
+                let key = s
+                    .partition_key
+                    .unwrap_or(PartitionKey::Day(s.partition_date));
+                map.insert((s.query_name.as_str(), key), s);
+            }
+            map
+        };
+        // Unpartitioned tables are one logical partition; the latest run
+        // stands for the whole table whatever date it was recorded under.
+        let latest_stored: HashMap<&str, &PartitionState> = {
+            let mut map: HashMap<&str, &PartitionState> = HashMap::new();
+            for s in stored_states {
+                match map.get(s.query_name.as_str()) {
+                    Some(existing) if existing.executed_at >= s.executed_at => {}
+                    _ => {
+                        map.insert(s.query_name.as_str(), s);
+                    }
+                }
             }
             map
         };
 
-        let partitions: Vec<PartitionDrift> = self
+        let managed_datasets: HashSet<String> = self
+            .catalog
+            .iter()
+            .map(|q| q.destination.dataset.to_lowercase())
+            .collect();
+
+        let per_query: Vec<(Vec<PartitionDrift>, Vec<DetectionWarning>)> = self
             .queries
             .par_iter()
-            .flat_map(|(&query_name, &query)| {
-                let yaml_content = self
-                    .yaml_contents
-                    .get(query_name)
-                    .map(|s| s.as_str())
-                    .unwrap_or("");
-
-                let query_name_owned = query_name.to_string();
+            .filter(|(&query_name, _)| !skipped.contains_key(query_name))
+            .map(|(&query_name, &query)| {
+                let yaml = self.yaml_contents.get(query_name);
+                let yaml_content = yaml.map(|s| s.as_str()).unwrap_or("");
+
+                let today = as_of;
+                let yaml_hash = self
+                    .checksum_cache
+                    .as_ref()
+                    .map(|_| Checksums::sha256(yaml_content));
                 let mut checksum_cache: HashMap<u32, Checksums> = HashMap::new();
-                let mut results = Vec::with_capacity(num_days);
+                if let (Some(cache), Some(yaml_hash)) = (&self.checksum_cache, &yaml_hash) {
+                    for v in &query.versions {
+                        let revision = v.revision_for_date(today).map(|r| r.revision);
+                        if let Some(c) = cache.get(query_name, yaml_hash, v.version, revision) {
+                            checksum_cache.insert(v.version, c);
+                        }
+                    }
+                }
+                let mut upstream_cache: UpstreamChecksumCache<'a> = HashMap::new();
+                let granularity = Self::detection_granularity(query);
+                let mut results = Vec::new();
 
-                let mut current = from;
-                while current <= to {
-                    let drift = Self::detect_partition_cached(
-                        &query_name_owned,
+                let unpartitioned = !query.destination.partition.is_partitioned();
+                let mut current = if unpartitioned {
+                    PartitionKey::Day(to.max(from))
+                } else {
+                    PartitionKey::for_date(from, &granularity).unwrap_or(PartitionKey::Day(from))
+                };
+                while current.to_naive_date() <= to {
+                    let partition_date = current.to_naive_date();
+                    // States recorded before a rename are under a previous
+                    // name. The current name wins for a partition; for an
+                    // unpartitioned table the latest run under any name does.
+                    let stored = if unpartitioned {
+                        Self::tracked_names(query)
+                            .filter_map(|name| latest_stored.get(name))
+                            .reduce(|best, s| {
+                                if s.executed_at > best.executed_at {
+                                    s
+                                } else {
+                                    best
+                                }
+                            })
+                    } else {
+                        // A day-keyed state can't say which hour it wrote.
+                        let legacy = (!matches!(current, PartitionKey::Hour(_)))
+                            .then_some(PartitionKey::Day(partition_date));
+                        Self::tracked_names(query).find_map(|name| {
+                            stored_map
+                                .get(&(name, current))
+                                .or_else(|| legacy.and_then(|key| stored_map.get(&(name, key))))
+                        })
+                    };
+                    let current_upstream = match stored {
+                        Some(s) if self.transitive && !s.upstream_checksums.is_empty() => {
+                            Some(self.upstream_checksums_cached(
+                                query,
+                                partition_date,
+                                as_of,
+                                &mut upstream_cache,
+                            ))
+                        }
+                        _ => None,
+                    };
+                    let drift = self.detect_partition_cached(
                         query,
                         current,
-                        stored_map.get(&(query_name, current)),
+                        stored,
                         yaml_content,
                         &mut checksum_cache,
+                        current_upstream.as_ref(),
+                        as_of,
                     );
                     results.push(drift);
-                    match current.succ_opt() {
-                        Some(next) => current = next,
-                        None => break,
+                    let next = current.next();
+                    if unpartitioned || next == current {
+                        break;
                     }
+                    current = next;
+                }
+
+                if let (Some(cache), Some(yaml_hash)) = (&self.checksum_cache, &yaml_hash) {
+                    for v in &query.versions {
+                        if let Some(c) = checksum_cache.remove(&v.version) {
+                            let revision = v.revision_for_date(today).map(|r| r.revision);
+                            cache.insert(query_name, yaml_hash, v.version, revision, c);
+                        }
+                    }
+                }
+
+                let mut warnings = Vec::new();
+                if yaml.is_none() {
+                    warnings.push(DetectionWarning {
+                        query_name: query.name.clone(),
+                        kind: DetectionWarningKind::MissingYamlContent,
+                    });
                 }
-                results
+                warnings.extend(self.unresolved_dependency_warnings(query, &managed_datasets));
+                warnings.extend(Self::backfill_since_warnings(query, &results, as_of));
+                (results, warnings)
             })
             .collect();
 
+        let mut partitions = Vec::with_capacity(estimated_capacity);
+        let mut warnings: Vec<DetectionWarning> = skipped
+            .into_iter()
+            .map(|(query_name, kind)| DetectionWarning {
+                query_name: query_name.to_string(),
+                kind,
+            })
+            .collect();
+        for (query_partitions, query_warnings) in per_query {
+            partitions.extend(query_partitions);
+            warnings.extend(query_warnings);
+        }
+
+        if let Some(cache) = &self.checksum_cache {
+            if let Err(e) = cache.save() {
+                tracing::warn!(error = %e, "Failed to write checksum cache");
+            }
+        }
+
+        // par_iter collection order varies between runs; keep output stable.
+        partitions.sort_unstable_by(|a, b| {
+            a.query_name
+                .cmp(&b.query_name)
+                .then_with(|| a.partition_key.cmp(&b.partition_key))
+        });
+
+        warnings.sort_by(|a, b| {
+            a.query_name
+                .cmp(&b.query_name)
+                .then_with(|| a.kind.as_str().cmp(b.kind.as_str()))
+        });
+
         let mut report = DriftReport::with_capacity(estimated_capacity);
         for drift in partitions {
             report.add(drift);
         }
+        report.warnings = warnings;
 
         Ok(report)
     }
 
+    /// Dependencies in a dataset the catalog writes to but that no query
+    /// produces, most likely a renamed or removed upstream. Tables in other
+    /// datasets are assumed to be external sources.
+    fn unresolved_dependency_warnings(
+        &self,
+        query: &QueryDef,
+        managed_datasets: &HashSet<String>,
+    ) -> Vec<DetectionWarning> {
+        let mut warnings = Vec::new();
+        for version in &query.versions {
+            let mut tables: Vec<&String> = version.dependencies.iter().collect();
+            tables.sort();
+            for table in tables {
+                let normalized = SqlDependencies::normalize_table(table);
+                let mut parts = normalized.rsplit('.');
+                let dataset = parts.nth(1);
+                if dataset.is_some_and(|d| managed_datasets.contains(d))
+                    && SqlDependencies::find_producer(table, self.catalog).is_none()
+                {
+                    warnings.push(DetectionWarning {
+                        query_name: query.name.clone(),
+                        kind: DetectionWarningKind::UnresolvedDependency {
+                            version: version.version,
+                            table: table.clone(),
+                        },
+                    });
+                }
+            }
+        }
+        warnings
+    }
+
+    /// One warning per version whose rerun candidates include partitions
+    /// earlier than the active revision's (or the version's)
+    /// `backfill_since`.
+    fn backfill_since_warnings(
+        query: &QueryDef,
+        results: &[PartitionDrift],
+        as_of: NaiveDate,
+    ) -> Vec<DetectionWarning> {
+        query
+            .versions
+            .iter()
+            .filter_map(|version| {
+                let backfill_since = version
+                    .revision_for_date(as_of)
+                    .and_then(|r| r.backfill_since)
+                    .or(version.backfill_since)?;
+                let partitions = results
+                    .iter()
+                    .filter(|p| {
+                        p.current_version == version.version
+                            && p.state.needs_rerun()
+                            && p.partition_date() < backfill_since
+                    })
+                    .count();
+                (partitions > 0).then(|| DetectionWarning {
+                    query_name: query.name.clone(),
+                    kind: DetectionWarningKind::BeforeBackfillSince {
+                        version: version.version,
+                        backfill_since,
+                        partitions,
+                    },
+                })
+            })
+            .collect()
+    }
+
+    /// Partition type to step by when detecting drift.
+    fn detection_granularity(query: &QueryDef) -> PartitionType {
+        let partition = &query.destination.partition;
+        match partition.partition_type {
+            PartitionType::IngestionTime => {
+                partition.granularity.clone().unwrap_or(PartitionType::Day)
+            }
+            ref other => other.clone(),
+        }
+    }
+
+    fn partition_count(granularity: &PartitionType, from: NaiveDate, to: NaiveDate) -> i64 {
+        if to < from {
+            return 0;
+        }
+        let months = |d: NaiveDate| d.year() as i64 * 12 + d.month0() as i64;
+        match granularity {
+            PartitionType::Hour => ((to - from).num_days() + 1) * 24,
+            PartitionType::Month => months(to) - months(from) + 1,
+            PartitionType::Year => (to.year() - from.year()) as i64 + 1,
+            _ => (to - from).num_days() + 1,
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
     fn detect_partition_cached(
-        query_name_owned: &str,
+        &self,
         query: &QueryDef,
-        partition_date: NaiveDate,
+        partition_key: PartitionKey,
         stored: Option<&&PartitionState>,
         yaml_content: &str,
         checksum_cache: &mut HashMap<u32, Checksums>,
+        current_upstream: Option<&HashMap<String, String>>,
+        as_of: NaiveDate,
     ) -> PartitionDrift {
-        let version = query.get_version_for_date(partition_date);
+        let version = query.get_version_for_date(partition_key.to_naive_date());
 
         let (state, executed_version, caused_by) = match (version, stored) {
             (None, _) => (DriftState::NeverRun, None, None),
 
+            (Some(v), stored) if v.is_blocked() => (
+                DriftState::Blocked,
+                stored.map(|s| s.version),
+                v.blocked_reason.clone(),
+            ),
+
             (Some(_), None) => (DriftState::NeverRun, None, None),
 
             (Some(v), Some(stored)) => {
@@ -109,15 +498,45 @@ impl<'a> DriftDetector<'a> {
                     (DriftState::Failed, Some(stored.version), None)
                 } else {
                     let current_checksums = checksum_cache.entry(v.version).or_insert_with(|| {
-                        Checksums::from_version(v, yaml_content, chrono::Utc::now().date_naive())
+                        Checksums::from_query_version(query, v, yaml_content, as_of)
                     });
 
-                    if current_checksums.schema != stored.schema_checksum {
+                    let columns_changed = match stored
+                        .unordered_schema_checksum
+                        .as_ref()
+                        .filter(|_| self.unordered_schema)
+                    {
+                        Some(c) => *c != Checksums::unordered_schema(query, v),
+                        None => current_checksums.schema != stored.schema_checksum,
+                    };
+                    let schema_changed = columns_changed
+                        || stored
+                            .cluster_checksum
+                            .as_ref()
+                            .is_some_and(|c| *c != Checksums::cluster(query.cluster.as_ref()));
+
+                    if schema_changed {
                         (DriftState::SchemaChanged, Some(stored.version), None)
                     } else if current_checksums.sql != stored.sql_checksum {
                         (DriftState::SqlChanged, Some(stored.version), None)
-                    } else if v.version != stored.version {
+                    } else if v.version < stored.version {
+                        (DriftState::VersionDowngraded, Some(stored.version), None)
+                    } else if v.version > stored.version {
                         (DriftState::VersionUpgraded, Some(stored.version), None)
+                    } else if let Some(upstream) =
+                        current_upstream.and_then(|c| Self::changed_upstream(stored, c))
+                    {
+                        (
+                            DriftState::UpstreamChanged,
+                            Some(stored.version),
+                            Some(upstream),
+                        )
+                    } else if stored
+                        .invariants_checksum
+                        .as_ref()
+                        .is_some_and(|c| *c != Checksums::invariants(&v.invariants))
+                    {
+                        (DriftState::InvariantsChanged, Some(stored.version), None)
                     } else {
                         (DriftState::Current, Some(stored.version), None)
                     }
@@ -128,17 +547,14 @@ impl<'a> DriftDetector<'a> {
         let executed_sql_b64 = stored.and_then(|s| s.executed_sql_b64.clone());
 
         let current_sql = if state.needs_rerun() {
-            version.map(|v| {
-                v.get_sql_for_date(chrono::Utc::now().date_naive())
-                    .to_string()
-            })
+            version.map(|v| v.get_sql_for_date(as_of).to_string())
         } else {
             None
         };
 
         PartitionDrift {
-            query_name: query_name_owned.to_string(),
-            partition_key: PartitionKey::Day(partition_date),
+            query_name: query.name.clone(),
+            partition_key,
             state,
             current_version: version.map(|v| v.version).unwrap_or(0),
             executed_version,
@@ -148,6 +564,99 @@ impl<'a> DriftDetector<'a> {
         }
     }
 
+    fn changed_upstream(
+        stored: &PartitionState,
+        current: &HashMap<String, String>,
+    ) -> Option<String> {
+        let mut names: Vec<&String> = stored.upstream_checksums.keys().collect();
+        names.sort();
+        names.into_iter().find_map(|name| {
+            let recorded = &stored.upstream_checksums[name];
+            match current.get(name) {
+                Some(checksum) if checksum != recorded => Some(name.clone()),
+                _ => None,
+            }
+        })
+    }
+
+    /// Transitive checksums of the managed queries `query` reads from on
+    /// `partition_date`, keyed by upstream query name. Record these in
+    /// `PartitionState::upstream_checksums` so transitive detection can
+    /// compare against them later.
+    pub fn upstream_checksums(
+        &self,
+        query: &QueryDef,
+        partition_date: NaiveDate,
+    ) -> HashMap<String, String> {
+        self.upstream_checksums_cached(query, partition_date, self.as_of(), &mut HashMap::new())
+    }
+
+    fn upstream_checksums_cached(
+        &self,
+        query: &QueryDef,
+        partition_date: NaiveDate,
+        as_of: NaiveDate,
+        cache: &mut UpstreamChecksumCache<'a>,
+    ) -> HashMap<String, String> {
+        let Some(version) = query.get_version_for_date(partition_date) else {
+            return HashMap::new();
+        };
+
+        let mut checksums = HashMap::new();
+        for upstream in self.managed_upstreams(&query.name, version) {
+            let mut visiting = HashSet::new();
+            if let Some(checksum) =
+                self.transitive_checksum(upstream, partition_date, as_of, &mut visiting, cache)
+            {
+                checksums.insert(upstream.name.clone(), checksum);
+            }
+        }
+        checksums
+    }
+
+    fn managed_upstreams(&self, query_name: &str, version: &VersionDef) -> Vec<&'a QueryDef> {
+        let mut upstreams: Vec<&'a QueryDef> = version
+            .dependencies
+            .iter()
+            .filter_map(|table| SqlDependencies::find_producer(table, self.catalog))
+            .filter(|q| q.name != query_name)
+            .collect();
+        upstreams.sort_by(|a, b| a.name.cmp(&b.name));
+        upstreams.dedup_by(|a, b| a.name == b.name);
+        upstreams
+    }
+
+    fn transitive_checksum(
+        &self,
+        query: &'a QueryDef,
+        partition_date: NaiveDate,
+        as_of: NaiveDate,
+        visiting: &mut HashSet<&'a str>,
+        cache: &mut UpstreamChecksumCache<'a>,
+    ) -> Option<String> {
+        if let Some(checksum) = cache.get(&(query.name.as_str(), partition_date)) {
+            return Some(checksum.clone());
+        }
+        let version = query.get_version_for_date(partition_date)?;
+        if !visiting.insert(query.name.as_str()) {
+            return None;
+        }
+
+        let mut combined = Checksums::sha256(version.get_sql_for_date(as_of));
+        for upstream in self.managed_upstreams(&query.name, version) {
+            if let Some(checksum) =
+                self.transitive_checksum(upstream, partition_date, as_of, visiting, cache)
+            {
+                combined.push_str(&format!("|{}={}", upstream.name, checksum));
+            }
+        }
+        visiting.remove(query.name.as_str());
+
+        let checksum = Checksums::sha256(&combined);
+        cache.insert((query.name.as_str(), partition_date), checksum.clone());
+        Some(checksum)
+    }
+
     /// Check if any upstream dependency was re-run after this partition
     /// Returns the name of the upstream query that changed, if any
     pub fn detect_upstream_changed(
@@ -200,7 +709,7 @@ mod tests {
     use super::*;
     use crate::drift::checksum::{compress_to_base64, Checksums};
     use crate::dsl::{Destination, VersionDef};
-    use crate::invariant::InvariantsDef;
+    use crate::invariant::{InvariantCheck, InvariantDef, InvariantsDef};
     use crate::schema::{PartitionConfig, Schema};
     use chrono::{NaiveDate, Utc};
     use std::collections::HashSet;
@@ -208,11 +717,14 @@ mod tests {
     fn create_test_query(name: &str, sql_content: &str) -> QueryDef {
         QueryDef {
             name: name.to_string(),
+            previous_names: vec![],
             destination: Destination {
+                project: None,
                 dataset: "test_dataset".to_string(),
                 table: "test_table".to_string(),
                 partition: PartitionConfig::day("date"),
                 cluster: None,
+                labels: Default::default(),
             },
             description: None,
             owner: None,
@@ -225,11 +737,14 @@ mod tests {
                 revisions: vec![],
                 description: None,
                 backfill_since: None,
+                blocked_reason: None,
                 schema: Schema::default(),
                 dependencies: HashSet::new(),
                 invariants: InvariantsDef::default(),
             }],
             cluster: None,
+            default_invariants: InvariantsDef::default(),
+            provenance: false,
         }
     }
 
@@ -243,14 +758,19 @@ mod tests {
         PartitionState {
             query_name: query_name.to_string(),
             partition_date,
+            partition_key: None,
             version: 1,
             sql_revision: None,
             effective_from: NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
             sql_checksum: checksums.sql,
             schema_checksum: checksums.schema,
             yaml_checksum: checksums.yaml,
+            invariants_checksum: None,
+            cluster_checksum: None,
+            unordered_schema_checksum: None,
             executed_sql_b64: Some(compress_to_base64(sql_content)),
             upstream_states: HashMap::new(),
+            upstream_checksums: HashMap::new(),
             executed_at: Utc::now(),
             execution_time_ms: Some(100),
             rows_written: Some(1000),
@@ -259,6 +779,21 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_detect_empty_catalog_returns_empty_report() {
+        let queries: Vec<QueryDef> = Vec::new();
+        let yaml_contents = HashMap::new();
+        let detector = DriftDetector::new(&queries, &yaml_contents);
+
+        let from = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let to = NaiveDate::from_ymd_opt(2024, 12, 31).unwrap();
+        let report = detector.detect(&[], from, to).unwrap();
+
+        assert!(report.partitions.is_empty());
+        assert!(report.is_current());
+        assert!(report.needs_rerun().is_empty());
+    }
+
     #[test]
     fn test_detect_never_run_has_current_sql() {
         let query = create_test_query("test_query", "SELECT * FROM source");
@@ -282,6 +817,29 @@ mod tests {
         assert!(drift.executed_sql_b64.is_none());
     }
 
+    #[test]
+    fn test_detect_before_first_effective_from_is_never_run() {
+        let sql = "SELECT * FROM source";
+        let yaml = "name: test_query";
+        let query = create_test_query("test_query", sql);
+        let yaml_contents = HashMap::from([("test_query".to_string(), yaml.to_string())]);
+        let queries = vec![query];
+        let detector = DriftDetector::new(&queries, &yaml_contents);
+
+        let before = NaiveDate::from_ymd_opt(2023, 12, 31).unwrap();
+        let first = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let stored = vec![
+            create_stored_state("test_query", before, sql, yaml),
+            create_stored_state("test_query", first, sql, yaml),
+        ];
+
+        let report = detector.detect(&stored, before, first).unwrap();
+
+        assert_eq!(report.partitions.len(), 2);
+        assert_eq!(report.partitions[0].state, DriftState::NeverRun);
+        assert_eq!(report.partitions[1].state, DriftState::Current);
+    }
+
     #[test]
     fn test_detect_current_preserves_executed_sql() {
         let sql = "SELECT * FROM source";
@@ -303,6 +861,115 @@ mod tests {
         assert!(drift.executed_sql_b64.is_some());
     }
 
+    #[test]
+    fn test_detect_version_downgraded_when_stored_version_is_newer() {
+        let sql = "SELECT * FROM source";
+        let yaml = "name: test_query";
+        let query = create_test_query("test_query", sql);
+        let yaml_contents = HashMap::from([("test_query".to_string(), yaml.to_string())]);
+        let queries = vec![query];
+        let detector = DriftDetector::new(&queries, &yaml_contents);
+
+        let date = NaiveDate::from_ymd_opt(2024, 1, 15).unwrap();
+        let mut stored = create_stored_state("test_query", date, sql, yaml);
+        stored.version = 2;
+
+        let report = detector.detect(&[stored], date, date).unwrap();
+
+        let drift = &report.partitions[0];
+        assert_eq!(drift.state, DriftState::VersionDowngraded);
+        assert_eq!(drift.current_version, 1);
+        assert_eq!(drift.executed_version, Some(2));
+    }
+
+    #[test]
+    fn test_detect_version_upgraded_when_stored_version_is_older() {
+        let sql = "SELECT * FROM source";
+        let yaml = "name: test_query";
+        let mut query = create_test_query("test_query", sql);
+        query.versions[0].version = 2;
+        let yaml_contents = HashMap::from([("test_query".to_string(), yaml.to_string())]);
+        let queries = vec![query];
+        let detector = DriftDetector::new(&queries, &yaml_contents);
+
+        let date = NaiveDate::from_ymd_opt(2024, 1, 15).unwrap();
+        let stored = create_stored_state("test_query", date, sql, yaml);
+
+        let report = detector.detect(&[stored], date, date).unwrap();
+
+        assert_eq!(report.partitions[0].state, DriftState::VersionUpgraded);
+    }
+
+    #[test]
+    fn test_detect_invariants_changed_without_rerun() {
+        let sql = "SELECT * FROM source";
+        let yaml = "name: test_query";
+        let query = create_test_query("test_query", sql);
+        let yaml_contents = HashMap::from([("test_query".to_string(), yaml.to_string())]);
+        let date = NaiveDate::from_ymd_opt(2024, 1, 15).unwrap();
+
+        let mut stored = create_stored_state("test_query", date, sql, yaml);
+        stored.invariants_checksum = Some(Checksums::invariants(&query.versions[0].invariants));
+
+        let mut changed = query.clone();
+        changed.versions[0].invariants.after.push(InvariantDef {
+            name: "has_rows".to_string(),
+            description: None,
+            severity: Default::default(),
+            check: InvariantCheck::RowCount {
+                source: None,
+                min: Some(1),
+                max: None,
+            },
+        });
+
+        let queries = vec![query];
+        let detector = DriftDetector::new(&queries, &yaml_contents);
+        let report = detector.detect(&[stored.clone()], date, date).unwrap();
+        assert_eq!(report.partitions[0].state, DriftState::Current);
+
+        let queries = vec![changed];
+        let detector = DriftDetector::new(&queries, &yaml_contents);
+        let report = detector.detect(&[stored], date, date).unwrap();
+        let drift = &report.partitions[0];
+        assert_eq!(drift.state, DriftState::InvariantsChanged);
+        assert!(!drift.state.needs_rerun());
+        assert!(drift.state.needs_revalidation());
+        assert!(drift.current_sql.is_none());
+    }
+
+    #[test]
+    fn test_detect_with_checksum_cache_persists_entries() {
+        let sql = "SELECT * FROM source";
+        let yaml = "name: test_query";
+        let query = create_test_query("test_query", sql);
+        let yaml_contents = HashMap::from([("test_query".to_string(), yaml.to_string())]);
+        let queries = vec![query];
+        let dir = tempfile::tempdir().unwrap();
+        let config = CacheConfig::new(dir.path().join("checksums.json"));
+
+        let date = NaiveDate::from_ymd_opt(2024, 1, 15).unwrap();
+        let stored = create_stored_state("test_query", date, sql, yaml);
+
+        let detector =
+            DriftDetector::new(&queries, &yaml_contents).with_checksum_cache(config.clone());
+        let report = detector
+            .detect(std::slice::from_ref(&stored), date, date)
+            .unwrap();
+        assert_eq!(report.partitions[0].state, DriftState::Current);
+        assert!(config.path.exists());
+
+        let cache = ChecksumCache::load(config.clone());
+        let cached = cache
+            .get("test_query", &Checksums::sha256(yaml), 1, None)
+            .unwrap();
+        assert_eq!(cached.sql, stored.sql_checksum);
+
+        let detector = DriftDetector::new(&queries, &yaml_contents).with_checksum_cache(config);
+        let report = detector.detect(&[stored], date, date).unwrap();
+        assert_eq!(report.partitions[0].state, DriftState::Current);
+    }
+
     #[test]
     fn test_detect_sql_changed_has_both_sqls() {
         let old_sql = "SELECT user_id FROM users";
@@ -376,6 +1043,201 @@ mod tests {
         assert!(drift.executed_sql_b64.is_some());
     }
 
+    #[test]
+    fn test_detect_blocked_version_is_not_rerun() {
+        let sql = "SELECT * FROM source";
+        let yaml = "name: test_query";
+        let mut query = create_test_query("test_query", sql);
+        query.versions[0].blocked_reason = Some("bad join".to_string());
+        let yaml_contents = HashMap::from([("test_query".to_string(), yaml.to_string())]);
+        let queries = vec![query];
+        let detector = DriftDetector::new(&queries, &yaml_contents);
+
+        let date = NaiveDate::from_ymd_opt(2024, 1, 15).unwrap();
+        let mut stored = create_stored_state("test_query", date, sql, yaml);
+        stored.sql_checksum = "stale".to_string();
+
+        let report = detector
+            .detect(&[stored], date, date.succ_opt().unwrap())
+            .unwrap();
+
+        assert_eq!(report.partitions.len(), 2);
+        for drift in &report.partitions {
+            assert_eq!(drift.state, DriftState::Blocked);
+            assert_eq!(drift.caused_by.as_deref(), Some("bad join"));
+            assert!(!drift.state.needs_rerun());
+        }
+        assert_eq!(report.partitions[0].executed_version, Some(1));
+    }
+
+    #[test]
+    fn test_detect_collects_warnings_without_failing() {
+        let mut query = create_test_query("test_query", "SELECT * FROM test_dataset.gone");
+        query.versions[0].backfill_since = Some(NaiveDate::from_ymd_opt(2024, 1, 16).unwrap());
+        query.versions[0]
+            .dependencies
+            .extend(["test_dataset.gone".to_string(), "raw.events".to_string()]);
+        let yaml_contents = HashMap::new();
+        let queries = vec![query];
+        let detector = DriftDetector::new(&queries, &yaml_contents);
+
+        let from = NaiveDate::from_ymd_opt(2024, 1, 15).unwrap();
+        let report = detector
+            .detect(&[], from, NaiveDate::from_ymd_opt(2024, 1, 17).unwrap())
+            .unwrap();
+
+        assert_eq!(report.partitions.len(), 3);
+        let kinds: Vec<_> = report.warnings.iter().map(|w| &w.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                &DetectionWarningKind::BeforeBackfillSince {
+                    version: 1,
+                    backfill_since: NaiveDate::from_ymd_opt(2024, 1, 16).unwrap(),
+                    partitions: 1,
+                },
+                &DetectionWarningKind::MissingYamlContent,
+                &DetectionWarningKind::UnresolvedDependency {
+                    version: 1,
+                    table: "test_dataset.gone".to_string(),
+                },
+            ]
+        );
+        assert!(report.warnings.iter().all(|w| w.query_name == "test_query"));
+    }
+
+    #[test]
+    fn test_detect_unpartitioned_table_is_one_partition() {
+        let sql = "SELECT * FROM source";
+        let yaml = "name: test_query";
+        let mut query = create_test_query("test_query", sql);
+        query.destination.partition = PartitionConfig::unpartitioned();
+        let yaml_contents = HashMap::from([("test_query".to_string(), yaml.to_string())]);
+        let queries = vec![query];
+        let detector = DriftDetector::new(&queries, &yaml_contents);
+
+        let from = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let to = NaiveDate::from_ymd_opt(2024, 3, 31).unwrap();
+        let report = detector.detect(&[], from, to).unwrap();
+        assert_eq!(report.partitions.len(), 1);
+        assert_eq!(report.partitions[0].partition_key, PartitionKey::Day(to));
+        assert_eq!(report.partitions[0].state, DriftState::NeverRun);
+
+        let last_run = NaiveDate::from_ymd_opt(2024, 2, 10).unwrap();
+        let stored = create_stored_state("test_query", last_run, sql, yaml);
+        let report = detector.detect(&[stored], from, to).unwrap();
+        assert_eq!(report.partitions.len(), 1);
+        assert_eq!(report.partitions[0].state, DriftState::Current);
+    }
+
+    #[test]
+    fn test_detect_matches_states_stored_under_previous_name() {
+        let sql = "SELECT * FROM source";
+        let yaml = "name: test_query";
+        let mut query = create_test_query("test_query", sql);
+        query.previous_names = vec!["old_query".to_string()];
+        let yaml_contents = HashMap::from([("test_query".to_string(), yaml.to_string())]);
+        let queries = vec![query];
+        let detector = DriftDetector::new(&queries, &yaml_contents);
+
+        let day1 = NaiveDate::from_ymd_opt(2024, 1, 15).unwrap();
+        let day2 = NaiveDate::from_ymd_opt(2024, 1, 16).unwrap();
+        let mut renamed = create_stored_state("old_query", day1, sql, yaml);
+        renamed.version = 2;
+        let stored = vec![
+            renamed,
+            create_stored_state("old_query", day2, sql, yaml),
+            create_stored_state("test_query", day1, sql, yaml),
+            create_stored_state("unrelated", day1, sql, yaml),
+        ];
+
+        let report = detector.detect(&stored, day1, day2).unwrap();
+
+        assert_eq!(report.partitions.len(), 2);
+        // The state under the current name wins over the old one
+        assert_eq!(report.partitions[0].state, DriftState::Current);
+        assert_eq!(report.partitions[1].query_name, "test_query");
+        assert_eq!(report.partitions[1].state, DriftState::Current);
+
+        let orphans = detector.find_orphan_states(&stored);
+        assert_eq!(orphans.len(), 1);
+        assert_eq!(orphans[0].query_name, "unrelated");
+    }
+
+    #[test]
+    fn test_find_orphan_states() {
+        let sql = "SELECT * FROM source";
+        let queries = vec![create_test_query("test_query", sql)];
+        let yaml_contents = HashMap::new();
+        let detector = DriftDetector::new(&queries, &yaml_contents);
+
+        let date = NaiveDate::from_ymd_opt(2024, 1, 15).unwrap();
+        let stored = vec![
+            create_stored_state("test_query", date, sql, ""),
+            create_stored_state("renamed_query", date, sql, ""),
+        ];
+
+        let orphans = detector.find_orphan_states(&stored);
+        assert_eq!(orphans.len(), 1);
+        assert_eq!(orphans[0].query_name, "renamed_query");
+    }
+
+    #[test]
+    fn test_what_if_reports_pending_revision() {
+        let sql = "SELECT * FROM source";
+        let yaml = "name: test_query";
+        let mut query = create_test_query("test_query", sql);
+        query.versions[0]
+            .revisions
+            .push(crate::dsl::ResolvedRevision {
+                revision: 1,
+                effective_from: NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(),
+                source: "test.r1.sql".to_string(),
+                sql_content: "SELECT * FROM fixed_source".to_string(),
+                reason: None,
+                backfill_since: None,
+                dependencies: HashSet::new(),
+            });
+        let yaml_contents = HashMap::from([("test_query".to_string(), yaml.to_string())]);
+        let queries = vec![query];
+        let detector = DriftDetector::new(&queries, &yaml_contents)
+            .with_as_of(NaiveDate::from_ymd_opt(2024, 2, 1).unwrap());
+
+        let date = NaiveDate::from_ymd_opt(2024, 1, 15).unwrap();
+        let stored = vec![create_stored_state("test_query", date, sql, yaml)];
+
+        let report = detector.detect(&stored, date, date).unwrap();
+        assert_eq!(report.partitions[0].state, DriftState::Current);
+
+        let unchanged = detector
+            .what_if(
+                &stored,
+                date,
+                date,
+                NaiveDate::from_ymd_opt(2024, 2, 28).unwrap(),
+            )
+            .unwrap();
+        assert!(unchanged.is_empty());
+
+        let transitions = detector
+            .what_if(
+                &stored,
+                date,
+                date,
+                NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(),
+            )
+            .unwrap();
+        assert_eq!(
+            transitions,
+            vec![StateTransition {
+                query_name: "test_query".to_string(),
+                partition_key: PartitionKey::Day(date),
+                from: DriftState::Current,
+                to: DriftState::SqlChanged,
+            }]
+        );
+    }
+
     #[test]
     fn test_detect_schema_changed_preserves_executed_sql() {
         let sql = "SELECT * FROM source";
@@ -418,4 +1280,232 @@ mod tests {
             assert!(drift.current_sql.is_some());
         }
     }
+
+    #[test]
+    fn test_detect_orders_partitions_deterministically() {
+        let names = ["query_c", "query_a", "query_d", "query_b"];
+        let queries: Vec<QueryDef> = names
+            .iter()
+            .map(|name| create_test_query(name, "SELECT * FROM source"))
+            .collect();
+        let yaml_contents: HashMap<String, String> = names
+            .iter()
+            .map(|name| (name.to_string(), format!("name: {}", name)))
+            .collect();
+        let detector = DriftDetector::new(&queries, &yaml_contents);
+
+        let from = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let to = NaiveDate::from_ymd_opt(2024, 1, 10).unwrap();
+        let keys = |report: &DriftReport| -> Vec<(String, PartitionKey)> {
+            report
+                .partitions
+                .iter()
+                .map(|p| (p.query_name.clone(), p.partition_key))
+                .collect()
+        };
+
+        let first = keys(&detector.detect(&[], from, to).unwrap());
+        let second = keys(&detector.detect(&[], from, to).unwrap());
+
+        assert_eq!(first.len(), 40);
+        assert_eq!(first, second);
+        let mut sorted = first.clone();
+        sorted.sort();
+        assert_eq!(first, sorted);
+        assert_eq!(first[0].0, "query_a");
+        assert_eq!(first[39].0, "query_d");
+    }
+
+    fn create_managed_query(name: &str, sql_content: &str) -> QueryDef {
+        let mut query = create_test_query(name, sql_content);
+        query.destination.table = name.to_string();
+        query.versions[0].dependencies = crate::dsl::SqlDependencies::extract(sql_content).tables;
+        query
+    }
+
+    #[test]
+    fn test_transitive_checksum_detects_upstream_sql_change() {
+        let yaml_contents = HashMap::new();
+        let date = NaiveDate::from_ymd_opt(2024, 1, 15).unwrap();
+        let downstream_sql = "SELECT * FROM test_dataset.middle";
+
+        let old_catalog = vec![
+            create_managed_query("root", "SELECT * FROM raw.events"),
+            create_managed_query("middle", "SELECT * FROM test_dataset.root"),
+            create_managed_query("leaf", downstream_sql),
+        ];
+        let old_detector = DriftDetector::new(&old_catalog, &yaml_contents);
+        let mut stored = create_stored_state("leaf", date, downstream_sql, "");
+        stored.upstream_checksums = old_detector.upstream_checksums(&old_catalog[2], date);
+        assert!(stored.upstream_checksums.contains_key("middle"));
+
+        let new_catalog = vec![
+            create_managed_query("root", "SELECT id FROM raw.events"),
+            create_managed_query("middle", "SELECT * FROM test_dataset.root"),
+            create_managed_query("leaf", downstream_sql),
+        ];
+
+        let detector =
+            DriftDetector::new(&new_catalog, &yaml_contents).with_transitive_checksums(true);
+        let report = detector
+            .detect(std::slice::from_ref(&stored), date, date)
+            .unwrap();
+        let by_query = report.by_query();
+        let leaf = &by_query["leaf"];
+        assert_eq!(leaf[0].state, DriftState::UpstreamChanged);
+        assert_eq!(leaf[0].caused_by.as_deref(), Some("middle"));
+
+        let detector = DriftDetector::new(&new_catalog, &yaml_contents);
+        let report = detector.detect(&[stored], date, date).unwrap();
+        assert_eq!(report.by_query()["leaf"][0].state, DriftState::Current);
+    }
+
+    #[test]
+    fn test_transitive_checksum_unchanged_upstream_is_current() {
+        let yaml_contents = HashMap::new();
+        let date = NaiveDate::from_ymd_opt(2024, 1, 15).unwrap();
+        let downstream_sql = "SELECT * FROM test_dataset.upstream";
+        let catalog = vec![
+            create_managed_query("upstream", "SELECT * FROM raw.events"),
+            create_managed_query("downstream", downstream_sql),
+        ];
+
+        let detector = DriftDetector::new(&catalog, &yaml_contents).with_transitive_checksums(true);
+        let mut stored = create_stored_state("downstream", date, downstream_sql, "");
+        stored.upstream_checksums = detector.upstream_checksums(&catalog[1], date);
+
+        let report = detector.detect(&[stored], date, date).unwrap();
+        assert_eq!(
+            report.by_query()["downstream"][0].state,
+            DriftState::Current
+        );
+    }
+
+    #[test]
+    fn test_detect_steps_by_partition_type() {
+        use crate::schema::PartitionConfig;
+
+        let mut hourly = create_test_query("hourly", "SELECT * FROM source");
+        hourly.destination.partition = PartitionConfig::hour("ts");
+        let mut monthly = create_test_query("monthly", "SELECT * FROM source");
+        monthly.destination.partition = PartitionConfig::month("month");
+        let yaml_contents = HashMap::new();
+        let queries = vec![hourly, monthly];
+        let detector = DriftDetector::new(&queries, &yaml_contents);
+
+        let from = NaiveDate::from_ymd_opt(2024, 1, 15).unwrap();
+        let to = NaiveDate::from_ymd_opt(2024, 3, 2).unwrap();
+        let report = detector.detect(&[], from, to).unwrap();
+
+        let by_query = |name: &str| -> Vec<PartitionKey> {
+            report
+                .partitions
+                .iter()
+                .filter(|p| p.query_name == name)
+                .map(|p| p.partition_key)
+                .collect()
+        };
+        let hours = by_query("hourly");
+        assert_eq!(hours.len(), 48 * 24);
+        assert_eq!(
+            hours[1],
+            PartitionKey::Hour(from.and_hms_opt(1, 0, 0).unwrap())
+        );
+        assert_eq!(
+            by_query("monthly"),
+            vec![
+                PartitionKey::Month {
+                    year: 2024,
+                    month: 1
+                },
+                PartitionKey::Month {
+                    year: 2024,
+                    month: 2
+                },
+                PartitionKey::Month {
+                    year: 2024,
+                    month: 3
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_detect_matches_hourly_states_by_partition_key() {
+        let mut hourly = create_test_query("hourly", "SELECT * FROM source");
+        hourly.destination.partition = PartitionConfig::hour("ts");
+        let yaml_contents = HashMap::new();
+        let queries = vec![hourly];
+        let detector = DriftDetector::new(&queries, &yaml_contents);
+
+        let date = NaiveDate::from_ymd_opt(2024, 1, 15).unwrap();
+        let ten = PartitionKey::Hour(date.and_hms_opt(10, 0, 0).unwrap());
+        let mut stored = create_stored_state("hourly", date, "SELECT * FROM source", "");
+        stored.partition_key = Some(ten);
+        let legacy = create_stored_state("hourly", date, "SELECT * FROM source", "");
+
+        let report = detector.detect(&[stored], date, date).unwrap();
+        for drift in &report.partitions {
+            let expected = if drift.partition_key == ten {
+                DriftState::Current
+            } else {
+                DriftState::NeverRun
+            };
+            assert_eq!(drift.state, expected, "{}", drift.partition_key);
+        }
+
+        let report = detector.detect(&[legacy], date, date).unwrap();
+        assert!(report
+            .partitions
+            .iter()
+            .all(|p| p.state == DriftState::NeverRun));
+    }
+
+    #[test]
+    fn test_detect_skips_range_partitioned_query_with_warning() {
+        let mut ranged = create_test_query("ranged", "SELECT * FROM source");
+        ranged.destination.partition = PartitionConfig::range("bucket", 0, 100, 10);
+        let yaml_contents = HashMap::new();
+        let queries = vec![ranged, create_test_query("daily", "SELECT * FROM source")];
+        let detector = DriftDetector::new(&queries, &yaml_contents);
+
+        let date = NaiveDate::from_ymd_opt(2024, 1, 15).unwrap();
+        let report = detector.detect(&[], date, date).unwrap();
+        assert_eq!(report.partitions.len(), 1);
+        assert_eq!(report.partitions[0].query_name, "daily");
+        assert!(report
+            .warnings
+            .iter()
+            .any(|w| w.query_name == "ranged" && w.kind == DetectionWarningKind::RangePartitioned));
+    }
+
+    #[test]
+    fn test_detect_budgets_partition_cap_per_query() {
+        let mut hourly = create_test_query("hourly", "SELECT * FROM source");
+        hourly.destination.partition = PartitionConfig::hour("ts");
+        let yaml_contents = HashMap::new();
+        let queries = vec![hourly, create_test_query("daily", "SELECT * FROM source")];
+        let detector = DriftDetector::new(&queries, &yaml_contents);
+
+        let from = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let report = detector
+            .detect(&[], from, NaiveDate::from_ymd_opt(2024, 12, 31).unwrap())
+            .unwrap();
+        assert_eq!(report.partitions.len(), 366 + 366 * 24);
+
+        let report = detector
+            .detect(&[], from, from + chrono::Duration::days(MAX_DETECTION_DAYS))
+            .unwrap();
+        assert_eq!(report.partitions.len(), 0);
+        let skipped: Vec<_> = report
+            .warnings
+            .iter()
+            .map(|w| w.query_name.as_str())
+            .collect();
+        assert_eq!(skipped, vec!["daily", "hourly"]);
+        assert!(matches!(
+            report.warnings[1].kind,
+            DetectionWarningKind::TooManyPartitions { max, .. } if max == MAX_DETECTION_DAYS * 24
+        ));
+    }
 }
@@ -113,7 +113,7 @@ impl<'a> ImmutabilityChecker<'a> {
         }
 
         for ((version_num, revision_num), version_states) in states_by_version {
-            let Some(version) = query.versions.iter().find(|v| v.version == version_num) else {
+            let Some(version) = query.version(version_num) else {
                 continue;
             };
 
@@ -173,17 +173,22 @@ mod tests {
     fn create_test_query(name: &str, versions: Vec<VersionDef>) -> QueryDef {
         QueryDef {
             name: name.to_string(),
+            previous_names: vec![],
             destination: Destination {
+                project: None,
                 dataset: "test_dataset".to_string(),
                 table: "test_table".to_string(),
                 partition: PartitionConfig::day("date"),
                 cluster: None,
+                labels: Default::default(),
             },
             description: None,
             owner: None,
             tags: vec![],
             versions,
             cluster: None,
+            default_invariants: InvariantsDef::default(),
+            provenance: false,
         }
     }
 
@@ -196,6 +201,7 @@ mod tests {
             revisions: vec![],
             description: None,
             backfill_since: None,
+            blocked_reason: None,
             schema: Schema::default(),
             dependencies: HashSet::new(),
             invariants: InvariantsDef::default(),
@@ -219,6 +225,7 @@ mod tests {
             }],
             description: None,
             backfill_since: None,
+            blocked_reason: None,
             schema: Schema::default(),
             dependencies: HashSet::new(),
             invariants: InvariantsDef::default(),
@@ -235,14 +242,19 @@ mod tests {
         PartitionState {
             query_name: query_name.to_string(),
             partition_date,
+            partition_key: None,
             version,
             sql_revision: revision,
             effective_from: NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
             sql_checksum: "checksum".to_string(),
             schema_checksum: "schema".to_string(),
             yaml_checksum: "yaml".to_string(),
+            invariants_checksum: None,
+            cluster_checksum: None,
+            unordered_schema_checksum: None,
             executed_sql_b64: Some(compress_to_base64(executed_sql)),
             upstream_states: HashMap::new(),
+            upstream_checksums: HashMap::new(),
             executed_at: Utc::now(),
             execution_time_ms: Some(100),
             rows_written: Some(1000),
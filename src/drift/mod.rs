@@ -1,5 +1,7 @@
 mod audit;
+mod check;
 mod checksum;
+mod checksum_cache;
 mod detector;
 mod immutability;
 mod state;
@@ -8,7 +10,12 @@ pub use audit::{
     AuditTableRow, SourceAuditEntry, SourceAuditReport, SourceAuditSummary, SourceAuditor,
     SourceStatus,
 };
+pub use check::{run_drift_check, DriftCheckOutcome, StateStore};
 pub use checksum::{compress_to_base64, decompress_from_base64, Checksums, ExecutionArtifact};
-pub use detector::DriftDetector;
+pub use checksum_cache::{CacheConfig, ChecksumCache};
+pub use detector::{DriftDetector, StateTransition};
 pub use immutability::{ImmutabilityChecker, ImmutabilityReport, ImmutabilityViolation};
-pub use state::{DriftReport, DriftState, ExecutionStatus, PartitionDrift, PartitionState};
+pub use state::{
+    DetectionWarning, DetectionWarningKind, DriftReport, DriftState, ExecutionStatus,
+    PartitionDrift, PartitionState, PartitionStateBuilder, RerunPolicy,
+};
@@ -1,3 +1,4 @@
+use super::checksum::{compress_to_base64, Checksums};
 use crate::schema::PartitionKey;
 use chrono::{DateTime, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
@@ -7,14 +8,29 @@ use std::collections::HashMap;
 pub struct PartitionState {
     pub query_name: String,
     pub partition_date: NaiveDate,
+    /// The exact partition written. States recorded without one are
+    /// matched by `partition_date`, except on hourly tables.
+    #[serde(default)]
+    pub partition_key: Option<PartitionKey>,
     pub version: u32,
     pub sql_revision: Option<u32>,
     pub effective_from: NaiveDate,
     pub sql_checksum: String,
     pub schema_checksum: String,
     pub yaml_checksum: String,
+    #[serde(default)]
+    pub invariants_checksum: Option<String>,
+    #[serde(default)]
+    pub cluster_checksum: Option<String>,
+    /// `Checksums::unordered_schema`, compared instead of `schema_checksum`
+    /// when the detector ignores column order.
+    #[serde(default)]
+    pub unordered_schema_checksum: Option<String>,
     pub executed_sql_b64: Option<String>,
+    #[serde(default)]
     pub upstream_states: HashMap<String, DateTime<Utc>>,
+    #[serde(default)]
+    pub upstream_checksums: HashMap<String, String>,
     pub executed_at: DateTime<Utc>,
     pub execution_time_ms: Option<i64>,
     pub rows_written: Option<i64>,
@@ -22,6 +38,142 @@ pub struct PartitionState {
     pub status: ExecutionStatus,
 }
 
+impl PartitionState {
+    /// Start building a state record for a successful run of `query_name` at
+    /// `partition_date`, executed now, at version 1 effective from the
+    /// partition date, with no checksums or upstreams recorded.
+    pub fn builder(
+        query_name: impl Into<String>,
+        partition_date: NaiveDate,
+    ) -> PartitionStateBuilder {
+        PartitionStateBuilder {
+            state: PartitionState {
+                query_name: query_name.into(),
+                partition_date,
+                partition_key: None,
+                version: 1,
+                sql_revision: None,
+                effective_from: partition_date,
+                sql_checksum: String::new(),
+                schema_checksum: String::new(),
+                yaml_checksum: String::new(),
+                invariants_checksum: None,
+                cluster_checksum: None,
+                unordered_schema_checksum: None,
+                executed_sql_b64: None,
+                upstream_states: HashMap::new(),
+                upstream_checksums: HashMap::new(),
+                executed_at: Utc::now(),
+                execution_time_ms: None,
+                rows_written: None,
+                bytes_processed: None,
+                status: ExecutionStatus::Success,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PartitionStateBuilder {
+    state: PartitionState,
+}
+
+impl PartitionStateBuilder {
+    /// Record the exact partition written; `partition_date` follows it.
+    pub fn with_partition_key(mut self, key: PartitionKey) -> Self {
+        self.state.partition_date = key.to_naive_date();
+        self.state.partition_key = Some(key);
+        self
+    }
+
+    pub fn with_version(mut self, version: u32) -> Self {
+        self.state.version = version;
+        self
+    }
+
+    pub fn with_sql_revision(mut self, revision: u32) -> Self {
+        self.state.sql_revision = Some(revision);
+        self
+    }
+
+    pub fn with_effective_from(mut self, date: NaiveDate) -> Self {
+        self.state.effective_from = date;
+        self
+    }
+
+    pub fn with_checksums(mut self, checksums: Checksums) -> Self {
+        self.state.sql_checksum = checksums.sql;
+        self.state.schema_checksum = checksums.schema;
+        self.state.yaml_checksum = checksums.yaml;
+        self
+    }
+
+    pub fn with_invariants_checksum(mut self, checksum: impl Into<String>) -> Self {
+        self.state.invariants_checksum = Some(checksum.into());
+        self
+    }
+
+    pub fn with_cluster_checksum(mut self, checksum: impl Into<String>) -> Self {
+        self.state.cluster_checksum = Some(checksum.into());
+        self
+    }
+
+    pub fn with_unordered_schema_checksum(mut self, checksum: impl Into<String>) -> Self {
+        self.state.unordered_schema_checksum = Some(checksum.into());
+        self
+    }
+
+    pub fn with_executed_sql(mut self, sql: &str) -> Self {
+        self.state.executed_sql_b64 = Some(compress_to_base64(sql));
+        self
+    }
+
+    pub fn with_upstream_state(mut self, table: impl Into<String>, at: DateTime<Utc>) -> Self {
+        self.state.upstream_states.insert(table.into(), at);
+        self
+    }
+
+    pub fn with_upstream_checksum(
+        mut self,
+        query_name: impl Into<String>,
+        checksum: impl Into<String>,
+    ) -> Self {
+        self.state
+            .upstream_checksums
+            .insert(query_name.into(), checksum.into());
+        self
+    }
+
+    pub fn with_executed_at(mut self, at: DateTime<Utc>) -> Self {
+        self.state.executed_at = at;
+        self
+    }
+
+    pub fn with_execution_time_ms(mut self, ms: i64) -> Self {
+        self.state.execution_time_ms = Some(ms);
+        self
+    }
+
+    pub fn with_rows_written(mut self, rows: i64) -> Self {
+        self.state.rows_written = Some(rows);
+        self
+    }
+
+    pub fn with_bytes_processed(mut self, bytes: i64) -> Self {
+        self.state.bytes_processed = Some(bytes);
+        self
+    }
+
+    pub fn with_status(mut self, status: ExecutionStatus) -> Self {
+        self.state.status = status;
+        self
+    }
+
+    pub fn build(self) -> PartitionState {
+        self.state
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "UPPERCASE")]
 pub enum ExecutionStatus {
@@ -29,15 +181,51 @@ pub enum ExecutionStatus {
     Failed,
 }
 
+/// Decides which drift states are scheduled for a rerun.
+///
+/// `include_failed` controls whether partitions whose last stored run is
+/// `Failed` are rerun automatically (the default) or left for human review.
+/// A stored failure is only recorded after the runner's `RetryPolicy` has
+/// given up, so transient errors are already retried within the run; turn
+/// this off when a persisted failure should not trigger another attempt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RerunPolicy {
+    pub include_failed: bool,
+}
+
+impl RerunPolicy {
+    pub fn new() -> Self {
+        Self {
+            include_failed: true,
+        }
+    }
+
+    pub fn with_include_failed(mut self, include: bool) -> Self {
+        self.include_failed = include;
+        self
+    }
+}
+
+impl Default for RerunPolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum DriftState {
     Current,
     SqlChanged,
     SchemaChanged,
     VersionUpgraded,
+    VersionDowngraded,
     UpstreamChanged,
+    InvariantsChanged,
     NeverRun,
     Failed,
+    /// The version covering the partition is blocked; it is reported but
+    /// never scheduled, since the write would be refused.
+    Blocked,
 }
 
 impl DriftState {
@@ -47,14 +235,57 @@ impl DriftState {
             DriftState::SqlChanged => "sql_changed",
             DriftState::SchemaChanged => "schema_changed",
             DriftState::VersionUpgraded => "version_upgraded",
+            DriftState::VersionDowngraded => "version_downgraded",
             DriftState::UpstreamChanged => "upstream_changed",
+            DriftState::InvariantsChanged => "invariants_changed",
             DriftState::NeverRun => "never_run",
             DriftState::Failed => "failed",
+            DriftState::Blocked => "blocked",
         }
     }
 
     pub fn needs_rerun(&self) -> bool {
-        !matches!(self, DriftState::Current)
+        !matches!(
+            self,
+            DriftState::Current | DriftState::InvariantsChanged | DriftState::Blocked
+        )
+    }
+
+    /// Only the after-invariants need re-checking against existing data.
+    pub fn needs_revalidation(&self) -> bool {
+        matches!(self, DriftState::InvariantsChanged)
+    }
+
+    pub fn needs_rerun_with(&self, policy: &RerunPolicy) -> bool {
+        match self {
+            DriftState::Failed => policy.include_failed,
+            _ => self.needs_rerun(),
+        }
+    }
+
+    pub const ALL: [DriftState; 10] = [
+        DriftState::Current,
+        DriftState::SqlChanged,
+        DriftState::SchemaChanged,
+        DriftState::VersionUpgraded,
+        DriftState::VersionDowngraded,
+        DriftState::UpstreamChanged,
+        DriftState::InvariantsChanged,
+        DriftState::NeverRun,
+        DriftState::Failed,
+        DriftState::Blocked,
+    ];
+
+    fn ansi_color(&self) -> &'static str {
+        match self {
+            DriftState::Current => "\x1b[32m",
+            DriftState::SqlChanged | DriftState::SchemaChanged | DriftState::Failed => "\x1b[31m",
+            DriftState::VersionUpgraded | DriftState::VersionDowngraded => "\x1b[33m",
+            DriftState::UpstreamChanged => "\x1b[35m",
+            DriftState::InvariantsChanged => "\x1b[34m",
+            DriftState::NeverRun => "\x1b[36m",
+            DriftState::Blocked => "\x1b[90m",
+        }
     }
 }
 
@@ -76,9 +307,90 @@ impl PartitionDrift {
     }
 }
 
+/// A non-fatal caveat about a drift report: detection completed, but some
+/// of its results may be less trustworthy than they look.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DetectionWarning {
+    pub query_name: String,
+    #[serde(flatten)]
+    pub kind: DetectionWarningKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum DetectionWarningKind {
+    /// No yaml content was supplied for the query, so its checksums were
+    /// computed without it.
+    MissingYamlContent,
+    /// A version reads from a table in a managed dataset that no query in
+    /// the catalog produces.
+    UnresolvedDependency { version: u32, table: String },
+    /// Partitions flagged for rerun predate the active `backfill_since`.
+    BeforeBackfillSince {
+        version: u32,
+        backfill_since: NaiveDate,
+        partitions: usize,
+    },
+    /// The query is range-partitioned, so it has no partitions by date and
+    /// was not checked.
+    RangePartitioned,
+    /// The date range covers more of the query's partitions than one
+    /// detection checks, so it was not checked.
+    TooManyPartitions { partitions: i64, max: i64 },
+}
+
+impl DetectionWarningKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DetectionWarningKind::MissingYamlContent => "missing_yaml_content",
+            DetectionWarningKind::UnresolvedDependency { .. } => "unresolved_dependency",
+            DetectionWarningKind::BeforeBackfillSince { .. } => "before_backfill_since",
+            DetectionWarningKind::RangePartitioned => "range_partitioned",
+            DetectionWarningKind::TooManyPartitions { .. } => "too_many_partitions",
+        }
+    }
+}
+
+impl std::fmt::Display for DetectionWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.kind {
+            DetectionWarningKind::MissingYamlContent => write!(
+                f,
+                "{}: no yaml content; checksums may not match recorded runs",
+                self.query_name
+            ),
+            DetectionWarningKind::UnresolvedDependency { version, table } => write!(
+                f,
+                "{} v{}: dependency '{}' has no producing query",
+                self.query_name, version, table
+            ),
+            DetectionWarningKind::BeforeBackfillSince {
+                version,
+                backfill_since,
+                partitions,
+            } => write!(
+                f,
+                "{} v{}: {} drifted partition(s) predate backfill_since {}",
+                self.query_name, version, partitions, backfill_since
+            ),
+            DetectionWarningKind::RangePartitioned => write!(
+                f,
+                "{}: range-partitioned, skipped by date-based drift detection",
+                self.query_name
+            ),
+            DetectionWarningKind::TooManyPartitions { partitions, max } => write!(
+                f,
+                "{}: {} partitions in range exceeds maximum of {}, skipped",
+                self.query_name, partitions, max
+            ),
+        }
+    }
+}
+
 #[derive(Debug, Default)]
 pub struct DriftReport {
     pub partitions: Vec<PartitionDrift>,
+    pub warnings: Vec<DetectionWarning>,
 }
 
 impl DriftReport {
@@ -89,6 +401,7 @@ impl DriftReport {
     pub fn with_capacity(capacity: usize) -> Self {
         Self {
             partitions: Vec::with_capacity(capacity),
+            warnings: Vec::new(),
         }
     }
 
@@ -113,12 +426,45 @@ impl DriftReport {
     }
 
     pub fn needs_rerun(&self) -> Vec<&PartitionDrift> {
+        self.needs_rerun_with(&RerunPolicy::default())
+    }
+
+    pub fn needs_rerun_with(&self, policy: &RerunPolicy) -> Vec<&PartitionDrift> {
         self.partitions
             .iter()
-            .filter(|p| p.state.needs_rerun())
+            .filter(|p| p.state.needs_rerun_with(policy))
             .collect()
     }
 
+    /// Contiguous runs of `NeverRun`/`Failed` partitions per query, as
+    /// inclusive `(query_name, from, to)` ranges ordered by query and start.
+    /// Each range can be passed straight to `Runner::backfill_partitions`.
+    pub fn gaps(&self) -> Vec<(String, PartitionKey, PartitionKey)> {
+        let mut holes: Vec<&PartitionDrift> = self
+            .partitions
+            .iter()
+            .filter(|p| matches!(p.state, DriftState::NeverRun | DriftState::Failed))
+            .collect();
+        holes.sort_by(|a, b| {
+            a.query_name
+                .cmp(&b.query_name)
+                .then_with(|| a.partition_key.cmp(&b.partition_key))
+        });
+
+        let mut gaps: Vec<(String, PartitionKey, PartitionKey)> = Vec::new();
+        for p in holes {
+            match gaps.last_mut() {
+                Some((query, _, end))
+                    if *query == p.query_name && end.next() == p.partition_key =>
+                {
+                    *end = p.partition_key;
+                }
+                _ => gaps.push((p.query_name.clone(), p.partition_key, p.partition_key)),
+            }
+        }
+        gaps
+    }
+
     pub fn is_current(&self) -> bool {
         self.partitions
             .iter()
@@ -132,4 +478,132 @@ impl DriftReport {
         }
         counts
     }
+
+    /// Render the report as an aligned table grouped by query, followed by a
+    /// per-state count footer. `color` wraps states in ANSI color codes.
+    pub fn render_table(&self, color: bool) -> String {
+        self.render(None, color)
+    }
+
+    /// Like `render_table`, but shows at most `per_query_limit` partitions per
+    /// query (drifted ones first) and summarizes the rest in an ellipsis row.
+    /// The footer still counts every partition in the report.
+    pub fn render_table_capped(&self, per_query_limit: usize, color: bool) -> String {
+        self.render(Some(per_query_limit), color)
+    }
+
+    fn render(&self, per_query_limit: Option<usize>, color: bool) -> String {
+        const HEADERS: [&str; 5] = ["QUERY", "PARTITION", "STATE", "VERSION", "CAUSED BY"];
+
+        let mut by_query: Vec<(&str, Vec<&PartitionDrift>)> = self.by_query().into_iter().collect();
+        by_query.sort_by(|a, b| a.0.cmp(b.0));
+
+        let mut rows: Vec<Option<([String; 5], Option<DriftState>)>> = Vec::new();
+        for (query_name, mut partitions) in by_query {
+            let mut hidden = 0;
+            if let Some(limit) = per_query_limit {
+                if partitions.len() > limit {
+                    partitions.sort_by_key(|p| (!p.state.needs_rerun(), p.partition_key));
+                    hidden = partitions.len() - limit;
+                    partitions.truncate(limit);
+                }
+            }
+            partitions.sort_by_key(|p| p.partition_key);
+
+            if !rows.is_empty() {
+                rows.push(None);
+            }
+            for (i, p) in partitions.iter().enumerate() {
+                let version = match p.executed_version {
+                    Some(executed) if executed != p.current_version => {
+                        format!("v{} -> v{}", executed, p.current_version)
+                    }
+                    _ => format!("v{}", p.current_version),
+                };
+                let cells = [
+                    if i == 0 {
+                        query_name.to_string()
+                    } else {
+                        String::new()
+                    },
+                    p.partition_key.to_string(),
+                    p.state.as_str().to_string(),
+                    version,
+                    p.caused_by.clone().unwrap_or_default(),
+                ];
+                rows.push(Some((cells, Some(p.state))));
+            }
+            if hidden > 0 {
+                let cells = [
+                    if partitions.is_empty() {
+                        query_name.to_string()
+                    } else {
+                        String::new()
+                    },
+                    format!("... {} more", hidden),
+                    String::new(),
+                    String::new(),
+                    String::new(),
+                ];
+                rows.push(Some((cells, None)));
+            }
+        }
+
+        let mut widths = HEADERS.map(str::len);
+        for (cells, _) in rows.iter().flatten() {
+            for (width, cell) in widths.iter_mut().zip(cells) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+
+        let format_row = |cells: &[String], state: Option<DriftState>| -> String {
+            let mut line = String::new();
+            for (i, (cell, width)) in cells.iter().zip(widths).enumerate() {
+                if i > 0 {
+                    line.push_str("  ");
+                }
+                let padded = format!("{:<width$}", cell, width = width);
+                match state {
+                    Some(s) if color && i == 2 => {
+                        line.push_str(&format!("{}{}\x1b[0m", s.ansi_color(), padded))
+                    }
+                    _ => line.push_str(&padded),
+                }
+            }
+            line.trim_end().to_string()
+        };
+
+        let mut output = format_row(&HEADERS.map(String::from), None);
+        output.push('\n');
+        for row in &rows {
+            if let Some((cells, state)) = row {
+                output.push_str(&format_row(cells, *state));
+            }
+            output.push('\n');
+        }
+
+        let summary = self.summary();
+        let counts: Vec<String> = DriftState::ALL
+            .iter()
+            .filter_map(|state| {
+                summary.get(state).map(|count| {
+                    if color {
+                        format!("{}{} {}\x1b[0m", state.ansi_color(), count, state.as_str())
+                    } else {
+                        format!("{} {}", count, state.as_str())
+                    }
+                })
+            })
+            .collect();
+        output.push_str(&format!(
+            "\n{} partitions: {}",
+            self.partitions.len(),
+            if counts.is_empty() {
+                "none".to_string()
+            } else {
+                counts.join(", ")
+            }
+        ));
+        output
+    }
 }
@@ -1,24 +1,49 @@
+use chrono::NaiveDate;
 use once_cell::sync::Lazy;
 use regex::Regex;
 use sqlparser::ast::{
-    Expr, FunctionArg, FunctionArgExpr, Query, Select, SelectItem, SetExpr, Statement, TableFactor,
-    TableWithJoins,
+    Expr, FunctionArg, FunctionArgExpr, ObjectName, Query, Select, SelectItem, SetExpr, Statement,
+    TableFactor, TableWithJoins,
 };
 use sqlparser::dialect::BigQueryDialect;
 use sqlparser::parser::Parser;
-use std::collections::HashSet;
+use std::collections::{BTreeSet, HashSet};
+
+use super::parser::QueryDef;
+use crate::error::{BqDriftError, Result};
 
 static TABLE_PATTERN: Lazy<Regex> = Lazy::new(|| {
     Regex::new(
-        r"(?i)\b(?:FROM|JOIN|INTO|UPDATE|MERGE\s+INTO)\s+`?([a-zA-Z_][a-zA-Z0-9_]*(?:\.[a-zA-Z_][a-zA-Z0-9_]*)*)`?"
+        r"(?i)\b(?:FROM|JOIN|INTO|UPDATE|MERGE\s+INTO)\s+(`?[a-zA-Z_][a-zA-Z0-9_-]*`?(?:\.`?[a-zA-Z0-9_-]+`?)*)"
     ).expect("table pattern regex is valid")
 });
 
 static DIALECT: BigQueryDialect = BigQueryDialect {};
 
+/// Function namespaces built into BigQuery, whose dotted calls are not
+/// persistent UDFs.
+const BUILTIN_FUNCTION_PREFIXES: &[&str] = &[
+    "safe",
+    "net",
+    "keys",
+    "aead",
+    "deterministic_encrypt",
+    "hll_count",
+    "kll_quantiles",
+    "approx_quantiles",
+    "ml",
+    "vector_search",
+];
+
+/// What a query reads. `tables` holds real tables only: names bound by a
+/// `WITH` clause in scope are recorded in `ctes` instead, and dotted calls
+/// to persistent UDFs or table functions in `routines`. Backtick quoting is
+/// stripped from every name.
 #[derive(Debug, Clone, Default)]
 pub struct SqlDependencies {
     pub tables: HashSet<String>,
+    pub ctes: HashSet<String>,
+    pub routines: HashSet<String>,
 }
 
 impl SqlDependencies {
@@ -41,54 +66,52 @@ impl SqlDependencies {
     }
 
     fn extract_from_statement(&mut self, statement: &Statement) {
+        let scope = HashSet::new();
         match statement {
             Statement::Query(query) => {
-                self.extract_from_query(query);
+                self.extract_from_query(query, &scope);
             }
             Statement::Insert(insert) => {
                 // Extract source from INSERT ... SELECT
                 if let Some(source) = &insert.source {
-                    self.extract_from_query(source);
+                    self.extract_from_query(source, &scope);
                 }
             }
             Statement::CreateTable(create) => {
                 // Extract from CREATE TABLE ... AS SELECT
                 if let Some(query) = &create.query {
-                    self.extract_from_query(query);
+                    self.extract_from_query(query, &scope);
                 }
             }
             Statement::CreateView { query, .. } => {
-                self.extract_from_query(query);
+                self.extract_from_query(query, &scope);
             }
             Statement::Merge { source, .. } => {
-                self.extract_from_table_factor(source);
+                self.extract_from_table_factor(source, &scope);
             }
             _ => {}
         }
     }
 
-    fn extract_from_query(&mut self, query: &Query) {
-        // Handle CTEs (WITH clause)
-        let cte_names: HashSet<String> = query
-            .with
-            .as_ref()
-            .map(|w| {
-                w.cte_tables
-                    .iter()
-                    .map(|cte| cte.alias.name.value.clone())
-                    .collect()
-            })
-            .unwrap_or_default();
+    /// `cte_names` holds the lowercased CTE names visible from enclosing
+    /// queries. Each CTE can see the ones defined before it, and itself
+    /// under `WITH RECURSIVE`.
+    fn extract_from_query(&mut self, query: &Query, cte_names: &HashSet<String>) {
+        let mut scope = cte_names.clone();
 
-        // Extract from CTEs themselves
         if let Some(with) = &query.with {
             for cte in &with.cte_tables {
-                self.extract_from_query(&cte.query);
+                let name = cte.alias.name.value.to_lowercase();
+                if with.recursive {
+                    scope.insert(name.clone());
+                }
+                self.extract_from_query(&cte.query, &scope);
+                scope.insert(name);
+                self.ctes.insert(cte.alias.name.value.clone());
             }
         }
 
-        // Extract from main query body
-        self.extract_from_set_expr(&query.body, &cte_names);
+        self.extract_from_set_expr(&query.body, &scope);
     }
 
     fn extract_from_set_expr(&mut self, set_expr: &SetExpr, cte_names: &HashSet<String>) {
@@ -97,7 +120,7 @@ impl SqlDependencies {
                 self.extract_from_select(select, cte_names);
             }
             SetExpr::Query(query) => {
-                self.extract_from_query(query);
+                self.extract_from_query(query, cte_names);
             }
             SetExpr::SetOperation { left, right, .. } => {
                 self.extract_from_set_expr(left, cte_names);
@@ -110,10 +133,10 @@ impl SqlDependencies {
                 let name = table
                     .table_name
                     .as_ref()
-                    .map(|n| n.to_string())
+                    .map(|n| n.replace('`', ""))
                     .unwrap_or_default();
-                if !name.is_empty() && !cte_names.contains(&name) {
-                    self.tables.insert(name);
+                if !name.is_empty() {
+                    self.add_table(name, cte_names);
                 }
             }
         }
@@ -148,32 +171,29 @@ impl SqlDependencies {
         table_with_joins: &TableWithJoins,
         cte_names: &HashSet<String>,
     ) {
-        self.extract_from_table_factor_with_ctes(&table_with_joins.relation, cte_names);
+        self.extract_from_table_factor(&table_with_joins.relation, cte_names);
 
         for join in &table_with_joins.joins {
-            self.extract_from_table_factor_with_ctes(&join.relation, cte_names);
+            self.extract_from_table_factor(&join.relation, cte_names);
         }
     }
 
-    fn extract_from_table_factor(&mut self, table_factor: &TableFactor) {
-        self.extract_from_table_factor_with_ctes(table_factor, &HashSet::new());
-    }
-
-    fn extract_from_table_factor_with_ctes(
+    fn extract_from_table_factor(
         &mut self,
         table_factor: &TableFactor,
         cte_names: &HashSet<String>,
     ) {
         match table_factor {
-            TableFactor::Table { name, .. } => {
-                let table_name = name.to_string();
-                // Don't add CTEs as dependencies
-                if !cte_names.contains(&table_name) {
-                    self.tables.insert(table_name);
+            TableFactor::Table { name, args, .. } => {
+                if args.is_some() {
+                    // `dataset.fn(...)` in FROM is a table function
+                    self.routines.insert(object_name(name));
+                } else {
+                    self.add_table(object_name(name), cte_names);
                 }
             }
             TableFactor::Derived { subquery, .. } => {
-                self.extract_from_query(subquery);
+                self.extract_from_query(subquery, cte_names);
             }
             TableFactor::TableFunction { .. } => {}
             TableFactor::UNNEST { .. } => {}
@@ -183,30 +203,39 @@ impl SqlDependencies {
                 self.extract_from_table_with_joins(table_with_joins, cte_names);
             }
             TableFactor::Pivot { table, .. } => {
-                self.extract_from_table_factor_with_ctes(table, cte_names);
+                self.extract_from_table_factor(table, cte_names);
             }
             TableFactor::Unpivot { table, .. } => {
-                self.extract_from_table_factor_with_ctes(table, cte_names);
+                self.extract_from_table_factor(table, cte_names);
             }
             TableFactor::Function { .. } => {}
             TableFactor::JsonTable { .. } => {}
             TableFactor::MatchRecognize { table, .. } => {
-                self.extract_from_table_factor_with_ctes(table, cte_names);
+                self.extract_from_table_factor(table, cte_names);
             }
             _ => {}
         }
     }
 
+    fn add_table(&mut self, name: String, cte_names: &HashSet<String>) {
+        if cte_names.contains(&name.to_lowercase()) {
+            self.ctes.insert(name);
+        } else {
+            self.tables.insert(name);
+        }
+    }
+
     fn extract_from_expr(&mut self, expr: &Expr, cte_names: &HashSet<String>) {
         match expr {
             Expr::Subquery(query) => {
-                self.extract_from_query(query);
+                self.extract_from_query(query, cte_names);
             }
-            Expr::InSubquery { subquery, .. } => {
-                self.extract_from_query(subquery);
+            Expr::InSubquery { expr, subquery, .. } => {
+                self.extract_from_expr(expr, cte_names);
+                self.extract_from_query(subquery, cte_names);
             }
             Expr::Exists { subquery, .. } => {
-                self.extract_from_query(subquery);
+                self.extract_from_query(subquery, cte_names);
             }
             Expr::BinaryOp { left, right, .. } => {
                 self.extract_from_expr(left, cte_names);
@@ -243,6 +272,12 @@ impl SqlDependencies {
                 }
             }
             Expr::Function(func) => {
+                let name = object_name(&func.name);
+                if let Some((prefix, _)) = name.split_once('.') {
+                    if !BUILTIN_FUNCTION_PREFIXES.contains(&prefix.to_lowercase().as_str()) {
+                        self.routines.insert(name);
+                    }
+                }
                 if let sqlparser::ast::FunctionArguments::List(arg_list) = &func.args {
                     for arg in &arg_list.args {
                         if let FunctionArg::Unnamed(FunctionArgExpr::Expr(e)) = arg {
@@ -261,7 +296,7 @@ impl SqlDependencies {
     fn extract_fallback(&mut self, sql: &str) {
         for cap in TABLE_PATTERN.captures_iter(sql) {
             if let Some(table) = cap.get(1) {
-                let table_name = table.as_str();
+                let table_name = table.as_str().replace('`', "");
                 if !["SELECT", "WHERE", "AND", "OR", "ON", "AS", "SET"]
                     .contains(&table_name.to_uppercase().as_str())
                 {
@@ -278,6 +313,218 @@ impl SqlDependencies {
                 .iter()
                 .any(|t| t.ends_with(&format!(".{}", table)))
     }
+
+    /// Splits dependencies into managed tables (produced by a query in `catalog`)
+    /// and external sources. Returns `(managed, external)`.
+    pub fn classify(&self, catalog: &[QueryDef]) -> (HashSet<String>, HashSet<String>) {
+        self.tables
+            .iter()
+            .cloned()
+            .partition(|table| Self::find_producer(table, catalog).is_some())
+    }
+
+    /// Finds the query in `catalog` whose destination is `table`, accepting
+    /// `dataset.table` as well as project-qualified and backtick-quoted names.
+    /// A project on both sides must agree; a missing one matches any, since
+    /// the default project isn't known here.
+    pub fn find_producer<'q>(table: &str, catalog: &'q [QueryDef]) -> Option<&'q QueryDef> {
+        Self::find_producer_index(table, catalog, None).map(|idx| &catalog[idx])
+    }
+
+    /// Like `find_producer`, reading a missing project on either side as
+    /// `default_project`, so `other-proj.analytics.events` doesn't match a
+    /// destination that lives in the default project.
+    pub fn find_producer_in<'q>(
+        table: &str,
+        catalog: &'q [QueryDef],
+        default_project: &str,
+    ) -> Option<&'q QueryDef> {
+        Self::find_producer_index(table, catalog, Some(default_project)).map(|idx| &catalog[idx])
+    }
+
+    fn find_producer_index(
+        table: &str,
+        catalog: &[QueryDef],
+        default_project: Option<&str>,
+    ) -> Option<usize> {
+        let normalized = Self::normalize_table(table);
+        let (project, dataset_table) = split_project(&normalized);
+        let default_project = default_project.map(str::to_lowercase);
+        let project = project
+            .map(str::to_string)
+            .or_else(|| default_project.clone());
+        catalog.iter().position(|q| {
+            let dest = format!("{}.{}", q.destination.dataset, q.destination.table).to_lowercase();
+            let dest_project = q
+                .destination
+                .project
+                .as_deref()
+                .map(str::to_lowercase)
+                .or_else(|| default_project.clone());
+            let same_project = match (&project, &dest_project) {
+                (Some(project), Some(dest_project)) => project == dest_project,
+                _ => true,
+            };
+            dataset_table == dest && same_project
+        })
+    }
+
+    /// `table` lowercased with backtick quoting removed, so
+    /// `` `My-Proj.analytics.Events` ``, `` `my-proj`.analytics.events `` and
+    /// `my-proj.analytics.events` compare equal.
+    pub fn normalize_table(table: &str) -> String {
+        table.replace('`', "").to_lowercase()
+    }
+
+    /// The `dataset.table` part of a normalized name, dropping any project.
+    pub fn without_project(table: &str) -> String {
+        split_project(&Self::normalize_table(table)).1.to_string()
+    }
+}
+
+/// `(project, dataset.table)`; names with fewer than three parts have no
+/// project.
+fn split_project(normalized: &str) -> (Option<&str>, &str) {
+    match normalized.rmatch_indices('.').nth(1) {
+        Some((idx, _)) => (Some(&normalized[..idx]), &normalized[idx + 1..]),
+        None => (None, normalized),
+    }
+}
+
+/// Dotted name with backtick quoting removed, so `` `p.d.t` ``, `` `p`.d.t ``
+/// and `p.d.t` all read `p.d.t`.
+fn object_name(name: &ObjectName) -> String {
+    name.0
+        .iter()
+        .map(|ident| ident.value.as_str())
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// Indexes into `queries` grouped so that every query's producers sit in an
+/// earlier group, each group in index order. Dependencies of every version
+/// and revision count; a query reading its own destination is not an edge.
+pub(crate) fn dependency_levels(queries: &[QueryDef]) -> Result<Vec<Vec<usize>>> {
+    levels_by(queries, None, |query| {
+        query
+            .versions
+            .iter()
+            .flat_map(|v| {
+                v.dependencies
+                    .iter()
+                    .chain(v.revisions.iter().flat_map(|r| r.dependencies.iter()))
+            })
+            .collect()
+    })
+}
+
+/// Like `dependency_levels`, counting only the dependencies of the version
+/// active for `partition_date` and its revision active on `as_of`, so that
+/// an edge reversed between versions isn't reported as a cycle. Queries
+/// with no version for the partition have no edges. Unqualified names on
+/// either side are read as `default_project` when it is known.
+pub(crate) fn dependency_levels_for(
+    queries: &[QueryDef],
+    partition_date: NaiveDate,
+    as_of: NaiveDate,
+    default_project: Option<&str>,
+) -> Result<Vec<Vec<usize>>> {
+    levels_of(
+        queries,
+        &upstreams_for(queries, partition_date, as_of, default_project),
+    )
+}
+
+/// For each query, the indexes of the queries it reads from when writing
+/// `partition_date` on `as_of`; the edges `dependency_levels_for` orders by.
+pub(crate) fn upstreams_for(
+    queries: &[QueryDef],
+    partition_date: NaiveDate,
+    as_of: NaiveDate,
+    default_project: Option<&str>,
+) -> Vec<BTreeSet<usize>> {
+    upstreams_by(queries, default_project, |query| {
+        query
+            .get_version_for_date(partition_date)
+            .map(|v| v.dependencies_for_date(as_of).iter().collect())
+            .unwrap_or_default()
+    })
+}
+
+fn levels_by<'q>(
+    queries: &'q [QueryDef],
+    default_project: Option<&str>,
+    tables: impl Fn(&'q QueryDef) -> Vec<&'q String>,
+) -> Result<Vec<Vec<usize>>> {
+    levels_of(queries, &upstreams_by(queries, default_project, tables))
+}
+
+fn upstreams_by<'q>(
+    queries: &'q [QueryDef],
+    default_project: Option<&str>,
+    tables: impl Fn(&'q QueryDef) -> Vec<&'q String>,
+) -> Vec<BTreeSet<usize>> {
+    queries
+        .iter()
+        .enumerate()
+        .map(|(idx, query)| {
+            tables(query)
+                .into_iter()
+                .filter_map(|table| {
+                    SqlDependencies::find_producer_index(table, queries, default_project)
+                })
+                .filter(|&upstream| upstream != idx)
+                .collect()
+        })
+        .collect()
+}
+
+fn levels_of(queries: &[QueryDef], upstreams: &[BTreeSet<usize>]) -> Result<Vec<Vec<usize>>> {
+    let mut placed = vec![false; queries.len()];
+    let mut levels = Vec::new();
+    let mut remaining = queries.len();
+    while remaining > 0 {
+        let level: Vec<usize> = (0..queries.len())
+            .filter(|&i| !placed[i] && upstreams[i].iter().all(|&u| placed[u]))
+            .collect();
+        if level.is_empty() {
+            return Err(cycle_error(queries, upstreams, &placed));
+        }
+        for &i in &level {
+            placed[i] = true;
+        }
+        remaining -= level.len();
+        levels.push(level);
+    }
+    Ok(levels)
+}
+
+/// Every unplaced query has an unplaced producer, so following producers
+/// from any of them must eventually revisit a query.
+fn cycle_error(
+    queries: &[QueryDef],
+    upstreams: &[BTreeSet<usize>],
+    placed: &[bool],
+) -> BqDriftError {
+    let mut path: Vec<usize> = Vec::new();
+    let mut current = placed.iter().position(|p| !p);
+    while let Some(idx) = current {
+        if let Some(pos) = path.iter().position(|&p| p == idx) {
+            let mut cycle: Vec<&str> = path[pos..]
+                .iter()
+                .rev()
+                .map(|&i| queries[i].name.as_str())
+                .collect();
+            cycle.push(cycle[0]);
+            return BqDriftError::Validation(format!(
+                "Dependency cycle between queries: {}",
+                cycle.join(" -> ")
+            ));
+        }
+        path.push(idx);
+        current = upstreams[idx].iter().copied().find(|&u| !placed[u]);
+    }
+    BqDriftError::Validation("Dependency cycle between queries".to_string())
 }
 
 #[cfg(test)]
@@ -384,6 +631,172 @@ mod tests {
             .any(|t| t.contains("project") && t.contains("dataset") && t.contains("table")));
     }
 
+    #[test]
+    fn test_backticks_are_stripped_from_names() {
+        let sql = r#"
+            SELECT *
+            FROM `my-project.raw.events` e
+            JOIN `my-project`.raw.`users` u ON e.user_id = u.id
+        "#;
+        let deps = SqlDependencies::extract(sql);
+        let mut tables: Vec<_> = deps.tables.iter().map(String::as_str).collect();
+        tables.sort();
+        assert_eq!(
+            tables,
+            vec!["my-project.raw.events", "my-project.raw.users"]
+        );
+    }
+
+    #[test]
+    fn test_ctes_are_scoped_across_nesting() {
+        let sql = r#"
+            WITH
+                base AS (SELECT * FROM `raw.events`),
+                daily AS (
+                    WITH inner_cte AS (SELECT * FROM base)
+                    SELECT * FROM inner_cte JOIN raw.users USING (user_id)
+                )
+            SELECT *
+            FROM (SELECT * FROM daily)
+            WHERE user_id IN (SELECT user_id FROM base)
+        "#;
+        let deps = SqlDependencies::extract(sql);
+        let mut tables: Vec<_> = deps.tables.iter().map(String::as_str).collect();
+        tables.sort();
+        assert_eq!(tables, vec!["raw.events", "raw.users"]);
+        assert!(deps.ctes.contains("base"));
+        assert!(deps.ctes.contains("daily"));
+        assert!(deps.ctes.contains("inner_cte"));
+    }
+
+    #[test]
+    fn test_recursive_cte_self_reference_is_not_a_table() {
+        let sql = r#"
+            WITH RECURSIVE tree AS (
+                SELECT id, parent_id FROM raw.nodes WHERE parent_id IS NULL
+                UNION ALL
+                SELECT n.id, n.parent_id FROM raw.nodes n JOIN tree t ON n.parent_id = t.id
+            )
+            SELECT * FROM tree
+        "#;
+        let deps = SqlDependencies::extract(sql);
+        assert_eq!(deps.tables.len(), 1);
+        assert!(deps.tables.contains("raw.nodes"));
+        assert!(deps.ctes.contains("tree"));
+    }
+
+    #[test]
+    fn test_routines_are_separate_from_tables() {
+        let sql = r#"
+            SELECT
+                udfs.normalize_email(email) AS email,
+                `my-project.udfs.parse_ua`(user_agent) AS ua,
+                SAFE.PARSE_DATE('%Y%m%d', day) AS day,
+                UPPER(country) AS country
+            FROM raw.events
+        "#;
+        let deps = SqlDependencies::extract(sql);
+        assert_eq!(deps.tables.len(), 1);
+        assert!(deps.tables.contains("raw.events"));
+        let mut routines: Vec<_> = deps.routines.iter().map(String::as_str).collect();
+        routines.sort();
+        assert_eq!(
+            routines,
+            vec!["my-project.udfs.parse_ua", "udfs.normalize_email"]
+        );
+    }
+
+    #[test]
+    fn test_hyphenated_project_ids() {
+        let deps = SqlDependencies::extract(
+            "SELECT * FROM my-proj.analytics.events e JOIN `my-proj`.analytics.users u USING (id)",
+        );
+        let mut tables: Vec<_> = deps.tables.iter().map(String::as_str).collect();
+        tables.sort();
+        assert_eq!(
+            tables,
+            vec!["my-proj.analytics.events", "my-proj.analytics.users"]
+        );
+
+        // Digits after a hyphen are outside what the parser accepts unquoted,
+        // so this goes through the regex fallback.
+        let deps = SqlDependencies::extract(
+            "SELECT * FROM my-proj-123.analytics.events WHERE id IN (SELECT id FROM `data-456.raw`.users)",
+        );
+        let mut tables: Vec<_> = deps.tables.iter().map(String::as_str).collect();
+        tables.sort();
+        assert_eq!(
+            tables,
+            vec!["data-456.raw.users", "my-proj-123.analytics.events"]
+        );
+    }
+
+    #[test]
+    fn test_normalize_table() {
+        for name in [
+            "`My-Proj.analytics.Events`",
+            "`my-proj`.analytics.events",
+            "my-proj.`analytics`.`events`",
+        ] {
+            assert_eq!(
+                SqlDependencies::normalize_table(name),
+                "my-proj.analytics.events"
+            );
+            assert_eq!(SqlDependencies::without_project(name), "analytics.events");
+        }
+        assert_eq!(
+            SqlDependencies::without_project("`analytics.events`"),
+            "analytics.events"
+        );
+        assert_eq!(
+            SqlDependencies::without_project("org.example:my-proj.analytics.events"),
+            "analytics.events"
+        );
+    }
+
+    #[test]
+    fn test_find_producer_across_name_forms() {
+        let mut pinned = catalog_query("analytics", "events");
+        pinned.destination.project = Some("my-proj".to_string());
+        let catalog = vec![pinned, catalog_query("analytics", "users")];
+
+        for table in [
+            "analytics.events",
+            "`my-proj.analytics.events`",
+            "my-proj.Analytics.Events",
+        ] {
+            assert_eq!(
+                SqlDependencies::find_producer(table, &catalog).map(|q| q.name.as_str()),
+                Some("events"),
+                "{}",
+                table
+            );
+        }
+        assert!(SqlDependencies::find_producer("other-proj.analytics.events", &catalog).is_none());
+        assert_eq!(
+            SqlDependencies::find_producer("`any-proj-1.analytics.users`", &catalog)
+                .map(|q| q.name.as_str()),
+            Some("users")
+        );
+    }
+
+    #[test]
+    fn test_find_producer_in_uses_default_project() {
+        let mut pinned = catalog_query("analytics", "events");
+        pinned.destination.project = Some("my-proj".to_string());
+        let catalog = vec![pinned, catalog_query("analytics", "users")];
+        let find = |table| {
+            SqlDependencies::find_producer_in(table, &catalog, "Default-Proj")
+                .map(|q| q.name.as_str())
+        };
+
+        assert_eq!(find("`default-proj.analytics.users`"), Some("users"));
+        assert_eq!(find("analytics.users"), Some("users"));
+        assert_eq!(find("other-proj.analytics.users"), None);
+        assert_eq!(find("my-proj.analytics.events"), Some("events"));
+        assert_eq!(find("analytics.events"), None);
+    }
+
     #[test]
     fn test_has_dependency() {
         let sql = "SELECT * FROM analytics.daily_stats";
@@ -391,4 +804,109 @@ mod tests {
         assert!(deps.has_dependency("analytics.daily_stats"));
         assert!(deps.has_dependency("daily_stats"));
     }
+
+    fn catalog_query(dataset: &str, table: &str) -> QueryDef {
+        use crate::dsl::Destination;
+        use crate::invariant::InvariantsDef;
+        use crate::schema::PartitionConfig;
+
+        QueryDef {
+            name: table.to_string(),
+            previous_names: vec![],
+            destination: Destination {
+                project: None,
+                dataset: dataset.to_string(),
+                table: table.to_string(),
+                partition: PartitionConfig::day("date"),
+                cluster: None,
+                labels: Default::default(),
+            },
+            description: None,
+            owner: None,
+            tags: vec![],
+            versions: vec![],
+            cluster: None,
+            default_invariants: InvariantsDef::default(),
+            provenance: false,
+        }
+    }
+
+    #[test]
+    fn test_classify_managed_and_external() {
+        let sql = r#"
+            SELECT *
+            FROM analytics.daily_stats d
+            JOIN raw.events e ON d.id = e.id
+            JOIN `my-project.analytics.users` u ON d.user_id = u.id
+        "#;
+        let deps = SqlDependencies::extract(sql);
+        let catalog = vec![
+            catalog_query("analytics", "daily_stats"),
+            catalog_query("analytics", "users"),
+        ];
+
+        let (managed, external) = deps.classify(&catalog);
+        assert_eq!(managed.len(), 2);
+        assert!(managed.contains("analytics.daily_stats"));
+        assert!(external.contains("raw.events"));
+        assert_eq!(external.len(), 1);
+    }
+
+    #[test]
+    fn test_classify_does_not_match_partial_table_names() {
+        let deps = SqlDependencies::extract("SELECT * FROM analytics.old_daily_stats");
+        let catalog = vec![catalog_query("analytics", "daily_stats")];
+
+        let (managed, external) = deps.classify(&catalog);
+        assert!(managed.is_empty());
+        assert!(external.contains("analytics.old_daily_stats"));
+    }
+
+    #[test]
+    fn test_dependency_levels_for_uses_active_version() {
+        use crate::dsl::VersionDef;
+        use crate::invariant::InvariantsDef;
+        use crate::schema::Schema;
+
+        let version = |number: u32, effective_from: NaiveDate, reads: &str| VersionDef {
+            version: number,
+            effective_from,
+            source: String::new(),
+            sql_content: format!("SELECT * FROM {}", reads),
+            revisions: vec![],
+            description: None,
+            backfill_since: None,
+            blocked_reason: None,
+            schema: Schema::default(),
+            dependencies: HashSet::from([reads.to_string()]),
+            invariants: InvariantsDef::default(),
+        };
+        let jan = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let jun = NaiveDate::from_ymd_opt(2024, 6, 1).unwrap();
+
+        // `a` read `b` until June, when the direction flipped.
+        let mut a = catalog_query("analytics", "a");
+        a.versions = vec![
+            version(1, jan, "analytics.b"),
+            version(2, jun, "raw.events"),
+        ];
+        let mut b = catalog_query("analytics", "b");
+        b.versions = vec![
+            version(1, jan, "raw.events"),
+            version(2, jun, "analytics.a"),
+        ];
+        let catalog = vec![a, b];
+
+        assert!(dependency_levels(&catalog).is_err());
+        let march = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap();
+        assert_eq!(
+            dependency_levels_for(&catalog, march, jun, None).unwrap(),
+            vec![vec![1], vec![0]]
+        );
+        let july = NaiveDate::from_ymd_opt(2024, 7, 1).unwrap();
+        assert_eq!(
+            dependency_levels_for(&catalog, july, july, None).unwrap(),
+            vec![vec![0], vec![1]]
+        );
+    }
 }
@@ -0,0 +1,130 @@
+use super::dependencies::SqlDependencies;
+use super::parser::QueryDef;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NodeKind {
+    /// The destination of a loaded query.
+    Query,
+    /// A table read by some query but produced by none of them.
+    Source,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct GraphNode {
+    /// `dataset.table`, or for sources the table name as written in the SQL,
+    /// lowercased and without backticks.
+    pub id: String,
+    pub kind: NodeKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub query: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct GraphEdge {
+    /// The table read.
+    pub from: String,
+    /// The destination of the query reading it.
+    pub to: String,
+}
+
+/// Tables and the queries connecting them, built from the dependencies of
+/// every version and revision. Nodes and edges are sorted by id.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DependencyGraph {
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
+}
+
+impl DependencyGraph {
+    pub fn from_queries(queries: &[QueryDef]) -> Self {
+        let destination = |q: &QueryDef| q.destination.qualified_table();
+
+        let mut nodes: BTreeMap<String, GraphNode> = queries
+            .iter()
+            .map(|q| {
+                let id = destination(q);
+                let node = GraphNode {
+                    id: id.clone(),
+                    kind: NodeKind::Query,
+                    query: Some(q.name.clone()),
+                };
+                (id, node)
+            })
+            .collect();
+        let mut edges = BTreeSet::new();
+
+        for query in queries {
+            let to = destination(query);
+            let tables = query.versions.iter().flat_map(|v| {
+                v.dependencies
+                    .iter()
+                    .chain(v.revisions.iter().flat_map(|r| r.dependencies.iter()))
+            });
+            for table in tables {
+                let from = match SqlDependencies::find_producer(table, queries) {
+                    Some(producer) => destination(producer),
+                    None => {
+                        let id = SqlDependencies::normalize_table(table);
+                        nodes.entry(id.clone()).or_insert_with(|| GraphNode {
+                            id: id.clone(),
+                            kind: NodeKind::Source,
+                            query: None,
+                        });
+                        id
+                    }
+                };
+                if from != to {
+                    edges.insert(GraphEdge {
+                        from,
+                        to: to.clone(),
+                    });
+                }
+            }
+        }
+
+        Self {
+            nodes: nodes.into_values().collect(),
+            edges: edges.into_iter().collect(),
+        }
+    }
+
+    /// Graphviz source: queries as boxes labelled with the query name,
+    /// sources as dashed ellipses.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph bqdrift {\n    rankdir=LR;\n");
+        for node in &self.nodes {
+            match (&node.kind, &node.query) {
+                (NodeKind::Query, Some(name)) => dot.push_str(&format!(
+                    "    {} [shape=box, label={}];\n",
+                    dot_id(&node.id),
+                    dot_id(&format!("{}\\n({})", node.id, name))
+                )),
+                _ => dot.push_str(&format!(
+                    "    {} [shape=ellipse, style=dashed];\n",
+                    dot_id(&node.id)
+                )),
+            }
+        }
+        for edge in &self.edges {
+            dot.push_str(&format!(
+                "    {} -> {};\n",
+                dot_id(&edge.from),
+                dot_id(&edge.to)
+            ));
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self)
+            .expect("dependency graph serialization should never fail")
+    }
+}
+
+fn dot_id(id: &str) -> String {
+    format!("\"{}\"", id.replace('"', "\\\""))
+}
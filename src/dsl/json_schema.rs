@@ -0,0 +1,59 @@
+use super::parser::RawQueryDef;
+use schemars::schema_for;
+
+/// JSON Schema for the query YAML format, suitable for editor validation
+/// (e.g. the `yaml.schemas` setting of the VS Code YAML extension).
+pub fn query_yaml_json_schema() -> String {
+    let schema = schema_for!(RawQueryDef);
+    serde_json::to_string_pretty(&schema).expect("JSON Schema serialization should never fail")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schema_describes_top_level_fields() {
+        let schema: serde_json::Value = serde_json::from_str(&query_yaml_json_schema()).unwrap();
+
+        assert_eq!(schema["title"], "RawQueryDef");
+        let required: Vec<&str> = schema["required"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|v| v.as_str().unwrap())
+            .collect();
+        assert!(required.contains(&"name"));
+        assert!(required.contains(&"destination"));
+        assert!(required.contains(&"versions"));
+        assert!(schema["properties"]["tags"].is_object());
+    }
+
+    #[test]
+    fn test_schema_includes_nested_definitions() {
+        let schema: serde_json::Value = serde_json::from_str(&query_yaml_json_schema()).unwrap();
+        let definitions = schema["definitions"].as_object().unwrap();
+
+        for name in [
+            "Destination",
+            "RawVersionDef",
+            "SchemaRef",
+            "Field",
+            "BqType",
+            "PartitionConfig",
+            "InvariantsRef",
+            "InvariantDef",
+        ] {
+            assert!(
+                definitions.contains_key(name),
+                "missing definition {}",
+                name
+            );
+        }
+
+        let field = &definitions["Field"];
+        assert!(field["properties"]["type"].is_object());
+        let bq_types = definitions["BqType"]["enum"].as_array().unwrap();
+        assert!(bq_types.iter().any(|t| t == "INT64"));
+    }
+}
@@ -1,17 +1,27 @@
-use super::dependencies::SqlDependencies;
+use super::dependencies::{dependency_levels, SqlDependencies};
 use super::parser::{QueryDef, RawQueryDef, ResolvedRevision, VersionDef};
 use super::preprocessor::YamlPreprocessor;
 use super::resolver::VariableResolver;
 use crate::bq_runner::{FileLoader, SqlFile, SqlLoader};
 use crate::error::{BqDriftError, Result};
 use crate::invariant::InvariantsDef;
-use crate::schema::{ClusterConfig, Schema};
-use std::collections::HashMap;
+use crate::schema::{
+    provenance_fields, BqType, ClusterConfig, FieldMode, PartitionColumnType, PartitionConfig,
+    PartitionType, Schema, MAX_CLUSTER_FIELDS,
+};
+use rayon::prelude::*;
+use std::collections::{BTreeMap, HashMap};
 use std::path::Path;
+use tracing::warn;
+
+/// A query with its document's preprocessed text and the file it came from.
+type LoadedQuery<'p> = (QueryDef, String, &'p Path);
 
 pub struct QueryLoader {
     resolver: VariableResolver,
     preprocessor: YamlPreprocessor,
+    ignore: Vec<String>,
+    vars: HashMap<String, String>,
 }
 
 impl QueryLoader {
@@ -19,37 +29,214 @@ impl QueryLoader {
         Self {
             resolver: VariableResolver::new(),
             preprocessor: YamlPreprocessor::new(),
+            ignore: Vec::new(),
+            vars: HashMap::new(),
         }
     }
 
+    /// Skip YAML files matching these globs, relative to the directory
+    /// passed to `load_dir`.
+    pub fn with_ignore<I, S>(mut self, patterns: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.ignore.extend(patterns.into_iter().map(Into::into));
+        self
+    }
+
+    /// Variables that `{% if name == "value" %}` blocks in query files are
+    /// evaluated against.
+    pub fn with_vars<I, K, V>(mut self, vars: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.vars
+            .extend(vars.into_iter().map(|(k, v)| (k.into(), v.into())));
+        self
+    }
+
     pub fn load_dir(&self, path: impl AsRef<Path>) -> Result<Vec<QueryDef>> {
         let (queries, _) = self.load_dir_with_contents(path)?;
         Ok(queries)
     }
 
+    /// `load_dir` keeping only queries that carry one of `include_tags`
+    /// (any query when empty) and none of `exclude_tags`.
+    pub fn load_dir_filtered(
+        &self,
+        path: impl AsRef<Path>,
+        include_tags: &[&str],
+        exclude_tags: &[&str],
+    ) -> Result<Vec<QueryDef>> {
+        let (queries, _) =
+            self.load_dir_with_contents_filtered(path, include_tags, exclude_tags)?;
+        Ok(queries)
+    }
+
+    /// `load_dir_with_contents` with the tag filter of `load_dir_filtered`;
+    /// the contents map only holds the queries kept.
+    pub fn load_dir_with_contents_filtered(
+        &self,
+        path: impl AsRef<Path>,
+        include_tags: &[&str],
+        exclude_tags: &[&str],
+    ) -> Result<(Vec<QueryDef>, HashMap<String, String>)> {
+        let (mut queries, mut contents) = self.load_dir_with_contents(path)?;
+        queries.retain(|q| q.matches_tags(include_tags, exclude_tags));
+        contents.retain(|name, _| queries.iter().any(|q| &q.name == name));
+        Ok((queries, contents))
+    }
+
     pub fn load_dir_with_contents(
         &self,
         path: impl AsRef<Path>,
     ) -> Result<(Vec<QueryDef>, HashMap<String, String>)> {
-        let yaml_files = FileLoader::load_dir(&path, "yaml")
+        let ignore: Vec<&str> = self.ignore.iter().map(String::as_str).collect();
+        let yaml_files = FileLoader::load_dir_with_ignore(&path, "yaml", &ignore)
             .map_err(|e| BqDriftError::DslParse(e.to_string()))?;
 
-        let mut queries = Vec::with_capacity(yaml_files.len());
-        let mut contents = HashMap::with_capacity(yaml_files.len());
+        if yaml_files.is_empty() {
+            warn!(path = %path.as_ref().display(), "No query YAML files found");
+        }
+
+        // Files load in parallel; the first error in path order wins so a
+        // broken catalog reports the same file every time.
+        let results: Vec<Result<Vec<LoadedQuery>>> = yaml_files
+            .par_iter()
+            .map(|file| {
+                let base_dir = file.path.parent().unwrap_or(Path::new("."));
+                self.preprocessor
+                    .process_with_vars(&file.content, base_dir, &self.vars)
+                    .and_then(|processed| self.load_documents(&processed))
+                    .map(|docs| {
+                        docs.into_iter()
+                            .map(|(query, doc)| (query, doc, file.path.as_path()))
+                            .collect()
+                    })
+                    .map_err(|e| Self::with_context(&file.path.display().to_string(), e))
+            })
+            .collect();
+        let mut loaded: Vec<LoadedQuery> = results
+            .into_iter()
+            .collect::<Result<Vec<_>>>()?
+            .into_iter()
+            .flatten()
+            .collect();
+        loaded.sort_by(|(a, _, _), (b, _, _)| a.name.cmp(&b.name));
+
+        Self::check_duplicate_names(&loaded)?;
+        Self::warn_duplicate_destinations(&loaded);
 
-        for file in yaml_files {
-            let base_dir = file.path.parent().unwrap_or(Path::new("."));
-            let processed = self.preprocessor.process(&file.content, base_dir)?;
-            let raw: RawQueryDef = serde_yaml::from_str(&processed)?;
-            let name = raw.name.clone();
-            let query = self.resolve_query(raw)?;
+        let mut queries = Vec::with_capacity(loaded.len());
+        let mut contents = HashMap::with_capacity(loaded.len());
+        for (query, processed, _) in loaded {
+            contents.insert(query.name.clone(), processed);
             queries.push(query);
-            contents.insert(name, processed);
         }
 
         Ok((queries, contents))
     }
 
+    /// `loaded` is sorted by name, so queries sharing one are adjacent.
+    fn check_duplicate_names(loaded: &[LoadedQuery]) -> Result<()> {
+        let conflicts: Vec<String> = loaded
+            .chunk_by(|(a, _, _), (b, _, _)| a.name == b.name)
+            .filter(|group| group.len() > 1)
+            .map(|group| {
+                let paths: Vec<String> = group
+                    .iter()
+                    .map(|(_, _, path)| path.display().to_string())
+                    .collect();
+                format!("'{}' in {}", group[0].0.name, paths.join(", "))
+            })
+            .collect();
+
+        if conflicts.is_empty() {
+            Ok(())
+        } else {
+            Err(BqDriftError::Validation(format!(
+                "Duplicate query names: {}",
+                conflicts.join("; ")
+            )))
+        }
+    }
+
+    /// Two queries writing one table overwrite each other's partitions, but
+    /// may be intended (e.g. during a migration), so this only warns.
+    fn warn_duplicate_destinations(loaded: &[LoadedQuery]) {
+        let mut by_destination: BTreeMap<String, Vec<&str>> = BTreeMap::new();
+        for (query, _, _) in loaded {
+            by_destination
+                .entry(query.destination.qualified_table())
+                .or_default()
+                .push(&query.name);
+        }
+
+        for (destination, names) in by_destination {
+            if names.len() > 1 {
+                warn!(
+                    destination = %destination,
+                    "Queries {} all write to the same destination",
+                    names.join(", ")
+                );
+            }
+        }
+    }
+
+    /// Each `---`-separated document of a preprocessed file as a query, with
+    /// the document's own text. Errors in a multi-document file name the
+    /// document (1-based).
+    fn load_documents(&self, processed: &str) -> Result<Vec<(QueryDef, String)>> {
+        let documents = split_documents(processed);
+        let multiple = documents.len() > 1;
+        documents
+            .into_iter()
+            .enumerate()
+            .map(|(i, doc)| {
+                serde_yaml::from_str::<RawQueryDef>(&doc)
+                    .map_err(BqDriftError::from)
+                    .and_then(|raw| self.resolve_query(raw))
+                    .map(|query| (query, doc))
+                    .map_err(|e| {
+                        if multiple {
+                            Self::with_context(&format!("document {}", i + 1), e)
+                        } else {
+                            e
+                        }
+                    })
+            })
+            .collect()
+    }
+
+    /// Prefix an error with where it came from (a file path, a document),
+    /// keeping the variant so callers can still match on it.
+    fn with_context(context: &str, err: BqDriftError) -> BqDriftError {
+        let at = |msg: String| format!("{}: {}", context, msg);
+        match err {
+            BqDriftError::DslParse(msg) => BqDriftError::DslParse(at(msg)),
+            BqDriftError::Yaml(e) => BqDriftError::DslParse(at(e.to_string())),
+            BqDriftError::Validation(msg) => BqDriftError::Validation(at(msg)),
+            BqDriftError::VariableResolution(msg) => BqDriftError::VariableResolution(at(msg)),
+            BqDriftError::InvalidVersionRef(msg) => BqDriftError::InvalidVersionRef(at(msg)),
+            BqDriftError::InvalidRevisionRef(msg) => BqDriftError::InvalidRevisionRef(at(msg)),
+            BqDriftError::FileInclude(msg) => BqDriftError::FileInclude(at(msg)),
+            BqDriftError::Schema(msg) => BqDriftError::Schema(at(msg)),
+            BqDriftError::SchemaParse(msg) => BqDriftError::SchemaParse(at(msg)),
+            BqDriftError::Cluster(msg) => BqDriftError::Cluster(at(msg)),
+            other => other,
+        }
+    }
+
+    /// Indexes into `queries` ordered so that a query producing a table runs
+    /// before every query that reads it. Fails with a `Validation` error
+    /// naming the queries of a dependency cycle.
+    pub fn topo_sort(queries: &[QueryDef]) -> Result<Vec<usize>> {
+        Ok(dependency_levels(queries)?.into_iter().flatten().collect())
+    }
+
     pub fn load_sql_dir(&self, path: impl AsRef<Path>) -> Result<Vec<SqlFile>> {
         SqlLoader::load_dir(path).map_err(|e| BqDriftError::DslParse(e.to_string()))
     }
@@ -69,10 +256,16 @@ impl QueryLoader {
             FileLoader::load_file(yaml_path).map_err(|e| BqDriftError::DslParse(e.to_string()))?;
 
         let base_dir = yaml_path.parent().unwrap_or(Path::new("."));
-        let processed = self.preprocessor.process(&file.content, base_dir)?;
+        self.load_from_str(&file.content, base_dir)
+    }
 
+    /// Like `load_query` for YAML already in memory; `base_dir` is where
+    /// `${{ file: }}` includes resolve from.
+    pub fn load_from_str(&self, yaml: &str, base_dir: &Path) -> Result<QueryDef> {
+        let processed = self
+            .preprocessor
+            .process_with_vars(yaml, base_dir, &self.vars)?;
         let raw: RawQueryDef = serde_yaml::from_str(&processed)?;
-
         self.resolve_query(raw)
     }
 
@@ -81,30 +274,111 @@ impl QueryLoader {
         let mut resolved_schemas: HashMap<u32, Schema> = HashMap::with_capacity(version_count);
         let mut resolved_invariants: HashMap<u32, InvariantsDef> =
             HashMap::with_capacity(version_count);
+        let mut resolved_sqls: HashMap<u32, String> = HashMap::with_capacity(version_count);
         let mut versions: Vec<VersionDef> = Vec::with_capacity(version_count);
 
+        self.resolver.validate_version_refs(&raw.versions)?;
+
         raw.versions.sort_by(|a, b| {
             a.effective_from
                 .cmp(&b.effective_from)
                 .then_with(|| a.version.cmp(&b.version))
         });
 
+        let default_invariants = self
+            .resolver
+            .resolve_default_invariants(&raw.default_invariants)?;
+
         for raw_version in raw.versions {
-            let schema = self
+            let mut schema = self
                 .resolver
                 .resolve_schema(&raw_version.schema, &resolved_schemas)?;
+            if raw.provenance {
+                for field in provenance_fields() {
+                    if !schema.has_field(&field.name) {
+                        schema.fields.push(field);
+                    }
+                }
+            }
 
-            let dependencies = SqlDependencies::extract(&raw_version.source).tables;
-            let sql_content = raw_version.source;
+            if let Some(base) = self.resolver.schema_base_version(&raw_version.schema)? {
+                if let Some(prev) = resolved_schemas.get(&base) {
+                    let warnings = Schema::check_evolution(prev, &schema).map_err(|e| match e {
+                        BqDriftError::Schema(msg) => BqDriftError::Validation(format!(
+                            "Query '{}' v{} (from v{}): {}",
+                            raw.name, raw_version.version, base, msg
+                        )),
+                        other => other,
+                    })?;
+                    for warning in warnings {
+                        warn!(
+                            query = %raw.name,
+                            version = raw_version.version,
+                            "Schema change from v{}: {}",
+                            base,
+                            warning
+                        );
+                    }
+                }
+            }
+
+            schema
+                .validate_names()
+                .and_then(|_| schema.validate_numeric_parameters())
+                .map_err(|e| {
+                    let msg = match e {
+                        BqDriftError::Schema(msg) => msg,
+                        other => other.to_string(),
+                    };
+                    BqDriftError::Validation(format!(
+                        "Query '{}' v{}: {}",
+                        raw.name, raw_version.version, msg
+                    ))
+                })?;
+
+            if let Some((path, _)) = schema.all_fields().into_iter().find(|(_, f)| {
+                f.default_expression
+                    .as_ref()
+                    .is_some_and(|expr| expr.trim().is_empty())
+            }) {
+                return Err(BqDriftError::Validation(format!(
+                    "Query '{}' v{}: field '{}' has an empty default; give a SQL expression such as CURRENT_TIMESTAMP() or remove the key",
+                    raw.name, raw_version.version, path
+                )));
+            }
+
+            let sql_content = if self.resolver.is_whole_ref(&raw_version.source) {
+                self.resolver
+                    .resolve_sql_ref(raw_version.source.trim(), &resolved_sqls)?
+            } else {
+                raw_version.source
+            };
+            let dependencies = SqlDependencies::extract(&sql_content).tables;
 
             let revisions = self.resolve_revisions(&raw_version.revisions)?;
+            Self::check_unresolved_refs(
+                &raw.name,
+                &format!("v{}", raw_version.version),
+                &sql_content,
+            )?;
+            for rev in &revisions {
+                Self::check_unresolved_refs(
+                    &raw.name,
+                    &format!("v{} revision {}", raw_version.version, rev.revision),
+                    &rev.sql_content,
+                )?;
+            }
 
             let invariants = self
                 .resolver
                 .resolve_invariants(&raw_version.invariants, &resolved_invariants)?;
 
             resolved_schemas.insert(raw_version.version, schema.clone());
+            resolved_sqls.insert(raw_version.version, sql_content.clone());
             resolved_invariants.insert(raw_version.version, invariants.clone());
+            let invariants = self
+                .resolver
+                .merge_default_invariants(&default_invariants, invariants);
 
             versions.push(VersionDef {
                 version: raw_version.version,
@@ -114,28 +388,180 @@ impl QueryLoader {
                 revisions,
                 description: raw_version.description,
                 backfill_since: raw_version.backfill_since,
+                blocked_reason: raw_version.blocked_reason,
                 schema,
                 dependencies,
                 invariants,
             });
         }
 
+        raw.destination
+            .partition
+            .validate_range()
+            .map_err(|e| BqDriftError::Validation(format!("Query '{}': {}", raw.name, e)))?;
+        raw.destination
+            .validate_labels()
+            .map_err(|e| BqDriftError::Validation(format!("Query '{}': {}", raw.name, e)))?;
+        Self::validate_partition_field(&raw.name, &raw.destination.partition, &versions)?;
+        Self::resolve_partition_column_type(&raw.name, &mut raw.destination.partition, &versions)?;
+
         let cluster = match &raw.destination.cluster {
-            Some(fields) => Some(ClusterConfig::new(fields.clone())?),
+            Some(fields) => {
+                Self::validate_cluster_fields(&raw.name, fields, &versions)?;
+                Some(ClusterConfig::new(fields.clone())?)
+            }
             None => None,
         };
 
         Ok(QueryDef {
             name: raw.name,
+            previous_names: raw.previous_names,
             destination: raw.destination,
             description: raw.description,
             owner: raw.owner,
             tags: raw.tags,
             versions,
             cluster,
+            default_invariants,
+            provenance: raw.provenance,
         })
     }
 
+    /// A `${{ ... }}` still present after resolution would be sent to
+    /// BigQuery as literal text, e.g. a mistyped version ref inside a query.
+    fn check_unresolved_refs(query_name: &str, location: &str, sql: &str) -> Result<()> {
+        let Some(start) = sql.find("${{") else {
+            return Ok(());
+        };
+        let rest = &sql[start..];
+        let token = match rest.find("}}") {
+            Some(end) => &rest[..end + 2],
+            None => rest.lines().next().unwrap_or(rest),
+        };
+        Err(BqDriftError::VariableResolution(format!(
+            "Query '{}' {}: unresolved reference '{}' in SQL",
+            query_name, location, token
+        )))
+    }
+
+    /// Clustering applies to the destination table, so every version's schema
+    /// must have each cluster column, and BigQuery allows at most four.
+    fn validate_cluster_fields(
+        query_name: &str,
+        fields: &[String],
+        versions: &[VersionDef],
+    ) -> Result<()> {
+        if let Some(extra) = fields.get(MAX_CLUSTER_FIELDS) {
+            return Err(BqDriftError::Validation(format!(
+                "Query '{}': cluster field '{}' exceeds BigQuery's limit of {} clustering fields",
+                query_name, extra, MAX_CLUSTER_FIELDS
+            )));
+        }
+        for version in versions {
+            if let Some(missing) = fields.iter().find(|f| !version.schema.has_field(f)) {
+                return Err(BqDriftError::Validation(format!(
+                    "Query '{}': cluster field '{}' not found in v{} schema",
+                    query_name, missing, version.version
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Every version's schema must have the partition column, with a type the
+    /// partitioning can use.
+    fn validate_partition_field(
+        query_name: &str,
+        partition: &PartitionConfig,
+        versions: &[VersionDef],
+    ) -> Result<()> {
+        let Some(field_name) = partition.field_name() else {
+            return Ok(());
+        };
+        let allowed: &[BqType] = match partition.partition_type {
+            PartitionType::Range => &[BqType::Int64],
+            PartitionType::Hour => &[BqType::Timestamp, BqType::Datetime],
+            _ => &[BqType::Date, BqType::Timestamp, BqType::Datetime],
+        };
+
+        for version in versions {
+            let Some(field) = version.schema.get_field(field_name) else {
+                let available: Vec<&str> = version
+                    .schema
+                    .fields
+                    .iter()
+                    .map(|f| f.name.as_str())
+                    .collect();
+                return Err(BqDriftError::Validation(format!(
+                    "Query '{}' v{}: partition field '{}' not found in schema (fields: {})",
+                    query_name,
+                    version.version,
+                    field_name,
+                    available.join(", ")
+                )));
+            };
+            if !allowed.contains(&field.field_type) {
+                let names: Vec<&str> = allowed.iter().map(BqType::sql_name).collect();
+                return Err(BqDriftError::Validation(format!(
+                    "Query '{}' v{}: partition field '{}' is {}, but {} partitioning needs {}",
+                    query_name,
+                    version.version,
+                    field_name,
+                    field.field_type.sql_name(),
+                    format!("{:?}", partition.partition_type).to_uppercase(),
+                    names.join(" or ")
+                )));
+            }
+            if field.mode == FieldMode::Repeated {
+                return Err(BqDriftError::Validation(format!(
+                    "Query '{}' v{}: partition field '{}' is REPEATED; arrays cannot be partitioned on or compared in the MERGE predicate",
+                    query_name, version.version, field_name
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Take the partition column type from the latest schema when it is not
+    /// set, and reject an explicit type the column does not have.
+    fn resolve_partition_column_type(
+        query_name: &str,
+        partition: &mut PartitionConfig,
+        versions: &[VersionDef],
+    ) -> Result<()> {
+        if !matches!(
+            partition.partition_type,
+            PartitionType::Hour | PartitionType::Day | PartitionType::Month | PartitionType::Year
+        ) {
+            return Ok(());
+        }
+        let Some(field_name) = partition.field.as_deref() else {
+            return Ok(());
+        };
+        let Some(field_type) = versions
+            .last()
+            .and_then(|v| v.schema.get_field(field_name))
+            .and_then(|f| PartitionColumnType::from_bq_type(&f.field_type))
+        else {
+            return Ok(());
+        };
+
+        match partition.column_type {
+            None => partition.column_type = Some(field_type),
+            Some(column_type) if column_type != field_type => {
+                return Err(BqDriftError::Validation(format!(
+                    "Query '{}': partition column_type {} does not match field '{}' of type {}",
+                    query_name,
+                    column_type.sql_name(),
+                    field_name,
+                    field_type.sql_name()
+                )));
+            }
+            Some(_) => {}
+        }
+        Ok(())
+    }
+
     fn resolve_revisions(
         &self,
         revisions: &[super::parser::Revision],
@@ -160,6 +586,30 @@ impl QueryLoader {
     }
 }
 
+/// Split YAML on `---` document markers at the start of a line. Markers
+/// can't occur inside block scalars, whose lines are indented. Empty
+/// documents (e.g. before a leading marker) are dropped, and a file holding
+/// a single document is returned unchanged so its checksum is stable.
+fn split_documents(yaml: &str) -> Vec<String> {
+    let mut documents = vec![String::new()];
+    for line in yaml.lines() {
+        let trimmed = line.trim_end();
+        if trimmed == "---" || trimmed.starts_with("--- ") {
+            documents.push(String::new());
+            continue;
+        }
+        if let Some(current) = documents.last_mut() {
+            current.push_str(line);
+            current.push('\n');
+        }
+    }
+    documents.retain(|doc| !doc.trim().is_empty());
+    if documents.len() <= 1 {
+        return vec![yaml.to_string()];
+    }
+    documents
+}
+
 impl Default for QueryLoader {
     fn default() -> Self {
         Self::new()
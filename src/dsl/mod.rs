@@ -1,4 +1,6 @@
 mod dependencies;
+mod graph;
+mod json_schema;
 mod loader;
 mod parser;
 mod preprocessor;
@@ -6,10 +8,15 @@ mod resolver;
 mod validator;
 
 pub use dependencies::SqlDependencies;
+pub(crate) use dependencies::{dependency_levels_for, upstreams_for};
+pub use graph::{DependencyGraph, GraphEdge, GraphNode, NodeKind};
+pub use json_schema::query_yaml_json_schema;
 pub use loader::QueryLoader;
 pub use parser::{
     Destination, QueryDef, RawQueryDef, ResolvedRevision, Revision, SchemaRef, VersionDef,
 };
 pub use preprocessor::YamlPreprocessor;
 pub use resolver::VariableResolver;
-pub use validator::{QueryValidator, ValidationError, ValidationResult, ValidationWarning};
+pub use validator::{
+    QueryValidator, ValidationError, ValidationResult, ValidationWarning, ValidatorConfig,
+};
@@ -1,12 +1,23 @@
+use crate::error::{BqDriftError, Result};
+use crate::executor::{with_provenance_columns, MergePlan};
 use crate::invariant::{InvariantsDef, InvariantsRef};
-use crate::schema::{ClusterConfig, Field, PartitionConfig, Schema};
+use crate::schema::{
+    AlterStatement, ClusterConfig, Field, PartitionConfig, PartitionKey, PartitionType, Schema,
+};
 use chrono::NaiveDate;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+const MAX_LABELS: usize = 64;
+const MAX_LABEL_LEN: usize = 63;
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct RawQueryDef {
     pub name: String,
+    /// Names the query was tracked under before being renamed.
+    #[serde(default)]
+    pub previous_names: Vec<String>,
     pub destination: Destination,
     #[serde(default)]
     pub description: Option<String>,
@@ -14,10 +25,14 @@ pub struct RawQueryDef {
     pub owner: Option<String>,
     #[serde(default)]
     pub tags: Vec<String>,
+    #[serde(default)]
+    pub default_invariants: Option<InvariantsDef>,
+    #[serde(default)]
+    pub provenance: bool,
     pub versions: Vec<RawVersionDef>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct RawVersionDef {
     pub version: u32,
     pub effective_from: NaiveDate,
@@ -28,12 +43,16 @@ pub struct RawVersionDef {
     pub description: Option<String>,
     #[serde(default)]
     pub backfill_since: Option<NaiveDate>,
+    /// Marks the version as unsafe to run; the reason is reported whenever a
+    /// write of one of its partitions is refused.
+    #[serde(default)]
+    pub blocked_reason: Option<String>,
     pub schema: SchemaRef,
     #[serde(default)]
     pub invariants: Option<InvariantsRef>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(untagged)]
 pub enum SchemaRef {
     Inline(Vec<Field>),
@@ -41,7 +60,7 @@ pub enum SchemaRef {
     Extended(ExtendedSchema),
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ExtendedSchema {
     pub base: String,
     #[serde(default)]
@@ -52,7 +71,7 @@ pub struct ExtendedSchema {
     pub remove: Vec<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Revision {
     pub revision: u32,
     pub effective_from: NaiveDate,
@@ -63,24 +82,37 @@ pub struct Revision {
     pub backfill_since: Option<NaiveDate>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Destination {
+    #[serde(default)]
+    pub project: Option<String>,
     pub dataset: String,
     pub table: String,
+    #[serde(default = "PartitionConfig::unpartitioned")]
     pub partition: PartitionConfig,
     #[serde(default)]
     pub cluster: Option<Vec<String>>,
+    /// Table labels, also attached to the query jobs that write the table.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub labels: BTreeMap<String, String>,
 }
 
 #[derive(Debug, Clone)]
 pub struct QueryDef {
     pub name: String,
+    /// Earlier names whose stored partition states still belong to this
+    /// query.
+    pub previous_names: Vec<String>,
     pub destination: Destination,
     pub description: Option<String>,
     pub owner: Option<String>,
     pub tags: Vec<String>,
     pub versions: Vec<VersionDef>,
     pub cluster: Option<ClusterConfig>,
+    pub default_invariants: InvariantsDef,
+    /// Add `_bqdrift_query`, `_bqdrift_version` and `_bqdrift_run_at`
+    /// columns to every written row.
+    pub provenance: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -92,6 +124,7 @@ pub struct VersionDef {
     pub revisions: Vec<ResolvedRevision>,
     pub description: Option<String>,
     pub backfill_since: Option<NaiveDate>,
+    pub blocked_reason: Option<String>,
     pub schema: Schema,
     pub dependencies: HashSet<String>,
     pub invariants: InvariantsDef,
@@ -108,15 +141,83 @@ pub struct ResolvedRevision {
     pub dependencies: HashSet<String>,
 }
 
+impl Destination {
+    /// `project.dataset.table` when a project override is set, otherwise
+    /// `dataset.table` (resolved against the client's project).
+    pub fn qualified_table(&self) -> String {
+        match &self.project {
+            Some(project) => format!("{}.{}.{}", project, self.dataset, self.table),
+            None => format!("{}.{}", self.dataset, self.table),
+        }
+    }
+
+    /// Check labels against BigQuery's rules: at most 64, keys of 1-63 and
+    /// values of 0-63 lowercase letters, digits, underscores or dashes, and
+    /// keys starting with a letter.
+    pub fn validate_labels(&self) -> std::result::Result<(), String> {
+        if self.labels.len() > MAX_LABELS {
+            return Err(format!(
+                "{} labels exceeds BigQuery's limit of {}",
+                self.labels.len(),
+                MAX_LABELS
+            ));
+        }
+        let valid_chars = |s: &str| {
+            s.chars()
+                .all(|c| c.is_lowercase() || c.is_numeric() || c == '_' || c == '-')
+        };
+        for (key, value) in &self.labels {
+            if key.is_empty()
+                || key.chars().count() > MAX_LABEL_LEN
+                || !key.chars().next().is_some_and(char::is_lowercase)
+                || !valid_chars(key)
+            {
+                return Err(format!(
+                    "label key '{}' must be 1-{} lowercase letters, digits, '_' or '-', starting with a letter",
+                    key, MAX_LABEL_LEN
+                ));
+            }
+            if value.chars().count() > MAX_LABEL_LEN || !valid_chars(value) {
+                return Err(format!(
+                    "label '{}' value '{}' must be at most {} lowercase letters, digits, '_' or '-'",
+                    key, value, MAX_LABEL_LEN
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
 impl VersionDef {
-    pub fn get_sql_for_date(&self, execution_date: NaiveDate) -> &str {
-        let applicable_revision = self
-            .revisions
+    pub fn is_blocked(&self) -> bool {
+        self.blocked_reason.is_some()
+    }
+
+    /// The revision whose SQL runs on `execution_date`: the one with the
+    /// latest `effective_from` on or before it. `None` means the version's
+    /// own SQL runs.
+    pub fn revision_for_date(&self, execution_date: NaiveDate) -> Option<&ResolvedRevision> {
+        self.revisions
             .iter()
             .filter(|r| r.effective_from <= execution_date)
-            .max_by_key(|r| r.effective_from);
+            .max_by_key(|r| r.effective_from)
+    }
+
+    #[deprecated(note = "renamed to `revision_for_date`")]
+    pub fn get_revision_for_date(&self, execution_date: NaiveDate) -> Option<&ResolvedRevision> {
+        self.revision_for_date(execution_date)
+    }
+
+    /// Tables read by the SQL `get_sql_for_date` returns.
+    pub fn dependencies_for_date(&self, execution_date: NaiveDate) -> &HashSet<String> {
+        match self.revision_for_date(execution_date) {
+            Some(rev) => &rev.dependencies,
+            None => &self.dependencies,
+        }
+    }
 
-        match applicable_revision {
+    pub fn get_sql_for_date(&self, execution_date: NaiveDate) -> &str {
+        match self.revision_for_date(execution_date) {
             Some(rev) => &rev.sql_content,
             None => &self.sql_content,
         }
@@ -124,6 +225,10 @@ impl VersionDef {
 }
 
 impl QueryDef {
+    /// The version with the latest `effective_from` on or before
+    /// `partition_date`; a version is active from its `effective_from`
+    /// inclusive. `None` when the date precedes every version, which drift
+    /// detection reports as `NeverRun`.
     pub fn get_version_for_date(&self, partition_date: NaiveDate) -> Option<&VersionDef> {
         if self.versions.is_empty() {
             return None;
@@ -138,7 +243,133 @@ impl QueryDef {
         }
     }
 
+    /// The version that would write `partition_key`, or an error when there is
+    /// none or it is blocked.
+    pub fn writable_version(&self, partition_key: &PartitionKey) -> Result<&VersionDef> {
+        let version = self
+            .get_version_for_date(partition_key.to_naive_date())
+            .ok_or_else(|| {
+                BqDriftError::Partition(format!("No version found for partition {}", partition_key))
+            })?;
+        match &version.blocked_reason {
+            Some(reason) => Err(BqDriftError::VersionBlocked(format!(
+                "'{}' v{} (partition {}): {}",
+                self.name, version.version, partition_key, reason
+            ))),
+            None => Ok(version),
+        }
+    }
+
+    /// True when the query has one of `include` (or `include` is empty) and
+    /// none of `exclude`.
+    pub fn matches_tags(&self, include: &[&str], exclude: &[&str]) -> bool {
+        let has = |tag: &&str| self.tags.iter().any(|t| t == tag);
+        (include.is_empty() || include.iter().any(has)) && !exclude.iter().any(has)
+    }
+
+    /// The version numbered `version`, regardless of its dates.
+    pub fn version(&self, version: u32) -> Option<&VersionDef> {
+        self.versions.iter().find(|v| v.version == version)
+    }
+
     pub fn latest_version(&self) -> Option<&VersionDef> {
         self.versions.iter().max_by_key(|v| v.version)
     }
+
+    /// `ALTER TABLE` statements that evolve the destination from the schema
+    /// of `from_version` to that of `to_version`, with dropped columns
+    /// marked `Destructive` and changes BigQuery cannot apply in place marked
+    /// `RequiresRebuild`.
+    pub fn schema_migration_plan(
+        &self,
+        from_version: u32,
+        to_version: u32,
+    ) -> Result<Vec<AlterStatement>> {
+        let schema_of = |number: u32| {
+            self.version(number).map(|v| &v.schema).ok_or_else(|| {
+                BqDriftError::InvalidVersionRef(format!(
+                    "Query '{}' has no version {}",
+                    self.name, number
+                ))
+            })
+        };
+        let from = schema_of(from_version)?;
+        let to = schema_of(to_version)?;
+        Ok(from.alter_statements(to, &self.destination.qualified_table()))
+    }
+
+    /// The MERGE used to write `partition_key`, with the revision active on
+    /// `as_of` selected.
+    pub fn merge_plan(&self, partition_key: PartitionKey, as_of: NaiveDate) -> Result<MergePlan> {
+        let version = self
+            .get_version_for_date(partition_key.to_naive_date())
+            .ok_or_else(|| {
+                BqDriftError::Partition(format!("No version found for partition {}", partition_key))
+            })?;
+        let dest_table = self.destination.qualified_table();
+        if !self.destination.partition.is_partitioned() {
+            return Ok(MergePlan::replace_table(
+                &dest_table,
+                &self.source_sql(version, as_of),
+                &partition_key,
+            ));
+        }
+
+        let (partition_field, column_type) = self
+            .destination
+            .partition
+            .predicate_column()
+            .ok_or_else(|| {
+                BqDriftError::Partition(format!(
+                    "Partition field not specified for query '{}'",
+                    self.name
+                ))
+            })?;
+
+        let plan = MergePlan::new(
+            &dest_table,
+            partition_field,
+            &self.source_sql(version, as_of),
+            &partition_key,
+        )
+        .with_column_type(column_type);
+        Ok(match self.ingestion_time_columns(version) {
+            Some(columns) => plan.with_ingestion_time(columns),
+            None => plan,
+        })
+    }
+
+    /// Top-level column names of `version` when the destination is
+    /// ingestion-time partitioned, where writes must set `_PARTITIONTIME`.
+    pub(crate) fn ingestion_time_columns(&self, version: &VersionDef) -> Option<Vec<String>> {
+        (self.destination.partition.partition_type == PartitionType::IngestionTime).then(|| {
+            version
+                .schema
+                .fields
+                .iter()
+                .map(|f| f.name.clone())
+                .collect()
+        })
+    }
+
+    /// The SELECT written for `version` on `as_of`, including provenance
+    /// columns when enabled.
+    pub fn source_sql(&self, version: &VersionDef, as_of: NaiveDate) -> String {
+        let sql = version.get_sql_for_date(as_of);
+        if self.provenance {
+            with_provenance_columns(sql, &self.name, version.version)
+        } else {
+            sql.to_string()
+        }
+    }
+
+    /// The exact SQL `PartitionWriter::write_partition` submits for
+    /// `partition_key` when executed on `as_of`.
+    pub fn resolve_executable_sql(
+        &self,
+        partition_key: PartitionKey,
+        as_of: NaiveDate,
+    ) -> Result<String> {
+        Ok(self.merge_plan(partition_key, as_of)?.render())
+    }
 }
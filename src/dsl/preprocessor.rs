@@ -1,7 +1,7 @@
 use crate::error::{BqDriftError, Result};
 use once_cell::sync::Lazy;
 use regex::Regex;
-use std::collections::HashSet;
+use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
 
@@ -9,6 +9,24 @@ static FILE_PATTERN: Lazy<Regex> = Lazy::new(|| {
     Regex::new(r#"\$\{\{\s*file:\s*([^\s}]+)\s*\}\}"#).expect("file pattern regex is valid")
 });
 
+static INCLUDE_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"\$\{\{\s*(file|glob):\s*([^\s}]+)\s*\}\}"#)
+        .expect("include pattern regex is valid")
+});
+
+static ENV_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"\$\{ENV:([A-Za-z_][A-Za-z0-9_]*)(?::-([^}]*))?\}"#)
+        .expect("env pattern regex is valid")
+});
+
+static TAG_PATTERN: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"\{%\s*(.*?)\s*%\}"#).expect("tag pattern regex is valid"));
+
+static IF_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"^if\s+([A-Za-z_][A-Za-z0-9_]*)\s*==\s*(?:"([^"]*)"|'([^']*)')$"#)
+        .expect("if pattern regex is valid")
+});
+
 pub struct YamlPreprocessor;
 
 impl YamlPreprocessor {
@@ -17,11 +35,159 @@ impl YamlPreprocessor {
     }
 
     pub fn process(&self, content: &str, base_dir: &Path) -> Result<String> {
-        let mut visited = HashSet::new();
+        self.process_with_vars(content, base_dir, &HashMap::new())
+    }
+
+    /// Like `process`, evaluating `{% if name == "value" %}` blocks against
+    /// `vars` once includes are inlined.
+    pub fn process_with_vars(
+        &self,
+        content: &str,
+        base_dir: &Path,
+        vars: &HashMap<String, String>,
+    ) -> Result<String> {
+        if !self.has_file_includes(content) {
+            return Self::expand_env(&Self::render_conditionals(content, vars)?);
+        }
+        let mut include_stack = Vec::new();
         let canonical_base = base_dir.canonicalize().map_err(|_| {
             BqDriftError::FileInclude(format!("Base directory not found: {}", base_dir.display()))
         })?;
-        self.process_recursive(content, base_dir, &canonical_base, &mut visited)
+        let included =
+            self.process_recursive(content, base_dir, &canonical_base, &mut include_stack)?;
+        Self::expand_env(&Self::render_conditionals(&included, vars)?)
+    }
+
+    /// Keep the branch of each `{% if %}...{% else %}...{% endif %}` block
+    /// whose condition holds. Conditions compare one variable to a quoted
+    /// string with `==`; blocks cannot nest. A tag alone on its line is
+    /// removed together with the line.
+    fn render_conditionals(content: &str, vars: &HashMap<String, String>) -> Result<String> {
+        if !content.contains("{%") {
+            return Ok(content.to_string());
+        }
+
+        let line_of = |pos: usize| content[..pos].matches('\n').count() + 1;
+        // (condition, in else branch, line of the opening tag)
+        let mut block: Option<(bool, bool, usize)> = None;
+        let mut result = String::with_capacity(content.len());
+        let mut last_end = 0;
+
+        for caps in TAG_PATTERN.captures_iter(content) {
+            let (Some(tag), Some(body)) = (caps.get(0), caps.get(1)) else {
+                continue;
+            };
+            let (start, end) = Self::tag_span(content, tag.start(), tag.end());
+            let active = block.is_none_or(|(cond, in_else, _)| cond != in_else);
+            if active {
+                result.push_str(&content[last_end..start]);
+            }
+            last_end = end;
+
+            let line = line_of(tag.start());
+            let body = body.as_str();
+            if let Some(cond) = IF_PATTERN.captures(body) {
+                if let Some((_, _, open)) = block {
+                    return Err(BqDriftError::DslParse(format!(
+                        "Nested {{% if %}} on line {} is not supported (block opened on line {})",
+                        line, open
+                    )));
+                }
+                let name = &cond[1];
+                let expected = cond
+                    .get(2)
+                    .or_else(|| cond.get(3))
+                    .map_or("", |m| m.as_str());
+                let value = vars.get(name).ok_or_else(|| {
+                    BqDriftError::DslParse(format!(
+                        "Undefined variable '{}' in {} on line {}",
+                        name,
+                        tag.as_str(),
+                        line
+                    ))
+                })?;
+                block = Some((value == expected, false, line));
+            } else if body == "else" {
+                match block {
+                    Some((cond, false, open)) => block = Some((cond, true, open)),
+                    _ => {
+                        return Err(BqDriftError::DslParse(format!(
+                            "{{% else %}} on line {} has no matching {{% if %}}",
+                            line
+                        )))
+                    }
+                }
+            } else if body == "endif" {
+                if block.take().is_none() {
+                    return Err(BqDriftError::DslParse(format!(
+                        "{{% endif %}} on line {} has no matching {{% if %}}",
+                        line
+                    )));
+                }
+            } else {
+                return Err(BqDriftError::DslParse(format!(
+                    "Unsupported tag {} on line {}",
+                    tag.as_str(),
+                    line
+                )));
+            }
+        }
+
+        if let Some((_, _, open)) = block {
+            return Err(BqDriftError::DslParse(format!(
+                "{{% if %}} on line {} has no matching {{% endif %}}",
+                open
+            )));
+        }
+        result.push_str(&content[last_end..]);
+        Ok(result)
+    }
+
+    /// Widen a tag to its whole line, newline included, when nothing but
+    /// whitespace shares the line with it.
+    fn tag_span(content: &str, start: usize, end: usize) -> (usize, usize) {
+        let line_start = content[..start].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = content[end..]
+            .find('\n')
+            .map_or(content.len(), |i| end + i + 1);
+        let alone = content[line_start..start].trim().is_empty()
+            && content[end..line_end].trim().is_empty();
+        if alone {
+            (line_start, line_end)
+        } else {
+            (start, end)
+        }
+    }
+
+    /// Replace `${ENV:NAME}` with the variable's value, or with `fallback` in
+    /// `${ENV:NAME:-fallback}` when it is unset or empty.
+    fn expand_env(content: &str) -> Result<String> {
+        let mut result = String::with_capacity(content.len());
+        let mut last_end = 0;
+
+        for caps in ENV_PATTERN.captures_iter(content) {
+            let (Some(full_match), Some(name)) = (caps.get(0), caps.get(1)) else {
+                continue;
+            };
+            let value = match (std::env::var(name.as_str()), caps.get(2)) {
+                (Ok(value), Some(fallback)) if value.is_empty() => fallback.as_str().to_string(),
+                (Ok(value), _) => value,
+                (Err(_), Some(fallback)) => fallback.as_str().to_string(),
+                (Err(_), None) => {
+                    return Err(BqDriftError::DslParse(format!(
+                        "Environment variable '{}' is not set and {} has no default",
+                        name.as_str(),
+                        full_match.as_str()
+                    )))
+                }
+            };
+            result.push_str(&content[last_end..full_match.start()]);
+            result.push_str(&value);
+            last_end = full_match.end();
+        }
+
+        result.push_str(&content[last_end..]);
+        Ok(result)
     }
 
     fn process_recursive(
@@ -29,64 +195,138 @@ impl YamlPreprocessor {
         content: &str,
         base_dir: &Path,
         root_base: &Path,
-        visited: &mut HashSet<PathBuf>,
+        include_stack: &mut Vec<PathBuf>,
     ) -> Result<String> {
         let mut result = String::new();
         let mut last_end = 0;
 
-        for caps in FILE_PATTERN.captures_iter(content) {
+        for caps in INCLUDE_PATTERN.captures_iter(content) {
             let full_match = match caps.get(0) {
                 Some(m) => m,
                 None => continue,
             };
-            let file_path = match caps.get(1) {
-                Some(m) => m.as_str(),
-                None => continue,
+            let (kind, target) = match (caps.get(1), caps.get(2)) {
+                (Some(k), Some(t)) => (k.as_str(), t.as_str()),
+                _ => continue,
             };
 
             result.push_str(&content[last_end..full_match.start()]);
 
-            let resolved_path = base_dir.join(file_path);
-            let canonical = resolved_path.canonicalize().map_err(|_| {
-                BqDriftError::FileInclude(format!("File not found: {}", resolved_path.display()))
-            })?;
-
-            if !canonical.starts_with(root_base) {
-                return Err(BqDriftError::FileInclude(format!(
-                    "Path traversal not allowed: {}",
-                    file_path
-                )));
-            }
-
-            if visited.contains(&canonical) {
-                return Err(BqDriftError::FileInclude(format!(
-                    "Circular include detected: {}",
-                    canonical.display()
-                )));
-            }
-            visited.insert(canonical.clone());
-
-            let included_content = fs::read_to_string(&canonical).map_err(|_| {
-                BqDriftError::FileInclude(format!("Failed to read: {}", canonical.display()))
-            })?;
-
-            let included_base = canonical.parent().unwrap_or(base_dir);
-            let processed =
-                self.process_recursive(&included_content, included_base, root_base, visited)?;
+            let processed = if kind == "glob" {
+                let paths = self.resolve_glob(target, base_dir, root_base)?;
+                let mut parts = Vec::with_capacity(paths.len());
+                for path in &paths {
+                    let included = self.include_file(path, root_base, include_stack)?;
+                    parts.push(included.trim().to_string());
+                }
+                parts.join("\n")
+            } else {
+                let resolved_path = base_dir.join(target);
+                let canonical = resolved_path.canonicalize().map_err(|_| {
+                    BqDriftError::FileInclude(format!(
+                        "File not found: {}",
+                        resolved_path.display()
+                    ))
+                })?;
+                Self::check_within_root(&canonical, root_base, target)?;
+                self.include_file(&canonical, root_base, include_stack)?
+            };
 
             let indent = self.detect_indent(content, full_match.start());
             let indented = self.apply_indent(&processed, &indent, full_match.start(), content);
 
             result.push_str(&indented);
             last_end = full_match.end();
-
-            visited.remove(&canonical);
         }
 
         result.push_str(&content[last_end..]);
         Ok(result)
     }
 
+    /// Inline `canonical`, resolving its own includes relative to its
+    /// directory. `include_stack` holds the files currently being included,
+    /// so a file reached again through its own includes is reported as a
+    /// loop.
+    fn include_file(
+        &self,
+        canonical: &Path,
+        root_base: &Path,
+        include_stack: &mut Vec<PathBuf>,
+    ) -> Result<String> {
+        if let Some(pos) = include_stack.iter().position(|p| p == canonical) {
+            let chain: Vec<String> = include_stack[pos..]
+                .iter()
+                .map(PathBuf::as_path)
+                .chain(std::iter::once(canonical))
+                .map(|p| p.strip_prefix(root_base).unwrap_or(p).display().to_string())
+                .collect();
+            return Err(BqDriftError::DslParse(format!(
+                "Circular include detected: {}",
+                chain.join(" -> ")
+            )));
+        }
+
+        let included_content = fs::read_to_string(canonical).map_err(|_| {
+            BqDriftError::FileInclude(format!("Failed to read: {}", canonical.display()))
+        })?;
+
+        let included_base = canonical.parent().unwrap_or(root_base);
+        include_stack.push(canonical.to_path_buf());
+        let processed =
+            self.process_recursive(&included_content, included_base, root_base, include_stack)?;
+        include_stack.pop();
+        Ok(processed)
+    }
+
+    /// Expand a glob relative to `base_dir` into canonical paths, sorted so
+    /// the concatenated include is deterministic.
+    fn resolve_glob(
+        &self,
+        pattern: &str,
+        base_dir: &Path,
+        root_base: &Path,
+    ) -> Result<Vec<PathBuf>> {
+        let full_pattern = base_dir.join(pattern);
+        let entries = glob::glob(&full_pattern.to_string_lossy()).map_err(|e| {
+            BqDriftError::FileInclude(format!("Invalid glob pattern '{}': {}", pattern, e))
+        })?;
+
+        let mut paths = Vec::new();
+        for entry in entries {
+            let path = entry.map_err(|e| BqDriftError::FileInclude(e.to_string()))?;
+            if !path.is_file() {
+                continue;
+            }
+            let canonical = path.canonicalize().map_err(|_| {
+                BqDriftError::FileInclude(format!("File not found: {}", path.display()))
+            })?;
+            Self::check_within_root(&canonical, root_base, pattern)?;
+            paths.push(canonical);
+        }
+
+        if paths.is_empty() {
+            return Err(BqDriftError::FileInclude(format!(
+                "No files match glob: {}",
+                pattern
+            )));
+        }
+
+        paths.sort();
+        paths.dedup();
+        Ok(paths)
+    }
+
+    fn check_within_root(canonical: &Path, root_base: &Path, target: &str) -> Result<()> {
+        if canonical.starts_with(root_base) {
+            Ok(())
+        } else {
+            Err(BqDriftError::FileInclude(format!(
+                "Path traversal not allowed: {}",
+                target
+            )))
+        }
+    }
+
     fn detect_indent(&self, content: &str, match_start: usize) -> String {
         let before = &content[..match_start];
         if let Some(line_start) = before.rfind('\n') {
@@ -155,7 +395,7 @@ impl YamlPreprocessor {
     }
 
     pub fn has_file_includes(&self, content: &str) -> bool {
-        FILE_PATTERN.is_match(content)
+        INCLUDE_PATTERN.is_match(content)
     }
 
     pub fn extract_file_refs(&self, content: &str) -> Vec<String> {
@@ -258,8 +498,140 @@ mod tests {
         let input = "root: ${{ file: a.yaml }}";
         let result = preprocessor.process(input, dir.path());
 
-        assert!(result.is_err());
-        assert!(result.unwrap_err().to_string().contains("Circular"));
+        let err = result.unwrap_err();
+        assert!(matches!(err, BqDriftError::DslParse(_)));
+        assert!(err
+            .to_string()
+            .contains("Circular include detected: a.yaml -> b.yaml -> a.yaml"));
+    }
+
+    #[test]
+    fn test_nested_include_resolves_relative_to_included_file() {
+        let dir = setup_test_dir();
+        fs::create_dir_all(dir.path().join("sql/common")).unwrap();
+        fs::write(
+            dir.path().join("sql/b.sql"),
+            "SELECT * FROM (\n${{ file: common/c.sql }}\n)",
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("sql/common/c.sql"),
+            "SELECT id FROM raw.events",
+        )
+        .unwrap();
+
+        let preprocessor = YamlPreprocessor::new();
+        let result = preprocessor
+            .process("source: ${{ file: sql/b.sql }}", dir.path())
+            .unwrap();
+
+        assert!(result.contains("SELECT id FROM raw.events"));
+        assert!(!result.contains("${{"));
+    }
+
+    #[test]
+    fn test_env_tokens_expanded() {
+        let dir = setup_test_dir();
+        std::env::set_var("BQDRIFT_TEST_DATASET_PREFIX", "dev_");
+        fs::write(
+            dir.path().join("dest.yaml"),
+            "dataset: ${ENV:BQDRIFT_TEST_DATASET_PREFIX}analytics",
+        )
+        .unwrap();
+
+        let preprocessor = YamlPreprocessor::new();
+        let input = "destination: ${{ file: dest.yaml }}\nowner: ${ENV:BQDRIFT_TEST_UNSET_OWNER:-data-team}\nsource: ${{ versions.1.sql }}";
+        let result = preprocessor.process(input, dir.path()).unwrap();
+
+        assert!(result.contains("dataset: dev_analytics"));
+        assert!(result.contains("owner: data-team"));
+        assert!(result.contains("${{ versions.1.sql }}"));
+
+        let err = preprocessor
+            .process("dataset: ${ENV:BQDRIFT_TEST_UNSET_DATASET}", dir.path())
+            .unwrap_err();
+        assert!(matches!(err, BqDriftError::DslParse(_)));
+        assert!(err
+            .to_string()
+            .contains("Environment variable 'BQDRIFT_TEST_UNSET_DATASET' is not set"));
+    }
+
+    const SAMPLED_SQL: &str = "source: |\n  SELECT * FROM raw.events\n  {% if env == \"prod\" %}\n  WHERE TRUE\n  {% else %}\n  WHERE RAND() < 0.01\n  {% endif %}\n";
+
+    fn vars(env: &str) -> HashMap<String, String> {
+        HashMap::from([("env".to_string(), env.to_string())])
+    }
+
+    #[test]
+    fn test_conditional_keeps_if_branch() {
+        let dir = setup_test_dir();
+        let result = YamlPreprocessor::new()
+            .process_with_vars(SAMPLED_SQL, dir.path(), &vars("prod"))
+            .unwrap();
+
+        assert_eq!(
+            result,
+            "source: |\n  SELECT * FROM raw.events\n  WHERE TRUE\n"
+        );
+    }
+
+    #[test]
+    fn test_conditional_keeps_else_branch() {
+        let dir = setup_test_dir();
+        let result = YamlPreprocessor::new()
+            .process_with_vars(SAMPLED_SQL, dir.path(), &vars("dev"))
+            .unwrap();
+
+        assert_eq!(
+            result,
+            "source: |\n  SELECT * FROM raw.events\n  WHERE RAND() < 0.01\n"
+        );
+    }
+
+    #[test]
+    fn test_conditional_inline_and_in_included_file() {
+        let dir = setup_test_dir();
+        fs::write(
+            dir.path().join("query.sql"),
+            "SELECT * FROM {% if env == 'prod' %}raw.events{% else %}raw.events_sample{% endif %}",
+        )
+        .unwrap();
+
+        let result = YamlPreprocessor::new()
+            .process_with_vars("source: ${{ file: query.sql }}", dir.path(), &vars("dev"))
+            .unwrap();
+
+        assert_eq!(result, "source: SELECT * FROM raw.events_sample");
+    }
+
+    #[test]
+    fn test_conditional_errors() {
+        let dir = setup_test_dir();
+        let preprocessor = YamlPreprocessor::new();
+        let err = |input: &str| {
+            let err = preprocessor
+                .process_with_vars(input, dir.path(), &vars("prod"))
+                .unwrap_err();
+            assert!(matches!(err, BqDriftError::DslParse(_)));
+            err.to_string()
+        };
+
+        assert!(err("a: 1\n{% if env == \"prod\" %}\nb: 2\n")
+            .contains("{% if %} on line 2 has no matching {% endif %}"));
+        assert!(err("a: 1\n{% endif %}\n").contains("{% endif %} on line 2 has no matching"));
+        assert!(err("{% else %}\n").contains("{% else %} on line 1 has no matching"));
+        assert!(
+            err("{% if env == \"prod\" %}\n{% else %}\n{% else %}\n{% endif %}\n")
+                .contains("{% else %} on line 3")
+        );
+        assert!(err(
+            "{% if env == \"prod\" %}\n{% if env == \"prod\" %}\n{% endif %}\n{% endif %}\n"
+        )
+        .contains("Nested {% if %} on line 2 is not supported"));
+        assert!(
+            err("{% if region == \"eu\" %}\n{% endif %}\n").contains("Undefined variable 'region'")
+        );
+        assert!(err("{% for x in xs %}\n").contains("Unsupported tag {% for x in xs %}"));
     }
 
     #[test]
@@ -301,6 +673,54 @@ source: ${{ file: query.sql }}
         assert!(result.contains("versions:"));
     }
 
+    #[test]
+    fn test_glob_include_concatenates_sorted() {
+        let dir = setup_test_dir();
+        let fragments = dir.path().join("invariants");
+        fs::create_dir(&fragments).unwrap();
+        fs::write(
+            fragments.join("b_nulls.yaml"),
+            "- name: nulls\n  type: null_percentage",
+        )
+        .unwrap();
+        fs::write(
+            fragments.join("a_rows.yaml"),
+            "- name: rows\n  type: row_count",
+        )
+        .unwrap();
+        fs::write(fragments.join("notes.txt"), "ignored").unwrap();
+
+        let preprocessor = YamlPreprocessor::new();
+        let input = "after:\n  ${{ glob: invariants/*.yaml }}";
+        let result = preprocessor.process(input, dir.path()).unwrap();
+
+        assert_eq!(
+            result,
+            "after:\n  - name: rows\n    type: row_count\n  - name: nulls\n    type: null_percentage"
+        );
+        assert!(!result.contains("ignored"));
+    }
+
+    #[test]
+    fn test_glob_include_no_matches() {
+        let dir = setup_test_dir();
+        let preprocessor = YamlPreprocessor::new();
+        let result = preprocessor.process("after: ${{ glob: missing/*.yaml }}", dir.path());
+
+        assert!(result.unwrap_err().to_string().contains("No files match"));
+    }
+
+    #[test]
+    fn test_glob_include_circular_detection() {
+        let dir = setup_test_dir();
+        fs::write(dir.path().join("a.yaml"), "x: ${{ glob: *.yaml }}").unwrap();
+
+        let preprocessor = YamlPreprocessor::new();
+        let result = preprocessor.process("root: ${{ glob: *.yaml }}", dir.path());
+
+        assert!(result.unwrap_err().to_string().contains("Circular"));
+    }
+
     #[test]
     fn test_path_traversal_blocked() {
         let dir = setup_test_dir();
@@ -1,4 +1,4 @@
-use super::parser::{ExtendedSchema, SchemaRef};
+use super::parser::{ExtendedSchema, RawVersionDef, SchemaRef};
 use crate::error::{BqDriftError, Result};
 use crate::invariant::{ExtendedInvariants, InvariantDef, InvariantsDef, InvariantsRef};
 use crate::schema::{Field, Schema};
@@ -40,6 +40,16 @@ impl VariableResolver {
         }
     }
 
+    /// The version a schema is derived from via `${{ versions.N.schema }}`,
+    /// either whole or as the `base` of an extended schema.
+    pub fn schema_base_version(&self, schema_ref: &SchemaRef) -> Result<Option<u32>> {
+        match schema_ref {
+            SchemaRef::Inline(_) => Ok(None),
+            SchemaRef::Reference(ref_str) => self.extract_version_ref(ref_str).map(Some),
+            SchemaRef::Extended(ext) => self.extract_version_ref(&ext.base).map(Some),
+        }
+    }
+
     fn resolve_extended_schema(
         &self,
         ext: &ExtendedSchema,
@@ -52,18 +62,43 @@ impl VariableResolver {
 
         let mut fields: Vec<Field> = base_schema.fields.clone();
 
-        let removals: HashSet<&str> = ext.remove.iter().map(|s| s.as_str()).collect();
-        fields.retain(|f| !removals.contains(f.name.as_str()));
+        // Names may be dotted paths into RECORD sub-fields (`user.address.zip`)
+        for path in &ext.remove {
+            let (parent, leaf) = split_field_path(path);
+            if let Some(siblings) = nested_fields_mut(&mut fields, parent, path)? {
+                siblings.retain(|f| f.name != leaf);
+            }
+        }
 
         // Modify existing fields (replace by name)
         for modified in &ext.modify {
-            if let Some(field) = fields.iter_mut().find(|f| f.name == modified.name) {
-                *field = modified.clone();
+            let (parent, leaf) = split_field_path(&modified.name);
+            if let Some(siblings) = nested_fields_mut(&mut fields, parent, &modified.name)? {
+                if let Some(field) = siblings.iter_mut().find(|f| f.name == leaf) {
+                    *field = Field {
+                        name: leaf.to_string(),
+                        ..modified.clone()
+                    };
+                }
             }
         }
 
         // Add new fields
-        fields.extend(ext.add.clone());
+        for added in &ext.add {
+            let (parent, leaf) = split_field_path(&added.name);
+            match nested_fields_mut(&mut fields, parent, &added.name)? {
+                Some(siblings) => siblings.push(Field {
+                    name: leaf.to_string(),
+                    ..added.clone()
+                }),
+                None => {
+                    return Err(BqDriftError::Schema(format!(
+                        "Cannot add '{}': parent is not a RECORD",
+                        added.name
+                    )))
+                }
+            }
+        }
 
         Ok(Schema::from_fields(fields))
     }
@@ -115,6 +150,73 @@ impl VariableResolver {
         VARIABLE_PATTERN.is_match(s)
     }
 
+    /// Whether `s` is nothing but a single `${{ versions.N.field }}`.
+    pub fn is_whole_ref(&self, s: &str) -> bool {
+        let s = s.trim();
+        VARIABLE_PATTERN
+            .find(s)
+            .is_some_and(|m| m.start() == 0 && m.end() == s.len())
+    }
+
+    /// Check every `${{ versions.N.* }}` reference in `versions` up front,
+    /// independent of the order versions are resolved in. A reference must
+    /// name the field it stands for and point to an existing, lower-numbered
+    /// version that takes effect no later than the referencing one.
+    pub fn validate_version_refs(&self, versions: &[RawVersionDef]) -> Result<()> {
+        let by_number: HashMap<u32, &RawVersionDef> =
+            versions.iter().map(|v| (v.version, v)).collect();
+
+        for version in versions {
+            for (kind, ref_str) in self.version_refs(version) {
+                let caps = VARIABLE_PATTERN
+                    .captures(ref_str)
+                    .ok_or_else(|| BqDriftError::InvalidVersionRef(ref_str.to_string()))?;
+                let target: u32 = caps[1]
+                    .parse()
+                    .map_err(|_| BqDriftError::InvalidVersionRef(ref_str.to_string()))?;
+
+                let problem = match by_number.get(&target) {
+                    _ if &caps[2] != kind => Some(format!("expected '{}' field", kind)),
+                    None => Some("version does not exist".to_string()),
+                    Some(_) if target >= version.version => {
+                        Some("must reference a lower-numbered version".to_string())
+                    }
+                    Some(t) if t.effective_from > version.effective_from => Some(format!(
+                        "v{} takes effect later ({})",
+                        target, t.effective_from
+                    )),
+                    _ => None,
+                };
+
+                if let Some(problem) = problem {
+                    return Err(BqDriftError::InvalidVersionRef(format!(
+                        "v{} {} reference '{}': {}",
+                        version.version, kind, ref_str, problem
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn version_refs<'v>(&self, version: &'v RawVersionDef) -> Vec<(&'static str, &'v str)> {
+        let mut refs = Vec::new();
+        match &version.schema {
+            SchemaRef::Reference(r) => refs.push(("schema", r.as_str())),
+            SchemaRef::Extended(ext) => refs.push(("schema", ext.base.as_str())),
+            SchemaRef::Inline(_) => {}
+        }
+        if self.is_whole_ref(&version.source) {
+            refs.push(("sql", version.source.trim()));
+        }
+        match &version.invariants {
+            Some(InvariantsRef::Reference(r)) => refs.push(("invariants", r.as_str())),
+            Some(InvariantsRef::Extended(ext)) => refs.push(("invariants", ext.base.as_str())),
+            Some(InvariantsRef::Inline(_)) | None => {}
+        }
+        refs
+    }
+
     pub fn resolve_invariants(
         &self,
         inv_ref: &Option<InvariantsRef>,
@@ -144,6 +246,56 @@ impl VariableResolver {
         Ok(result)
     }
 
+    /// Validate query-level default invariants. Names must be unique per
+    /// phase, since merging by name would otherwise silently drop one.
+    pub fn resolve_default_invariants(
+        &self,
+        defaults: &Option<InvariantsDef>,
+    ) -> Result<InvariantsDef> {
+        let Some(defaults) = defaults else {
+            return Ok(InvariantsDef::default());
+        };
+
+        for (phase, invariants) in [("before", &defaults.before), ("after", &defaults.after)] {
+            let mut seen = HashSet::new();
+            for inv in invariants {
+                if !seen.insert(inv.name.as_str()) {
+                    return Err(BqDriftError::Validation(format!(
+                        "Duplicate default invariant '{}' ({})",
+                        inv.name, phase
+                    )));
+                }
+            }
+        }
+
+        self.validate_invariants_def(defaults)?;
+        Ok(defaults.clone())
+    }
+
+    /// Merge query-level defaults into a version's invariants. A version
+    /// invariant with the same name as a default replaces it.
+    pub fn merge_default_invariants(
+        &self,
+        defaults: &InvariantsDef,
+        version: InvariantsDef,
+    ) -> InvariantsDef {
+        fn merge(defaults: &[InvariantDef], own: Vec<InvariantDef>) -> Vec<InvariantDef> {
+            let own_names: HashSet<&str> = own.iter().map(|i| i.name.as_str()).collect();
+            let mut merged: Vec<InvariantDef> = defaults
+                .iter()
+                .filter(|d| !own_names.contains(d.name.as_str()))
+                .cloned()
+                .collect();
+            merged.extend(own);
+            merged
+        }
+
+        InvariantsDef {
+            before: merge(&defaults.before, version.before),
+            after: merge(&defaults.after, version.after),
+        }
+    }
+
     fn validate_invariants_def(&self, def: &InvariantsDef) -> Result<()> {
         for inv in &def.before {
             if let Err(msg) = inv.check.validate() {
@@ -245,6 +397,37 @@ impl VariableResolver {
     }
 }
 
+/// Split `a.b.c` into (`["a", "b"]`, `"c"`).
+fn split_field_path(path: &str) -> (Vec<&str>, &str) {
+    let mut parts: Vec<&str> = path.split('.').collect();
+    let leaf = parts.pop().unwrap_or(path);
+    (parts, leaf)
+}
+
+/// Sub-fields of the RECORD at `parent`, or the top-level fields when
+/// `parent` is empty. `Ok(None)` means the parent exists but holds no
+/// sub-fields; a missing parent is an error.
+fn nested_fields_mut<'a>(
+    fields: &'a mut Vec<Field>,
+    parent: Vec<&str>,
+    full_path: &str,
+) -> Result<Option<&'a mut Vec<Field>>> {
+    let mut current = fields;
+    for part in parent {
+        let field = current.iter_mut().find(|f| f.name == part).ok_or_else(|| {
+            BqDriftError::Schema(format!(
+                "Field path '{}': '{}' not found in base schema",
+                full_path, part
+            ))
+        })?;
+        match field.fields.as_mut() {
+            Some(sub_fields) => current = sub_fields,
+            None => return Ok(None),
+        }
+    }
+    Ok(Some(current))
+}
+
 impl Default for VariableResolver {
     fn default() -> Self {
         Self::new()
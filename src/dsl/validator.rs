@@ -1,5 +1,11 @@
+use super::dependencies::SqlDependencies;
 use super::parser::QueryDef;
+use crate::error::{BqDriftError, Result};
 use crate::schema::BqType;
+use chrono::NaiveDate;
+use sqlparser::ast::{SelectItem, SetExpr, Statement};
+use sqlparser::dialect::BigQueryDialect;
+use sqlparser::parser::Parser;
 
 #[derive(Debug, Clone)]
 pub struct ValidationResult {
@@ -30,22 +36,83 @@ impl ValidationResult {
     }
 }
 
+#[derive(Debug, Clone)]
+pub struct ValidatorConfig {
+    pub select_star: bool,
+    /// Glob patterns for tables read from outside the loaded queries. When
+    /// set, `validate_catalog` warns about dependencies matching none of
+    /// them and produced by no query.
+    pub external_sources: Option<Vec<String>>,
+    /// Earliest partition date with source data; a `backfill_since` before
+    /// it fails validation.
+    pub data_available_from: Option<NaiveDate>,
+}
+
+impl ValidatorConfig {
+    pub fn new() -> Self {
+        Self {
+            select_star: true,
+            external_sources: None,
+            data_available_from: None,
+        }
+    }
+
+    pub fn with_select_star(mut self, enabled: bool) -> Self {
+        self.select_star = enabled;
+        self
+    }
+
+    pub fn with_data_available_from(mut self, date: NaiveDate) -> Self {
+        self.data_available_from = Some(date);
+        self
+    }
+
+    /// Enable the W008 unknown dependency check, allowing tables that match
+    /// any of `patterns` (e.g. `raw_*.*`).
+    pub fn with_external_sources<I, S>(mut self, patterns: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.external_sources
+            .get_or_insert_with(Vec::new)
+            .extend(patterns.into_iter().map(Into::into));
+        self
+    }
+}
+
+impl Default for ValidatorConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 pub struct QueryValidator;
 
 impl QueryValidator {
     pub fn validate(query: &QueryDef) -> ValidationResult {
+        Self::validate_with_config(query, &ValidatorConfig::default())
+    }
+
+    pub fn validate_with_config(query: &QueryDef, config: &ValidatorConfig) -> ValidationResult {
         let mut errors = Vec::new();
         let mut warnings = Vec::new();
 
+        Self::check_destination_identifiers(query, &mut errors);
         Self::check_partition_field(query, &mut errors);
-        Self::check_cluster_fields(query, &mut errors);
         Self::check_duplicate_versions(query, &mut errors);
         Self::check_record_fields(query, &mut errors);
+        Self::check_revision_windows(query, &mut errors);
+        Self::check_effective_from_ties(query, &mut errors);
+        Self::check_backfill_since(query, config.data_available_from, &mut errors);
         Self::check_effective_from_order(query, &mut warnings);
         Self::check_duplicate_revisions(query, &mut warnings);
         Self::check_schema_breaking_changes(query, &mut warnings);
         Self::check_sql_partition_placeholder(query, &mut warnings);
         Self::check_empty_schema(query, &mut warnings);
+        if config.select_star {
+            Self::check_select_star(query, &mut warnings);
+        }
 
         ValidationResult {
             query_name: query.name.clone(),
@@ -54,6 +121,77 @@ impl QueryValidator {
         }
     }
 
+    /// Validate every query in `queries`, also running the checks that need
+    /// the whole catalog. Fails only for an invalid external source pattern.
+    pub fn validate_catalog(
+        queries: &[QueryDef],
+        config: &ValidatorConfig,
+    ) -> Result<Vec<ValidationResult>> {
+        let external = match &config.external_sources {
+            Some(patterns) => Some(
+                patterns
+                    .iter()
+                    .map(|p| {
+                        glob::Pattern::new(p).map_err(|e| {
+                            BqDriftError::Validation(format!(
+                                "Invalid external source pattern '{}': {}",
+                                p, e
+                            ))
+                        })
+                    })
+                    .collect::<Result<Vec<_>>>()?,
+            ),
+            None => None,
+        };
+
+        Ok(queries
+            .iter()
+            .map(|query| {
+                let mut result = Self::validate_with_config(query, config);
+                if let Some(external) = &external {
+                    Self::check_unknown_dependencies(
+                        query,
+                        queries,
+                        external,
+                        &mut result.warnings,
+                    );
+                }
+                result
+            })
+            .collect())
+    }
+
+    fn check_destination_identifiers(query: &QueryDef, errors: &mut Vec<ValidationError>) {
+        let destination = &query.destination;
+        let mut checks = vec![
+            (
+                "dataset",
+                destination.dataset.as_str(),
+                dataset_id_violation(&destination.dataset),
+            ),
+            (
+                "table",
+                destination.table.as_str(),
+                table_id_violation(&destination.table),
+            ),
+        ];
+        if let Some(project) = &destination.project {
+            checks.insert(
+                0,
+                ("project", project.as_str(), project_id_violation(project)),
+            );
+        }
+
+        for (kind, value, violation) in checks {
+            if let Some(rule) = violation {
+                errors.push(ValidationError {
+                    code: "E007",
+                    message: format!("invalid destination {} '{}': {}", kind, value, rule),
+                });
+            }
+        }
+    }
+
     fn check_partition_field(query: &QueryDef, errors: &mut Vec<ValidationError>) {
         if let Some(ref partition_field) = query.destination.partition.field {
             for version in &query.versions {
@@ -61,8 +199,8 @@ impl QueryValidator {
                     errors.push(ValidationError {
                         code: "E001",
                         message: format!(
-                            "v{}: partition field '{}' not found in schema",
-                            version.version, partition_field
+                            "v{}: partition field '{}' not found in schema of query '{}'",
+                            version.version, partition_field, query.name
                         ),
                     });
                 }
@@ -70,24 +208,6 @@ impl QueryValidator {
         }
     }
 
-    fn check_cluster_fields(query: &QueryDef, errors: &mut Vec<ValidationError>) {
-        if let Some(ref cluster) = query.cluster {
-            for version in &query.versions {
-                for field in &cluster.fields {
-                    if !version.schema.has_field(field) {
-                        errors.push(ValidationError {
-                            code: "E002",
-                            message: format!(
-                                "v{}: cluster field '{}' not found in schema",
-                                version.version, field
-                            ),
-                        });
-                    }
-                }
-            }
-        }
-    }
-
     fn check_duplicate_versions(query: &QueryDef, errors: &mut Vec<ValidationError>) {
         let mut seen = std::collections::HashSet::new();
         for version in &query.versions {
@@ -158,6 +278,110 @@ impl QueryValidator {
         }
     }
 
+    fn check_revision_windows(query: &QueryDef, errors: &mut Vec<ValidationError>) {
+        let mut ordered: Vec<_> = query.versions.iter().collect();
+        ordered.sort_by_key(|v| v.effective_from);
+
+        for (i, version) in ordered.iter().enumerate() {
+            let next = ordered.get(i + 1);
+            for revision in &version.revisions {
+                if revision.effective_from < version.effective_from {
+                    errors.push(ValidationError {
+                        code: "E006",
+                        message: format!(
+                            "v{}.r{}: effective_from ({}) is before version effective_from ({})",
+                            version.version,
+                            revision.revision,
+                            revision.effective_from,
+                            version.effective_from
+                        ),
+                    });
+                } else if let Some(next) =
+                    next.filter(|n| revision.effective_from >= n.effective_from)
+                {
+                    errors.push(ValidationError {
+                        code: "E006",
+                        message: format!(
+                            "v{}.r{}: effective_from ({}) is not before v{} effective_from ({})",
+                            version.version,
+                            revision.revision,
+                            revision.effective_from,
+                            next.version,
+                            next.effective_from
+                        ),
+                    });
+                }
+            }
+        }
+    }
+
+    /// Two versions starting on the same date leave no window for one of
+    /// them, and which one a partition resolves to depends on load order.
+    fn check_effective_from_ties(query: &QueryDef, errors: &mut Vec<ValidationError>) {
+        let mut ordered: Vec<_> = query.versions.iter().collect();
+        ordered.sort_by_key(|v| (v.effective_from, v.version));
+
+        for pair in ordered.windows(2) {
+            let (prev, curr) = (pair[0], pair[1]);
+            if prev.effective_from == curr.effective_from && prev.version != curr.version {
+                errors.push(ValidationError {
+                    code: "E009",
+                    message: format!(
+                        "v{} and v{} share effective_from ({})",
+                        prev.version, curr.version, curr.effective_from
+                    ),
+                });
+            }
+        }
+    }
+
+    /// `backfill_since` must not be after the `effective_from` it belongs
+    /// to, nor before `floor`, where there is no data to rebuild from.
+    fn check_backfill_since(
+        query: &QueryDef,
+        floor: Option<NaiveDate>,
+        errors: &mut Vec<ValidationError>,
+    ) {
+        let mut check = |location: String, backfill_since: Option<NaiveDate>, effective_from| {
+            let Some(backfill_since) = backfill_since else {
+                return;
+            };
+            if backfill_since > effective_from {
+                errors.push(ValidationError {
+                    code: "E010",
+                    message: format!(
+                        "{}: backfill_since ({}) is after effective_from ({})",
+                        location, backfill_since, effective_from
+                    ),
+                });
+            }
+            if let Some(floor) = floor.filter(|&floor| backfill_since < floor) {
+                errors.push(ValidationError {
+                    code: "E010",
+                    message: format!(
+                        "{}: backfill_since ({}) is before data is available ({})",
+                        location, backfill_since, floor
+                    ),
+                });
+            }
+        };
+
+        for version in &query.versions {
+            check(
+                format!("v{}", version.version),
+                version.backfill_since,
+                version.effective_from,
+            );
+            for revision in &version.revisions {
+                check(
+                    format!("v{}.r{}", version.version, revision.revision),
+                    revision.backfill_since,
+                    revision.effective_from,
+                );
+            }
+        }
+    }
+
     fn check_effective_from_order(query: &QueryDef, warnings: &mut Vec<ValidationWarning>) {
         let mut indices: Vec<usize> = (0..query.versions.len()).collect();
         indices.sort_by_key(|&i| query.versions[i].version);
@@ -236,6 +460,11 @@ impl QueryValidator {
     }
 
     fn check_sql_partition_placeholder(query: &QueryDef, warnings: &mut Vec<ValidationWarning>) {
+        // An unpartitioned table is rewritten whole, so its SQL needn't
+        // filter by date.
+        if !query.destination.partition.is_partitioned() {
+            return;
+        }
         for version in &query.versions {
             if !version.sql_content.contains("@partition_date")
                 && !version.sql_content.contains("@run_date")
@@ -269,7 +498,7 @@ impl QueryValidator {
 
     fn check_empty_schema(query: &QueryDef, warnings: &mut Vec<ValidationWarning>) {
         for version in &query.versions {
-            if version.schema.fields.is_empty() {
+            if version.schema.is_empty() {
                 warnings.push(ValidationWarning {
                     code: "W006",
                     message: format!("v{}: schema has no fields", version.version),
@@ -277,12 +506,184 @@ impl QueryValidator {
             }
         }
     }
+
+    fn check_select_star(query: &QueryDef, warnings: &mut Vec<ValidationWarning>) {
+        for version in &query.versions {
+            if has_top_level_wildcard(&version.sql_content) {
+                warnings.push(ValidationWarning {
+                    code: "W007",
+                    message: format!(
+                        "v{}: SQL selects * at the top level; list columns explicitly to match the declared schema",
+                        version.version
+                    ),
+                });
+            }
+
+            for revision in &version.revisions {
+                if has_top_level_wildcard(&revision.sql_content) {
+                    warnings.push(ValidationWarning {
+                        code: "W007",
+                        message: format!(
+                            "v{}.r{}: SQL selects * at the top level; list columns explicitly to match the declared schema",
+                            version.version, revision.revision
+                        ),
+                    });
+                }
+            }
+        }
+    }
+
+    fn check_unknown_dependencies(
+        query: &QueryDef,
+        catalog: &[QueryDef],
+        external: &[glob::Pattern],
+        warnings: &mut Vec<ValidationWarning>,
+    ) {
+        let is_known = |table: &str| {
+            SqlDependencies::find_producer(table, catalog).is_some()
+                || is_external_source(table, external)
+        };
+
+        for version in &query.versions {
+            let locations =
+                std::iter::once((format!("v{}", version.version), &version.dependencies)).chain(
+                    version.revisions.iter().map(|r| {
+                        (
+                            format!("v{}.r{}", version.version, r.revision),
+                            &r.dependencies,
+                        )
+                    }),
+                );
+            for (location, dependencies) in locations {
+                let mut unknown: Vec<&String> =
+                    dependencies.iter().filter(|t| !is_known(t)).collect();
+                unknown.sort();
+                for table in unknown {
+                    warnings.push(ValidationWarning {
+                        code: "W008",
+                        message: format!(
+                            "{}: dependency '{}' is not produced by any query and matches no external source",
+                            location, table
+                        ),
+                    });
+                }
+            }
+        }
+    }
+}
+
+/// A project-qualified table also matches on its `dataset.table` part, so
+/// `raw_*.*` allows `my-project.raw_events.clicks`.
+fn is_external_source(table: &str, patterns: &[glob::Pattern]) -> bool {
+    let table = table.replace('`', "");
+    let parts: Vec<&str> = table.split('.').collect();
+    let dataset_table = parts[parts.len().saturating_sub(2)..].join(".");
+    patterns
+        .iter()
+        .any(|p| p.matches(&table) || p.matches(&dataset_table))
+}
+
+const MAX_IDENTIFIER_LEN: usize = 1024;
+
+fn dataset_id_violation(id: &str) -> Option<String> {
+    identifier_violation(
+        id,
+        |c| c.is_ascii_alphanumeric() || c == '_',
+        "letters, digits and underscores",
+    )
+}
+
+fn table_id_violation(id: &str) -> Option<String> {
+    identifier_violation(
+        id,
+        |c| c.is_ascii_alphanumeric() || c == '_' || c == '-',
+        "letters, digits, underscores and dashes",
+    )
+}
+
+fn identifier_violation(
+    id: &str,
+    allowed: impl Fn(char) -> bool,
+    allowed_desc: &str,
+) -> Option<String> {
+    if id.is_empty() {
+        return Some("must not be empty".to_string());
+    }
+    if id.len() > MAX_IDENTIFIER_LEN {
+        return Some(format!(
+            "must be at most {} characters, got {}",
+            MAX_IDENTIFIER_LEN,
+            id.len()
+        ));
+    }
+    if let Some(c) = id.chars().find(|&c| !allowed(c)) {
+        return Some(format!(
+            "character {:?} not allowed, use only {}",
+            c, allowed_desc
+        ));
+    }
+    if id.starts_with(|c: char| c.is_ascii_digit()) {
+        return Some("must start with a letter or underscore".to_string());
+    }
+    None
+}
+
+/// Project IDs are 6-30 lowercase letters, digits or hyphens, start with a
+/// letter and don't end with a hyphen. A `domain:` prefix is allowed.
+fn project_id_violation(id: &str) -> Option<String> {
+    let name = id.rsplit_once(':').map_or(id, |(_, name)| name);
+    if !(6..=30).contains(&name.len()) {
+        return Some(format!("must be 6 to 30 characters, got {}", name.len()));
+    }
+    if let Some(c) = name
+        .chars()
+        .find(|&c| !(c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-'))
+    {
+        return Some(format!(
+            "character {:?} not allowed, use only lowercase letters, digits and hyphens",
+            c
+        ));
+    }
+    if !name.starts_with(|c: char| c.is_ascii_lowercase()) {
+        return Some("must start with a lowercase letter".to_string());
+    }
+    if name.ends_with('-') {
+        return Some("must not end with a hyphen".to_string());
+    }
+    None
+}
+
+fn has_top_level_wildcard(sql: &str) -> bool {
+    let Ok(statements) = Parser::parse_sql(&BigQueryDialect {}, sql) else {
+        return false;
+    };
+    statements.iter().any(|statement| match statement {
+        Statement::Query(query) => set_expr_has_wildcard(&query.body),
+        _ => false,
+    })
+}
+
+fn set_expr_has_wildcard(set_expr: &SetExpr) -> bool {
+    match set_expr {
+        SetExpr::Select(select) => select.projection.iter().any(|item| {
+            matches!(
+                item,
+                SelectItem::Wildcard(_) | SelectItem::QualifiedWildcard(_, _)
+            )
+        }),
+        SetExpr::Query(query) => set_expr_has_wildcard(&query.body),
+        SetExpr::SetOperation { left, right, .. } => {
+            set_expr_has_wildcard(left) || set_expr_has_wildcard(right)
+        }
+        _ => false,
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::dsl::QueryLoader;
+    use chrono::NaiveDate;
     use std::path::Path;
 
     #[test]
@@ -306,4 +707,239 @@ mod tests {
 
         assert!(result.is_valid());
     }
+
+    #[test]
+    fn test_partition_field_missing_from_schema() {
+        let loader = QueryLoader::new();
+        let mut query = loader
+            .load_query(Path::new("tests/fixtures/analytics/versioned_query.yaml"))
+            .unwrap();
+        query.destination.partition.field = Some("event_date".to_string());
+
+        let result = QueryValidator::validate(&query);
+        assert!(!result.is_valid());
+
+        let e001: Vec<_> = result.errors.iter().filter(|e| e.code == "E001").collect();
+        assert_eq!(e001.len(), query.versions.len());
+        assert!(e001[0].message.contains("'event_date'"));
+        assert!(e001[0].message.contains("'versioned_query'"));
+    }
+
+    #[test]
+    fn test_revision_before_version_effective_from() {
+        let loader = QueryLoader::new();
+        let mut query = loader
+            .load_query(Path::new("tests/fixtures/analytics/versioned_query.yaml"))
+            .unwrap();
+        let v2 = query.versions.iter_mut().find(|v| v.version == 2).unwrap();
+        v2.revisions[0].effective_from = NaiveDate::from_ymd_opt(2024, 2, 15).unwrap();
+
+        let result = QueryValidator::validate(&query);
+        assert!(!result.is_valid());
+
+        let e006: Vec<_> = result.errors.iter().filter(|e| e.code == "E006").collect();
+        assert_eq!(e006.len(), 1);
+        assert!(e006[0].message.starts_with("v2.r1:"));
+        assert!(e006[0].message.contains("before version effective_from"));
+    }
+
+    #[test]
+    fn test_revision_after_next_version_effective_from() {
+        let loader = QueryLoader::new();
+        let mut query = loader
+            .load_query(Path::new("tests/fixtures/analytics/versioned_query.yaml"))
+            .unwrap();
+        let v2 = query.versions.iter_mut().find(|v| v.version == 2).unwrap();
+        v2.revisions[0].effective_from = NaiveDate::from_ymd_opt(2024, 6, 1).unwrap();
+
+        let result = QueryValidator::validate(&query);
+        assert!(!result.is_valid());
+
+        let e006: Vec<_> = result.errors.iter().filter(|e| e.code == "E006").collect();
+        assert_eq!(e006.len(), 1);
+        assert!(e006[0].message.starts_with("v2.r1:"));
+        assert!(e006[0].message.contains("v3"));
+    }
+
+    #[test]
+    fn test_versions_sharing_effective_from() {
+        let loader = QueryLoader::new();
+        let mut query = loader
+            .load_query(Path::new("tests/fixtures/analytics/versioned_query.yaml"))
+            .unwrap();
+        let v2_from = query
+            .versions
+            .iter()
+            .find(|v| v.version == 2)
+            .unwrap()
+            .effective_from;
+        let v3 = query.versions.iter_mut().find(|v| v.version == 3).unwrap();
+        v3.effective_from = v2_from;
+
+        let result = QueryValidator::validate(&query);
+        let e009: Vec<_> = result.errors.iter().filter(|e| e.code == "E009").collect();
+        assert_eq!(e009.len(), 1);
+        assert_eq!(
+            e009[0].message,
+            format!("v2 and v3 share effective_from ({})", v2_from)
+        );
+    }
+
+    #[test]
+    fn test_destination_identifier_rules() {
+        assert!(dataset_id_violation("analytics_v2").is_none());
+        assert!(dataset_id_violation("my dataset")
+            .unwrap()
+            .contains("' ' not allowed"));
+        assert!(dataset_id_violation("my-dataset").is_some());
+        assert!(dataset_id_violation("2024_stats")
+            .unwrap()
+            .contains("must start with a letter"));
+        assert!(dataset_id_violation(&"a".repeat(1025))
+            .unwrap()
+            .contains("at most 1024"));
+
+        assert!(table_id_violation("daily-stats_v1").is_none());
+        assert!(table_id_violation("").is_some());
+
+        assert!(project_id_violation("my-project-123").is_none());
+        assert!(project_id_violation("example.com:my-project").is_none());
+        assert!(project_id_violation("proj").is_some());
+        assert!(project_id_violation("My-Project").is_some());
+        assert!(project_id_violation("my-project-").is_some());
+    }
+
+    #[test]
+    fn test_invalid_destination_table_fails_validation() {
+        let loader = QueryLoader::new();
+        let mut query = loader
+            .load_query(Path::new("tests/fixtures/analytics/simple_query.yaml"))
+            .unwrap();
+        query.destination.table = "daily stats".to_string();
+
+        let result = QueryValidator::validate(&query);
+        assert!(!result.is_valid());
+
+        let e007: Vec<_> = result.errors.iter().filter(|e| e.code == "E007").collect();
+        assert_eq!(e007.len(), 1);
+        assert!(e007[0].message.contains("destination table 'daily stats'"));
+        assert!(e007[0].message.contains("' ' not allowed"));
+    }
+
+    #[test]
+    fn test_select_star_detection() {
+        assert!(has_top_level_wildcard(
+            "SELECT * FROM events WHERE date = @partition_date"
+        ));
+        assert!(has_top_level_wildcard("SELECT e.* FROM events e"));
+        assert!(has_top_level_wildcard(
+            "SELECT id FROM a UNION ALL SELECT * FROM b"
+        ));
+        assert!(!has_top_level_wildcard(
+            "SELECT COUNT(*) AS cnt FROM events"
+        ));
+        assert!(!has_top_level_wildcard(
+            "SELECT id, name FROM (SELECT * FROM events)"
+        ));
+        assert!(!has_top_level_wildcard(
+            "WITH src AS (SELECT * FROM events) SELECT id FROM src"
+        ));
+    }
+
+    #[test]
+    fn test_select_star_lint_is_configurable() {
+        let loader = QueryLoader::new();
+        let mut query = loader
+            .load_query(Path::new("tests/fixtures/analytics/simple_query.yaml"))
+            .unwrap();
+        query.versions[0].sql_content =
+            "SELECT * FROM events WHERE date = @partition_date".to_string();
+
+        let result = QueryValidator::validate(&query);
+        assert!(result.is_valid());
+        assert!(result.warnings.iter().any(|w| w.code == "W007"));
+
+        let config = ValidatorConfig::new().with_select_star(false);
+        let result = QueryValidator::validate_with_config(&query, &config);
+        assert!(!result.warnings.iter().any(|w| w.code == "W007"));
+    }
+
+    #[test]
+    fn test_unknown_dependencies_warn_against_catalog_and_allowlist() {
+        let loader = QueryLoader::new();
+        let upstream = loader
+            .load_query(Path::new("tests/fixtures/analytics/simple_query.yaml"))
+            .unwrap();
+        let mut query = loader
+            .load_query(Path::new("tests/fixtures/analytics/versioned_query.yaml"))
+            .unwrap();
+        query.versions.truncate(1);
+        query.versions[0].dependencies = [
+            "test_dataset.simple_table",
+            "raw_events.clicks",
+            "`my-project.raw_users.profiles`",
+            "analtyics.events",
+        ]
+        .into_iter()
+        .map(String::from)
+        .collect();
+        let catalog = vec![upstream, query];
+
+        let results = QueryValidator::validate_catalog(&catalog, &ValidatorConfig::new()).unwrap();
+        assert!(!results[1].warnings.iter().any(|w| w.code == "W008"));
+
+        let config = ValidatorConfig::new().with_external_sources(["raw_*.*"]);
+        let results = QueryValidator::validate_catalog(&catalog, &config).unwrap();
+        let w008: Vec<_> = results[1]
+            .warnings
+            .iter()
+            .filter(|w| w.code == "W008")
+            .collect();
+        assert_eq!(w008.len(), 1);
+        assert!(w008[0]
+            .message
+            .contains("v1: dependency 'analtyics.events'"));
+        assert!(results[1].is_valid());
+
+        let config = ValidatorConfig::new().with_external_sources(["raw_[.*"]);
+        let err = QueryValidator::validate_catalog(&catalog, &config).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("Invalid external source pattern 'raw_[.*'"));
+    }
+
+    #[test]
+    fn test_backfill_since_bounds() {
+        let loader = QueryLoader::new();
+        let mut query = loader
+            .load_query(Path::new("tests/fixtures/analytics/versioned_query.yaml"))
+            .unwrap();
+        let date = |m, d| NaiveDate::from_ymd_opt(2024, m, d).unwrap();
+        let e010 = |result: &ValidationResult| -> Vec<String> {
+            result
+                .errors
+                .iter()
+                .filter(|e| e.code == "E010")
+                .map(|e| e.message.clone())
+                .collect()
+        };
+
+        // v2 starts 2024-03-01; its r1 runs from 2024-03-15 with backfill_since 2024-03-01
+        let config = ValidatorConfig::new().with_data_available_from(date(1, 1));
+        assert!(e010(&QueryValidator::validate_with_config(&query, &config)).is_empty());
+
+        let config = ValidatorConfig::new().with_data_available_from(date(3, 2));
+        assert_eq!(
+            e010(&QueryValidator::validate_with_config(&query, &config)),
+            vec!["v2.r1: backfill_since (2024-03-01) is before data is available (2024-03-02)"]
+        );
+
+        let v2 = query.versions.iter_mut().find(|v| v.version == 2).unwrap();
+        v2.backfill_since = Some(date(3, 2));
+        v2.revisions[0].backfill_since = Some(date(3, 15));
+        assert_eq!(
+            e010(&QueryValidator::validate(&query)),
+            vec!["v2: backfill_since (2024-03-02) is after effective_from (2024-03-01)"]
+        );
+    }
 }
@@ -200,6 +200,22 @@ impl BigQueryError {
             BigQueryError::Unknown { .. } => "UNKNOWN",
         }
     }
+
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            BigQueryError::ConnectionFailed { .. } | BigQueryError::Timeout { .. } => true,
+            BigQueryError::QuotaExceeded { quota_type, .. } => {
+                matches!(
+                    quota_type.as_str(),
+                    "concurrent queries" | "rate limit" | "API"
+                )
+            }
+            BigQueryError::Unknown {
+                code: Some(code), ..
+            } => code == "backendError" || code.starts_with("HTTP_5"),
+            _ => false,
+        }
+    }
 }
 
 impl fmt::Display for BigQueryError {
@@ -706,4 +722,34 @@ mod tests {
         assert!(debug.contains("5"));
         assert!(debug.contains("100"));
     }
+
+    #[test]
+    fn test_is_retryable() {
+        assert!(BigQueryError::ConnectionFailed {
+            reason: "reset".into()
+        }
+        .is_retryable());
+        assert!(BigQueryError::QuotaExceeded {
+            quota_type: "rate limit".into(),
+            message: "Rate limit exceeded".into(),
+        }
+        .is_retryable());
+        assert!(!BigQueryError::QuotaExceeded {
+            quota_type: "daily query limit".into(),
+            message: "Daily limit exceeded".into(),
+        }
+        .is_retryable());
+        assert!(BigQueryError::Unknown {
+            code: Some("HTTP_503".into()),
+            message: "unavailable".into(),
+            raw_error: String::new(),
+        }
+        .is_retryable());
+        assert!(!BigQueryError::TableNotFound {
+            project: "p".into(),
+            dataset: "d".into(),
+            table: "t".into(),
+        }
+        .is_retryable());
+    }
 }
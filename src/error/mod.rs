@@ -17,6 +17,9 @@ pub enum BqDriftError {
     #[error("Schema error: {0}")]
     Schema(String),
 
+    #[error("Schema parse error: {0}")]
+    SchemaParse(String),
+
     #[error("DSL parse error: {0}")]
     DslParse(String),
 
@@ -41,6 +44,9 @@ pub enum BqDriftError {
     #[error("Partition error: {0}")]
     Partition(String),
 
+    #[error("Version blocked: {0}")]
+    VersionBlocked(String),
+
     #[error("Cluster error: {0}")]
     Cluster(String),
 
@@ -72,4 +78,13 @@ pub enum BqDriftError {
     Json(#[from] serde_json::Error),
 }
 
+impl BqDriftError {
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            BqDriftError::BigQuery(e) => e.is_retryable(),
+            _ => false,
+        }
+    }
+}
+
 pub type Result<T> = std::result::Result<T, BqDriftError>;
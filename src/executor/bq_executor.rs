@@ -1,3 +1,5 @@
+use crate::schema::Schema;
+
 #[derive(Debug, Clone)]
 pub struct ColumnInfo {
     pub name: String,
@@ -6,6 +8,38 @@ pub struct ColumnInfo {
 
 pub type ColumnDef = ColumnInfo;
 
+#[derive(Debug, Clone)]
+pub struct DryRunResult {
+    pub schema: Schema,
+    pub total_bytes_processed: Option<i64>,
+}
+
+/// Job statistics for an executed query. Fields are `None` when BigQuery
+/// did not report them (e.g. the job was still running).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct QueryStats {
+    pub total_bytes_processed: Option<i64>,
+    pub total_bytes_billed: Option<i64>,
+    pub total_slot_ms: Option<i64>,
+}
+
+impl QueryStats {
+    /// Combined stats of two jobs run for the same write.
+    pub fn combine(self, other: QueryStats) -> QueryStats {
+        fn add(a: Option<i64>, b: Option<i64>) -> Option<i64> {
+            match (a, b) {
+                (Some(a), Some(b)) => Some(a.saturating_add(b)),
+                (a, b) => a.or(b),
+            }
+        }
+        QueryStats {
+            total_bytes_processed: add(self.total_bytes_processed, other.total_bytes_processed),
+            total_bytes_billed: add(self.total_bytes_billed, other.total_bytes_billed),
+            total_slot_ms: add(self.total_slot_ms, other.total_slot_ms),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct QueryResult {
     pub columns: Vec<ColumnInfo>,
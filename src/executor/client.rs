@@ -1,5 +1,7 @@
+use super::bq_executor::{DryRunResult, QueryStats};
 use crate::dsl::QueryDef;
 use crate::error::{parse_bq_error, BqDriftError, ErrorContext, Result};
+use crate::schema::PartitionKey;
 use crate::schema::{
     BqType, ClusterConfig, Field, FieldMode, PartitionConfig, PartitionType, Schema,
 };
@@ -7,17 +9,29 @@ use chrono::{DateTime, Utc};
 use gcp_bigquery_client::model::clustering::Clustering;
 use gcp_bigquery_client::model::dataset::Dataset;
 use gcp_bigquery_client::model::field_type::FieldType;
+use gcp_bigquery_client::model::job::Job;
+use gcp_bigquery_client::model::job_configuration::JobConfiguration;
+use gcp_bigquery_client::model::job_configuration_table_copy::JobConfigurationTableCopy;
 use gcp_bigquery_client::model::query_request::QueryRequest;
+use gcp_bigquery_client::model::range_partitioning::RangePartitioning;
+use gcp_bigquery_client::model::range_partitioning_range::RangePartitioningRange;
 use gcp_bigquery_client::model::table::Table;
 use gcp_bigquery_client::model::table_field_schema::TableFieldSchema;
+use gcp_bigquery_client::model::table_field_schema_policy::TableFieldSchemaPolicyTags;
+use gcp_bigquery_client::model::table_reference::TableReference;
 use gcp_bigquery_client::model::table_schema::TableSchema;
 use gcp_bigquery_client::model::time_partitioning::TimePartitioning;
 use gcp_bigquery_client::Client;
+use std::collections::{BTreeMap, HashMap};
+use tracing::warn;
+
+const COPY_JOB_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
 
 #[derive(Clone)]
 pub struct BqClient {
     client: Client,
     project_id: String,
+    job_labels: HashMap<String, String>,
 }
 
 impl BqClient {
@@ -32,35 +46,89 @@ impl BqClient {
         Ok(Self {
             client,
             project_id: project_id.into(),
+            job_labels: HashMap::new(),
         })
     }
 
+    /// Attach `labels` to every query job this client runs, e.g. a
+    /// destination's labels for cost attribution.
+    pub fn with_job_labels<'l>(
+        mut self,
+        labels: impl IntoIterator<Item = (&'l String, &'l String)>,
+    ) -> Self {
+        self.job_labels
+            .extend(labels.into_iter().map(|(k, v)| (k.clone(), v.clone())));
+        self
+    }
+
+    fn query_request(&self, sql: &str) -> QueryRequest {
+        let mut request = QueryRequest::new(sql);
+        if !self.job_labels.is_empty() {
+            request.labels = Some(self.job_labels.clone());
+        }
+        request
+    }
+
     pub async fn create_table(&self, query_def: &QueryDef) -> Result<()> {
         let latest = query_def
             .latest_version()
             .ok_or_else(|| BqDriftError::Schema("No versions defined".into()))?;
 
+        let project_id = query_def
+            .destination
+            .project
+            .as_deref()
+            .unwrap_or(&self.project_id);
+
+        if latest.schema.has_column_parameters() {
+            let ddl = parameterized_table_ddl(
+                &latest.schema,
+                &format!(
+                    "{}.{}.{}",
+                    project_id, query_def.destination.dataset, query_def.destination.table
+                ),
+                &query_def.destination.partition,
+                query_def.cluster.as_ref(),
+                &query_def.destination.labels,
+            )?;
+            return self.execute_query(&ddl).await;
+        }
+
         let schema = self.build_table_schema(&latest.schema);
-        let time_partitioning = self.build_time_partitioning(&query_def.destination.partition);
         let clustering = query_def.cluster.as_ref().map(|c| self.build_clustering(c));
 
         let mut table = Table::new(
-            &self.project_id,
+            project_id,
             &query_def.destination.dataset,
             &query_def.destination.table,
             schema,
         );
 
-        table.time_partitioning = Some(time_partitioning);
+        self.apply_partitioning(&mut table, &query_def.destination.partition);
+        if !query_def.destination.labels.is_empty() {
+            table.labels = Some(
+                query_def
+                    .destination
+                    .labels
+                    .iter()
+                    .map(|(k, v)| (k.clone(), v.clone()))
+                    .collect(),
+            );
+        }
         if let Some(c) = clustering {
             table.clustering = Some(c);
         }
+        if query_def.destination.partition.require_filter
+            && query_def.destination.partition.is_partitioned()
+        {
+            table.require_partition_filter = Some(true);
+        }
 
         self.client.table().create(table).await.map_err(|e| {
             let ctx = ErrorContext::new()
                 .with_operation("create_table")
                 .with_table(
-                    &self.project_id,
+                    project_id,
                     &query_def.destination.dataset,
                     &query_def.destination.table,
                 );
@@ -71,7 +139,7 @@ impl BqClient {
     }
 
     pub async fn execute_query(&self, sql: &str) -> Result<()> {
-        let request = QueryRequest::new(sql);
+        let request = self.query_request(sql);
 
         self.client
             .job()
@@ -87,6 +155,138 @@ impl BqClient {
         Ok(())
     }
 
+    /// Execute `sql` and return its job statistics. Slot time and bytes
+    /// billed come from a follow-up job lookup; if that lookup fails the
+    /// write still counts as successful and those fields are left `None`.
+    pub async fn execute_query_stats(&self, sql: &str) -> Result<QueryStats> {
+        let request = self.query_request(sql);
+
+        let response = self
+            .client
+            .job()
+            .query(&self.project_id, request)
+            .await
+            .map_err(|e| {
+                let ctx = ErrorContext::new()
+                    .with_operation("execute_query")
+                    .with_sql(sql);
+                BqDriftError::BigQuery(parse_bq_error(e, ctx))
+            })?;
+
+        let mut stats = QueryStats {
+            total_bytes_processed: response
+                .total_bytes_processed
+                .and_then(|b| b.parse::<i64>().ok()),
+            ..Default::default()
+        };
+
+        let Some(job_ref) = response.job_reference else {
+            return Ok(stats);
+        };
+        let Some(job_id) = job_ref.job_id.as_deref() else {
+            return Ok(stats);
+        };
+
+        match self
+            .client
+            .job()
+            .get_job(&self.project_id, job_id, job_ref.location.as_deref())
+            .await
+        {
+            Ok(job) => {
+                if let Some(job_stats) = job.statistics {
+                    stats.total_slot_ms =
+                        job_stats.total_slot_ms.and_then(|v| v.parse::<i64>().ok());
+                    stats.total_bytes_billed = job_stats
+                        .query
+                        .and_then(|q| q.total_bytes_billed)
+                        .and_then(|v| v.parse::<i64>().ok());
+                }
+            }
+            Err(e) => warn!(job_id, error = %e, "failed to fetch job statistics"),
+        }
+
+        Ok(stats)
+    }
+
+    pub async fn dry_run(&self, sql: &str) -> Result<DryRunResult> {
+        let mut request = self.query_request(sql);
+        request.dry_run = Some(true);
+
+        let result = self
+            .client
+            .job()
+            .query(&self.project_id, request)
+            .await
+            .map_err(|e| {
+                let ctx = ErrorContext::new().with_operation("dry_run").with_sql(sql);
+                BqDriftError::BigQuery(parse_bq_error(e, ctx))
+            })?;
+
+        let fields = result
+            .schema
+            .and_then(|s| s.fields)
+            .unwrap_or_default()
+            .iter()
+            .map(Self::field_from_schema)
+            .collect();
+
+        Ok(DryRunResult {
+            schema: Schema::from_fields(fields),
+            total_bytes_processed: result
+                .total_bytes_processed
+                .and_then(|b| b.parse::<i64>().ok()),
+        })
+    }
+
+    fn field_from_schema(tfs: &TableFieldSchema) -> Field {
+        let mode = match tfs.mode.as_deref() {
+            Some("REQUIRED") => FieldMode::Required,
+            Some("REPEATED") => FieldMode::Repeated,
+            _ => FieldMode::Nullable,
+        };
+
+        Field {
+            name: tfs.name.clone(),
+            field_type: Self::from_field_type(&tfs.r#type),
+            mode,
+            description: tfs.description.clone(),
+            fields: tfs
+                .fields
+                .as_ref()
+                .map(|nested| nested.iter().map(Self::field_from_schema).collect()),
+            default_expression: None,
+            policy_tags: tfs
+                .policy_tags
+                .as_ref()
+                .and_then(|tags| tags.names.clone())
+                .unwrap_or_default(),
+            precision: None,
+            scale: None,
+            rounding_mode: None,
+        }
+    }
+
+    fn from_field_type(field_type: &FieldType) -> BqType {
+        match field_type {
+            FieldType::String => BqType::String,
+            FieldType::Bytes => BqType::Bytes,
+            FieldType::Integer | FieldType::Int64 => BqType::Int64,
+            FieldType::Float | FieldType::Float64 => BqType::Float64,
+            FieldType::Numeric => BqType::Numeric,
+            FieldType::Bignumeric => BqType::Bignumeric,
+            FieldType::Boolean | FieldType::Bool => BqType::Bool,
+            FieldType::Timestamp => BqType::Timestamp,
+            FieldType::Date => BqType::Date,
+            FieldType::Time => BqType::Time,
+            FieldType::Datetime => BqType::Datetime,
+            FieldType::Record | FieldType::Struct => BqType::Record,
+            FieldType::Geography => BqType::Geography,
+            FieldType::Json => BqType::Json,
+            FieldType::Interval => BqType::Interval,
+        }
+    }
+
     pub async fn table_exists(&self, dataset: &str, table: &str) -> Result<bool> {
         use gcp_bigquery_client::error::BQError;
 
@@ -133,6 +333,12 @@ impl BqClient {
             tfs.description = Some(desc.clone());
         }
 
+        if !field.policy_tags.is_empty() {
+            tfs.policy_tags = Some(TableFieldSchemaPolicyTags {
+                names: Some(field.policy_tags.clone()),
+            });
+        }
+
         if let Some(nested) = &field.fields {
             tfs.fields = Some(nested.iter().map(|f| self.build_field_schema(f)).collect());
         }
@@ -155,10 +361,30 @@ impl BqClient {
             BqType::Timestamp => FieldType::Timestamp,
             BqType::Geography => FieldType::Geography,
             BqType::Json => FieldType::Json,
+            BqType::Interval => FieldType::Interval,
             BqType::Record => FieldType::Record,
         }
     }
 
+    fn apply_partitioning(&self, table: &mut Table, config: &PartitionConfig) {
+        if !config.is_partitioned() {
+            return;
+        }
+        match config.range_spec() {
+            Some(spec) if config.partition_type == PartitionType::Range => {
+                table.range_partitioning = Some(RangePartitioning {
+                    field: config.field.clone(),
+                    range: Some(RangePartitioningRange {
+                        start: spec.start.to_string(),
+                        end: spec.end.to_string(),
+                        interval: spec.interval.to_string(),
+                    }),
+                });
+            }
+            _ => table.time_partitioning = Some(self.build_time_partitioning(config)),
+        }
+    }
+
     fn build_time_partitioning(&self, config: &PartitionConfig) -> TimePartitioning {
         let mut tp = TimePartitioning::default();
 
@@ -175,6 +401,10 @@ impl BqClient {
             tp.field = Some(field.clone());
         }
 
+        if let Some(days) = config.expiration_days {
+            tp.expiration_ms = Some((days as i64 * 24 * 60 * 60 * 1000).to_string());
+        }
+
         tp
     }
 
@@ -218,7 +448,7 @@ impl BqClient {
     }
 
     pub async fn query_row_count(&self, sql: &str) -> Result<i64> {
-        let request = QueryRequest::new(sql);
+        let request = self.query_request(sql);
 
         let result = self
             .client
@@ -240,7 +470,7 @@ impl BqClient {
     }
 
     pub async fn query_single_float(&self, sql: &str) -> Result<Option<f64>> {
-        let request = QueryRequest::new(sql);
+        let request = self.query_request(sql);
 
         let result = self
             .client
@@ -258,7 +488,7 @@ impl BqClient {
     }
 
     pub async fn query_single_int(&self, sql: &str) -> Result<Option<i64>> {
-        let request = QueryRequest::new(sql);
+        let request = self.query_request(sql);
 
         let result = self
             .client
@@ -276,7 +506,7 @@ impl BqClient {
     }
 
     pub async fn query_two_floats(&self, sql: &str) -> Result<(Option<f64>, Option<f64>)> {
-        let request = QueryRequest::new(sql);
+        let request = self.query_request(sql);
 
         let result = self
             .client
@@ -296,6 +526,46 @@ impl BqClient {
         Ok((first, second))
     }
 
+    /// Rows of `(key, values...)`: the first column as a string key and the
+    /// remaining columns parsed as floats.
+    pub async fn query_keyed_floats(&self, sql: &str) -> Result<Vec<(String, Vec<Option<f64>>)>> {
+        let request = self.query_request(sql);
+
+        let result = self
+            .client
+            .job()
+            .query(&self.project_id, request)
+            .await
+            .map_err(|e| {
+                let ctx = ErrorContext::new()
+                    .with_operation("query_keyed_floats")
+                    .with_sql(sql);
+                BqDriftError::BigQuery(parse_bq_error(e, ctx))
+            })?;
+
+        let rows = result.rows.unwrap_or_default();
+        let mut keyed = Vec::with_capacity(rows.len());
+        for row in rows {
+            let columns = row.columns.unwrap_or_default();
+            let key = match columns
+                .first()
+                .and_then(|c| c.value.as_ref())
+                .and_then(|v| v.as_str())
+            {
+                Some(key) => key.to_string(),
+                None => continue,
+            };
+            let values = columns
+                .iter()
+                .skip(1)
+                .map(|c| c.value.as_ref().and_then(Self::parse_cell_as_f64))
+                .collect();
+            keyed.push((key, values));
+        }
+
+        Ok(keyed)
+    }
+
     pub async fn ensure_dataset(&self, dataset: &str) -> Result<()> {
         use gcp_bigquery_client::error::BQError;
 
@@ -347,13 +617,31 @@ impl BqClient {
         cluster_config: Option<&ClusterConfig>,
         expiration: DateTime<Utc>,
     ) -> Result<()> {
+        if schema.has_column_parameters() {
+            let name = format!("{}.{}.{}", self.project_id, dataset, table);
+            let ddl = parameterized_table_ddl(
+                schema,
+                &name,
+                partition_config,
+                cluster_config,
+                &BTreeMap::new(),
+            )?;
+            self.execute_query(&ddl).await?;
+            return self
+                .execute_query(&format!(
+                    "ALTER TABLE `{}` SET OPTIONS(expiration_timestamp = TIMESTAMP '{}')",
+                    name,
+                    expiration.format("%Y-%m-%d %H:%M:%S UTC")
+                ))
+                .await;
+        }
+
         let table_schema = self.build_table_schema(schema);
-        let time_partitioning = self.build_time_partitioning(partition_config);
         let clustering = cluster_config.map(|c| self.build_clustering(c));
 
         let mut tbl = Table::new(&self.project_id, dataset, table, table_schema);
 
-        tbl.time_partitioning = Some(time_partitioning);
+        self.apply_partitioning(&mut tbl, partition_config);
         if let Some(c) = clustering {
             tbl.clustering = Some(c);
         }
@@ -369,6 +657,117 @@ impl BqClient {
         Ok(())
     }
 
+    /// Schema of an existing table, given as `project.dataset.table` or
+    /// `dataset.table`.
+    pub async fn table_schema(&self, table: &str) -> Result<Schema> {
+        let reference = table_reference(&self.project_id, table)?;
+        let tbl = self
+            .client
+            .table()
+            .get(
+                &reference.project_id,
+                &reference.dataset_id,
+                &reference.table_id,
+                None,
+            )
+            .await
+            .map_err(|e| {
+                let ctx = ErrorContext::new()
+                    .with_operation("table_schema")
+                    .with_table(
+                        &reference.project_id,
+                        &reference.dataset_id,
+                        &reference.table_id,
+                    );
+                BqDriftError::BigQuery(parse_bq_error(e, ctx))
+            })?;
+
+        let fields = tbl
+            .schema
+            .fields
+            .unwrap_or_default()
+            .iter()
+            .map(Self::field_from_schema)
+            .collect();
+        Ok(Schema::from_fields(fields))
+    }
+
+    /// Replace one partition of `dst_table` with the same partition of
+    /// `src_table` using a copy job on the decorated partitions. No query is
+    /// run, so nothing is billed, but both tables must share a schema and
+    /// partitioning and `dst_table` must already exist.
+    pub async fn copy_partition(
+        &self,
+        src_table: &str,
+        dst_table: &str,
+        partition_key: &PartitionKey,
+    ) -> Result<()> {
+        let mut source = table_reference(&self.project_id, src_table)?;
+        let mut destination = table_reference(&self.project_id, dst_table)?;
+        let error_ctx = {
+            let (project, dataset, table) = (
+                destination.project_id.clone(),
+                destination.dataset_id.clone(),
+                destination.table_id.clone(),
+            );
+            move || {
+                ErrorContext::new()
+                    .with_operation("copy_partition")
+                    .with_table(project.as_str(), dataset.as_str(), table.as_str())
+            }
+        };
+        source.table_id.push_str(&partition_key.decorator());
+        destination.table_id.push_str(&partition_key.decorator());
+
+        let job = Job {
+            configuration: Some(JobConfiguration {
+                copy: Some(JobConfigurationTableCopy {
+                    source_table: Some(source),
+                    destination_table: Some(destination),
+                    create_disposition: Some("CREATE_NEVER".to_string()),
+                    write_disposition: Some("WRITE_TRUNCATE".to_string()),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let mut job = self
+            .client
+            .job()
+            .insert(&self.project_id, job)
+            .await
+            .map_err(|e| BqDriftError::BigQuery(parse_bq_error(e, error_ctx())))?;
+
+        let job_ref = job.job_reference.clone().unwrap_or_default();
+        let job_id = job_ref.job_id.ok_or_else(|| {
+            BqDriftError::Client("Copy job was accepted without a job id".to_string())
+        })?;
+
+        loop {
+            let status = job.status.clone().unwrap_or_default();
+            if let Some(error) = status.error_result {
+                return Err(BqDriftError::Executor(format!(
+                    "Copy job {} failed: {}",
+                    job_id,
+                    error.message.unwrap_or_else(|| "unknown error".to_string())
+                )));
+            }
+            if status.state.as_deref() == Some("DONE") {
+                return Ok(());
+            }
+
+            tokio::time::sleep(COPY_JOB_POLL_INTERVAL).await;
+            job = self
+                .client
+                .job()
+                .get_job(&self.project_id, &job_id, job_ref.location.as_deref())
+                .await
+                .map_err(|e| BqDriftError::BigQuery(parse_bq_error(e, error_ctx())))?;
+        }
+    }
+
     pub async fn list_tables(&self, dataset: &str) -> Result<Vec<String>> {
         let tables = self
             .client
@@ -390,3 +789,75 @@ impl BqClient {
         Ok(table_names)
     }
 }
+
+/// `CREATE TABLE` DDL for a schema whose NUMERIC parameters the table API
+/// would silently drop. Policy tags have no DDL form, so a schema that needs
+/// both cannot be created faithfully either way and is rejected.
+fn parameterized_table_ddl(
+    schema: &Schema,
+    table: &str,
+    partition: &PartitionConfig,
+    cluster: Option<&ClusterConfig>,
+    labels: &BTreeMap<String, String>,
+) -> Result<String> {
+    if let Some((path, _)) = schema
+        .all_fields()
+        .into_iter()
+        .find(|(_, f)| !f.policy_tags.is_empty())
+    {
+        return Err(BqDriftError::Schema(format!(
+            "Cannot create table '{}': column '{}' has policy tags, which need the table API, \
+             but the schema sets NUMERIC precision, scale or rounding mode, which the table API drops",
+            table, path
+        )));
+    }
+    Ok(schema.to_ddl_with_labels(table, partition, cluster, labels))
+}
+
+/// Parse `project.dataset.table` or `dataset.table`, optionally backquoted.
+fn table_reference(default_project: &str, table: &str) -> Result<TableReference> {
+    let parts: Vec<&str> = table.trim_matches('`').split('.').collect();
+    match parts.as_slice() {
+        [project, dataset, table] => Ok(TableReference::new(project, dataset, table)),
+        [dataset, table] => Ok(TableReference::new(default_project, dataset, table)),
+        _ => Err(BqDriftError::Client(format!(
+            "Invalid table reference '{}': expected project.dataset.table or dataset.table",
+            table
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_table_reference_parsing() {
+        let full = table_reference("default", "`proj.analytics.events`").unwrap();
+        assert_eq!(full.project_id, "proj");
+        assert_eq!(full.dataset_id, "analytics");
+        assert_eq!(full.table_id, "events");
+
+        let short = table_reference("default", "analytics.events").unwrap();
+        assert_eq!(short.project_id, "default");
+
+        assert!(table_reference("default", "events").is_err());
+    }
+
+    #[test]
+    fn test_parameterized_table_ddl() {
+        let partition = PartitionConfig::day("date");
+        let schema = Schema::from_fields(vec![
+            Field::new("date", BqType::Date),
+            Field::new("amount", BqType::Numeric).with_precision(10, 2),
+        ]);
+        let ddl =
+            parameterized_table_ddl(&schema, "p.d.t", &partition, None, &BTreeMap::new()).unwrap();
+        assert!(ddl.contains("`amount` NUMERIC(10, 2)"));
+
+        let tagged = schema.add_field(Field::new("email", BqType::String).with_policy_tag("tag"));
+        let err = parameterized_table_ddl(&tagged, "p.d.t", &partition, None, &BTreeMap::new())
+            .unwrap_err();
+        assert!(err.to_string().contains("'email'"));
+    }
+}
@@ -0,0 +1,57 @@
+const BYTES_PER_TIB: f64 = 1024.0 * 1024.0 * 1024.0 * 1024.0;
+const MS_PER_HOUR: f64 = 60.0 * 60.0 * 1000.0;
+
+/// How BigQuery bills the project running the queries. On-demand projects
+/// pay per byte processed; capacity (editions) projects pay per slot-hour.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CostModel {
+    OnDemand { usd_per_tib: f64 },
+    Capacity { usd_per_slot_hour: f64 },
+}
+
+impl Default for CostModel {
+    fn default() -> Self {
+        CostModel::OnDemand { usd_per_tib: 6.25 }
+    }
+}
+
+impl CostModel {
+    pub fn on_demand(usd_per_tib: f64) -> Self {
+        CostModel::OnDemand { usd_per_tib }
+    }
+
+    pub fn capacity(usd_per_slot_hour: f64) -> Self {
+        CostModel::Capacity { usd_per_slot_hour }
+    }
+
+    /// Estimated USD cost. Only the input the model bills on is used.
+    pub fn estimate(&self, bytes_processed: i64, slot_ms: i64) -> f64 {
+        match self {
+            CostModel::OnDemand { usd_per_tib } => {
+                bytes_processed.max(0) as f64 / BYTES_PER_TIB * usd_per_tib
+            }
+            CostModel::Capacity { usd_per_slot_hour } => {
+                slot_ms.max(0) as f64 / MS_PER_HOUR * usd_per_slot_hour
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_on_demand_bills_bytes() {
+        let model = CostModel::on_demand(5.0);
+        let two_tib = 2 * 1024_i64.pow(4);
+        assert_eq!(model.estimate(two_tib, 1_000_000), 10.0);
+    }
+
+    #[test]
+    fn test_capacity_bills_slot_hours() {
+        let model = CostModel::capacity(0.04);
+        let ten_slot_hours = 10 * 60 * 60 * 1000;
+        assert!((model.estimate(1 << 40, ten_slot_hours) - 0.4).abs() < 1e-9);
+    }
+}
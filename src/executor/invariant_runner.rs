@@ -49,21 +49,21 @@ pub(crate) async fn run_after_checks(
     checker.run_checks(after_checks).await
 }
 
-pub(crate) async fn execute_with_invariants<F, Fut>(
+pub(crate) async fn execute_with_invariants<T, F, Fut>(
     client: &BqClient,
     destination: &Destination,
     partition_date: NaiveDate,
     version: &VersionDef,
     run_invariants: bool,
     execute_fn: F,
-) -> Result<Option<InvariantReport>>
+) -> Result<(T, Option<InvariantReport>)>
 where
     F: FnOnce() -> Fut,
-    Fut: Future<Output = Result<()>>,
+    Fut: Future<Output = Result<T>>,
 {
     if !run_invariants {
-        execute_fn().await?;
-        return Ok(None);
+        let output = execute_fn().await?;
+        return Ok((output, None));
     }
 
     let (before_checks, after_checks) = resolve_invariants_def(&version.invariants);
@@ -71,13 +71,16 @@ where
     let before_results =
         run_before_checks(client, destination, partition_date, &before_checks).await?;
 
-    execute_fn().await?;
+    let output = execute_fn().await?;
 
     let after_results =
         run_after_checks(client, destination, partition_date, &after_checks).await?;
 
-    Ok(Some(InvariantReport {
-        before: before_results,
-        after: after_results,
-    }))
+    Ok((
+        output,
+        Some(InvariantReport {
+            before: before_results,
+            after: after_results,
+        }),
+    ))
 }
@@ -1,14 +1,25 @@
 mod bq_executor;
 mod client;
+mod cost;
 mod invariant_runner;
+mod output_schema;
 mod partition_writer;
+mod retry;
 mod runner;
 mod scratch;
 mod sql_builder;
 
 pub use client::BqClient;
+pub use cost::CostModel;
+pub use output_schema::{compare_output_schema, OutputSchemaMismatch, OutputSchemaReport};
 pub use partition_writer::{PartitionWriteStats, PartitionWriter};
-pub use runner::{RunFailure, RunReport, Runner};
-pub use scratch::{PromoteStats, ScratchConfig, ScratchWriteStats, ScratchWriter};
+pub use retry::RetryPolicy;
+pub use runner::{
+    BudgetedBackfillReport, FailureMode, RunEvent, RunFailure, RunReport, Runner,
+    DEFAULT_MAX_BACKFILL_PARTITIONS,
+};
+pub use scratch::{PromoteMethod, PromoteStats, ScratchConfig, ScratchWriteStats, ScratchWriter};
+pub(crate) use sql_builder::partition_condition;
+pub use sql_builder::{build_merge_sql, with_provenance_columns, MergePlan, MergeStrategy};
 
-pub use bq_executor::{ColumnDef, ColumnInfo, QueryResult};
+pub use bq_executor::{ColumnDef, ColumnInfo, DryRunResult, QueryResult, QueryStats};
@@ -0,0 +1,224 @@
+use crate::schema::{BqType, Field, FieldMode, Schema};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum OutputSchemaMismatch {
+    Extra {
+        column: String,
+        actual: BqType,
+    },
+    Missing {
+        column: String,
+    },
+    TypeMismatch {
+        column: String,
+        declared: BqType,
+        actual: BqType,
+    },
+    RepeatedMismatch {
+        column: String,
+        declared_repeated: bool,
+    },
+}
+
+impl std::fmt::Display for OutputSchemaMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OutputSchemaMismatch::Extra { column, actual } => {
+                write!(
+                    f,
+                    "column '{}' ({:?}) is not declared in schema",
+                    column, actual
+                )
+            }
+            OutputSchemaMismatch::Missing { column } => {
+                write!(f, "declared column '{}' is not produced by SQL", column)
+            }
+            OutputSchemaMismatch::TypeMismatch {
+                column,
+                declared,
+                actual,
+            } => write!(
+                f,
+                "column '{}' declared as {:?} but SQL produces {:?}",
+                column, declared, actual
+            ),
+            OutputSchemaMismatch::RepeatedMismatch {
+                column,
+                declared_repeated,
+            } => {
+                if *declared_repeated {
+                    write!(
+                        f,
+                        "column '{}' declared REPEATED but SQL produces a scalar",
+                        column
+                    )
+                } else {
+                    write!(
+                        f,
+                        "column '{}' is REPEATED in SQL output but not declared so",
+                        column
+                    )
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct OutputSchemaReport {
+    pub query_name: String,
+    pub version: u32,
+    pub mismatches: Vec<OutputSchemaMismatch>,
+}
+
+impl OutputSchemaReport {
+    pub fn is_clean(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+}
+
+/// Compare a declared schema against the schema a query actually produces
+/// (e.g. from a dry run). Column names are matched case-insensitively and
+/// nested RECORD fields are reported with dotted paths. Nullability is not
+/// compared because query output columns are always NULLABLE.
+pub fn compare_output_schema(declared: &Schema, actual: &Schema) -> Vec<OutputSchemaMismatch> {
+    let mut mismatches = Vec::new();
+    compare_fields(&declared.fields, &actual.fields, "", &mut mismatches);
+    mismatches
+}
+
+fn compare_fields(
+    declared: &[Field],
+    actual: &[Field],
+    prefix: &str,
+    mismatches: &mut Vec<OutputSchemaMismatch>,
+) {
+    let find = |fields: &'_ [Field], name: &str| -> Option<usize> {
+        fields
+            .iter()
+            .position(|f| f.name.eq_ignore_ascii_case(name))
+    };
+
+    for declared_field in declared {
+        let column = format!("{}{}", prefix, declared_field.name);
+        let Some(idx) = find(actual, &declared_field.name) else {
+            mismatches.push(OutputSchemaMismatch::Missing { column });
+            continue;
+        };
+        let actual_field = &actual[idx];
+
+        if declared_field.field_type != actual_field.field_type {
+            mismatches.push(OutputSchemaMismatch::TypeMismatch {
+                column,
+                declared: declared_field.field_type.clone(),
+                actual: actual_field.field_type.clone(),
+            });
+            continue;
+        }
+
+        let declared_repeated = declared_field.mode == FieldMode::Repeated;
+        if declared_repeated != (actual_field.mode == FieldMode::Repeated) {
+            mismatches.push(OutputSchemaMismatch::RepeatedMismatch {
+                column: column.clone(),
+                declared_repeated,
+            });
+        }
+
+        if declared_field.field_type == BqType::Record {
+            compare_fields(
+                declared_field.fields.as_deref().unwrap_or_default(),
+                actual_field.fields.as_deref().unwrap_or_default(),
+                &format!("{}.", column),
+                mismatches,
+            );
+        }
+    }
+
+    for actual_field in actual {
+        if find(declared, &actual_field.name).is_none() {
+            mismatches.push(OutputSchemaMismatch::Extra {
+                column: format!("{}{}", prefix, actual_field.name),
+                actual: actual_field.field_type.clone(),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matching_schemas_are_clean() {
+        let declared = Schema::from_fields(vec![
+            Field::new("date", BqType::Date).required(),
+            Field::new("user_id", BqType::String),
+        ]);
+        let actual = Schema::from_fields(vec![
+            Field::new("DATE", BqType::Date),
+            Field::new("user_id", BqType::String),
+        ]);
+
+        assert!(compare_output_schema(&declared, &actual).is_empty());
+    }
+
+    #[test]
+    fn test_extra_missing_and_type_mismatch() {
+        let declared = Schema::from_fields(vec![
+            Field::new("date", BqType::Date),
+            Field::new("count", BqType::Int64),
+            Field::new("region", BqType::String),
+        ]);
+        let actual = Schema::from_fields(vec![
+            Field::new("date", BqType::Date),
+            Field::new("count", BqType::Float64),
+            Field::new("country", BqType::String),
+        ]);
+
+        let mismatches = compare_output_schema(&declared, &actual);
+        assert_eq!(mismatches.len(), 3);
+        assert!(mismatches.contains(&OutputSchemaMismatch::TypeMismatch {
+            column: "count".to_string(),
+            declared: BqType::Int64,
+            actual: BqType::Float64,
+        }));
+        assert!(mismatches.contains(&OutputSchemaMismatch::Missing {
+            column: "region".to_string(),
+        }));
+        assert!(mismatches.contains(&OutputSchemaMismatch::Extra {
+            column: "country".to_string(),
+            actual: BqType::String,
+        }));
+    }
+
+    #[test]
+    fn test_nested_record_and_repeated() {
+        let declared = Schema::from_fields(vec![
+            Field::new("tags", BqType::String).repeated(),
+            Field::new("meta", BqType::Record)
+                .with_fields(vec![Field::new("source", BqType::String)]),
+        ]);
+        let actual = Schema::from_fields(vec![
+            Field::new("tags", BqType::String),
+            Field::new("meta", BqType::Record).with_fields(vec![
+                Field::new("source", BqType::String),
+                Field::new("ingested_at", BqType::Timestamp),
+            ]),
+        ]);
+
+        let mismatches = compare_output_schema(&declared, &actual);
+        assert_eq!(
+            mismatches,
+            vec![
+                OutputSchemaMismatch::RepeatedMismatch {
+                    column: "tags".to_string(),
+                    declared_repeated: true,
+                },
+                OutputSchemaMismatch::Extra {
+                    column: "meta.ingested_at".to_string(),
+                    actual: BqType::Timestamp,
+                },
+            ]
+        );
+    }
+}
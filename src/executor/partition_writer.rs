@@ -1,25 +1,61 @@
+use super::bq_executor::QueryStats;
 use super::client::BqClient;
-use super::invariant_runner::execute_with_invariants;
+use super::invariant_runner::{execute_with_invariants, run_after_checks};
+use super::sql_builder::MergePlan;
 use crate::dsl::QueryDef;
 use crate::error::{BqDriftError, Result};
-use crate::invariant::InvariantReport;
+use crate::invariant::{resolve_invariants_def, InvariantReport};
 use crate::schema::PartitionKey;
+use chrono::NaiveDate;
 
 #[derive(Debug, Clone)]
 pub struct PartitionWriteStats {
     pub query_name: String,
     pub version: u32,
+    /// The revision whose SQL was executed, `None` for the version's own SQL.
+    pub sql_revision: Option<u32>,
     pub partition_key: PartitionKey,
     pub invariant_report: Option<InvariantReport>,
+    pub query_stats: QueryStats,
+}
+
+/// `as_of`, or today when no date was pinned.
+pub(crate) fn as_of_or_today(as_of: Option<NaiveDate>) -> NaiveDate {
+    as_of.unwrap_or_else(|| chrono::Utc::now().date_naive())
+}
+
+/// The MERGE a `PartitionWriter` with the given `as_of` runs for
+/// `partition_key`.
+pub(crate) fn partition_merge_plan(
+    query_def: &QueryDef,
+    partition_key: PartitionKey,
+    as_of: Option<NaiveDate>,
+) -> Result<MergePlan> {
+    query_def.merge_plan(partition_key, as_of_or_today(as_of))
 }
 
 pub struct PartitionWriter {
     client: BqClient,
+    as_of: Option<NaiveDate>,
 }
 
 impl PartitionWriter {
     pub fn new(client: BqClient) -> Self {
-        Self { client }
+        Self {
+            client,
+            as_of: None,
+        }
+    }
+
+    /// Resolve revisions (and so the SQL written) as of `date` instead of
+    /// today.
+    pub fn with_as_of(mut self, date: NaiveDate) -> Self {
+        self.as_of = Some(date);
+        self
+    }
+
+    pub fn client(&self) -> &BqClient {
+        &self.client
     }
 
     pub async fn write_partition(
@@ -40,12 +76,13 @@ impl PartitionWriter {
             .await
     }
 
-    async fn write_partition_impl(
+    /// Run the after-invariants of the version active for `partition_key`
+    /// against the existing destination partition, without writing any data.
+    pub async fn revalidate_partition(
         &self,
         query_def: &QueryDef,
         partition_key: PartitionKey,
-        run_invariants: bool,
-    ) -> Result<PartitionWriteStats> {
+    ) -> Result<InvariantReport> {
         let partition_date = partition_key.to_naive_date();
         let version = query_def
             .get_version_for_date(partition_date)
@@ -53,52 +90,68 @@ impl PartitionWriter {
                 BqDriftError::Partition(format!("No version found for partition {}", partition_key))
             })?;
 
-        let sql = version.get_sql_for_date(chrono::Utc::now().date_naive());
-        let full_sql = Self::build_merge_sql(query_def, sql, &partition_key)?;
-
-        let invariant_report = execute_with_invariants(
+        let (_, after_checks) = resolve_invariants_def(&version.invariants);
+        let after = run_after_checks(
             &self.client,
             &query_def.destination,
             partition_date,
+            &after_checks,
+        )
+        .await?;
+
+        Ok(InvariantReport {
+            before: Vec::new(),
+            after,
+        })
+    }
+
+    async fn write_partition_impl(
+        &self,
+        query_def: &QueryDef,
+        partition_key: PartitionKey,
+        run_invariants: bool,
+    ) -> Result<PartitionWriteStats> {
+        let partition_date = partition_key.to_naive_date();
+        let version = query_def.writable_version(&partition_key)?;
+        let as_of = as_of_or_today(self.as_of);
+
+        let full_sql = query_def.merge_plan(partition_key, as_of)?.render();
+
+        let client = self.labeled_client(query_def);
+        let (query_stats, invariant_report) = execute_with_invariants(
+            &client,
+            &query_def.destination,
+            partition_date,
             version,
             run_invariants,
-            || async { self.client.execute_query(&full_sql).await },
+            || async { client.execute_query_stats(&full_sql).await },
         )
         .await?;
 
         Ok(PartitionWriteStats {
             query_name: query_def.name.clone(),
             version: version.version,
+            sql_revision: version.revision_for_date(as_of).map(|r| r.revision),
             partition_key,
             invariant_report,
+            query_stats,
         })
     }
 
-    fn build_merge_sql(
+    /// Client whose jobs carry the destination's labels.
+    fn labeled_client(&self, query_def: &QueryDef) -> BqClient {
+        self.client
+            .clone()
+            .with_job_labels(&query_def.destination.labels)
+    }
+
+    /// The MERGE that `write_partition` would run for `partition_key`.
+    pub fn merge_plan(
+        &self,
         query_def: &QueryDef,
-        sql: &str,
-        partition_key: &PartitionKey,
-    ) -> Result<String> {
-        let dest_table = format!(
-            "{}.{}",
-            query_def.destination.dataset, query_def.destination.table
-        );
-        let partition_field = query_def
-            .destination
-            .partition
-            .field_name()
-            .ok_or_else(|| {
-                BqDriftError::Partition(format!(
-                    "Partition field not specified for query '{}'",
-                    query_def.name
-                ))
-            })?;
-        Ok(super::sql_builder::build_merge_sql(
-            &dest_table,
-            partition_field,
-            sql,
-            partition_key,
-        ))
+        partition_key: PartitionKey,
+    ) -> Result<MergePlan> {
+        partition_merge_plan(query_def, partition_key, self.as_of)
     }
 
     pub async fn write_partition_truncate(
@@ -126,20 +179,15 @@ impl PartitionWriter {
         run_invariants: bool,
     ) -> Result<PartitionWriteStats> {
         let partition_date = partition_key.to_naive_date();
-        let version = query_def
-            .get_version_for_date(partition_date)
-            .ok_or_else(|| {
-                BqDriftError::Partition(format!("No version found for partition {}", partition_key))
-            })?;
+        let version = query_def.writable_version(&partition_key)?;
 
-        let dest_table = format!(
-            "{}.{}{}",
-            query_def.destination.dataset,
-            query_def.destination.table,
-            partition_key.decorator()
-        );
+        let mut dest_table = query_def.destination.qualified_table();
+        if query_def.destination.partition.is_partitioned() {
+            dest_table.push_str(&partition_key.decorator());
+        }
 
-        let sql = version.get_sql_for_date(chrono::Utc::now().date_naive());
+        let as_of = as_of_or_today(self.as_of);
+        let sql = query_def.source_sql(version, as_of);
         let parameterized_sql = sql.replace(
             "@partition_date",
             &format!("'{}'", partition_key.sql_value()),
@@ -156,16 +204,17 @@ impl PartitionWriter {
 
         let delete_sql = format!("DELETE FROM `{}` WHERE TRUE", dest_table);
 
-        let client = &self.client;
-        let invariant_report = execute_with_invariants(
+        let client = &self.labeled_client(query_def);
+        let (query_stats, invariant_report) = execute_with_invariants(
             client,
             &query_def.destination,
             partition_date,
             version,
             run_invariants,
             || async {
-                client.execute_query(&delete_sql).await?;
-                client.execute_query(&insert_sql).await
+                let deleted = client.execute_query_stats(&delete_sql).await?;
+                let inserted = client.execute_query_stats(&insert_sql).await?;
+                Ok(deleted.combine(inserted))
             },
         )
         .await?;
@@ -173,8 +222,35 @@ impl PartitionWriter {
         Ok(PartitionWriteStats {
             query_name: query_def.name.clone(),
             version: version.version,
+            sql_revision: version.revision_for_date(as_of).map(|r| r.revision),
             partition_key,
             invariant_report,
+            query_stats,
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dsl::QueryLoader;
+    use std::path::Path;
+
+    #[test]
+    fn test_merge_plan_uses_as_of_revision() {
+        let query = QueryLoader::new()
+            .load_query(Path::new("tests/fixtures/analytics/versioned_query.yaml"))
+            .unwrap();
+        let partition = PartitionKey::Day(NaiveDate::from_ymd_opt(2024, 3, 10).unwrap());
+
+        let before = partition_merge_plan(&query, partition, NaiveDate::from_ymd_opt(2024, 3, 10))
+            .unwrap()
+            .render();
+        assert!(!before.contains("COALESCE"));
+
+        let after = partition_merge_plan(&query, partition, NaiveDate::from_ymd_opt(2024, 4, 1))
+            .unwrap()
+            .render();
+        assert!(after.contains("COALESCE(e.user_id, 'unknown')"));
+    }
+}
@@ -0,0 +1,178 @@
+use crate::error::{BqDriftError, Result};
+use regex::Regex;
+use std::future::Future;
+use std::time::Duration;
+
+/// Retry behaviour for partition writes.
+///
+/// An error is retried when it matches any of `retryable_patterns` (checked
+/// against its display message) or when `BqDriftError::is_retryable` classifies
+/// it as transient. Patterns can only force a retry; they never suppress the
+/// built-in classification.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    pub retryable_patterns: Vec<Regex>,
+}
+
+impl RetryPolicy {
+    pub fn new() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(30),
+            retryable_patterns: Vec::new(),
+        }
+    }
+
+    pub fn with_max_attempts(mut self, attempts: u32) -> Self {
+        self.max_attempts = attempts.max(1);
+        self
+    }
+
+    pub fn with_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max.max(initial);
+        self
+    }
+
+    pub fn with_retryable_pattern(mut self, pattern: &str) -> Result<Self> {
+        let regex = Regex::new(pattern).map_err(|e| {
+            BqDriftError::Executor(format!("Invalid retryable pattern '{}': {}", pattern, e))
+        })?;
+        self.retryable_patterns.push(regex);
+        Ok(self)
+    }
+
+    pub fn is_retryable(&self, error: &BqDriftError) -> bool {
+        if !self.retryable_patterns.is_empty() {
+            let message = error.to_string();
+            if self.retryable_patterns.iter().any(|p| p.is_match(&message)) {
+                return true;
+            }
+        }
+        error.is_retryable()
+    }
+
+    pub fn backoff_for(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+
+    pub async fn run<T, F, Fut>(&self, mut operation: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut attempt = 1;
+        loop {
+            match operation().await {
+                Ok(value) => return Ok(value),
+                Err(e) if attempt < self.max_attempts && self.is_retryable(&e) => {
+                    tokio::time::sleep(self.backoff_for(attempt)).await;
+                    attempt += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::BigQueryError;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    fn unknown_error(message: &str) -> BqDriftError {
+        BqDriftError::BigQuery(BigQueryError::Unknown {
+            code: None,
+            message: message.to_string(),
+            raw_error: String::new(),
+        })
+    }
+
+    #[test]
+    fn test_builtin_classification() {
+        let policy = RetryPolicy::new();
+        assert!(
+            policy.is_retryable(&BqDriftError::BigQuery(BigQueryError::ConnectionFailed {
+                reason: "reset".into(),
+            }))
+        );
+        assert!(!policy.is_retryable(&unknown_error("Table is being updated")));
+        assert!(!policy.is_retryable(&BqDriftError::Schema("bad".into())));
+    }
+
+    #[test]
+    fn test_custom_pattern_forces_retry() {
+        let policy = RetryPolicy::new()
+            .with_retryable_pattern(r"(?i)table .* is being updated")
+            .unwrap();
+        assert!(policy.is_retryable(&unknown_error("Table analytics.daily is being updated")));
+        assert!(!policy.is_retryable(&unknown_error("Syntax error")));
+    }
+
+    #[test]
+    fn test_invalid_pattern_is_rejected() {
+        assert!(RetryPolicy::new().with_retryable_pattern("(").is_err());
+    }
+
+    #[test]
+    fn test_backoff_is_capped() {
+        let policy =
+            RetryPolicy::new().with_backoff(Duration::from_millis(100), Duration::from_millis(350));
+        assert_eq!(policy.backoff_for(1), Duration::from_millis(100));
+        assert_eq!(policy.backoff_for(2), Duration::from_millis(200));
+        assert_eq!(policy.backoff_for(3), Duration::from_millis(350));
+    }
+
+    #[tokio::test]
+    async fn test_run_retries_until_success() {
+        let policy = RetryPolicy::new()
+            .with_max_attempts(3)
+            .with_backoff(Duration::ZERO, Duration::ZERO)
+            .with_retryable_pattern("flaky")
+            .unwrap();
+        let calls = AtomicU32::new(0);
+
+        let result = policy
+            .run(|| async {
+                if calls.fetch_add(1, Ordering::SeqCst) < 2 {
+                    Err(unknown_error("flaky warehouse"))
+                } else {
+                    Ok(42)
+                }
+            })
+            .await;
+
+        assert_eq!(result.unwrap(), 42);
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_run_stops_on_non_retryable() {
+        let policy = RetryPolicy::new().with_backoff(Duration::ZERO, Duration::ZERO);
+        let calls = AtomicU32::new(0);
+
+        let result: Result<()> = policy
+            .run(|| async {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Err(unknown_error("Syntax error"))
+            })
+            .await;
+
+        assert!(result.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+}
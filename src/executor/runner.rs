@@ -1,14 +1,24 @@
+use super::bq_executor::QueryStats;
 use super::client::BqClient;
-use super::partition_writer::{PartitionWriteStats, PartitionWriter};
-use crate::dsl::QueryDef;
+use super::cost::CostModel;
+use super::output_schema::{compare_output_schema, OutputSchemaReport};
+use super::partition_writer::{as_of_or_today, PartitionWriteStats, PartitionWriter};
+use super::retry::RetryPolicy;
+use super::sql_builder::parameterize_sql;
+use crate::dsl::{dependency_levels_for, upstreams_for, QueryDef};
 use crate::error::{BqDriftError, Result};
-use crate::schema::PartitionKey;
-use chrono::{NaiveDate, Utc};
+use crate::schema::{PartitionConfig, PartitionKey, PartitionType};
+use chrono::{DateTime, NaiveDate, Utc};
 use futures::stream::{self, StreamExt};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use tracing::warn;
 
-const MAX_BACKFILL_PARTITIONS: usize = 3652;
+/// Most partitions one backfill may cover unless raised with
+/// `Runner::with_max_partitions`.
+pub const DEFAULT_MAX_BACKFILL_PARTITIONS: usize = 366;
 
 fn default_parallelism() -> usize {
     std::env::var("BQDRIFT_PARALLELISM")
@@ -20,7 +30,125 @@ fn default_parallelism() -> usize {
 #[derive(Debug)]
 pub struct RunReport {
     pub stats: Vec<PartitionWriteStats>,
+    /// Failures in completion order. Under `FailureMode::AbortOnFirst` the
+    /// first entry is the error that stopped scheduling.
     pub failures: Vec<RunFailure>,
+    /// Queries not run because a query they read from failed; `error` names
+    /// that query.
+    pub skipped: Vec<RunFailure>,
+    /// Partitions never started because an earlier partition failed.
+    pub cancelled: Vec<PartitionKey>,
+    /// Partitions in the requested range that were skipped on purpose.
+    pub excluded: Vec<PartitionKey>,
+}
+
+impl RunReport {
+    pub fn was_aborted(&self) -> bool {
+        !self.cancelled.is_empty()
+    }
+
+    /// Bytes processed across written partitions that reported them.
+    pub fn total_bytes_processed(&self) -> i64 {
+        self.stats
+            .iter()
+            .filter_map(|s| s.query_stats.total_bytes_processed)
+            .fold(0i64, i64::saturating_add)
+    }
+
+    /// Slot-milliseconds across written partitions that reported them.
+    pub fn total_slot_ms(&self) -> i64 {
+        self.stats
+            .iter()
+            .filter_map(|s| s.query_stats.total_slot_ms)
+            .fold(0i64, i64::saturating_add)
+    }
+
+    pub fn estimated_cost(&self, model: &CostModel) -> f64 {
+        model.estimate(self.total_bytes_processed(), self.total_slot_ms())
+    }
+}
+
+/// How a backfill reacts to a failed partition.
+///
+/// `AbortOnFirst` suits freshly edited queries, where one SQL error usually
+/// means every remaining partition fails the same way. Partitions already in
+/// flight still finish; only scheduling of new partitions stops.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FailureMode {
+    #[default]
+    CollectAll,
+    AbortOnFirst,
+}
+
+#[derive(Debug, Clone)]
+pub enum RunEvent {
+    Started {
+        query_name: String,
+        partition_key: PartitionKey,
+        at: DateTime<Utc>,
+    },
+    Succeeded {
+        stats: PartitionWriteStats,
+        started_at: DateTime<Utc>,
+        at: DateTime<Utc>,
+    },
+    Failed {
+        query_name: String,
+        partition_key: PartitionKey,
+        error: String,
+        started_at: DateTime<Utc>,
+        at: DateTime<Utc>,
+    },
+}
+
+impl RunEvent {
+    pub fn query_name(&self) -> &str {
+        match self {
+            RunEvent::Started { query_name, .. } | RunEvent::Failed { query_name, .. } => {
+                query_name
+            }
+            RunEvent::Succeeded { stats, .. } => &stats.query_name,
+        }
+    }
+
+    pub fn partition_key(&self) -> PartitionKey {
+        match self {
+            RunEvent::Started { partition_key, .. } | RunEvent::Failed { partition_key, .. } => {
+                *partition_key
+            }
+            RunEvent::Succeeded { stats, .. } => stats.partition_key,
+        }
+    }
+
+    pub fn at(&self) -> DateTime<Utc> {
+        match self {
+            RunEvent::Started { at, .. }
+            | RunEvent::Succeeded { at, .. }
+            | RunEvent::Failed { at, .. } => *at,
+        }
+    }
+}
+
+type EventSink = Arc<dyn Fn(RunEvent) + Send + Sync>;
+
+#[derive(Debug)]
+pub struct BudgetedBackfillReport {
+    pub report: RunReport,
+    pub projected_bytes: i64,
+    pub max_bytes: i64,
+    pub skipped: Vec<PartitionKey>,
+}
+
+impl BudgetedBackfillReport {
+    pub fn is_complete(&self) -> bool {
+        self.skipped.is_empty()
+    }
+
+    /// Spend projected from the dry-run byte estimates. Dry runs report no
+    /// slot time, so this is zero under `CostModel::Capacity`.
+    pub fn projected_cost(&self, model: &CostModel) -> f64 {
+        model.estimate(self.projected_bytes, 0)
+    }
 }
 
 #[derive(Debug)]
@@ -35,6 +163,11 @@ pub struct Runner {
     queries: Arc<Vec<QueryDef>>,
     query_index: HashMap<String, usize>,
     parallelism: usize,
+    max_partitions: usize,
+    retry_policy: Option<RetryPolicy>,
+    failure_mode: FailureMode,
+    event_sink: Option<EventSink>,
+    as_of: Option<NaiveDate>,
 }
 
 impl Runner {
@@ -49,6 +182,11 @@ impl Runner {
             queries,
             query_index,
             parallelism: default_parallelism(),
+            max_partitions: DEFAULT_MAX_BACKFILL_PARTITIONS,
+            retry_policy: None,
+            failure_mode: FailureMode::default(),
+            event_sink: None,
+            as_of: None,
         }
     }
 
@@ -61,6 +199,68 @@ impl Runner {
         self
     }
 
+    /// Largest number of partitions one backfill or revalidation may cover
+    /// (default 366), so a mistyped range fails instead of launching
+    /// thousands of writes.
+    pub fn with_max_partitions(mut self, max_partitions: usize) -> Self {
+        self.max_partitions = max_partitions.max(1);
+        self
+    }
+
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = Some(policy);
+        self
+    }
+
+    pub fn with_failure_mode(mut self, mode: FailureMode) -> Self {
+        self.failure_mode = mode;
+        self
+    }
+
+    /// Write each partition with the SQL revisions active on `date`
+    /// instead of today. See `PartitionWriter::with_as_of`. Dependency
+    /// ordering, cost estimates and output schema checks use the same date.
+    pub fn with_as_of(mut self, date: NaiveDate) -> Self {
+        self.writer = self.writer.with_as_of(date);
+        self.as_of = Some(date);
+        self
+    }
+
+    fn as_of(&self) -> NaiveDate {
+        as_of_or_today(self.as_of)
+    }
+
+    pub fn with_event_sink(mut self, sink: impl Fn(RunEvent) + Send + Sync + 'static) -> Self {
+        self.event_sink = Some(Arc::new(sink));
+        self
+    }
+
+    async fn write_partition(
+        &self,
+        query: &QueryDef,
+        partition_key: PartitionKey,
+    ) -> Result<PartitionWriteStats> {
+        with_events(self.event_sink.as_ref(), &query.name, partition_key, || {
+            self.write_partition_with_retry(query, partition_key)
+        })
+        .await
+    }
+
+    async fn write_partition_with_retry(
+        &self,
+        query: &QueryDef,
+        partition_key: PartitionKey,
+    ) -> Result<PartitionWriteStats> {
+        match &self.retry_policy {
+            Some(policy) => {
+                policy
+                    .run(|| self.writer.write_partition(query, partition_key))
+                    .await
+            }
+            None => self.writer.write_partition(query, partition_key).await,
+        }
+    }
+
     pub async fn run_today(&self) -> Result<RunReport> {
         let today = Utc::now().date_naive();
         self.run_for_date(today).await
@@ -70,32 +270,19 @@ impl Runner {
         self.run_for_partition(PartitionKey::Day(date)).await
     }
 
+    /// Write `partition_key` for every query, producers before the queries
+    /// that read their tables; queries with no dependency between them run
+    /// concurrently. Fails before writing anything on a dependency cycle.
     pub async fn run_for_partition(&self, partition_key: PartitionKey) -> Result<RunReport> {
-        let results: Vec<_> = stream::iter(0..self.queries.len())
-            .map(|idx| async move {
-                let query = &self.queries[idx];
-                let result = self.writer.write_partition(query, partition_key).await;
-                (idx, result)
-            })
-            .buffer_unordered(self.parallelism)
-            .collect()
-            .await;
-
-        let mut stats = Vec::new();
-        let mut failures = Vec::new();
-
-        for (idx, result) in results {
-            match result {
-                Ok(s) => stats.push(s),
-                Err(e) => failures.push(RunFailure {
-                    query_name: self.queries[idx].name.clone(),
-                    partition_key,
-                    error: e.to_string(),
-                }),
-            }
-        }
-
-        Ok(RunReport { stats, failures })
+        run_levels(
+            &self.queries,
+            partition_key,
+            self.as_of(),
+            Some(self.writer.client().project_id()),
+            self.parallelism,
+            |query| self.write_partition(query, partition_key),
+        )
+        .await
     }
 
     pub async fn run_query(
@@ -116,7 +303,7 @@ impl Runner {
             .get_query(query_name)
             .ok_or_else(|| BqDriftError::QueryNotFound(query_name.to_string()))?;
 
-        self.writer.write_partition(query, partition_key).await
+        self.write_partition(query, partition_key).await
     }
 
     pub async fn backfill(
@@ -140,18 +327,143 @@ impl Runner {
         from: PartitionKey,
         to: PartitionKey,
         interval: Option<i64>,
+    ) -> Result<RunReport> {
+        self.backfill_partitions_excluding(query_name, from, to, interval, &HashSet::new())
+            .await
+    }
+
+    /// Like `backfill_partitions`, skipping the partitions in `exclude` (e.g.
+    /// known-bad upstream days). Skipped partitions are listed in
+    /// `RunReport::excluded`.
+    pub async fn backfill_partitions_excluding(
+        &self,
+        query_name: &str,
+        from: PartitionKey,
+        to: PartitionKey,
+        interval: Option<i64>,
+        exclude: &HashSet<PartitionKey>,
     ) -> Result<RunReport> {
         let query = self
             .get_query(query_name)
             .ok_or_else(|| BqDriftError::QueryNotFound(query_name.to_string()))?;
 
+        let interval = interval.or_else(|| query.destination.partition.step());
+        let (partitions, excluded) = Self::split_excluded(
+            Self::partition_range(from, to, interval, self.max_partitions)?,
+            exclude,
+        );
+        Self::ensure_not_blocked(query, &partitions)?;
+        Self::warn_if_expired(query, &partitions, self.as_of());
+
+        let mut report = self.write_partitions(query, partitions).await;
+        report.excluded = excluded;
+        Ok(report)
+    }
+
+    /// Refuse a backfill up front when any partition in it falls under a
+    /// blocked version, rather than failing those partitions one by one.
+    fn ensure_not_blocked(query: &QueryDef, partitions: &[PartitionKey]) -> Result<()> {
+        let blocked = partitions.iter().find(|pk| {
+            query
+                .get_version_for_date(pk.to_naive_date())
+                .is_some_and(|v| v.is_blocked())
+        });
+        match blocked {
+            Some(pk) => query.writable_version(pk).map(|_| ()),
+            None => Ok(()),
+        }
+    }
+
+    /// Warn about partitions older than the table's partition expiration as
+    /// of `as_of`; BigQuery drops them again right after they are written.
+    fn warn_if_expired(query: &QueryDef, partitions: &[PartitionKey], as_of: NaiveDate) {
+        let Some(days) = query.destination.partition.expiration_days else {
+            return;
+        };
+        let earliest = as_of - chrono::Duration::days(i64::from(days));
+        if let Some(pk) = partitions.iter().find(|pk| {
+            !matches!(pk, PartitionKey::Range(_)) && !pk.is_within(earliest, NaiveDate::MAX)
+        }) {
+            warn!(
+                "Partition {} of '{}' is older than the table's {}-day partition expiration (earliest {}); BigQuery will drop it after the write",
+                pk, query.name, days, earliest
+            );
+        }
+    }
+
+    /// Backfill the partitions covering `from..=to`, but dry-run the MERGE of
+    /// every partition first and only write partitions (in order) while the
+    /// projected bytes processed stay within `max_bytes`. Partitions past the
+    /// budget are returned as skipped.
+    pub async fn backfill_with_budget(
+        &self,
+        query_name: &str,
+        from: NaiveDate,
+        to: NaiveDate,
+        max_bytes: i64,
+    ) -> Result<BudgetedBackfillReport> {
+        let query = self
+            .get_query(query_name)
+            .ok_or_else(|| BqDriftError::QueryNotFound(query_name.to_string()))?;
+
+        let partition = &query.destination.partition;
+        let (first, last) = Self::date_partition_bounds(query_name, partition, from, to)?;
+        let partitions = Self::partition_range(first, last, partition.step(), self.max_partitions)?;
+        Self::ensure_not_blocked(query, &partitions)?;
+        Self::warn_if_expired(query, &partitions, self.as_of());
+
+        let estimates: Vec<Result<i64>> = stream::iter(partitions.iter().copied())
+            .map(|pk| async move {
+                let sql = self.writer.merge_plan(query, pk)?.render();
+                let dry_run = self.writer.client().dry_run(&sql).await?;
+                Ok(dry_run.total_bytes_processed.unwrap_or(0))
+            })
+            .buffered(self.parallelism)
+            .collect()
+            .await;
+
+        let mut projected_bytes = 0i64;
+        let mut within_budget = Vec::new();
+        let mut skipped = Vec::new();
+        for (pk, estimate) in partitions.into_iter().zip(estimates) {
+            let bytes = estimate?;
+            if skipped.is_empty() && projected_bytes.saturating_add(bytes) <= max_bytes {
+                projected_bytes += bytes;
+                within_budget.push(pk);
+            } else {
+                skipped.push(pk);
+            }
+        }
+
+        let report = self.write_partitions(query, within_budget).await;
+
+        Ok(BudgetedBackfillReport {
+            report,
+            projected_bytes,
+            max_bytes,
+            skipped,
+        })
+    }
+
+    fn partition_range(
+        from: PartitionKey,
+        to: PartitionKey,
+        interval: Option<i64>,
+        max_partitions: usize,
+    ) -> Result<Vec<PartitionKey>> {
+        if let Some(i) = interval.filter(|i| *i <= 0) {
+            return Err(BqDriftError::Partition(format!(
+                "Backfill interval must be positive, got {}",
+                i
+            )));
+        }
         let mut partitions = Vec::new();
         let mut current = from;
         while current <= to {
-            if partitions.len() >= MAX_BACKFILL_PARTITIONS {
+            if partitions.len() >= max_partitions {
                 return Err(BqDriftError::Partition(format!(
-                    "Backfill range too large: exceeds maximum of {} partitions",
-                    MAX_BACKFILL_PARTITIONS
+                    "Backfill range too large: {} to {} exceeds maximum of {} partitions",
+                    from, to, max_partitions
                 )));
             }
             partitions.push(current);
@@ -160,10 +472,96 @@ impl Runner {
                 None => current.next(),
             };
         }
+        Ok(partitions)
+    }
+
+    /// First and last partition of the destination's partition type that
+    /// cover the dates `from..=to`: every hour of both days for hourly
+    /// tables, the containing month or year for monthly and yearly ones.
+    fn date_partition_bounds(
+        query_name: &str,
+        partition: &PartitionConfig,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<(PartitionKey, PartitionKey)> {
+        let partition_type = match partition.partition_type {
+            PartitionType::IngestionTime => {
+                partition.granularity.clone().unwrap_or(PartitionType::Day)
+            }
+            ref other => other.clone(),
+        };
+        let key = |date| {
+            PartitionKey::for_date(date, &partition_type).ok_or_else(|| {
+                BqDriftError::Partition(format!(
+                    "Date ranges are not supported for range-partitioned query '{}'",
+                    query_name
+                ))
+            })
+        };
+        let last = match key(to)? {
+            PartitionKey::Hour(start) => PartitionKey::Hour(start + chrono::Duration::hours(23)),
+            other => other,
+        };
+        Ok((key(from)?, last))
+    }
+
+    /// `(to_write, excluded)`, both in range order.
+    fn split_excluded(
+        partitions: Vec<PartitionKey>,
+        exclude: &HashSet<PartitionKey>,
+    ) -> (Vec<PartitionKey>, Vec<PartitionKey>) {
+        partitions.into_iter().partition(|pk| !exclude.contains(pk))
+    }
+
+    async fn write_partitions(&self, query: &QueryDef, partitions: Vec<PartitionKey>) -> RunReport {
+        let (results, cancelled) =
+            schedule_partitions(partitions, self.parallelism, self.failure_mode, |pk| {
+                self.write_partition(query, pk)
+            })
+            .await;
+
+        let mut stats = Vec::new();
+        let mut failures = Vec::new();
+
+        for (partition_key, result) in results {
+            match result {
+                Ok(s) => stats.push(s),
+                Err(e) => failures.push(RunFailure {
+                    query_name: query.name.clone(),
+                    partition_key,
+                    error: e.to_string(),
+                }),
+            }
+        }
+
+        RunReport {
+            stats,
+            failures,
+            skipped: Vec::new(),
+            cancelled,
+            excluded: Vec::new(),
+        }
+    }
+
+    /// Re-run the after-invariants for every partition in `from..=to` against
+    /// existing data. Each successful check lands in `stats` with its report.
+    pub async fn revalidate(
+        &self,
+        query_name: &str,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<RunReport> {
+        let query = self
+            .get_query(query_name)
+            .ok_or_else(|| BqDriftError::QueryNotFound(query_name.to_string()))?;
+
+        let (first, last) =
+            Self::date_partition_bounds(&query.name, &query.destination.partition, from, to)?;
+        let partitions = Self::partition_range(first, last, None, self.max_partitions)?;
 
         let results: Vec<_> = stream::iter(partitions)
             .map(|pk| async move {
-                let result = self.writer.write_partition(query, pk).await;
+                let result = self.writer.revalidate_partition(query, pk).await;
                 (pk, result)
             })
             .buffer_unordered(self.parallelism)
@@ -175,19 +573,576 @@ impl Runner {
 
         for (partition_key, result) in results {
             match result {
-                Ok(s) => stats.push(s),
+                Ok(report) => stats.push(PartitionWriteStats {
+                    query_name: query.name.clone(),
+                    version: query
+                        .get_version_for_date(partition_key.to_naive_date())
+                        .map(|v| v.version)
+                        .unwrap_or(0),
+                    sql_revision: None,
+                    partition_key,
+                    invariant_report: Some(report),
+                    query_stats: QueryStats::default(),
+                }),
                 Err(e) => failures.push(RunFailure {
-                    query_name: query_name.to_string(),
+                    query_name: query.name.clone(),
                     partition_key,
                     error: e.to_string(),
                 }),
             }
         }
 
-        Ok(RunReport { stats, failures })
+        Ok(RunReport {
+            stats,
+            failures,
+            skipped: Vec::new(),
+            cancelled: Vec::new(),
+            excluded: Vec::new(),
+        })
+    }
+
+    /// Dry-run the SELECT each query writes for `date`, provenance columns
+    /// included, and diff the result schema against the declared schema of
+    /// the version active on that date.
+    pub async fn validate_output_schemas(
+        &self,
+        date: NaiveDate,
+    ) -> Result<Vec<OutputSchemaReport>> {
+        let partition_key = PartitionKey::Day(date);
+        let as_of = self.as_of();
+
+        let results: Vec<_> = stream::iter(self.queries.iter())
+            .filter_map(|query| async move {
+                let version = query.get_version_for_date(date)?;
+                let sql = parameterize_sql(&query.source_sql(version, as_of), &partition_key);
+                Some(async move {
+                    let dry_run = self.writer.client().dry_run(&sql).await?;
+                    Ok(OutputSchemaReport {
+                        query_name: query.name.clone(),
+                        version: version.version,
+                        mismatches: compare_output_schema(&version.schema, &dry_run.schema),
+                    })
+                })
+            })
+            .buffer_unordered(self.parallelism)
+            .collect()
+            .await;
+
+        let mut reports: Vec<OutputSchemaReport> = results.into_iter().collect::<Result<_>>()?;
+        reports.sort_by(|a, b| a.query_name.cmp(&b.query_name));
+        Ok(reports)
     }
 
     pub fn queries(&self) -> &[QueryDef] {
         &self.queries
     }
 }
+
+/// Write `partition_key` for every query in `queries`, one dependency level
+/// at a time. A query reading from one that failed or was itself skipped is
+/// not run and is listed in `RunReport::skipped`. An empty catalog yields an
+/// empty report.
+async fn run_levels<'q, F, Fut>(
+    queries: &'q [QueryDef],
+    partition_key: PartitionKey,
+    as_of: NaiveDate,
+    default_project: Option<&str>,
+    parallelism: usize,
+    write: F,
+) -> Result<RunReport>
+where
+    F: Fn(&'q QueryDef) -> Fut,
+    Fut: Future<Output = Result<PartitionWriteStats>>,
+{
+    let mut report = RunReport {
+        stats: Vec::new(),
+        failures: Vec::new(),
+        skipped: Vec::new(),
+        cancelled: Vec::new(),
+        excluded: Vec::new(),
+    };
+    if queries.is_empty() {
+        return Ok(report);
+    }
+
+    let partition_date = partition_key.to_naive_date();
+    let levels = dependency_levels_for(queries, partition_date, as_of, default_project)?;
+    let upstreams = upstreams_for(queries, partition_date, as_of, default_project);
+    let mut incomplete: HashSet<usize> = HashSet::new();
+    for level in levels {
+        let mut to_run = Vec::with_capacity(level.len());
+        for idx in level {
+            match upstreams[idx].iter().find(|u| incomplete.contains(u)) {
+                Some(&upstream) => {
+                    incomplete.insert(idx);
+                    report.skipped.push(RunFailure {
+                        query_name: queries[idx].name.clone(),
+                        partition_key,
+                        error: format!(
+                            "upstream query '{}' did not complete",
+                            queries[upstream].name
+                        ),
+                    });
+                }
+                None => to_run.push(idx),
+            }
+        }
+
+        let level_results: Vec<_> = stream::iter(to_run)
+            .map(|idx| {
+                let write = &write;
+                async move { (idx, write(&queries[idx]).await) }
+            })
+            .buffer_unordered(parallelism)
+            .collect()
+            .await;
+        for (idx, result) in level_results {
+            match result {
+                Ok(s) => report.stats.push(s),
+                Err(e) => {
+                    incomplete.insert(idx);
+                    report.failures.push(RunFailure {
+                        query_name: queries[idx].name.clone(),
+                        partition_key,
+                        error: e.to_string(),
+                    });
+                }
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+/// Run `write` for one partition, emitting `RunEvent::Started` before it and
+/// `Succeeded` or `Failed` after it to `sink`.
+async fn with_events<F, Fut>(
+    sink: Option<&EventSink>,
+    query_name: &str,
+    partition_key: PartitionKey,
+    write: F,
+) -> Result<PartitionWriteStats>
+where
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = Result<PartitionWriteStats>>,
+{
+    let emit = |event: RunEvent| {
+        if let Some(sink) = sink {
+            sink(event);
+        }
+    };
+
+    let started_at = Utc::now();
+    emit(RunEvent::Started {
+        query_name: query_name.to_string(),
+        partition_key,
+        at: started_at,
+    });
+
+    let result = write().await;
+
+    match &result {
+        Ok(stats) => emit(RunEvent::Succeeded {
+            stats: stats.clone(),
+            started_at,
+            at: Utc::now(),
+        }),
+        Err(e) => emit(RunEvent::Failed {
+            query_name: query_name.to_string(),
+            partition_key,
+            error: e.to_string(),
+            started_at,
+            at: Utc::now(),
+        }),
+    }
+
+    result
+}
+
+/// Run `op` for each partition with bounded concurrency. Under
+/// `FailureMode::AbortOnFirst`, partitions not yet started when a failure
+/// lands are returned as cancelled instead of being run.
+async fn schedule_partitions<T, F, Fut>(
+    partitions: Vec<PartitionKey>,
+    parallelism: usize,
+    failure_mode: FailureMode,
+    op: F,
+) -> (Vec<(PartitionKey, Result<T>)>, Vec<PartitionKey>)
+where
+    F: Fn(PartitionKey) -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let aborted = AtomicBool::new(false);
+    let outcomes: Vec<(PartitionKey, Option<Result<T>>)> = stream::iter(partitions)
+        .map(|pk| {
+            let aborted = &aborted;
+            let op = &op;
+            async move {
+                if aborted.load(Ordering::SeqCst) {
+                    return (pk, None);
+                }
+                let result = op(pk).await;
+                if result.is_err() && failure_mode == FailureMode::AbortOnFirst {
+                    aborted.store(true, Ordering::SeqCst);
+                }
+                (pk, Some(result))
+            }
+        })
+        .buffer_unordered(parallelism)
+        .collect()
+        .await;
+
+    let mut results = Vec::with_capacity(outcomes.len());
+    let mut cancelled = Vec::new();
+    for (pk, outcome) in outcomes {
+        match outcome {
+            Some(result) => results.push((pk, result)),
+            None => cancelled.push(pk),
+        }
+    }
+    cancelled.sort();
+    (results, cancelled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dsl::QueryLoader;
+    use chrono::Datelike;
+    use std::path::Path;
+    use std::sync::atomic::AtomicUsize;
+
+    fn day_partitions(days: u32) -> Vec<PartitionKey> {
+        (1..=days)
+            .map(|d| PartitionKey::Day(NaiveDate::from_ymd_opt(2024, 1, d).unwrap()))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_abort_on_first_cancels_remaining_partitions() {
+        let partitions = day_partitions(10);
+        let calls = AtomicUsize::new(0);
+
+        let (results, cancelled) =
+            schedule_partitions(partitions, 1, FailureMode::AbortOnFirst, |pk| {
+                calls.fetch_add(1, Ordering::SeqCst);
+                async move {
+                    if pk.to_naive_date().day() == 3 {
+                        Err(BqDriftError::Executor("syntax error".to_string()))
+                    } else {
+                        Ok(pk)
+                    }
+                }
+            })
+            .await;
+
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+        assert_eq!(results.len(), 3);
+        assert_eq!(results.iter().filter(|(_, r)| r.is_err()).count(), 1);
+        assert_eq!(cancelled, day_partitions(10)[3..].to_vec());
+    }
+
+    #[tokio::test]
+    async fn test_collect_all_runs_every_partition() {
+        let partitions = day_partitions(5);
+
+        let (results, cancelled) =
+            schedule_partitions(partitions, 2, FailureMode::CollectAll, |_| async {
+                Err::<(), _>(BqDriftError::Executor("syntax error".to_string()))
+            })
+            .await;
+
+        assert_eq!(results.len(), 5);
+        assert!(cancelled.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_run_emits_started_then_outcome_per_partition() {
+        let events = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let captured = events.clone();
+        let sink: EventSink = Arc::new(move |event| captured.lock().unwrap().push(event));
+        let partitions = day_partitions(2);
+
+        let (results, _) =
+            schedule_partitions(partitions.clone(), 1, FailureMode::CollectAll, |pk| {
+                with_events(Some(&sink), "daily_stats", pk, move || async move {
+                    if pk.to_naive_date().day() == 2 {
+                        Err(BqDriftError::Executor("syntax error".to_string()))
+                    } else {
+                        Ok(PartitionWriteStats {
+                            query_name: "daily_stats".to_string(),
+                            version: 1,
+                            sql_revision: None,
+                            partition_key: pk,
+                            invariant_report: None,
+                            query_stats: QueryStats::default(),
+                        })
+                    }
+                })
+            })
+            .await;
+        assert_eq!(results.len(), 2);
+
+        let events = events.lock().unwrap();
+        let sequence: Vec<_> = events
+            .iter()
+            .map(|e| {
+                let kind = match e {
+                    RunEvent::Started { .. } => "started",
+                    RunEvent::Succeeded { .. } => "succeeded",
+                    RunEvent::Failed { .. } => "failed",
+                };
+                (kind, e.partition_key())
+            })
+            .collect();
+        assert_eq!(
+            sequence,
+            vec![
+                ("started", partitions[0]),
+                ("succeeded", partitions[0]),
+                ("started", partitions[1]),
+                ("failed", partitions[1]),
+            ]
+        );
+        assert!(events.iter().all(|e| e.query_name() == "daily_stats"));
+        let RunEvent::Failed { error, .. } = &events[3] else {
+            panic!("expected a Failed event, got {:?}", events[3]);
+        };
+        assert!(error.contains("syntax error"));
+    }
+
+    #[tokio::test]
+    async fn test_run_levels_on_empty_catalog_returns_empty_report() {
+        let partition_key = day_partitions(1)[0];
+        let report = run_levels(
+            &[],
+            partition_key,
+            Utc::now().date_naive(),
+            None,
+            4,
+            |_| async {
+                Err(BqDriftError::Executor(
+                    "no query should be written".to_string(),
+                ))
+            },
+        )
+        .await
+        .unwrap();
+
+        assert!(report.stats.is_empty());
+        assert!(report.failures.is_empty());
+        assert!(report.skipped.is_empty());
+        assert!(report.cancelled.is_empty());
+        assert!(report.excluded.is_empty());
+    }
+
+    fn chained_query(name: &str, reads: &str) -> QueryDef {
+        let mut query = QueryLoader::new()
+            .load_query(Path::new("tests/fixtures/analytics/simple_query.yaml"))
+            .unwrap();
+        query.name = name.to_string();
+        query.destination.table = name.to_string();
+        query.versions[0].dependencies = HashSet::from([reads.to_string()]);
+        query
+    }
+
+    #[tokio::test]
+    async fn test_run_levels_skips_dependents_of_failed_queries() {
+        let queries = vec![
+            chained_query("a", "raw.events"),
+            chained_query("b", "test_dataset.a"),
+            chained_query("c", "test_dataset.b"),
+            chained_query("d", "raw.events"),
+        ];
+        let partition_key = PartitionKey::Day(NaiveDate::from_ymd_opt(2024, 6, 1).unwrap());
+        let as_of = NaiveDate::from_ymd_opt(2024, 6, 2).unwrap();
+        let written = std::sync::Mutex::new(Vec::new());
+
+        let report = run_levels(&queries, partition_key, as_of, None, 2, |query| {
+            written.lock().unwrap().push(query.name.clone());
+            async move {
+                if query.name == "a" {
+                    return Err(BqDriftError::Executor("syntax error".to_string()));
+                }
+                Ok(PartitionWriteStats {
+                    query_name: query.name.clone(),
+                    version: 1,
+                    sql_revision: None,
+                    partition_key,
+                    invariant_report: None,
+                    query_stats: QueryStats::default(),
+                })
+            }
+        })
+        .await
+        .unwrap();
+
+        let mut written = written.into_inner().unwrap();
+        written.sort();
+        assert_eq!(written, vec!["a", "d"]);
+        assert_eq!(report.stats.len(), 1);
+        assert_eq!(report.stats[0].query_name, "d");
+        assert_eq!(report.failures.len(), 1);
+        assert_eq!(report.failures[0].query_name, "a");
+
+        let skipped: Vec<_> = report
+            .skipped
+            .iter()
+            .map(|s| (s.query_name.as_str(), s.error.as_str()))
+            .collect();
+        assert_eq!(
+            skipped,
+            vec![
+                ("b", "upstream query 'a' did not complete"),
+                ("c", "upstream query 'b' did not complete"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_split_excluded_keeps_range_order() {
+        let partitions = day_partitions(5);
+        let exclude = HashSet::from([partitions[3], partitions[1]]);
+
+        let (to_write, excluded) = Runner::split_excluded(partitions.clone(), &exclude);
+        assert_eq!(to_write, vec![partitions[0], partitions[2], partitions[4]]);
+        assert_eq!(excluded, vec![partitions[1], partitions[3]]);
+    }
+
+    #[test]
+    fn test_partition_range_steps_by_interval() {
+        let partitions = Runner::partition_range(
+            PartitionKey::Range(0),
+            PartitionKey::Range(250),
+            Some(100),
+            10,
+        )
+        .unwrap();
+        assert_eq!(
+            partitions,
+            vec![
+                PartitionKey::Range(0),
+                PartitionKey::Range(100),
+                PartitionKey::Range(200)
+            ]
+        );
+
+        assert!(Runner::partition_range(
+            PartitionKey::Range(0),
+            PartitionKey::Range(10),
+            Some(0),
+            10
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_date_partition_bounds_follow_partition_type() {
+        let from = NaiveDate::from_ymd_opt(2024, 1, 15).unwrap();
+        let to = NaiveDate::from_ymd_opt(2024, 3, 2).unwrap();
+        let bounds =
+            |partition: PartitionConfig| Runner::date_partition_bounds("q", &partition, from, to);
+
+        assert_eq!(
+            bounds(PartitionConfig::day("date")).unwrap(),
+            (PartitionKey::Day(from), PartitionKey::Day(to))
+        );
+        assert_eq!(
+            bounds(PartitionConfig::hour("ts")).unwrap(),
+            (
+                PartitionKey::Hour(from.and_hms_opt(0, 0, 0).unwrap()),
+                PartitionKey::Hour(to.and_hms_opt(23, 0, 0).unwrap())
+            )
+        );
+        assert_eq!(
+            bounds(PartitionConfig::month("date")).unwrap(),
+            (
+                PartitionKey::Month {
+                    year: 2024,
+                    month: 1
+                },
+                PartitionKey::Month {
+                    year: 2024,
+                    month: 3
+                }
+            )
+        );
+        assert!(bounds(PartitionConfig::range("id", 0, 100, 10))
+            .unwrap_err()
+            .to_string()
+            .contains("range-partitioned query 'q'"));
+    }
+
+    #[test]
+    fn test_partition_range_capped_at_max_partitions() {
+        let days = day_partitions(5);
+        let range = |max| Runner::partition_range(days[0], days[4], None, max);
+
+        assert_eq!(range(5).unwrap(), days);
+        let err = range(4).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("2024-01-01 to 2024-01-05 exceeds maximum of 4 partitions"));
+    }
+
+    #[test]
+    fn test_run_event_accessors() {
+        let pk = PartitionKey::Day(NaiveDate::from_ymd_opt(2024, 1, 15).unwrap());
+        let at = Utc::now();
+
+        let started = RunEvent::Started {
+            query_name: "daily_stats".to_string(),
+            partition_key: pk,
+            at,
+        };
+        assert_eq!(started.query_name(), "daily_stats");
+        assert_eq!(started.partition_key(), pk);
+
+        let succeeded = RunEvent::Succeeded {
+            stats: PartitionWriteStats {
+                query_name: "daily_stats".to_string(),
+                version: 2,
+                sql_revision: None,
+                partition_key: pk,
+                invariant_report: None,
+                query_stats: QueryStats::default(),
+            },
+            started_at: at,
+            at,
+        };
+        assert_eq!(succeeded.query_name(), "daily_stats");
+        assert_eq!(succeeded.at(), at);
+    }
+
+    #[test]
+    fn test_run_report_aggregates_query_stats() {
+        let stats = |day: u32, bytes: Option<i64>, slot_ms: Option<i64>| PartitionWriteStats {
+            query_name: "daily_stats".to_string(),
+            version: 1,
+            sql_revision: None,
+            partition_key: PartitionKey::Day(NaiveDate::from_ymd_opt(2024, 1, day).unwrap()),
+            invariant_report: None,
+            query_stats: QueryStats {
+                total_bytes_processed: bytes,
+                total_bytes_billed: bytes,
+                total_slot_ms: slot_ms,
+            },
+        };
+        let report = RunReport {
+            stats: vec![
+                stats(1, Some(1 << 40), Some(1_800_000)),
+                stats(2, Some(1 << 40), None),
+                stats(3, None, Some(1_800_000)),
+            ],
+            failures: Vec::new(),
+            skipped: Vec::new(),
+            cancelled: Vec::new(),
+            excluded: Vec::new(),
+        };
+
+        assert_eq!(report.total_bytes_processed(), 2 << 40);
+        assert_eq!(report.total_slot_ms(), 3_600_000);
+        assert_eq!(report.estimated_cost(&CostModel::on_demand(6.25)), 12.5);
+        assert_eq!(report.estimated_cost(&CostModel::capacity(0.06)), 0.06);
+    }
+}
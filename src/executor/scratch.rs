@@ -1,11 +1,14 @@
 use super::client::BqClient;
 use super::invariant_runner::execute_with_invariants;
+use super::partition_writer::as_of_or_today;
+use super::sql_builder::{insert_clause, partition_condition, MergePlan};
 use crate::dsl::Destination;
-use crate::dsl::QueryDef;
+use crate::dsl::{QueryDef, VersionDef};
 use crate::error::Result;
 use crate::invariant::InvariantReport;
-use crate::schema::PartitionKey;
-use chrono::{DateTime, Duration, NaiveTime, Utc};
+use crate::schema::{PartitionColumnType, PartitionKey};
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, Utc};
+use tracing::warn;
 
 const SCRATCH_DATASET: &str = "bqdrift_scratch";
 
@@ -31,11 +34,22 @@ impl ScratchConfig {
 pub struct ScratchWriter {
     client: BqClient,
     config: ScratchConfig,
+    as_of: Option<NaiveDate>,
 }
 
 impl ScratchWriter {
     pub fn new(client: BqClient, config: ScratchConfig) -> Self {
-        Self { client, config }
+        Self {
+            client,
+            config,
+            as_of: None,
+        }
+    }
+
+    /// Resolve revisions as of `date` instead of today.
+    pub fn with_as_of(mut self, date: NaiveDate) -> Self {
+        self.as_of = Some(date);
+        self
     }
 
     pub fn scratch_table_name(query_def: &QueryDef) -> String {
@@ -102,14 +116,7 @@ impl ScratchWriter {
         run_invariants: bool,
     ) -> Result<ScratchWriteStats> {
         let partition_date = partition_key.to_naive_date();
-        let version = query_def
-            .get_version_for_date(partition_date)
-            .ok_or_else(|| {
-                crate::error::BqDriftError::Partition(format!(
-                    "No version found for partition {}",
-                    partition_key
-                ))
-            })?;
+        let version = query_def.writable_version(&partition_key)?;
 
         let scratch_table = Self::scratch_table_name(query_def);
         let expiration = self.calculate_expiration(&partition_key);
@@ -130,16 +137,23 @@ impl ScratchWriter {
             .await?;
 
         let scratch_destination = Destination {
+            project: None,
             dataset: SCRATCH_DATASET.to_string(),
             table: scratch_table.clone(),
             partition: query_def.destination.partition.clone(),
             cluster: query_def.destination.cluster.clone(),
+            labels: query_def.destination.labels.clone(),
         };
 
-        let sql = version.get_sql_for_date(chrono::Utc::now().date_naive());
-        let full_sql = self.build_merge_sql(query_def, &scratch_destination, sql, &partition_key);
+        let full_sql = scratch_merge_sql(
+            &self.config.project,
+            query_def,
+            version,
+            &partition_key,
+            self.as_of,
+        );
 
-        let invariant_report = execute_with_invariants(
+        let ((), invariant_report) = execute_with_invariants(
             &self.client,
             &scratch_destination,
             partition_date,
@@ -159,30 +173,14 @@ impl ScratchWriter {
         })
     }
 
-    fn build_merge_sql(
-        &self,
-        query_def: &QueryDef,
-        scratch_dest: &Destination,
-        sql: &str,
-        partition_key: &PartitionKey,
-    ) -> String {
-        let dest_table = format!(
-            "{}.{}.{}",
-            self.config.project, scratch_dest.dataset, scratch_dest.table
-        );
-        let partition_field = query_def
-            .destination
-            .partition
-            .field
-            .as_deref()
-            .unwrap_or("date");
-        super::sql_builder::build_merge_sql(&dest_table, partition_field, sql, partition_key)
-    }
-
     pub async fn list_tables(&self) -> Result<Vec<String>> {
         self.client.list_tables(SCRATCH_DATASET).await
     }
 
+    /// Replace the production partition with the scratch one. When both
+    /// tables share a schema this is a copy job on the partition decorators;
+    /// otherwise, or if the copy job is rejected, it falls back to a MERGE
+    /// that reads the scratch table.
     pub async fn promote_to_production(
         &self,
         query_def: &QueryDef,
@@ -192,45 +190,88 @@ impl ScratchWriter {
         let scratch_table = self.scratch_table_fqn(query_def);
         let production_table = format!(
             "{}.{}.{}",
-            production_client.project_id(),
+            query_def
+                .destination
+                .project
+                .as_deref()
+                .unwrap_or(production_client.project_id()),
             query_def.destination.dataset,
             query_def.destination.table
         );
 
-        let partition_field = query_def
-            .destination
-            .partition
-            .field
-            .as_deref()
-            .unwrap_or("date");
-
-        let partition_condition = match partition_key {
-            PartitionKey::Hour(_) => format!(
-                "TIMESTAMP_TRUNC(target.{}, HOUR) = {}",
-                partition_field,
-                partition_key.sql_literal()
-            ),
-            PartitionKey::Day(_) => format!(
-                "target.{} = {}",
-                partition_field,
-                partition_key.sql_literal()
-            ),
-            PartitionKey::Month { .. } => format!(
-                "DATE_TRUNC(target.{}, MONTH) = {}",
-                partition_field,
-                partition_key.sql_literal()
-            ),
-            PartitionKey::Year(_) => format!(
-                "DATE_TRUNC(target.{}, YEAR) = {}",
-                partition_field,
-                partition_key.sql_literal()
-            ),
-            PartitionKey::Range(_) => format!(
-                "target.{} = {}",
-                partition_field,
-                partition_key.sql_literal()
-            ),
+        let copied = query_def.destination.partition.is_partitioned()
+            && self
+                .schemas_match(&scratch_table, &production_table, production_client)
+                .await?
+            && match production_client
+                .copy_partition(&scratch_table, &production_table, partition_key)
+                .await
+            {
+                Ok(()) => true,
+                Err(e) => {
+                    warn!(
+                        "Copy job into {} failed, falling back to MERGE: {}",
+                        production_table, e
+                    );
+                    false
+                }
+            };
+
+        let method = if copied {
+            PromoteMethod::Copy
+        } else {
+            self.promote_with_merge(
+                query_def,
+                partition_key,
+                &scratch_table,
+                &production_table,
+                production_client,
+            )
+            .await?
+        };
+
+        Ok(PromoteStats {
+            query_name: query_def.name.clone(),
+            partition_key: partition_key.clone(),
+            scratch_table,
+            production_table,
+            method,
+        })
+    }
+
+    async fn schemas_match(
+        &self,
+        scratch_table: &str,
+        production_table: &str,
+        production_client: &BqClient,
+    ) -> Result<bool> {
+        let scratch = self.client.table_schema(scratch_table).await?;
+        let production = production_client.table_schema(production_table).await?;
+        Ok(scratch.diff(&production).is_empty())
+    }
+
+    async fn promote_with_merge(
+        &self,
+        query_def: &QueryDef,
+        partition_key: &PartitionKey,
+        scratch_table: &str,
+        production_table: &str,
+        production_client: &BqClient,
+    ) -> Result<PromoteMethod> {
+        let partition_condition = if query_def.destination.partition.is_partitioned() {
+            let (partition_field, column_type) = query_def
+                .destination
+                .partition
+                .predicate_column()
+                .unwrap_or(("date", PartitionColumnType::Date));
+            partition_condition(partition_field, partition_key, column_type)
+        } else {
+            "TRUE".to_string()
         };
+        let ingestion_time_columns = query_def
+            .get_version_for_date(partition_key.to_naive_date())
+            .and_then(|v| query_def.ingestion_time_columns(v));
+        let insert = insert_clause(ingestion_time_columns.as_deref(), partition_key);
 
         let merge_sql = format!(
             r#"
@@ -238,22 +279,50 @@ impl ScratchWriter {
             USING `{scratch_table}` AS source
             ON FALSE
             WHEN NOT MATCHED BY SOURCE AND {partition_condition} THEN DELETE
-            WHEN NOT MATCHED BY TARGET THEN INSERT ROW
+            WHEN NOT MATCHED BY TARGET THEN {insert}
             "#,
             production_table = production_table,
             scratch_table = scratch_table,
             partition_condition = partition_condition,
+            insert = insert,
         );
 
         production_client.execute_query(&merge_sql).await?;
+        Ok(PromoteMethod::Merge)
+    }
+}
 
-        Ok(PromoteStats {
-            query_name: query_def.name.clone(),
-            partition_key: partition_key.clone(),
-            scratch_table,
-            production_table,
-        })
+/// The MERGE a `ScratchWriter` with the given `as_of` runs to write
+/// `partition_key` of `query_def` into its scratch table in `project`.
+pub(crate) fn scratch_merge_sql(
+    project: &str,
+    query_def: &QueryDef,
+    version: &VersionDef,
+    partition_key: &PartitionKey,
+    as_of: Option<NaiveDate>,
+) -> String {
+    let dest_table = format!(
+        "{}.{}.{}",
+        project,
+        SCRATCH_DATASET,
+        ScratchWriter::scratch_table_name(query_def)
+    );
+    let sql = query_def.source_sql(version, as_of_or_today(as_of));
+    if !query_def.destination.partition.is_partitioned() {
+        return MergePlan::replace_table(&dest_table, &sql, partition_key).render();
     }
+    let (partition_field, column_type) = query_def
+        .destination
+        .partition
+        .predicate_column()
+        .unwrap_or(("date", PartitionColumnType::Date));
+    let plan = MergePlan::new(&dest_table, partition_field, &sql, partition_key)
+        .with_column_type(column_type);
+    match query_def.ingestion_time_columns(version) {
+        Some(columns) => plan.with_ingestion_time(columns),
+        None => plan,
+    }
+    .render()
 }
 
 #[derive(Debug, Clone)]
@@ -272,12 +341,34 @@ pub struct PromoteStats {
     pub partition_key: PartitionKey,
     pub scratch_table: String,
     pub production_table: String,
+    pub method: PromoteMethod,
+}
+
+/// How a scratch partition reached production.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PromoteMethod {
+    /// Table copy job on the partition decorators.
+    Copy,
+    /// MERGE reading from the scratch table.
+    Merge,
+}
+
+impl std::fmt::Display for PromoteMethod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PromoteMethod::Copy => write!(f, "copy"),
+            PromoteMethod::Merge => write!(f, "merge"),
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::dsl::QueryLoader;
+    use crate::invariant::InvariantsDef;
     use chrono::NaiveDate;
+    use std::path::Path;
 
     #[test]
     fn test_scratch_table_name() {
@@ -285,7 +376,9 @@ mod tests {
 
         let query_def = QueryDef {
             name: "daily_stats".to_string(),
+            previous_names: vec![],
             destination: Destination {
+                project: None,
                 dataset: "analytics".to_string(),
                 table: "daily_user_stats".to_string(),
                 partition: PartitionConfig {
@@ -295,14 +388,20 @@ mod tests {
                     end: None,
                     interval: None,
                     granularity: None,
+                    require_filter: false,
+                    expiration_days: None,
+                    column_type: Default::default(),
                 },
                 cluster: None,
+                labels: Default::default(),
             },
             description: None,
             owner: None,
             tags: vec![],
             versions: vec![],
             cluster: None,
+            default_invariants: InvariantsDef::default(),
+            provenance: false,
         };
 
         assert_eq!(
@@ -334,6 +433,34 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_scratch_merge_sql_uses_as_of_revision() {
+        let query = QueryLoader::new()
+            .load_query(Path::new("tests/fixtures/analytics/versioned_query.yaml"))
+            .unwrap();
+        let version = &query.versions[1];
+        let partition = PartitionKey::Day(NaiveDate::from_ymd_opt(2024, 3, 10).unwrap());
+
+        let before = scratch_merge_sql(
+            "proj",
+            &query,
+            version,
+            &partition,
+            NaiveDate::from_ymd_opt(2024, 3, 10),
+        );
+        assert!(before.contains("`proj.bqdrift_scratch.test_dataset__versioned_table`"));
+        assert!(!before.contains("COALESCE"));
+
+        let after = scratch_merge_sql(
+            "proj",
+            &query,
+            version,
+            &partition,
+            NaiveDate::from_ymd_opt(2024, 4, 1),
+        );
+        assert!(after.contains("COALESCE(e.user_id, 'unknown')"));
+    }
+
     #[test]
     fn test_calculate_expiration_with_ttl_override() {
         let config = ScratchConfig::new("test-project".to_string()).with_ttl(48);
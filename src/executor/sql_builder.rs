@@ -1,56 +1,351 @@
-use crate::schema::PartitionKey;
+use crate::schema::{
+    PartitionColumnType, PartitionKey, PROVENANCE_QUERY_COLUMN, PROVENANCE_RUN_AT_COLUMN,
+    PROVENANCE_VERSION_COLUMN,
+};
 
-pub(crate) fn build_merge_sql(
-    dest_table: &str,
-    partition_field: &str,
-    sql: &str,
-    partition_key: &PartitionKey,
-) -> String {
-    let parameterized_sql = sql.replace(
+/// Wrap `sql` so every row carries the query name, version and write time.
+pub fn with_provenance_columns(sql: &str, query_name: &str, version: u32) -> String {
+    format!(
+        "SELECT source.*, '{query}' AS {query_col}, {version} AS {version_col}, CURRENT_TIMESTAMP() AS {run_at_col}\nFROM (\n{sql}\n) AS source",
+        query = query_name.replace('\\', "\\\\").replace('\'', "\\'"),
+        query_col = PROVENANCE_QUERY_COLUMN,
+        version = version,
+        version_col = PROVENANCE_VERSION_COLUMN,
+        run_at_col = PROVENANCE_RUN_AT_COLUMN,
+        sql = sql,
+    )
+}
+
+pub(crate) fn parameterize_sql(sql: &str, partition_key: &PartitionKey) -> String {
+    sql.replace(
         "@partition_date",
         &format!("'{}'", partition_key.sql_value()),
-    );
-
-    let partition_condition = match partition_key {
-        PartitionKey::Hour(_) => format!(
-            "TIMESTAMP_TRUNC(target.{}, HOUR) = {}",
-            partition_field,
-            partition_key.sql_literal()
-        ),
-        PartitionKey::Day(_) => format!(
-            "target.{} = {}",
-            partition_field,
-            partition_key.sql_literal()
-        ),
-        PartitionKey::Month { .. } => format!(
-            "DATE_TRUNC(target.{}, MONTH) = {}",
-            partition_field,
-            partition_key.sql_literal()
-        ),
-        PartitionKey::Year(_) => format!(
-            "DATE_TRUNC(target.{}, YEAR) = {}",
-            partition_field,
-            partition_key.sql_literal()
-        ),
-        PartitionKey::Range(_) => format!(
-            "target.{} = {}",
-            partition_field,
-            partition_key.sql_literal()
-        ),
-    };
+    )
+}
 
-    format!(
-        r#"
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergeStrategy {
+    /// Delete every target row in the partition and insert the source rows.
+    ReplacePartition,
+    /// Delete every target row and insert the source rows, for tables
+    /// without partitioning.
+    ReplaceTable,
+}
+
+/// Structured description of the MERGE statement used to write a partition.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MergePlan {
+    pub destination: String,
+    pub partition_field: String,
+    pub partition_key: PartitionKey,
+    pub column_type: PartitionColumnType,
+    pub partition_condition: String,
+    pub strategy: MergeStrategy,
+    pub source_sql: String,
+    /// Destination columns of an ingestion-time table. Rows are then
+    /// inserted with an explicit `_PARTITIONTIME` instead of `INSERT ROW`,
+    /// which would land them in the current partition.
+    pub ingestion_time_columns: Option<Vec<String>>,
+}
+
+impl MergePlan {
+    pub fn new(
+        dest_table: &str,
+        partition_field: &str,
+        sql: &str,
+        partition_key: &PartitionKey,
+    ) -> Self {
+        Self {
+            destination: dest_table.to_string(),
+            partition_field: partition_field.to_string(),
+            partition_key: *partition_key,
+            column_type: PartitionColumnType::Date,
+            partition_condition: partition_condition(
+                partition_field,
+                partition_key,
+                PartitionColumnType::Date,
+            ),
+            strategy: MergeStrategy::ReplacePartition,
+            source_sql: parameterize_sql(sql, partition_key),
+            ingestion_time_columns: None,
+        }
+    }
+
+    /// Replace the whole of an unpartitioned table; `partition_key` only
+    /// fills in `@partition_date`.
+    pub fn replace_table(dest_table: &str, sql: &str, partition_key: &PartitionKey) -> Self {
+        Self {
+            destination: dest_table.to_string(),
+            partition_field: String::new(),
+            partition_key: *partition_key,
+            column_type: PartitionColumnType::Date,
+            partition_condition: "TRUE".to_string(),
+            strategy: MergeStrategy::ReplaceTable,
+            source_sql: parameterize_sql(sql, partition_key),
+            ingestion_time_columns: None,
+        }
+    }
+
+    /// Match target rows by a partition column of `column_type`.
+    pub fn with_column_type(mut self, column_type: PartitionColumnType) -> Self {
+        self.column_type = column_type;
+        if self.strategy == MergeStrategy::ReplacePartition {
+            self.partition_condition =
+                partition_condition(&self.partition_field, &self.partition_key, column_type);
+        }
+        self
+    }
+
+    /// Write into an ingestion-time table whose columns are `columns`.
+    pub fn with_ingestion_time(mut self, columns: Vec<String>) -> Self {
+        self.ingestion_time_columns = Some(columns);
+        self
+    }
+
+    /// Rows are matched `ON FALSE` and written with `INSERT ROW`, so the
+    /// partition column is the only one that appears in a predicate and
+    /// `REPEATED` columns are copied without being compared.
+    pub fn render(&self) -> String {
+        let insert = insert_clause(self.ingestion_time_columns.as_deref(), &self.partition_key);
+        match self.strategy {
+            MergeStrategy::ReplaceTable => format!(
+                r#"
+            MERGE `{dest_table}` AS target
+            USING (
+                {parameterized_sql}
+            ) AS source
+            ON FALSE
+            WHEN NOT MATCHED BY SOURCE THEN DELETE
+            WHEN NOT MATCHED BY TARGET THEN {insert}
+            "#,
+                dest_table = self.destination,
+                parameterized_sql = self.source_sql,
+                insert = insert,
+            ),
+            MergeStrategy::ReplacePartition => format!(
+                r#"
             MERGE `{dest_table}` AS target
             USING (
                 {parameterized_sql}
             ) AS source
             ON FALSE
             WHEN NOT MATCHED BY SOURCE AND {partition_condition} THEN DELETE
-            WHEN NOT MATCHED BY TARGET THEN INSERT ROW
+            WHEN NOT MATCHED BY TARGET THEN {insert}
             "#,
-        dest_table = dest_table,
-        parameterized_sql = parameterized_sql,
-        partition_condition = partition_condition,
+                dest_table = self.destination,
+                parameterized_sql = self.source_sql,
+                partition_condition = self.partition_condition,
+                insert = insert,
+            ),
+        }
+    }
+}
+
+/// `INSERT ROW`, or for an ingestion-time table an insert naming
+/// `_PARTITIONTIME` so rows land in `partition_key`'s partition.
+pub(crate) fn insert_clause(columns: Option<&[String]>, partition_key: &PartitionKey) -> String {
+    let Some(columns) = columns else {
+        return "INSERT ROW".to_string();
+    };
+    let quoted: Vec<String> = columns.iter().map(|c| format!("`{}`", c)).collect();
+    format!(
+        "INSERT (_PARTITIONTIME, {columns}) VALUES (TIMESTAMP '{value}', {columns})",
+        columns = quoted.join(", "),
+        value = partition_key.sql_value(),
     )
 }
+
+/// Predicate selecting the target rows of `partition_key`. `TIMESTAMP` and
+/// `DATETIME` columns are truncated to the partition unit so that every row
+/// in the partition matches, not only those at midnight.
+pub(crate) fn partition_condition(
+    partition_field: &str,
+    partition_key: &PartitionKey,
+    column_type: PartitionColumnType,
+) -> String {
+    let literal = partition_key.sql_literal();
+    match (column_type, partition_key) {
+        (_, PartitionKey::Range(_)) | (PartitionColumnType::Date, PartitionKey::Day(_)) => {
+            format!("target.{} = {}", partition_field, literal)
+        }
+        (PartitionColumnType::Date, PartitionKey::Hour(_)) => {
+            format!(
+                "TIMESTAMP_TRUNC(target.{}, HOUR) = {}",
+                partition_field, literal
+            )
+        }
+        (PartitionColumnType::Date, PartitionKey::Month { .. }) => {
+            format!(
+                "DATE_TRUNC(target.{}, MONTH) = {}",
+                partition_field, literal
+            )
+        }
+        (PartitionColumnType::Date, PartitionKey::Year(_)) => {
+            format!("DATE_TRUNC(target.{}, YEAR) = {}", partition_field, literal)
+        }
+        (_, PartitionKey::Day(_)) => format!("DATE(target.{}) = {}", partition_field, literal),
+        (_, key) => {
+            let unit = match key {
+                PartitionKey::Hour(_) => "HOUR",
+                PartitionKey::Month { .. } => "MONTH",
+                _ => "YEAR",
+            };
+            format!(
+                "{type_name}_TRUNC(target.{field}, {unit}) = {type_name} '{value}'",
+                type_name = column_type.sql_name(),
+                field = partition_field,
+                unit = unit,
+                value = key.sql_value()
+            )
+        }
+    }
+}
+
+pub fn build_merge_sql(
+    dest_table: &str,
+    partition_field: &str,
+    sql: &str,
+    partition_key: &PartitionKey,
+    column_type: PartitionColumnType,
+) -> String {
+    MergePlan::new(dest_table, partition_field, sql, partition_key)
+        .with_column_type(column_type)
+        .render()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn test_with_provenance_columns() {
+        let sql = with_provenance_columns(
+            "SELECT date, count FROM raw.events WHERE date = @partition_date",
+            "daily_stats",
+            3,
+        );
+
+        assert!(sql.starts_with("SELECT source.*, 'daily_stats' AS _bqdrift_query"));
+        assert!(sql.contains("3 AS _bqdrift_version"));
+        assert!(sql.contains("CURRENT_TIMESTAMP() AS _bqdrift_run_at"));
+        assert!(sql.contains("WHERE date = @partition_date\n) AS source"));
+
+        let escaped = with_provenance_columns("SELECT 1", "it's", 1);
+        assert!(escaped.contains("'it\\'s' AS _bqdrift_query"));
+    }
+
+    #[test]
+    fn test_merge_plan_fields() {
+        let pk = PartitionKey::Day(NaiveDate::from_ymd_opt(2024, 6, 15).unwrap());
+        let plan = MergePlan::new(
+            "analytics.daily",
+            "date",
+            "SELECT * FROM raw.events WHERE date = @partition_date",
+            &pk,
+        );
+
+        assert_eq!(plan.destination, "analytics.daily");
+        assert_eq!(plan.partition_field, "date");
+        assert_eq!(plan.partition_condition, "target.date = DATE '2024-06-15'");
+        assert_eq!(plan.strategy, MergeStrategy::ReplacePartition);
+        assert_eq!(
+            plan.source_sql,
+            "SELECT * FROM raw.events WHERE date = '2024-06-15'"
+        );
+    }
+
+    #[test]
+    fn test_ingestion_time_plan_sets_partitiontime() {
+        let pk = PartitionKey::Day(NaiveDate::from_ymd_opt(2024, 6, 15).unwrap());
+        let sql = MergePlan::new("raw.events", "_PARTITIONDATE", "SELECT 1", &pk)
+            .with_ingestion_time(vec!["id".to_string(), "name".to_string()])
+            .render();
+
+        assert!(sql.contains("target._PARTITIONDATE = DATE '2024-06-15'"));
+        assert!(sql.contains(
+            "INSERT (_PARTITIONTIME, `id`, `name`) VALUES (TIMESTAMP '2024-06-15', `id`, `name`)"
+        ));
+        assert!(!sql.contains("INSERT ROW"));
+    }
+
+    #[test]
+    fn test_replace_table_plan_has_no_partition_predicate() {
+        let pk = PartitionKey::Day(NaiveDate::from_ymd_opt(2024, 6, 15).unwrap());
+        let plan = MergePlan::replace_table(
+            "dims.customers",
+            "SELECT * FROM raw.customers WHERE updated <= @partition_date",
+            &pk,
+        );
+
+        assert_eq!(plan.strategy, MergeStrategy::ReplaceTable);
+        let sql = plan.render();
+        assert!(sql.contains("WHEN NOT MATCHED BY SOURCE THEN DELETE"));
+        assert!(sql.contains("updated <= '2024-06-15'"));
+        assert!(!sql.contains("target.date"));
+    }
+
+    #[test]
+    fn test_build_merge_sql_renders_plan() {
+        let pk = PartitionKey::Day(NaiveDate::from_ymd_opt(2024, 6, 15).unwrap());
+        let sql = build_merge_sql(
+            "analytics.daily",
+            "date",
+            "SELECT 1",
+            &pk,
+            PartitionColumnType::Date,
+        );
+
+        assert!(sql.contains("MERGE `analytics.daily` AS target"));
+        assert!(sql.contains("WHEN NOT MATCHED BY SOURCE AND target.date = DATE '2024-06-15'"));
+    }
+
+    #[test]
+    fn test_partition_condition_truncates_timestamp_and_datetime() {
+        let day = PartitionKey::Day(NaiveDate::from_ymd_opt(2024, 6, 15).unwrap());
+        let hour = PartitionKey::Hour(
+            NaiveDate::from_ymd_opt(2024, 6, 15)
+                .unwrap()
+                .and_hms_opt(13, 0, 0)
+                .unwrap(),
+        );
+        let month = PartitionKey::Month {
+            year: 2024,
+            month: 6,
+        };
+
+        assert_eq!(
+            partition_condition("ts", &day, PartitionColumnType::Timestamp),
+            "DATE(target.ts) = DATE '2024-06-15'"
+        );
+        assert_eq!(
+            partition_condition("ts", &hour, PartitionColumnType::Timestamp),
+            "TIMESTAMP_TRUNC(target.ts, HOUR) = TIMESTAMP '2024-06-15 13:00:00'"
+        );
+        assert_eq!(
+            partition_condition("ts", &month, PartitionColumnType::Timestamp),
+            "TIMESTAMP_TRUNC(target.ts, MONTH) = TIMESTAMP '2024-06-01'"
+        );
+        assert_eq!(
+            partition_condition("dt", &hour, PartitionColumnType::Datetime),
+            "DATETIME_TRUNC(target.dt, HOUR) = DATETIME '2024-06-15 13:00:00'"
+        );
+        assert_eq!(
+            partition_condition("dt", &month, PartitionColumnType::Datetime),
+            "DATETIME_TRUNC(target.dt, MONTH) = DATETIME '2024-06-01'"
+        );
+        assert_eq!(
+            partition_condition("d", &month, PartitionColumnType::Date),
+            "DATE_TRUNC(target.d, MONTH) = DATE '2024-06-01'"
+        );
+    }
+
+    #[test]
+    fn test_with_column_type_keeps_replace_table_predicate() {
+        let pk = PartitionKey::Day(NaiveDate::from_ymd_opt(2024, 6, 15).unwrap());
+        let plan = MergePlan::replace_table("dims.customers", "SELECT 1", &pk)
+            .with_column_type(PartitionColumnType::Timestamp);
+
+        assert_eq!(plan.partition_condition, "TRUE");
+    }
+}
@@ -1,8 +1,9 @@
 use super::result::CheckResult;
-use super::types::{InvariantCheck, InvariantDef, InvariantsDef, Severity};
+use super::types::{is_valid_table_name, InvariantCheck, InvariantDef, InvariantsDef, Severity};
 use crate::dsl::Destination;
 use crate::error::{BqDriftError, Result};
-use crate::executor::BqClient;
+use crate::executor::{partition_condition, BqClient};
+use crate::schema::{PartitionColumnType, PartitionKey};
 use chrono::NaiveDate;
 use futures::future::join_all;
 use once_cell::sync::Lazy;
@@ -15,7 +16,18 @@ const MAX_CONCURRENT_CHECKS: usize = 10;
 static COLUMN_NAME_RE: Lazy<Regex> =
     Lazy::new(|| Regex::new(r"^[a-zA-Z_][a-zA-Z0-9_]*$").expect("valid regex"));
 
-fn validate_column_name(column: &str) -> Result<()> {
+fn validate_table_name(table: &str) -> Result<()> {
+    if is_valid_table_name(table) {
+        Ok(())
+    } else {
+        Err(BqDriftError::InvariantFailed(format!(
+            "Invalid table name '{}': expected dataset.table or project.dataset.table",
+            table
+        )))
+    }
+}
+
+pub(super) fn validate_column_name(column: &str) -> Result<()> {
     if COLUMN_NAME_RE.is_match(column) {
         Ok(())
     } else {
@@ -56,6 +68,12 @@ pub enum ResolvedCheck {
         min: Option<i64>,
         max: Option<i64>,
     },
+    ReconcileCount {
+        upstream_table: String,
+        upstream_partition_field: Option<String>,
+        upstream_partition_column_type: Option<PartitionColumnType>,
+        tolerance_fraction: f64,
+    },
 }
 
 pub struct InvariantChecker<'a> {
@@ -94,7 +112,7 @@ impl<'a> InvariantChecker<'a> {
         results.into_iter().collect()
     }
 
-    async fn run_check(&self, inv: &ResolvedInvariant) -> Result<CheckResult> {
+    pub(super) async fn run_check(&self, inv: &ResolvedInvariant) -> Result<CheckResult> {
         match &inv.check {
             ResolvedCheck::RowCount {
                 source_sql,
@@ -150,23 +168,42 @@ impl<'a> InvariantChecker<'a> {
                 )
                 .await
             }
+            ResolvedCheck::ReconcileCount {
+                upstream_table,
+                upstream_partition_field,
+                upstream_partition_column_type,
+                tolerance_fraction,
+            } => {
+                self.check_reconcile_count(
+                    &inv.name,
+                    inv.severity,
+                    upstream_table,
+                    upstream_partition_field.as_deref(),
+                    *upstream_partition_column_type,
+                    *tolerance_fraction,
+                )
+                .await
+            }
         }
     }
 
     fn destination_table(&self) -> String {
-        format!("`{}.{}`", self.destination.dataset, self.destination.table)
+        format!("`{}`", self.destination.qualified_table())
     }
 
     fn default_source_sql(&self) -> String {
-        let partition_field = self
-            .destination
-            .partition
-            .field_name()
-            .unwrap_or("_PARTITIONDATE");
+        if !self.destination.partition.is_partitioned() {
+            return format!("SELECT * FROM {}", self.destination_table());
+        }
+        let partition_column = match self.destination.partition.predicate_column() {
+            Some((field, PartitionColumnType::Date)) => field.to_string(),
+            Some((field, _)) => format!("DATE({})", field),
+            None => "_PARTITIONDATE".to_string(),
+        };
         format!(
             "SELECT * FROM {} WHERE {} = '{}'",
             self.destination_table(),
-            partition_field,
+            partition_column,
             self.partition_date
         )
     }
@@ -233,28 +270,7 @@ impl<'a> InvariantChecker<'a> {
         let count_sql = format!("SELECT COUNT(*) as cnt FROM ({}) _source", source);
         let count = self.client.query_row_count(&count_sql).await?;
 
-        let mut violations = Vec::new();
-        if let Some(min_val) = min {
-            if count < min_val {
-                violations.push(format!("count {} < min {}", count, min_val));
-            }
-        }
-        if let Some(max_val) = max {
-            if count > max_val {
-                violations.push(format!("count {} > max {}", count, max_val));
-            }
-        }
-
-        if violations.is_empty() {
-            Ok(CheckResult::passed(
-                name,
-                severity,
-                format!("Row count: {}", count),
-            ))
-        } else {
-            Ok(CheckResult::failed(name, severity, violations.join(", "))
-                .with_details(format!("Actual row count: {}", count)))
-        }
+        Ok(evaluate_row_count(name, severity, count, min, max))
     }
 
     async fn check_null_percentage(
@@ -282,23 +298,13 @@ impl<'a> InvariantChecker<'a> {
             .await?
             .unwrap_or(0.0);
 
-        if null_pct <= max_percentage {
-            Ok(CheckResult::passed(
-                name,
-                severity,
-                format!("Null percentage: {:.2}%", null_pct),
-            ))
-        } else {
-            Ok(CheckResult::failed(
-                name,
-                severity,
-                format!(
-                    "Null percentage {:.2}% > max {:.2}%",
-                    null_pct, max_percentage
-                ),
-            )
-            .with_details(format!("Column: {}, Actual: {:.2}%", column, null_pct)))
-        }
+        Ok(evaluate_null_percentage(
+            name,
+            severity,
+            column,
+            null_pct,
+            max_percentage,
+        ))
     }
 
     async fn check_value_range(
@@ -323,32 +329,9 @@ impl<'a> InvariantChecker<'a> {
 
         let (min_val, max_val) = self.client.query_two_floats(&check_sql).await?;
 
-        let mut violations = Vec::new();
-        if let (Some(threshold), Some(actual)) = (min, min_val) {
-            if actual < threshold {
-                violations.push(format!("min value {} < threshold {}", actual, threshold));
-            }
-        }
-        if let (Some(threshold), Some(actual)) = (max, max_val) {
-            if actual > threshold {
-                violations.push(format!("max value {} > threshold {}", actual, threshold));
-            }
-        }
-
-        if violations.is_empty() {
-            Ok(CheckResult::passed(
-                name,
-                severity,
-                format!("Value range for {}: [{:?}, {:?}]", column, min_val, max_val),
-            ))
-        } else {
-            Ok(
-                CheckResult::failed(name, severity, violations.join(", ")).with_details(format!(
-                    "Column: {}, Actual range: [{:?}, {:?}]",
-                    column, min_val, max_val
-                )),
-            )
-        }
+        Ok(evaluate_value_range(
+            name, severity, column, min_val, max_val, min, max,
+        ))
     }
 
     async fn check_distinct_count(
@@ -373,33 +356,238 @@ impl<'a> InvariantChecker<'a> {
 
         let count = self.client.query_row_count(&check_sql).await?;
 
-        let mut violations = Vec::new();
-        if let Some(min_val) = min {
-            if count < min_val {
-                violations.push(format!("distinct count {} < min {}", count, min_val));
-            }
-        }
-        if let Some(max_val) = max {
-            if count > max_val {
-                violations.push(format!("distinct count {} > max {}", count, max_val));
-            }
-        }
+        Ok(evaluate_distinct_count(
+            name, severity, column, count, min, max,
+        ))
+    }
 
-        if violations.is_empty() {
-            Ok(CheckResult::passed(
+    async fn check_reconcile_count(
+        &self,
+        name: &str,
+        severity: Severity,
+        upstream_table: &str,
+        upstream_partition_field: Option<&str>,
+        upstream_partition_column_type: Option<PartitionColumnType>,
+        tolerance_fraction: f64,
+    ) -> Result<CheckResult> {
+        let destination_sql = format!(
+            "SELECT COUNT(*) as cnt FROM ({}) _source",
+            self.default_source_sql()
+        );
+        let upstream_sql = reconcile_upstream_sql(
+            self.destination,
+            upstream_table,
+            upstream_partition_field,
+            upstream_partition_column_type,
+            self.partition_date,
+        )?;
+
+        let destination_count = self.client.query_row_count(&destination_sql).await?;
+        let upstream_count = self.client.query_row_count(&upstream_sql).await?;
+
+        match reconcile_violation(destination_count, upstream_count, tolerance_fraction) {
+            None => Ok(CheckResult::passed(
                 name,
                 severity,
-                format!("Distinct count for {}: {}", column, count),
-            ))
-        } else {
-            Ok(
-                CheckResult::failed(name, severity, violations.join(", ")).with_details(format!(
-                    "Column: {}, Actual distinct count: {}",
-                    column, count
-                )),
-            )
+                format!(
+                    "Row count {} matches {} ({})",
+                    destination_count, upstream_table, upstream_count
+                ),
+            )),
+            Some(violation) => Ok(CheckResult::failed(name, severity, violation).with_details(
+                format!(
+                    "Destination: {}, {}: {}",
+                    destination_count, upstream_table, upstream_count
+                ),
+            )),
+        }
+    }
+}
+
+/// Row count of the upstream partition for `partition_date`. The partition
+/// column defaults to the destination's, with its type, and is matched the
+/// way the MERGE matches a partition, so `TIMESTAMP` and `DATETIME` columns
+/// cover the whole day.
+fn reconcile_upstream_sql(
+    destination: &Destination,
+    upstream_table: &str,
+    upstream_partition_field: Option<&str>,
+    upstream_partition_column_type: Option<PartitionColumnType>,
+    partition_date: NaiveDate,
+) -> Result<String> {
+    validate_table_name(upstream_table)?;
+
+    let (partition_field, column_type) = match upstream_partition_field {
+        Some(field) => (field, upstream_partition_column_type.unwrap_or_default()),
+        None => destination
+            .partition
+            .predicate_column()
+            .unwrap_or(("_PARTITIONDATE", PartitionColumnType::Date)),
+    };
+    validate_column_name(partition_field)?;
+
+    Ok(format!(
+        "SELECT COUNT(*) as cnt FROM `{}` AS target WHERE {}",
+        upstream_table,
+        partition_condition(
+            partition_field,
+            &PartitionKey::Day(partition_date),
+            column_type
+        )
+    ))
+}
+
+pub(super) fn evaluate_row_count(
+    name: &str,
+    severity: Severity,
+    count: i64,
+    min: Option<i64>,
+    max: Option<i64>,
+) -> CheckResult {
+    let mut violations = Vec::new();
+    if let Some(min_val) = min {
+        if count < min_val {
+            violations.push(format!("count {} < min {}", count, min_val));
+        }
+    }
+    if let Some(max_val) = max {
+        if count > max_val {
+            violations.push(format!("count {} > max {}", count, max_val));
         }
     }
+
+    if violations.is_empty() {
+        CheckResult::passed(name, severity, format!("Row count: {}", count))
+    } else {
+        CheckResult::failed(name, severity, violations.join(", "))
+            .with_details(format!("Actual row count: {}", count))
+    }
+    .with_observed(count as f64)
+}
+
+pub(super) fn evaluate_null_percentage(
+    name: &str,
+    severity: Severity,
+    column: &str,
+    null_pct: f64,
+    max_percentage: f64,
+) -> CheckResult {
+    if null_pct <= max_percentage {
+        CheckResult::passed(name, severity, format!("Null percentage: {:.2}%", null_pct))
+    } else {
+        CheckResult::failed(
+            name,
+            severity,
+            format!(
+                "Null percentage {:.2}% > max {:.2}%",
+                null_pct, max_percentage
+            ),
+        )
+        .with_details(format!("Column: {}, Actual: {:.2}%", column, null_pct))
+    }
+    .with_observed(null_pct)
+}
+
+/// `observed` is the extreme that broke its threshold, otherwise the max
+/// (the min when only a min threshold is set).
+pub(super) fn evaluate_value_range(
+    name: &str,
+    severity: Severity,
+    column: &str,
+    min_val: Option<f64>,
+    max_val: Option<f64>,
+    min: Option<f64>,
+    max: Option<f64>,
+) -> CheckResult {
+    let mut violations = Vec::new();
+    if let (Some(threshold), Some(actual)) = (min, min_val) {
+        if actual < threshold {
+            violations.push(format!("min value {} < threshold {}", actual, threshold));
+        }
+    }
+    if let (Some(threshold), Some(actual)) = (max, max_val) {
+        if actual > threshold {
+            violations.push(format!("max value {} > threshold {}", actual, threshold));
+        }
+    }
+
+    let below_min = min.zip(min_val).is_some_and(|(t, v)| v < t);
+    let above_max = max.zip(max_val).is_some_and(|(t, v)| v > t);
+    let observed = if (below_min && !above_max) || (min.is_some() && max.is_none()) {
+        min_val
+    } else {
+        max_val
+    };
+
+    let result = if violations.is_empty() {
+        CheckResult::passed(
+            name,
+            severity,
+            format!("Value range for {}: [{:?}, {:?}]", column, min_val, max_val),
+        )
+    } else {
+        CheckResult::failed(name, severity, violations.join(", ")).with_details(format!(
+            "Column: {}, Actual range: [{:?}, {:?}]",
+            column, min_val, max_val
+        ))
+    };
+    match observed {
+        Some(v) => result.with_observed(v),
+        None => result,
+    }
+}
+
+pub(super) fn evaluate_distinct_count(
+    name: &str,
+    severity: Severity,
+    column: &str,
+    count: i64,
+    min: Option<i64>,
+    max: Option<i64>,
+) -> CheckResult {
+    let mut violations = Vec::new();
+    if let Some(min_val) = min {
+        if count < min_val {
+            violations.push(format!("distinct count {} < min {}", count, min_val));
+        }
+    }
+    if let Some(max_val) = max {
+        if count > max_val {
+            violations.push(format!("distinct count {} > max {}", count, max_val));
+        }
+    }
+
+    if violations.is_empty() {
+        CheckResult::passed(
+            name,
+            severity,
+            format!("Distinct count for {}: {}", column, count),
+        )
+        .with_observed(count as f64)
+    } else {
+        CheckResult::failed(name, severity, violations.join(", "))
+            .with_details(format!(
+                "Column: {}, Actual distinct count: {}",
+                column, count
+            ))
+            .with_observed(count as f64)
+    }
+}
+
+fn reconcile_violation(actual: i64, upstream: i64, tolerance_fraction: f64) -> Option<String> {
+    let diff = (actual - upstream).abs();
+    let allowed = upstream.abs() as f64 * tolerance_fraction;
+    if diff as f64 <= allowed {
+        None
+    } else {
+        Some(format!(
+            "count {} differs from upstream {} by {} (tolerance {:.2}%)",
+            actual,
+            upstream,
+            diff,
+            tolerance_fraction * 100.0
+        ))
+    }
 }
 
 pub fn resolve_invariants_def(
@@ -457,5 +645,77 @@ fn resolve_check(check: &InvariantCheck) -> ResolvedCheck {
             min: *min,
             max: *max,
         },
+        InvariantCheck::ReconcileCount {
+            upstream_table,
+            upstream_partition_field,
+            upstream_partition_column_type,
+            tolerance_fraction,
+        } => ResolvedCheck::ReconcileCount {
+            upstream_table: upstream_table.clone(),
+            upstream_partition_field: upstream_partition_field.clone(),
+            upstream_partition_column_type: *upstream_partition_column_type,
+            tolerance_fraction: *tolerance_fraction,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reconcile_violation_within_tolerance() {
+        assert!(reconcile_violation(1000, 1000, 0.0).is_none());
+        assert!(reconcile_violation(990, 1000, 0.01).is_none());
+        assert!(reconcile_violation(0, 0, 0.0).is_none());
+    }
+
+    #[test]
+    fn test_reconcile_violation_beyond_tolerance() {
+        let violation = reconcile_violation(980, 1000, 0.01).unwrap();
+        assert!(violation.contains("differs from upstream 1000 by 20"));
+        assert!(reconcile_violation(5, 0, 0.5).is_some());
+    }
+
+    #[test]
+    fn test_reconcile_upstream_sql_uses_partition_column_type() {
+        use crate::schema::PartitionConfig;
+
+        let destination = |partition: PartitionConfig| Destination {
+            project: None,
+            dataset: "analytics".to_string(),
+            table: "events".to_string(),
+            partition,
+            cluster: None,
+            labels: Default::default(),
+        };
+        let date = NaiveDate::from_ymd_opt(2024, 6, 15).unwrap();
+        let sql = |dest: &Destination, field: Option<&str>, ty: Option<PartitionColumnType>| {
+            reconcile_upstream_sql(dest, "raw.events", field, ty, date).unwrap()
+        };
+
+        let daily = destination(PartitionConfig::day("date"));
+        assert_eq!(
+            sql(&daily, None, None),
+            "SELECT COUNT(*) as cnt FROM `raw.events` AS target WHERE target.date = DATE '2024-06-15'"
+        );
+
+        let by_ts = destination(
+            PartitionConfig::day("ts").with_column_type(PartitionColumnType::Timestamp),
+        );
+        assert!(sql(&by_ts, None, None).ends_with("WHERE DATE(target.ts) = DATE '2024-06-15'"));
+
+        assert!(sql(
+            &daily,
+            Some("event_dt"),
+            Some(PartitionColumnType::Datetime)
+        )
+        .ends_with("WHERE DATE(target.event_dt) = DATE '2024-06-15'"));
+        assert!(sql(&by_ts, Some("day"), None).ends_with("WHERE target.day = DATE '2024-06-15'"));
+
+        let unpartitioned = destination(PartitionConfig::unpartitioned());
+        assert!(sql(&unpartitioned, None, None)
+            .ends_with("WHERE target._PARTITIONDATE = DATE '2024-06-15'"));
+        assert!(reconcile_upstream_sql(&daily, "raw.events", Some("bad col"), None, date).is_err());
     }
 }
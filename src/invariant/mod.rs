@@ -1,8 +1,10 @@
 mod checker;
+mod range;
 mod result;
 mod types;
 
 pub use checker::{resolve_invariants_def, InvariantChecker, ResolvedCheck, ResolvedInvariant};
+pub use range::RangeInvariantChecker;
 pub use result::{CheckResult, CheckStatus, InvariantReport};
 pub use types::{
     ExtendedInvariants, InvariantCheck, InvariantDef, InvariantsDef, InvariantsRef,
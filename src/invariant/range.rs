@@ -0,0 +1,407 @@
+use super::checker::{
+    evaluate_distinct_count, evaluate_null_percentage, evaluate_row_count, evaluate_value_range,
+    validate_column_name, InvariantChecker, ResolvedCheck, ResolvedInvariant,
+};
+use super::result::CheckResult;
+use crate::dsl::Destination;
+use crate::error::{BqDriftError, Result};
+use crate::executor::BqClient;
+use crate::schema::{PartitionColumnType, PartitionType};
+use chrono::NaiveDate;
+use std::collections::{BTreeMap, HashMap};
+
+/// Runs invariants across every partition in `[from, to]`.
+///
+/// Checks against the destination partition are computed with a single
+/// `GROUP BY` query over the range; checks with a custom `source` or an
+/// upstream table fall back to one query per partition.
+pub struct RangeInvariantChecker<'a> {
+    client: &'a BqClient,
+    destination: &'a Destination,
+    from: NaiveDate,
+    to: NaiveDate,
+}
+
+impl<'a> RangeInvariantChecker<'a> {
+    pub fn new(
+        client: &'a BqClient,
+        destination: &'a Destination,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Self {
+        Self {
+            client,
+            destination,
+            from,
+            to,
+        }
+    }
+
+    /// Results per partition date, in the order of `invariants`.
+    pub async fn run_checks(
+        &self,
+        invariants: &[ResolvedInvariant],
+    ) -> Result<BTreeMap<NaiveDate, Vec<CheckResult>>> {
+        let dates = self.dates()?;
+        let mut results: BTreeMap<NaiveDate, Vec<CheckResult>> = dates
+            .iter()
+            .map(|d| (*d, Vec::with_capacity(invariants.len())))
+            .collect();
+
+        for inv in invariants {
+            match self.aggregate_sql(inv)? {
+                Some((aggregate, sql)) => {
+                    let rows = self.client.query_keyed_floats(&sql).await?;
+                    let values = key_by_date(rows);
+                    for date in &dates {
+                        let row = values.get(date).map(|v| v.as_slice()).unwrap_or(&[]);
+                        if let Some(partition_results) = results.get_mut(date) {
+                            partition_results.push(aggregate.evaluate(inv, row));
+                        }
+                    }
+                }
+                None => {
+                    for date in &dates {
+                        let checker = InvariantChecker::new(self.client, self.destination, *date);
+                        let result = checker.run_check(inv).await?;
+                        if let Some(partition_results) = results.get_mut(date) {
+                            partition_results.push(result);
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(results)
+    }
+
+    fn dates(&self) -> Result<Vec<NaiveDate>> {
+        if self.from > self.to {
+            return Err(BqDriftError::Partition(format!(
+                "Invalid range: {} is after {}",
+                self.from, self.to
+            )));
+        }
+        if self.destination.partition.partition_type == PartitionType::Range {
+            return Err(BqDriftError::Partition(format!(
+                "Invariant checks by date are not supported for range-partitioned table '{}'",
+                self.destination.qualified_table()
+            )));
+        }
+        Ok(self
+            .from
+            .iter_days()
+            .take_while(|d| *d <= self.to)
+            .collect())
+    }
+
+    /// Grouped query for checks that only read the destination partition,
+    /// `None` for checks that must run per partition.
+    fn aggregate_sql<'c>(
+        &self,
+        inv: &'c ResolvedInvariant,
+    ) -> Result<Option<(Aggregate<'c>, String)>> {
+        let (Some(aggregate), Some(date_expr)) = (
+            Aggregate::for_check(&inv.check),
+            partition_date_expr(self.destination),
+        ) else {
+            return Ok(None);
+        };
+        let sql = format!(
+            "SELECT CAST({date} AS STRING) AS partition_date, {exprs} FROM `{table}` WHERE {date} BETWEEN '{from}' AND '{to}' GROUP BY partition_date",
+            date = date_expr,
+            exprs = aggregate.select_exprs()?,
+            table = self.destination.qualified_table(),
+            from = self.from,
+            to = self.to,
+        );
+        Ok(Some((aggregate, sql)))
+    }
+}
+
+/// A check computed for every partition by one grouped query: one that
+/// reads the destination partition rather than a custom `source` or an
+/// upstream table.
+enum Aggregate<'c> {
+    RowCount {
+        min: Option<i64>,
+        max: Option<i64>,
+    },
+    NullPercentage {
+        column: &'c str,
+        max_percentage: f64,
+    },
+    ValueRange {
+        column: &'c str,
+        min: Option<f64>,
+        max: Option<f64>,
+    },
+    DistinctCount {
+        column: &'c str,
+        min: Option<i64>,
+        max: Option<i64>,
+    },
+}
+
+impl<'c> Aggregate<'c> {
+    fn for_check(check: &'c ResolvedCheck) -> Option<Self> {
+        match check {
+            ResolvedCheck::RowCount {
+                source_sql: None,
+                min,
+                max,
+            } => Some(Aggregate::RowCount {
+                min: *min,
+                max: *max,
+            }),
+            ResolvedCheck::NullPercentage {
+                source_sql: None,
+                column,
+                max_percentage,
+            } => Some(Aggregate::NullPercentage {
+                column,
+                max_percentage: *max_percentage,
+            }),
+            ResolvedCheck::ValueRange {
+                source_sql: None,
+                column,
+                min,
+                max,
+            } => Some(Aggregate::ValueRange {
+                column,
+                min: *min,
+                max: *max,
+            }),
+            ResolvedCheck::DistinctCount {
+                source_sql: None,
+                column,
+                min,
+                max,
+            } => Some(Aggregate::DistinctCount {
+                column,
+                min: *min,
+                max: *max,
+            }),
+            _ => None,
+        }
+    }
+
+    fn select_exprs(&self) -> Result<String> {
+        Ok(match self {
+            Aggregate::RowCount { .. } => "COUNT(*)".to_string(),
+            Aggregate::NullPercentage { column, .. } => {
+                validate_column_name(column)?;
+                format!("COUNTIF({} IS NULL) * 100.0 / NULLIF(COUNT(*), 0)", column)
+            }
+            Aggregate::ValueRange { column, .. } => {
+                validate_column_name(column)?;
+                format!("MIN({}), MAX({})", column, column)
+            }
+            Aggregate::DistinctCount { column, .. } => {
+                validate_column_name(column)?;
+                format!("COUNT(DISTINCT {})", column)
+            }
+        })
+    }
+
+    /// Evaluate one partition's row of the grouped result. A partition
+    /// missing from the result has no rows.
+    fn evaluate(&self, inv: &ResolvedInvariant, row: &[Option<f64>]) -> CheckResult {
+        let value = |i: usize| row.get(i).copied().flatten();
+        match *self {
+            Aggregate::RowCount { min, max } => evaluate_row_count(
+                &inv.name,
+                inv.severity,
+                value(0).unwrap_or(0.0) as i64,
+                min,
+                max,
+            ),
+            Aggregate::NullPercentage {
+                column,
+                max_percentage,
+            } => evaluate_null_percentage(
+                &inv.name,
+                inv.severity,
+                column,
+                value(0).unwrap_or(0.0),
+                max_percentage,
+            ),
+            Aggregate::ValueRange { column, min, max } => evaluate_value_range(
+                &inv.name,
+                inv.severity,
+                column,
+                value(0),
+                value(1),
+                min,
+                max,
+            ),
+            Aggregate::DistinctCount { column, min, max } => evaluate_distinct_count(
+                &inv.name,
+                inv.severity,
+                column,
+                value(0).unwrap_or(0.0) as i64,
+                min,
+                max,
+            ),
+        }
+    }
+}
+
+/// The partition's date, the same expression `InvariantChecker` filters on.
+/// `None` for unpartitioned tables.
+fn partition_date_expr(destination: &Destination) -> Option<String> {
+    if !destination.partition.is_partitioned() {
+        return None;
+    }
+    Some(match destination.partition.predicate_column() {
+        Some((field, PartitionColumnType::Date)) => field.to_string(),
+        Some((field, _)) => format!("DATE({})", field),
+        None => "_PARTITIONDATE".to_string(),
+    })
+}
+
+fn key_by_date(rows: Vec<(String, Vec<Option<f64>>)>) -> HashMap<NaiveDate, Vec<Option<f64>>> {
+    rows.into_iter()
+        .filter_map(|(key, values)| {
+            NaiveDate::parse_from_str(&key, "%Y-%m-%d")
+                .ok()
+                .map(|d| (d, values))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::invariant::result::CheckStatus;
+    use crate::invariant::Severity;
+    use crate::schema::PartitionConfig;
+
+    fn evaluate(inv: &ResolvedInvariant, row: &[Option<f64>]) -> CheckResult {
+        Aggregate::for_check(&inv.check).unwrap().evaluate(inv, row)
+    }
+
+    fn invariant(check: ResolvedCheck) -> ResolvedInvariant {
+        ResolvedInvariant {
+            name: "check".to_string(),
+            description: None,
+            severity: Severity::Error,
+            check,
+        }
+    }
+
+    #[test]
+    fn test_key_by_date_skips_unparseable_keys() {
+        let rows = vec![
+            ("2024-01-01".to_string(), vec![Some(10.0)]),
+            ("__NULL__".to_string(), vec![Some(1.0)]),
+        ];
+        let keyed = key_by_date(rows);
+
+        assert_eq!(keyed.len(), 1);
+        assert_eq!(
+            keyed[&NaiveDate::from_ymd_opt(2024, 1, 1).unwrap()],
+            vec![Some(10.0)]
+        );
+    }
+
+    #[test]
+    fn test_evaluate_missing_partition_has_zero_rows() {
+        let inv = invariant(ResolvedCheck::RowCount {
+            source_sql: None,
+            min: Some(1),
+            max: None,
+        });
+
+        let result = evaluate(&inv, &[]);
+        assert_eq!(result.status, CheckStatus::Failed);
+        assert_eq!(result.observed, Some(0.0));
+    }
+
+    #[test]
+    fn test_evaluate_value_range_uses_both_columns() {
+        let inv = invariant(ResolvedCheck::ValueRange {
+            source_sql: None,
+            column: "amount".to_string(),
+            min: Some(0.0),
+            max: Some(100.0),
+        });
+
+        assert_eq!(
+            evaluate(&inv, &[Some(1.0), Some(50.0)]).status,
+            CheckStatus::Passed
+        );
+        assert_eq!(
+            evaluate(&inv, &[Some(1.0), Some(500.0)]).status,
+            CheckStatus::Failed
+        );
+    }
+
+    #[test]
+    fn test_partition_date_expr_follows_partition_column() {
+        let destination = |partition: PartitionConfig| Destination {
+            project: None,
+            dataset: "analytics".to_string(),
+            table: "events".to_string(),
+            partition,
+            cluster: None,
+            labels: Default::default(),
+        };
+
+        assert_eq!(
+            partition_date_expr(&destination(PartitionConfig::day("date"))).as_deref(),
+            Some("date")
+        );
+        assert_eq!(
+            partition_date_expr(&destination(
+                PartitionConfig::hour("ts").with_column_type(PartitionColumnType::Timestamp)
+            ))
+            .as_deref(),
+            Some("DATE(ts)")
+        );
+        assert_eq!(
+            partition_date_expr(&destination(PartitionConfig::ingestion_time(
+                PartitionType::Day
+            )))
+            .as_deref(),
+            Some("_PARTITIONDATE")
+        );
+        assert_eq!(
+            partition_date_expr(&destination(PartitionConfig::unpartitioned())),
+            None
+        );
+    }
+
+    #[test]
+    fn test_evaluate_sets_observed_for_every_check() {
+        let nulls = invariant(ResolvedCheck::NullPercentage {
+            source_sql: None,
+            column: "email".to_string(),
+            max_percentage: 5.0,
+        });
+        assert_eq!(evaluate(&nulls, &[Some(12.5)]).observed, Some(12.5));
+
+        let range = invariant(ResolvedCheck::ValueRange {
+            source_sql: None,
+            column: "amount".to_string(),
+            min: Some(0.0),
+            max: Some(100.0),
+        });
+        assert_eq!(
+            evaluate(&range, &[Some(-3.0), Some(50.0)]).observed,
+            Some(-3.0)
+        );
+        assert_eq!(
+            evaluate(&range, &[Some(1.0), Some(50.0)]).observed,
+            Some(50.0)
+        );
+
+        let distinct = invariant(ResolvedCheck::DistinctCount {
+            source_sql: None,
+            column: "user_id".to_string(),
+            min: Some(1),
+            max: None,
+        });
+        assert_eq!(evaluate(&distinct, &[Some(7.0)]).observed, Some(7.0));
+    }
+}
@@ -7,6 +7,8 @@ pub struct CheckResult {
     pub severity: Severity,
     pub message: String,
     pub details: Option<String>,
+    /// Measured value the check compared against its bounds, if any.
+    pub observed: Option<f64>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -103,6 +105,7 @@ impl CheckResult {
             severity,
             message: message.into(),
             details: None,
+            observed: None,
         }
     }
 
@@ -113,6 +116,7 @@ impl CheckResult {
             severity,
             message: message.into(),
             details: None,
+            observed: None,
         }
     }
 
@@ -127,6 +131,7 @@ impl CheckResult {
             severity,
             message: message.into(),
             details: None,
+            observed: None,
         }
     }
 
@@ -135,6 +140,11 @@ impl CheckResult {
         self
     }
 
+    pub fn with_observed(mut self, observed: f64) -> Self {
+        self.observed = Some(observed);
+        self
+    }
+
     pub fn is_blocking_error(&self) -> bool {
         self.status == CheckStatus::Failed && self.severity == Severity::Error
     }
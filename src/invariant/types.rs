@@ -1,7 +1,22 @@
+use crate::schema::PartitionColumnType;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
+static TABLE_NAME_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^([a-zA-Z0-9_-]+\.)?[a-zA-Z0-9_]+\.[a-zA-Z0-9_]+$").expect("valid regex")
+});
+
+/// Whether `table` is `dataset.table` or `project.dataset.table`. Only the
+/// project may contain hyphens. Checked when invariants are loaded and again
+/// before the name is put into SQL.
+pub(super) fn is_valid_table_name(table: &str) -> bool {
+    TABLE_NAME_RE.is_match(table)
+}
+
 /// Raw invariants definition - can be inline, reference, or extended
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(untagged)]
 pub enum InvariantsRef {
     /// Reference to another version's invariants: ${{ versions.1.invariants }}
@@ -20,7 +35,7 @@ impl Default for InvariantsRef {
 }
 
 /// Extended invariants - inherit from base and add/modify/remove
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ExtendedInvariants {
     /// Base invariants to inherit from: ${{ versions.1.invariants }}
     pub base: String,
@@ -36,7 +51,7 @@ pub struct ExtendedInvariants {
 }
 
 /// Names of invariants to remove, scoped by before/after
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
 pub struct InvariantsRemove {
     #[serde(default)]
     pub before: Vec<String>,
@@ -45,7 +60,7 @@ pub struct InvariantsRemove {
 }
 
 /// Resolved invariants definition with before/after lists
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
 pub struct InvariantsDef {
     #[serde(default)]
     pub before: Vec<InvariantDef>,
@@ -53,7 +68,7 @@ pub struct InvariantsDef {
     pub after: Vec<InvariantDef>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct InvariantDef {
     pub name: String,
     #[serde(default)]
@@ -64,7 +79,7 @@ pub struct InvariantDef {
     pub check: InvariantCheck,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum InvariantCheck {
     /// Row count check - validates min/max row counts
@@ -106,6 +121,20 @@ pub enum InvariantCheck {
         #[serde(default)]
         max: Option<i64>,
     },
+
+    /// Reconcile count check - compares the partition row count with the
+    /// same partition of an upstream table
+    ReconcileCount {
+        upstream_table: String,
+        /// Defaults to the destination's partition column and its type.
+        #[serde(default)]
+        upstream_partition_field: Option<String>,
+        /// Type of `upstream_partition_field`; `DATE` when not set.
+        #[serde(default)]
+        upstream_partition_column_type: Option<PartitionColumnType>,
+        #[serde(default)]
+        tolerance_fraction: f64,
+    },
 }
 
 impl InvariantCheck {
@@ -121,10 +150,44 @@ impl InvariantCheck {
                     return Err("value_range check requires at least min or max".to_string());
                 }
             }
-            InvariantCheck::DistinctCount { min, max, .. } => {
+            InvariantCheck::DistinctCount {
+                column, min, max, ..
+            } => {
+                if column.trim().is_empty() {
+                    return Err("distinct_count check requires a non-empty column".to_string());
+                }
                 if min.is_none() && max.is_none() {
                     return Err("distinct_count check requires at least min or max".to_string());
                 }
+                if min.is_some_and(|m| m < 0) || max.is_some_and(|m| m < 0) {
+                    return Err("distinct_count min and max must not be negative".to_string());
+                }
+                if let (Some(min), Some(max)) = (min, max) {
+                    if min > max {
+                        return Err(format!(
+                            "distinct_count min ({}) must not exceed max ({})",
+                            min, max
+                        ));
+                    }
+                }
+            }
+            InvariantCheck::ReconcileCount {
+                upstream_table,
+                tolerance_fraction,
+                ..
+            } => {
+                if !is_valid_table_name(upstream_table) {
+                    return Err(format!(
+                        "reconcile_count upstream_table must be dataset.table or project.dataset.table, got '{}'",
+                        upstream_table
+                    ));
+                }
+                if !(0.0..=1.0).contains(tolerance_fraction) {
+                    return Err(format!(
+                        "reconcile_count tolerance_fraction must be between 0 and 1, got {}",
+                        tolerance_fraction
+                    ));
+                }
             }
             InvariantCheck::NullPercentage { max_percentage, .. } => {
                 if *max_percentage < 0.0 || *max_percentage > 100.0 {
@@ -139,7 +202,7 @@ impl InvariantCheck {
     }
 }
 
-#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum Severity {
     #[default]
@@ -424,4 +487,65 @@ severity: warning
             _ => panic!("Expected RowCount with source"),
         }
     }
+
+    #[test]
+    fn test_parse_reconcile_count() {
+        let yaml = r#"
+name: matches_upstream
+type: reconcile_count
+upstream_table: raw.events
+tolerance_fraction: 0.01
+"#;
+        let inv: InvariantDef = serde_yaml::from_str(yaml).unwrap();
+        match &inv.check {
+            InvariantCheck::ReconcileCount {
+                upstream_table,
+                upstream_partition_field,
+                upstream_partition_column_type,
+                tolerance_fraction,
+            } => {
+                assert_eq!(upstream_table, "raw.events");
+                assert!(upstream_partition_field.is_none());
+                assert!(upstream_partition_column_type.is_none());
+                assert_eq!(*tolerance_fraction, 0.01);
+            }
+            _ => panic!("Expected ReconcileCount"),
+        }
+        assert!(inv.check.validate().is_ok());
+    }
+
+    #[test]
+    fn test_reconcile_count_validation() {
+        let check = |table: &str, tolerance: f64| InvariantCheck::ReconcileCount {
+            upstream_table: table.to_string(),
+            upstream_partition_field: None,
+            upstream_partition_column_type: None,
+            tolerance_fraction: tolerance,
+        };
+
+        assert!(check("my-project.raw.events", 0.0).validate().is_ok());
+        assert!(check("events", 0.0).validate().is_err());
+        assert!(check("raw.events; DROP TABLE x", 0.0).validate().is_err());
+        assert!(check("raw-data.events", 0.0).validate().is_err());
+        assert!(check("a.b.c.d", 0.0).validate().is_err());
+        assert!(check("raw.events", 1.5).validate().is_err());
+    }
+
+    #[test]
+    fn test_distinct_count_validation() {
+        let check =
+            |column: &str, min: Option<i64>, max: Option<i64>| InvariantCheck::DistinctCount {
+                source: None,
+                column: column.to_string(),
+                min,
+                max,
+            };
+
+        assert!(check("user_id", Some(10), Some(100)).validate().is_ok());
+        assert!(check("user_id", None, Some(100)).validate().is_ok());
+        assert!(check("", Some(1), None).validate().is_err());
+        assert!(check("user_id", None, None).validate().is_err());
+        assert!(check("user_id", Some(-1), None).validate().is_err());
+        assert!(check("user_id", Some(100), Some(10)).validate().is_err());
+    }
 }
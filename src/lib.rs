@@ -11,20 +11,22 @@ pub mod schema;
 
 pub use diff::{decode_sql, encode_sql, format_sql_diff, has_changes};
 pub use drift::{
-    compress_to_base64, decompress_from_base64, AuditTableRow, Checksums, DriftDetector,
+    compress_to_base64, decompress_from_base64, run_drift_check, AuditTableRow, CacheConfig,
+    Checksums, DetectionWarning, DetectionWarningKind, DriftCheckOutcome, DriftDetector,
     DriftReport, DriftState, ExecutionArtifact, ExecutionStatus, ImmutabilityChecker,
-    ImmutabilityReport, ImmutabilityViolation, PartitionDrift, PartitionState, SourceAuditEntry,
-    SourceAuditReport, SourceAuditor, SourceStatus,
+    ImmutabilityReport, ImmutabilityViolation, PartitionDrift, PartitionState,
+    PartitionStateBuilder, RerunPolicy, SourceAuditEntry, SourceAuditReport, SourceAuditor,
+    SourceStatus, StateStore, StateTransition,
 };
 pub use dsl::{
-    QueryDef, QueryLoader, QueryValidator, ResolvedRevision, Revision, SqlDependencies,
-    ValidationResult, VersionDef,
+    query_yaml_json_schema, DependencyGraph, QueryDef, QueryLoader, QueryValidator,
+    ResolvedRevision, Revision, SqlDependencies, ValidationResult, ValidatorConfig, VersionDef,
 };
 pub use error::{BqDriftError, Result};
 pub use executor::{BqClient, ColumnDef, ColumnInfo, PartitionWriter, QueryResult, Runner};
 pub use invariant::{
     resolve_invariants_def, CheckResult, CheckStatus, InvariantCheck, InvariantChecker,
-    InvariantDef, InvariantReport, InvariantsDef, InvariantsRef, Severity,
+    InvariantDef, InvariantReport, InvariantsDef, InvariantsRef, RangeInvariantChecker, Severity,
 };
 pub use migration::MigrationTracker;
 pub use repl::{
@@ -32,5 +34,6 @@ pub use repl::{
     ServerConfigInfo, SessionInfo, SessionManager,
 };
 pub use schema::{
-    BqType, ClusterConfig, Field, FieldMode, PartitionConfig, PartitionKey, PartitionType, Schema,
+    AlterStatement, BqType, ClusterConfig, Field, FieldMode, PartitionColumnType, PartitionConfig,
+    PartitionKey, PartitionType, RangeSpec, RoundingMode, Schema, SchemaDiff,
 };
@@ -1,3 +1,3 @@
 mod tracker;
 
-pub use tracker::MigrationTracker;
+pub use tracker::{MigrationTracker, QueryRun, RunStatus};
@@ -1,5 +1,5 @@
 use crate::error::Result;
-use crate::executor::BqClient;
+use crate::executor::{BqClient, PartitionWriteStats};
 use chrono::{DateTime, NaiveDate, Utc};
 
 const DEFAULT_TRACKING_TABLE: &str = "_bqdrift_query_runs";
@@ -21,6 +21,27 @@ pub struct QueryRun {
     pub status: RunStatus,
 }
 
+impl QueryRun {
+    /// Build a successful run for `MigrationTracker::record_run` from what
+    /// the writer reports, so the version and revision are the ones that
+    /// actually executed. Nothing in bqdrift records runs itself; this is a
+    /// helper for callers that keep a run history. The writer reports
+    /// neither rows written nor execution time, so both are left `None`.
+    pub fn from_write_stats(stats: &PartitionWriteStats, executed_at: DateTime<Utc>) -> Self {
+        Self {
+            query_name: stats.query_name.clone(),
+            query_version: stats.version,
+            sql_revision: stats.sql_revision,
+            partition_date: stats.partition_key.to_naive_date(),
+            executed_at,
+            rows_written: None,
+            bytes_processed: stats.query_stats.total_bytes_processed,
+            execution_time_ms: None,
+            status: RunStatus::Success,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum RunStatus {
     Success,
@@ -51,6 +72,11 @@ impl MigrationTracker {
         format!("{}.{}", self.dataset, self.table_name)
     }
 
+    /// Create the run history table if missing, in the client's project.
+    /// Runs are keyed by query name, so a destination's project override
+    /// doesn't affect where they are recorded. The table is partitioned by
+    /// execution date with no partition expiration; `expiration_days` on a
+    /// query's destination never applies here.
     pub async fn ensure_tracking_table(&self) -> Result<()> {
         let table_name = self.full_table_name();
 
@@ -119,4 +145,57 @@ impl MigrationTracker {
 
         self.client.execute_query(&sql).await
     }
+
+    /// Delete every run recorded for `query_name`, for queries that have
+    /// been removed or renamed (see `DriftDetector::find_orphan_states`).
+    pub async fn delete_runs(&self, query_name: &str) -> Result<()> {
+        let sql = format!(
+            "DELETE FROM `{}` WHERE query_name = '{}'",
+            self.full_table_name(),
+            escape_sql_string(query_name)
+        );
+        self.client.execute_query(&sql).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::QueryStats;
+    use crate::schema::PartitionKey;
+
+    #[test]
+    fn test_query_run_from_write_stats_keeps_revision() {
+        let stats = PartitionWriteStats {
+            query_name: "daily_stats".to_string(),
+            version: 2,
+            sql_revision: Some(1),
+            partition_key: PartitionKey::Day(NaiveDate::from_ymd_opt(2024, 3, 20).unwrap()),
+            invariant_report: None,
+            query_stats: QueryStats {
+                total_bytes_processed: Some(1024),
+                ..QueryStats::default()
+            },
+        };
+        let executed_at = Utc::now();
+
+        let run = QueryRun::from_write_stats(&stats, executed_at);
+        assert_eq!(run.query_version, 2);
+        assert_eq!(run.sql_revision, Some(1));
+        assert_eq!(
+            run.partition_date,
+            NaiveDate::from_ymd_opt(2024, 3, 20).unwrap()
+        );
+        assert_eq!(run.bytes_processed, Some(1024));
+        assert!(matches!(run.status, RunStatus::Success));
+
+        let base = PartitionWriteStats {
+            sql_revision: None,
+            ..stats
+        };
+        assert_eq!(
+            QueryRun::from_write_stats(&base, executed_at).sql_revision,
+            None
+        );
+    }
 }
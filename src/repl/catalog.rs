@@ -0,0 +1,164 @@
+use crate::dsl::{QueryDef, QueryLoader};
+use crate::error::{BqDriftError, Result};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+
+/// One resolved load of a queries directory.
+#[derive(Debug)]
+pub struct CatalogSnapshot {
+    pub queries: Arc<Vec<QueryDef>>,
+    pub yaml_contents: Arc<HashMap<String, String>>,
+}
+
+/// Resolved queries for a directory, loaded once and shared between
+/// sessions. Cloning is cheap; clones see each other's reloads.
+#[derive(Clone)]
+pub struct SharedCatalog {
+    queries_path: PathBuf,
+    ignore: Vec<String>,
+    vars: Vec<(String, String)>,
+    current: Arc<RwLock<Option<Arc<CatalogSnapshot>>>>,
+}
+
+impl SharedCatalog {
+    pub fn new(queries_path: PathBuf) -> Self {
+        Self {
+            queries_path,
+            ignore: Vec::new(),
+            vars: Vec::new(),
+            current: Arc::new(RwLock::new(None)),
+        }
+    }
+
+    /// Skip YAML files matching these globs, see `QueryLoader::with_ignore`.
+    pub fn with_ignore(mut self, patterns: impl IntoIterator<Item = String>) -> Self {
+        self.ignore.extend(patterns);
+        self
+    }
+
+    /// Variables for `{% if %}` blocks, see `QueryLoader::with_vars`.
+    pub fn with_vars(mut self, vars: impl IntoIterator<Item = (String, String)>) -> Self {
+        self.vars.extend(vars);
+        self
+    }
+
+    pub fn queries_path(&self) -> &Path {
+        &self.queries_path
+    }
+
+    /// The loaded snapshot, if any, without touching disk.
+    pub fn current(&self) -> Option<Arc<CatalogSnapshot>> {
+        self.current
+            .read()
+            .ok()
+            .and_then(|guard| guard.as_ref().map(Arc::clone))
+    }
+
+    /// The loaded snapshot, loading it on first use.
+    pub fn get(&self) -> Result<Arc<CatalogSnapshot>> {
+        if let Some(snapshot) = self.current() {
+            return Ok(snapshot);
+        }
+
+        let loaded = Arc::new(self.load()?);
+        let mut guard = self.lock_for_write()?;
+        Ok(Arc::clone(guard.get_or_insert(loaded)))
+    }
+
+    /// Load from disk and swap the result in. Sessions holding the previous
+    /// snapshot keep it until their next command.
+    pub fn reload(&self) -> Result<Arc<CatalogSnapshot>> {
+        let loaded = Arc::new(self.load()?);
+        *self.lock_for_write()? = Some(Arc::clone(&loaded));
+        Ok(loaded)
+    }
+
+    fn load(&self) -> Result<CatalogSnapshot> {
+        let (queries, yaml_contents) = QueryLoader::new()
+            .with_ignore(self.ignore.iter().cloned())
+            .with_vars(self.vars.iter().cloned())
+            .load_dir_with_contents(&self.queries_path)?;
+        Ok(CatalogSnapshot {
+            queries: Arc::new(queries),
+            yaml_contents: Arc::new(yaml_contents),
+        })
+    }
+
+    fn lock_for_write(
+        &self,
+    ) -> Result<std::sync::RwLockWriteGuard<'_, Option<Arc<CatalogSnapshot>>>> {
+        self.current
+            .write()
+            .map_err(|_| BqDriftError::Repl("Query catalog lock poisoned".to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clones_share_loaded_snapshot() {
+        let catalog = SharedCatalog::new(PathBuf::from("tests/fixtures"));
+        let other = catalog.clone();
+        assert!(other.current().is_none());
+
+        let first = catalog.get().unwrap();
+        let shared = other.current().unwrap();
+        assert!(Arc::ptr_eq(&first.queries, &shared.queries));
+
+        let reloaded = other.reload().unwrap();
+        assert!(!Arc::ptr_eq(&first.queries, &reloaded.queries));
+        assert!(Arc::ptr_eq(
+            &catalog.get().unwrap().queries,
+            &reloaded.queries
+        ));
+    }
+
+    #[test]
+    fn test_vars_reach_loader() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("events.yaml"),
+            r#"
+name: events
+destination:
+  dataset: analytics
+  table: events
+  partition:
+    field: date
+    type: DAY
+versions:
+  - version: 1
+    effective_from: 2024-01-01
+    source: SELECT @partition_date AS date FROM {% if env == "prod" %}raw.events{% else %}raw.events_sample{% endif %}
+    schema:
+      - name: date
+        type: DATE
+"#,
+        )
+        .unwrap();
+
+        let catalog = SharedCatalog::new(dir.path().to_path_buf())
+            .with_vars([("env".to_string(), "prod".to_string())]);
+        let snapshot = catalog.get().unwrap();
+        assert!(snapshot.queries[0].versions[0]
+            .dependencies
+            .contains("raw.events"));
+
+        assert!(SharedCatalog::new(dir.path().to_path_buf()).get().is_err());
+    }
+
+    #[test]
+    fn test_ignore_reaches_loader() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("broken.yaml"), "name: [unterminated").unwrap();
+
+        assert!(SharedCatalog::new(dir.path().to_path_buf()).get().is_err());
+
+        let catalog =
+            SharedCatalog::new(dir.path().to_path_buf()).with_ignore(["broken.yaml".to_string()]);
+        assert!(catalog.get().unwrap().queries.is_empty());
+    }
+}
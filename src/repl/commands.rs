@@ -1,4 +1,5 @@
 use crate::error::Result;
+use chrono::NaiveDate;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
@@ -32,6 +33,14 @@ pub enum ReplCommand {
         query: String,
         version: Option<u32>,
     },
+    ShowInvariants {
+        query: String,
+        version: Option<u32>,
+    },
+    Preview {
+        query: String,
+        partition: NaiveDate,
+    },
     Validate,
     Sync {
         from: Option<String>,
@@ -63,6 +72,31 @@ pub enum ReplCommand {
     Exit,
 }
 
+impl ReplCommand {
+    /// Whether the command writes to BigQuery (MERGE/INSERT/DELETE or table
+    /// creation). Dry runs are not writes.
+    pub fn is_write(&self) -> bool {
+        match self {
+            ReplCommand::Run { dry_run, .. }
+            | ReplCommand::Backfill { dry_run, .. }
+            | ReplCommand::Sync { dry_run, .. } => !dry_run,
+            ReplCommand::Init { .. } | ReplCommand::ScratchPromote { .. } => true,
+            ReplCommand::Check { .. }
+            | ReplCommand::List { .. }
+            | ReplCommand::Show { .. }
+            | ReplCommand::ShowInvariants { .. }
+            | ReplCommand::Preview { .. }
+            | ReplCommand::Validate
+            | ReplCommand::Audit { .. }
+            | ReplCommand::ScratchList { .. }
+            | ReplCommand::Reload
+            | ReplCommand::Status
+            | ReplCommand::Help
+            | ReplCommand::Exit => false,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ReplResult {
     pub success: bool,
@@ -155,6 +189,31 @@ impl ReplCommand {
                 let version = find_arg(&parts, "--version", "-v").and_then(|v| v.parse().ok());
                 Ok(ReplCommand::Show { query, version })
             }
+            "invariants" => {
+                let query = find_arg(&parts, "--query", "-q")
+                    .or_else(|| parts.get(1).map(|s| s.to_string()))
+                    .ok_or_else(|| {
+                        crate::error::BqDriftError::Repl(
+                            "invariants requires query name".to_string(),
+                        )
+                    })?;
+                let version = find_arg(&parts, "--version", "-v").and_then(|v| v.parse().ok());
+                Ok(ReplCommand::ShowInvariants { query, version })
+            }
+            "preview" => {
+                let query = find_arg(&parts, "--query", "-q")
+                    .or_else(|| parts.get(1).map(|s| s.to_string()))
+                    .ok_or_else(|| {
+                        crate::error::BqDriftError::Repl("preview requires query name".to_string())
+                    })?;
+                let partition = find_arg(&parts, "--partition", "-p").ok_or_else(|| {
+                    crate::error::BqDriftError::Repl("preview requires --partition".to_string())
+                })?;
+                Ok(ReplCommand::Preview {
+                    query,
+                    partition: parse_date(&partition)?,
+                })
+            }
             "run" => {
                 let query = find_arg(&parts, "--query", "-q");
                 let partition = find_arg(&parts, "--partition", "-p");
@@ -317,6 +376,45 @@ impl ReplCommand {
                     .map(|v| v as u32);
                 Ok(ReplCommand::Show { query, version })
             }
+            "show_invariants" => {
+                let query = params
+                    .and_then(|p| p.get("query"))
+                    .and_then(|v| v.as_str())
+                    .map(|s| s.to_string())
+                    .ok_or_else(|| {
+                        crate::error::BqDriftError::Repl(
+                            "show_invariants requires 'query' param".to_string(),
+                        )
+                    })?;
+                let version = params
+                    .and_then(|p| p.get("version"))
+                    .and_then(|v| v.as_u64())
+                    .map(|v| v as u32);
+                Ok(ReplCommand::ShowInvariants { query, version })
+            }
+            "preview" => {
+                let query = params
+                    .and_then(|p| p.get("query"))
+                    .and_then(|v| v.as_str())
+                    .map(|s| s.to_string())
+                    .ok_or_else(|| {
+                        crate::error::BqDriftError::Repl(
+                            "preview requires 'query' param".to_string(),
+                        )
+                    })?;
+                let partition = params
+                    .and_then(|p| p.get("partition"))
+                    .and_then(|v| v.as_str())
+                    .ok_or_else(|| {
+                        crate::error::BqDriftError::Repl(
+                            "preview requires 'partition' param".to_string(),
+                        )
+                    })?;
+                Ok(ReplCommand::Preview {
+                    query,
+                    partition: parse_date(partition)?,
+                })
+            }
             "run" => {
                 let query = params
                     .and_then(|p| p.get("query"))
@@ -556,6 +654,15 @@ fn has_flag(parts: &[&str], flag: &str) -> bool {
     parts.iter().any(|&p| p == flag)
 }
 
+fn parse_date(s: &str) -> Result<NaiveDate> {
+    NaiveDate::parse_from_str(s, "%Y-%m-%d").map_err(|_| {
+        crate::error::BqDriftError::Repl(format!(
+            "Invalid partition date '{}'. Expected format: YYYY-MM-DD",
+            s
+        ))
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -597,6 +704,43 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_is_write() {
+        let write = |line: &str| ReplCommand::parse_interactive(line).unwrap().is_write();
+
+        assert!(write("run --query daily_stats"));
+        assert!(!write("run --query daily_stats --dry-run"));
+        assert!(write(
+            "backfill daily_stats --from 2024-01-01 --to 2024-01-31"
+        ));
+        assert!(write("sync"));
+        assert!(!write("sync --dry-run"));
+        assert!(write("init"));
+        assert!(!write("list"));
+        assert!(!write("validate"));
+        assert!(!write("check daily_stats"));
+        assert!(!write("audit"));
+    }
+
+    #[test]
+    fn test_parse_preview() {
+        let cmd =
+            ReplCommand::parse_interactive("preview daily_stats --partition 2024-06-15").unwrap();
+        if let ReplCommand::Preview { query, partition } = cmd {
+            assert_eq!(query, "daily_stats");
+            assert_eq!(partition, NaiveDate::from_ymd_opt(2024, 6, 15).unwrap());
+        } else {
+            panic!("Expected Preview command");
+        }
+
+        assert!(ReplCommand::parse_interactive("preview daily_stats").is_err());
+        assert!(ReplCommand::parse_interactive("preview daily_stats -p 2024-13-01").is_err());
+
+        let params = serde_json::json!({"query": "daily_stats", "partition": "2024-06-15"});
+        let cmd = ReplCommand::from_json_rpc("preview", Some(&params)).unwrap();
+        assert!(matches!(cmd, ReplCommand::Preview { .. }));
+    }
+
     #[test]
     fn test_parse_run_dry_run() {
         let cmd = ReplCommand::parse_interactive("run --query test --dry-run").unwrap();
@@ -607,6 +751,24 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_show_invariants() {
+        let cmd = ReplCommand::parse_interactive("invariants my_query --version 2").unwrap();
+        if let ReplCommand::ShowInvariants { query, version } = cmd {
+            assert_eq!(query, "my_query");
+            assert_eq!(version, Some(2));
+        } else {
+            panic!("Expected ShowInvariants command");
+        }
+
+        let params = serde_json::json!({"query": "my_query"});
+        let cmd = ReplCommand::from_json_rpc("show_invariants", Some(&params)).unwrap();
+        assert!(matches!(
+            cmd,
+            ReplCommand::ShowInvariants { version: None, .. }
+        ));
+    }
+
     #[test]
     fn test_from_json_rpc_list() {
         let params = serde_json::json!({"detailed": true});
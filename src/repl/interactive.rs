@@ -12,8 +12,23 @@ use std::borrow::Cow;
 use std::path::PathBuf;
 
 const COMMANDS: &[&str] = &[
-    "list", "show", "validate", "run", "backfill", "check", "sync", "audit", "init", "scratch",
-    "reload", "status", "help", "exit", "quit",
+    "list",
+    "show",
+    "invariants",
+    "preview",
+    "validate",
+    "run",
+    "backfill",
+    "check",
+    "sync",
+    "audit",
+    "init",
+    "scratch",
+    "reload",
+    "status",
+    "help",
+    "exit",
+    "quit",
 ];
 
 const FLAGS: &[&str] = &[
@@ -141,7 +156,12 @@ impl Completer for ReplHelper {
 
         if words.len() == 1 && line_to_pos.ends_with(' ') {
             let cmd = words.first().copied().unwrap_or("");
-            if cmd == "show" || cmd == "check" || cmd == "backfill" {
+            if cmd == "show"
+                || cmd == "invariants"
+                || cmd == "preview"
+                || cmd == "check"
+                || cmd == "backfill"
+            {
                 let completions: Vec<Pair> = self
                     .queries
                     .iter()
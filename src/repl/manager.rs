@@ -1,11 +1,13 @@
+use super::catalog::SharedCatalog;
 use super::commands::ReplCommand;
 use super::protocol::{
-    JsonRpcRequest, JsonRpcResponse, ServerConfigInfo, SessionInfo, SESSION_EXPIRED, SESSION_LIMIT,
+    JsonRpcRequest, JsonRpcResponse, ServerConfigInfo, SessionInfo, READ_ONLY_MODE,
+    SESSION_EXPIRED, SESSION_LIMIT,
 };
 use super::session::ReplSession;
 use chrono::{DateTime, Duration, Utc};
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
 use std::sync::Arc;
 use tokio::sync::{mpsc, oneshot};
@@ -17,6 +19,11 @@ pub struct ServerConfig {
     pub default_idle_timeout_secs: u64,
     pub max_idle_timeout_secs: u64,
     pub cleanup_interval_secs: u64,
+    pub read_only: bool,
+    /// Globs of query files to skip, relative to the queries directory.
+    pub ignore: Vec<String>,
+    /// Variables for `{% if %}` blocks in query files.
+    pub vars: Vec<(String, String)>,
 }
 
 impl ServerConfig {
@@ -28,6 +35,9 @@ impl ServerConfig {
             default_idle_timeout_secs: 300,
             max_idle_timeout_secs: 3600,
             cleanup_interval_secs: 60,
+            read_only: false,
+            ignore: Vec::new(),
+            vars: Vec::new(),
         }
     }
 
@@ -45,6 +55,21 @@ impl ServerConfig {
         self.max_idle_timeout_secs = secs;
         self
     }
+
+    pub fn with_read_only(mut self, read_only: bool) -> Self {
+        self.read_only = read_only;
+        self
+    }
+
+    pub fn with_ignore(mut self, patterns: Vec<String>) -> Self {
+        self.ignore = patterns;
+        self
+    }
+
+    pub fn with_vars(mut self, vars: Vec<(String, String)>) -> Self {
+        self.vars = vars;
+        self
+    }
 }
 
 #[derive(Debug, Clone, Default)]
@@ -207,6 +232,7 @@ impl SessionActor {
 
 pub struct SessionManager {
     sessions: HashMap<String, SessionHandle>,
+    catalogs: HashMap<PathBuf, SharedCatalog>,
     config: ServerConfig,
 }
 
@@ -214,10 +240,38 @@ impl SessionManager {
     pub fn new(config: ServerConfig) -> Self {
         Self {
             sessions: HashMap::new(),
+            catalogs: HashMap::new(),
             config,
         }
     }
 
+    /// The catalog shared by every session reading from `queries_path`.
+    pub fn catalog(&mut self, queries_path: &Path) -> SharedCatalog {
+        self.catalogs
+            .entry(queries_path.to_path_buf())
+            .or_insert_with(|| {
+                SharedCatalog::new(queries_path.to_path_buf())
+                    .with_ignore(self.config.ignore.clone())
+                    .with_vars(self.config.vars.clone())
+            })
+            .clone()
+    }
+
+    /// Reload every loaded catalog from disk. All sessions pick up the new
+    /// definitions on their next command. Returns the number of catalogs
+    /// reloaded.
+    pub fn reload_catalogs(&self) -> crate::error::Result<usize> {
+        let loaded: Vec<&SharedCatalog> = self
+            .catalogs
+            .values()
+            .filter(|c| c.current().is_some())
+            .collect();
+        for catalog in &loaded {
+            catalog.reload()?;
+        }
+        Ok(loaded.len())
+    }
+
     pub fn config(&self) -> &ServerConfig {
         &self.config
     }
@@ -234,9 +288,20 @@ impl SessionManager {
                 .default_queries_path
                 .to_string_lossy()
                 .to_string(),
+            read_only: self.config.read_only,
         }
     }
 
+    pub fn is_read_only(&self) -> bool {
+        self.config.read_only
+    }
+
+    /// Toggle read-only mode. While enabled, commands that write to BigQuery
+    /// are rejected with `READ_ONLY_MODE`; reads and dry runs still work.
+    pub fn set_read_only(&mut self, read_only: bool) {
+        self.config.read_only = read_only;
+    }
+
     pub fn can_create_session(&self) -> bool {
         self.sessions.len() < self.config.max_sessions
     }
@@ -289,7 +354,7 @@ impl SessionManager {
         Ok(info)
     }
 
-    fn create_session(&self, params: SessionCreateParams) -> SessionHandle {
+    fn create_session(&mut self, params: SessionCreateParams) -> SessionHandle {
         let id = params
             .session_id
             .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
@@ -307,7 +372,8 @@ impl SessionManager {
             .map(|t| t.min(self.config.max_idle_timeout_secs))
             .unwrap_or(self.config.default_idle_timeout_secs);
 
-        let session = ReplSession::new(project.clone(), queries_path.clone());
+        let catalog = self.catalog(&queries_path);
+        let session = ReplSession::with_catalog(project.clone(), catalog);
 
         let (request_tx, request_rx) = mpsc::channel(32);
         let request_count = Arc::new(AtomicU64::new(0));
@@ -341,6 +407,18 @@ impl SessionManager {
         session_id: &str,
         request: JsonRpcRequest,
     ) -> JsonRpcResponse {
+        if self.config.read_only {
+            if let Ok(cmd) = ReplCommand::from_json_rpc(&request.method, request.params.as_ref()) {
+                if cmd.is_write() {
+                    return JsonRpcResponse::error(
+                        request.id,
+                        READ_ONLY_MODE,
+                        format!("Server is in read-only mode: '{}' rejected", request.method),
+                    );
+                }
+            }
+        }
+
         if let Some(handle) = self.sessions.get(session_id) {
             if handle.is_expired() {
                 self.sessions.remove(session_id);
@@ -419,3 +497,66 @@ impl SessionManager {
         self.sessions.len()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(method: &str, params: serde_json::Value) -> JsonRpcRequest {
+        JsonRpcRequest {
+            jsonrpc: "2.0".to_string(),
+            method: method.to_string(),
+            params: Some(params),
+            id: Some(serde_json::json!(1)),
+            session: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_read_only_mode_rejects_writes() {
+        let config = ServerConfig::new(None, PathBuf::from("tests/fixtures")).with_read_only(true);
+        let mut manager = SessionManager::new(config);
+        assert!(manager.server_info().read_only);
+
+        let response = manager
+            .send_request("default", request("run", serde_json::json!({"query": "q"})))
+            .await;
+        assert_eq!(response.error.unwrap().code, READ_ONLY_MODE);
+        assert_eq!(manager.session_count(), 0);
+
+        let response = manager
+            .send_request("default", request("list", serde_json::json!({})))
+            .await;
+        assert!(response.error.is_none());
+
+        manager.set_read_only(false);
+        assert!(!manager.server_info().read_only);
+    }
+
+    #[tokio::test]
+    async fn test_sessions_share_catalog() {
+        let config = ServerConfig::new(None, PathBuf::from("tests/fixtures"));
+        let mut manager = SessionManager::new(config);
+        assert_eq!(manager.reload_catalogs().unwrap(), 0);
+
+        manager
+            .send_request("a", request("list", serde_json::json!({})))
+            .await;
+        let catalog = manager.catalog(Path::new("tests/fixtures"));
+        let loaded = catalog.current().unwrap();
+
+        manager
+            .send_request("b", request("list", serde_json::json!({})))
+            .await;
+        assert!(Arc::ptr_eq(
+            &loaded.queries,
+            &catalog.current().unwrap().queries
+        ));
+
+        assert_eq!(manager.reload_catalogs().unwrap(), 1);
+        assert!(!Arc::ptr_eq(
+            &loaded.queries,
+            &catalog.current().unwrap().queries
+        ));
+    }
+}
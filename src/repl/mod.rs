@@ -1,3 +1,4 @@
+mod catalog;
 mod commands;
 mod interactive;
 mod manager;
@@ -5,6 +6,7 @@ mod protocol;
 mod server;
 mod session;
 
+pub use catalog::{CatalogSnapshot, SharedCatalog};
 pub use commands::{ReplCommand, ReplResult};
 pub use interactive::InteractiveRepl;
 pub use manager::{ServerConfig, SessionCreateParams, SessionManager};
@@ -11,6 +11,7 @@ pub const INTERNAL_ERROR: i32 = -32603;
 pub const SESSION_EXPIRED: i32 = -32001;
 pub const SESSION_LIMIT: i32 = -32002;
 pub const INVALID_SESSION_CONFIG: i32 = -32003;
+pub const READ_ONLY_MODE: i32 = -32004;
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct JsonRpcRequest {
@@ -142,6 +143,7 @@ pub struct ServerConfigInfo {
     pub max_idle_timeout_secs: u64,
     pub default_project: Option<String>,
     pub default_queries_path: String,
+    pub read_only: bool,
 }
 
 #[cfg(test)]
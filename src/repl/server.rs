@@ -112,6 +112,32 @@ impl AsyncJsonRpcServer {
                 return false;
             }
 
+            "set_read_only" => {
+                let enabled = match request
+                    .params
+                    .as_ref()
+                    .and_then(|p| p.get("enabled"))
+                    .and_then(|v| v.as_bool())
+                {
+                    Some(enabled) => enabled,
+                    None => {
+                        let _ = self.response_tx.send(JsonRpcResponse::invalid_params(
+                            request.id,
+                            "Missing required 'enabled' parameter",
+                        ));
+                        return false;
+                    }
+                };
+
+                let mut mgr = self.manager.lock().await;
+                mgr.set_read_only(enabled);
+                let _ = self.response_tx.send(JsonRpcResponse::success(
+                    request.id,
+                    serde_json::json!({"read_only": enabled}),
+                ));
+                return false;
+            }
+
             "session_create" => {
                 let params = SessionCreateParams::from_json(request.params.as_ref());
                 let mut mgr = self.manager.lock().await;
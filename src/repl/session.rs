@@ -1,7 +1,8 @@
+use super::catalog::SharedCatalog;
 use super::commands::{ReplCommand, ReplResult};
-use crate::dsl::{QueryDef, QueryLoader, QueryValidator};
+use crate::dsl::{QueryDef, QueryValidator};
 use crate::error::{BqDriftError, Result};
-use crate::executor::BqClient;
+use crate::executor::{BqClient, DEFAULT_MAX_BACKFILL_PARTITIONS};
 use crate::invariant::{resolve_invariants_def, CheckStatus, InvariantChecker, Severity};
 use crate::schema::{PartitionKey, PartitionType};
 use chrono::{NaiveDate, Utc};
@@ -9,25 +10,23 @@ use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::Arc;
 
-const MAX_BACKFILL_PARTITIONS: usize = 3652;
-
 pub struct ReplSession {
     project: Option<String>,
-    queries_path: PathBuf,
-    loader: QueryLoader,
-    cached_queries: Option<Arc<Vec<QueryDef>>>,
-    cached_yaml_contents: Option<Arc<HashMap<String, String>>>,
+    catalog: SharedCatalog,
     client: Option<BqClient>,
 }
 
 impl ReplSession {
     pub fn new(project: Option<String>, queries_path: PathBuf) -> Self {
+        Self::with_catalog(project, SharedCatalog::new(queries_path))
+    }
+
+    /// A session reading queries from `catalog`, which may be shared with
+    /// other sessions so a single load (or reload) serves all of them.
+    pub fn with_catalog(project: Option<String>, catalog: SharedCatalog) -> Self {
         Self {
             project,
-            queries_path,
-            loader: QueryLoader::new(),
-            cached_queries: None,
-            cached_yaml_contents: None,
+            catalog,
             client: None,
         }
     }
@@ -42,37 +41,23 @@ impl ReplSession {
     }
 
     pub fn query_names(&self) -> Vec<String> {
-        self.cached_queries
-            .as_ref()
+        self.queries()
             .map(|qs| qs.iter().map(|q| q.name.clone()).collect())
             .unwrap_or_default()
     }
 
-    pub fn queries(&self) -> Option<&[QueryDef]> {
-        self.cached_queries.as_ref().map(|arc| arc.as_slice())
+    pub fn queries(&self) -> Option<Arc<Vec<QueryDef>>> {
+        self.catalog
+            .current()
+            .map(|snapshot| Arc::clone(&snapshot.queries))
     }
 
     fn ensure_queries(&mut self) -> Result<Arc<Vec<QueryDef>>> {
-        if self.cached_queries.is_none() {
-            let (queries, yaml_contents) =
-                self.loader.load_dir_with_contents(&self.queries_path)?;
-            self.cached_queries = Some(Arc::new(queries));
-            self.cached_yaml_contents = Some(Arc::new(yaml_contents));
-        }
-        match &self.cached_queries {
-            Some(queries) => Ok(Arc::clone(queries)),
-            None => Err(BqDriftError::Repl("Failed to load queries".to_string())),
-        }
+        Ok(Arc::clone(&self.catalog.get()?.queries))
     }
 
     fn ensure_yaml_contents(&mut self) -> Result<Arc<HashMap<String, String>>> {
-        self.ensure_queries()?;
-        match &self.cached_yaml_contents {
-            Some(contents) => Ok(Arc::clone(contents)),
-            None => Err(BqDriftError::Repl(
-                "Failed to load YAML contents".to_string(),
-            )),
-        }
+        Ok(Arc::clone(&self.catalog.get()?.yaml_contents))
     }
 
     async fn ensure_client(&mut self) -> Result<&BqClient> {
@@ -92,11 +77,7 @@ impl ReplSession {
     }
 
     pub fn reload_queries(&mut self) -> Result<usize> {
-        let (queries, yaml_contents) = self.loader.load_dir_with_contents(&self.queries_path)?;
-        let count = queries.len();
-        self.cached_queries = Some(Arc::new(queries));
-        self.cached_yaml_contents = Some(Arc::new(yaml_contents));
-        Ok(count)
+        Ok(self.catalog.reload()?.queries.len())
     }
 
     pub async fn execute(&mut self, cmd: ReplCommand) -> ReplResult {
@@ -108,6 +89,10 @@ impl ReplSession {
             ReplCommand::Validate => self.cmd_validate(),
             ReplCommand::List { detailed } => self.cmd_list(detailed),
             ReplCommand::Show { query, version } => self.cmd_show(&query, version),
+            ReplCommand::ShowInvariants { query, version } => {
+                self.cmd_show_invariants(&query, version)
+            }
+            ReplCommand::Preview { query, partition } => self.cmd_preview(&query, partition).await,
             ReplCommand::Run {
                 query,
                 partition,
@@ -175,6 +160,8 @@ impl ReplSession {
         let help = r#"Available commands:
   list [--detailed]                    List all queries
   show <query> [--version N]           Show query details
+  invariants <query> [--version N]     Show resolved invariants
+  preview <query> --partition DATE     Show SQL, byte estimate and invariants
   validate                             Validate all query definitions
   run [--query Q] [--partition P]      Run query (all if no query specified)
       [--dry-run] [--skip-invariants]
@@ -198,7 +185,7 @@ impl ReplSession {
 
     fn cmd_status(&self) -> ReplResult {
         let project_str = self.project.as_deref().unwrap_or("(not set)");
-        let queries_count = self.cached_queries.as_ref().map(|q| q.len()).unwrap_or(0);
+        let queries_count = self.queries().map(|q| q.len()).unwrap_or(0);
         let client_status = if self.client.is_some() {
             "connected"
         } else {
@@ -208,14 +195,14 @@ impl ReplSession {
         let output = format!(
             "Project: {}\nQueries path: {}\nQueries loaded: {}\nClient: {}",
             project_str,
-            self.queries_path.display(),
+            self.catalog.queries_path().display(),
             queries_count,
             client_status
         );
 
         let data = serde_json::json!({
             "project": self.project,
-            "queries_path": self.queries_path.to_string_lossy(),
+            "queries_path": self.catalog.queries_path().to_string_lossy(),
             "queries_loaded": queries_count,
             "client_connected": self.client.is_some()
         });
@@ -323,7 +310,7 @@ impl ReplSession {
         if queries.is_empty() {
             return ReplResult::success_with_output(format!(
                 "No queries found in {}",
-                self.queries_path.display()
+                self.catalog.queries_path().display()
             ));
         }
 
@@ -334,8 +321,8 @@ impl ReplSession {
             if detailed {
                 output_lines.push(query.name.clone());
                 output_lines.push(format!(
-                    "  destination: {}.{}",
-                    query.destination.dataset, query.destination.table
+                    "  destination: {}",
+                    query.destination.qualified_table()
                 ));
                 if let Some(desc) = &query.description {
                     output_lines.push(format!("  description: {}", desc));
@@ -354,13 +341,16 @@ impl ReplSession {
             } else {
                 let latest = query.latest_version().map(|v| v.version).unwrap_or(0);
                 output_lines.push(format!(
-                    "{:<30} v{:<3} {}.{}",
-                    query.name, latest, query.destination.dataset, query.destination.table
+                    "{:<30} v{:<3} {}",
+                    query.name,
+                    latest,
+                    query.destination.qualified_table()
                 ));
             }
 
             data_list.push(serde_json::json!({
                 "name": query.name,
+                "project": query.destination.project,
                 "dataset": query.destination.dataset,
                 "table": query.destination.table,
                 "latest_version": query.latest_version().map(|v| v.version),
@@ -376,6 +366,154 @@ impl ReplSession {
         ReplResult::success_with_both(output_lines.join("\n"), data)
     }
 
+    fn cmd_show_invariants(&mut self, query_name: &str, version_num: Option<u32>) -> ReplResult {
+        let queries = match self.ensure_queries() {
+            Ok(q) => q,
+            Err(e) => return ReplResult::failure(e.to_string()),
+        };
+
+        let query = match queries.iter().find(|q| q.name == query_name) {
+            Some(q) => q,
+            None => return ReplResult::failure(format!("Query '{}' not found", query_name)),
+        };
+
+        let version = match version_num {
+            Some(v) => query.versions.iter().find(|ver| ver.version == v),
+            None => query.get_version_for_date(chrono::Utc::now().date_naive()),
+        };
+        let version = match version {
+            Some(v) => v,
+            None => {
+                return ReplResult::failure(match version_num {
+                    Some(v) => format!("Version {} not found for '{}'", v, query_name),
+                    None => format!("No effective version for '{}'", query_name),
+                })
+            }
+        };
+
+        let mut output_lines = vec![format!(
+            "Invariants for {} v{}",
+            query.name, version.version
+        )];
+        for (phase, invariants) in [
+            ("before", &version.invariants.before),
+            ("after", &version.invariants.after),
+        ] {
+            output_lines.push(format!("\n{}:", phase));
+            if invariants.is_empty() {
+                output_lines.push("  (none)".to_string());
+            }
+            for inv in invariants {
+                let check = serde_json::to_value(&inv.check).unwrap_or_default();
+                let check_type = check.get("type").and_then(|t| t.as_str()).unwrap_or("");
+                output_lines.push(format!("  {} [{}] {}", inv.name, inv.severity, check_type));
+            }
+        }
+
+        let data = serde_json::json!({
+            "query": query.name,
+            "version": version.version,
+            "before": version.invariants.before,
+            "after": version.invariants.after,
+        });
+
+        ReplResult::success_with_both(output_lines.join("\n"), data)
+    }
+
+    async fn cmd_preview(&mut self, query_name: &str, partition: NaiveDate) -> ReplResult {
+        let queries = match self.ensure_queries() {
+            Ok(q) => q,
+            Err(e) => return ReplResult::failure(e.to_string()),
+        };
+
+        let query = match queries.iter().find(|q| q.name == query_name) {
+            Some(q) => q,
+            None => return ReplResult::failure(format!("Query '{}' not found", query_name)),
+        };
+
+        let partition_key = match Self::partition_key_for_date(
+            partition,
+            &query.destination.partition.partition_type,
+        ) {
+            Ok(k) => k,
+            Err(e) => return ReplResult::failure(e),
+        };
+        let version = match query.get_version_for_date(partition) {
+            Some(v) => v,
+            None => {
+                return ReplResult::failure(format!(
+                    "No version found for '{}' on {}",
+                    query_name, partition
+                ))
+            }
+        };
+        let sql = match query.resolve_executable_sql(partition_key, Utc::now().date_naive()) {
+            Ok(sql) => sql,
+            Err(e) => return ReplResult::failure(e.to_string()),
+        };
+
+        let estimate = match self.ensure_client().await {
+            Ok(client) => client.dry_run(&sql).await,
+            Err(e) => Err(e),
+        };
+
+        let mut output_lines = vec![
+            format!("Query: {}", query.name),
+            format!("Destination: {}", query.destination.qualified_table()),
+            format!("Partition: {}", partition_key),
+            format!("Version: {}", version.version),
+            format!("\n--- SQL ---\n{}\n-----------\n", sql.trim()),
+        ];
+        let bytes_processed = match &estimate {
+            Ok(dry_run) => {
+                let bytes = dry_run.total_bytes_processed.unwrap_or(0);
+                output_lines.push(format!("Estimated bytes processed: {}", bytes));
+                Some(bytes)
+            }
+            Err(e) => {
+                output_lines.push(format!("Estimated bytes processed: unavailable ({})", e));
+                None
+            }
+        };
+
+        for (phase, invariants) in [
+            ("before", &version.invariants.before),
+            ("after", &version.invariants.after),
+        ] {
+            output_lines.push(format!("\nInvariants ({}):", phase));
+            if invariants.is_empty() {
+                output_lines.push("  (none)".to_string());
+            }
+            for inv in invariants {
+                output_lines.push(format!("  {} [{}]", inv.name, inv.severity));
+            }
+        }
+
+        let data = serde_json::json!({
+            "query": query.name,
+            "partition": partition_key.to_string(),
+            "version": version.version,
+            "sql": sql,
+            "total_bytes_processed": bytes_processed,
+            "estimate_error": estimate.as_ref().err().map(|e| e.to_string()),
+            "invariants": {
+                "before": version.invariants.before,
+                "after": version.invariants.after,
+            },
+        });
+
+        ReplResult::success_with_both(output_lines.join("\n"), data)
+    }
+
+    fn partition_key_for_date(
+        date: NaiveDate,
+        partition_type: &PartitionType,
+    ) -> std::result::Result<PartitionKey, String> {
+        PartitionKey::for_date(date, partition_type).ok_or_else(|| {
+            "Preview by date is not supported for RANGE partitioned queries".to_string()
+        })
+    }
+
     fn cmd_show(&mut self, query_name: &str, version_num: Option<u32>) -> ReplResult {
         let queries = match self.ensure_queries() {
             Ok(q) => q,
@@ -390,8 +528,8 @@ impl ReplSession {
         let mut output_lines = Vec::new();
         output_lines.push(format!("Name: {}", query.name));
         output_lines.push(format!(
-            "Destination: {}.{}",
-            query.destination.dataset, query.destination.table
+            "Destination: {}",
+            query.destination.qualified_table()
         ));
 
         if let Some(desc) = &query.description {
@@ -425,7 +563,7 @@ impl ReplSession {
             output_lines.push(format!("\n  Version {}", version.version));
             output_lines.push(format!("  effective_from: {}", version.effective_from));
             output_lines.push(format!("  source: {}", version.source));
-            output_lines.push(format!("  schema: {} fields", version.schema.fields.len()));
+            output_lines.push(format!("  schema: {} fields", version.schema.len()));
         }
 
         if let Some(v) = version_num {
@@ -439,6 +577,7 @@ impl ReplSession {
         let data = serde_json::json!({
             "name": query.name,
             "destination": {
+                "project": query.destination.project,
                 "dataset": query.destination.dataset,
                 "table": query.destination.table
             },
@@ -446,7 +585,7 @@ impl ReplSession {
                 "version": v.version,
                 "effective_from": v.effective_from.to_string(),
                 "source": v.source,
-                "schema_fields": v.schema.fields.len()
+                "schema_fields": v.schema.len()
             })).collect::<Vec<_>>()
         });
 
@@ -515,6 +654,7 @@ impl ReplSession {
                         let data = serde_json::json!({
                             "query": stats.query_name,
                             "version": stats.version,
+                            "sql_revision": stats.sql_revision,
                             "partition": stats.partition_key.to_string()
                         });
                         ReplResult::success_with_both(output, data)
@@ -543,15 +683,23 @@ impl ReplSession {
                                 failure.query_name, failure.partition_key, failure.error
                             ));
                         }
+                        for skipped in &report.skipped {
+                            output_lines.push(format!(
+                                "- {} ({}): skipped, {}",
+                                skipped.query_name, skipped.partition_key, skipped.error
+                            ));
+                        }
                         output_lines.push(format!(
-                            "\n{} succeeded, {} failed",
+                            "\n{} succeeded, {} failed, {} skipped",
                             report.stats.len(),
-                            report.failures.len()
+                            report.failures.len(),
+                            report.skipped.len()
                         ));
 
                         let data = serde_json::json!({
                             "succeeded": report.stats.len(),
-                            "failed": report.failures.len()
+                            "failed": report.failures.len(),
+                            "skipped": report.skipped.len()
                         });
                         ReplResult::success_with_both(output_lines.join("\n"), data)
                     }
@@ -592,8 +740,8 @@ impl ReplSession {
 
             output_lines.push(format!("Query: {}", query.name));
             output_lines.push(format!(
-                "Destination: {}.{}",
-                query.destination.dataset, query.destination.table
+                "Destination: {}",
+                query.destination.qualified_table()
             ));
             output_lines.push(format!("Partition type: {:?}", partition_type));
 
@@ -749,10 +897,10 @@ impl ReplSession {
             let mut count = 0;
             while current <= to_key {
                 count += 1;
-                if count > MAX_BACKFILL_PARTITIONS {
+                if count > DEFAULT_MAX_BACKFILL_PARTITIONS {
                     return ReplResult::failure(format!(
                         "Backfill range too large: exceeds {} partitions",
-                        MAX_BACKFILL_PARTITIONS
+                        DEFAULT_MAX_BACKFILL_PARTITIONS
                     ));
                 }
                 let date = current.to_naive_date();
@@ -799,9 +947,17 @@ impl ReplSession {
                     report.failures.len()
                 ));
 
+                output_lines.push(format!(
+                    "Slot time: {} ms, bytes processed: {}",
+                    report.total_slot_ms(),
+                    report.total_bytes_processed()
+                ));
+
                 let data = serde_json::json!({
                     "succeeded": report.stats.len(),
-                    "failed": report.failures.len()
+                    "failed": report.failures.len(),
+                    "total_slot_ms": report.total_slot_ms(),
+                    "total_bytes_processed": report.total_bytes_processed()
                 });
                 ReplResult::success_with_both(output_lines.join("\n"), data)
             }
@@ -1014,6 +1170,9 @@ impl ReplSession {
 
         let summary = report.summary();
         let mut output_lines = Vec::new();
+        for warning in &report.warnings {
+            output_lines.push(format!("⚠ [{}] {}", warning.kind.as_str(), warning));
+        }
         output_lines.push("Drift summary:".to_string());
         for (state, count) in &summary {
             if *state != crate::DriftState::Current {
@@ -1032,7 +1191,8 @@ impl ReplSession {
 
         let data = serde_json::json!({
             "drifted_count": drifted.len(),
-            "dry_run": dry_run
+            "dry_run": dry_run,
+            "warnings": report.warnings
         });
         ReplResult::success_with_both(output_lines.join("\n"), data)
     }
@@ -1196,17 +1356,19 @@ impl ReplSession {
         {
             Ok(stats) => {
                 let output = format!(
-                    "✓ Promoted {} to production\n  From: {}\n  To: {}\n  Partition: {}",
+                    "✓ Promoted {} to production\n  From: {}\n  To: {}\n  Partition: {}\n  Method: {}",
                     stats.query_name,
                     stats.scratch_table,
                     stats.production_table,
-                    stats.partition_key
+                    stats.partition_key,
+                    stats.method
                 );
                 let data = serde_json::json!({
                     "query": stats.query_name,
                     "scratch_table": stats.scratch_table,
                     "production_table": stats.production_table,
-                    "partition": stats.partition_key.to_string()
+                    "partition": stats.partition_key.to_string(),
+                    "method": stats.method.to_string()
                 });
                 ReplResult::success_with_both(output, data)
             }
@@ -1228,3 +1390,76 @@ impl ReplSession {
         PartitionKey::default_for_type(partition_type)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_show_invariants_reflects_extended_resolution() {
+        let mut session = ReplSession::new(None, PathBuf::from("tests/fixtures"));
+
+        let result = session
+            .execute(ReplCommand::ShowInvariants {
+                query: "query_with_invariants".to_string(),
+                version: Some(2),
+            })
+            .await;
+
+        assert!(result.success);
+        let data = result.data.unwrap();
+        let after: Vec<&str> = data["after"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|inv| inv["name"].as_str().unwrap())
+            .collect();
+        assert!(after.contains(&"new_check"));
+        assert!(!after.contains(&"null_check"));
+
+        let min_rows = data["after"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|inv| inv["name"] == "min_rows")
+            .unwrap();
+        assert_eq!(min_rows["min"], 100);
+    }
+
+    #[tokio::test]
+    async fn test_show_invariants_unknown_version() {
+        let mut session = ReplSession::new(None, PathBuf::from("tests/fixtures"));
+
+        let result = session
+            .execute(ReplCommand::ShowInvariants {
+                query: "query_with_invariants".to_string(),
+                version: Some(9),
+            })
+            .await;
+
+        assert!(!result.success);
+    }
+
+    #[tokio::test]
+    async fn test_preview_without_project_still_shows_sql_and_invariants() {
+        let mut session = ReplSession::new(None, PathBuf::from("tests/fixtures"));
+
+        let result = session
+            .execute(ReplCommand::Preview {
+                query: "query_with_invariants".to_string(),
+                partition: NaiveDate::from_ymd_opt(2024, 6, 15).unwrap(),
+            })
+            .await;
+
+        assert!(result.success);
+        let data = result.data.unwrap();
+        assert_eq!(data["partition"], "2024-06-15");
+        assert!(data["sql"].as_str().unwrap().contains("MERGE"));
+        assert!(data["total_bytes_processed"].is_null());
+        assert!(data["estimate_error"]
+            .as_str()
+            .unwrap()
+            .contains("No project set"));
+        assert!(!data["invariants"]["after"].as_array().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,219 @@
+use super::diff::FieldChange;
+use super::evolution::is_widening;
+use super::field::FieldMode;
+use super::table::{column_ddl, parameterized_type, string_literal, Schema};
+use std::fmt;
+
+/// One step of evolving a table from one schema to another.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AlterStatement {
+    /// DDL BigQuery applies in place.
+    Ddl(String),
+    /// DDL BigQuery applies in place but that discards existing data, such
+    /// as `DROP COLUMN`. Not part of a non-destructive migration.
+    Destructive(String),
+    /// A change BigQuery cannot make with `ALTER TABLE`; the table has to be
+    /// rebuilt (or the change made through the API) and backfilled.
+    RequiresRebuild { column: String, reason: String },
+}
+
+impl AlterStatement {
+    /// Whether this can be applied in place without losing data.
+    pub fn is_supported(&self) -> bool {
+        matches!(self, AlterStatement::Ddl(_))
+    }
+
+    pub fn is_destructive(&self) -> bool {
+        matches!(self, AlterStatement::Destructive(_))
+    }
+}
+
+impl fmt::Display for AlterStatement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AlterStatement::Ddl(sql) => write!(f, "{};", sql),
+            AlterStatement::Destructive(sql) => write!(f, "-- destructive: {};", sql),
+            AlterStatement::RequiresRebuild { column, reason } => {
+                write!(f, "-- requires rebuild: column '{}' {}", column, reason)
+            }
+        }
+    }
+}
+
+impl Schema {
+    /// Statements that turn a table with this schema into one with `next`,
+    /// in the order added, removed, modified. Dropped columns are
+    /// `Destructive`. Nested RECORD changes, type and NUMERIC parameter
+    /// changes other than widening, new `REQUIRED` columns, mode changes
+    /// other than dropping `NOT NULL` and policy tag changes are returned as
+    /// `RequiresRebuild`.
+    pub fn alter_statements(&self, next: &Schema, table: &str) -> Vec<AlterStatement> {
+        let diff = self.diff(next);
+        let mut statements = Vec::new();
+        let rebuild = |column: &str, reason: String| AlterStatement::RequiresRebuild {
+            column: column.to_string(),
+            reason,
+        };
+
+        for name in &diff.added {
+            let Some(field) = next.get_field_path(name) else {
+                continue;
+            };
+            if name.contains('.') {
+                statements.push(rebuild(
+                    name,
+                    "is a new nested field, which ALTER TABLE cannot add".to_string(),
+                ));
+            } else if field.mode == FieldMode::Required {
+                statements.push(rebuild(name, "is a new REQUIRED column".to_string()));
+            } else {
+                statements.push(AlterStatement::Ddl(format!(
+                    "ALTER TABLE `{}` ADD COLUMN {}",
+                    table,
+                    column_ddl(field)
+                )));
+            }
+        }
+
+        for name in &diff.removed {
+            if name.contains('.') {
+                statements.push(rebuild(
+                    name,
+                    "is a removed nested field, which ALTER TABLE cannot drop".to_string(),
+                ));
+            } else {
+                statements.push(AlterStatement::Destructive(format!(
+                    "ALTER TABLE `{}` DROP COLUMN `{}`",
+                    table, name
+                )));
+            }
+        }
+
+        for change in &diff.modified {
+            let alter = format!("ALTER TABLE `{}` ALTER COLUMN `{}`", table, change.name);
+            if change.name.contains('.') {
+                statements.push(rebuild(
+                    &change.name,
+                    "is a changed nested field, which ALTER TABLE cannot modify".to_string(),
+                ));
+                continue;
+            }
+
+            let new_type =
+                parameterized_type(&change.new_type, change.new_precision, change.new_scale);
+            if change.old_type != change.new_type {
+                if is_widening(&change.old_type, &change.new_type) && change.new_precision.is_none()
+                {
+                    statements.push(AlterStatement::Ddl(format!(
+                        "{} SET DATA TYPE {}",
+                        alter, new_type
+                    )));
+                } else {
+                    statements.push(rebuild(
+                        &change.name,
+                        format!(
+                            "changes type from {} to {}",
+                            parameterized_type(
+                                &change.old_type,
+                                change.old_precision,
+                                change.old_scale
+                            ),
+                            new_type
+                        ),
+                    ));
+                }
+            } else if change.old_precision != change.new_precision
+                || change.old_scale != change.new_scale
+            {
+                if widens_parameters(change) {
+                    statements.push(AlterStatement::Ddl(format!(
+                        "{} SET DATA TYPE {}",
+                        alter, new_type
+                    )));
+                } else {
+                    statements.push(rebuild(
+                        &change.name,
+                        format!(
+                            "changes type from {} to {}",
+                            parameterized_type(
+                                &change.old_type,
+                                change.old_precision,
+                                change.old_scale
+                            ),
+                            new_type
+                        ),
+                    ));
+                }
+            }
+
+            if change.old_mode != change.new_mode {
+                if change.old_mode == FieldMode::Required && change.new_mode == FieldMode::Nullable
+                {
+                    statements.push(AlterStatement::Ddl(format!("{} DROP NOT NULL", alter)));
+                } else {
+                    statements.push(rebuild(
+                        &change.name,
+                        format!(
+                            "changes mode from {} to {}",
+                            change.old_mode.sql_name(),
+                            change.new_mode.sql_name()
+                        ),
+                    ));
+                }
+            }
+
+            if change.old_default != change.new_default {
+                statements.push(AlterStatement::Ddl(match &change.new_default {
+                    Some(expr) => format!("{} SET DEFAULT {}", alter, expr),
+                    None => format!("{} DROP DEFAULT", alter),
+                }));
+            }
+
+            let mut options = Vec::new();
+            if change.old_description != change.new_description {
+                let value = change.new_description.as_deref().map(string_literal);
+                options.push(format!(
+                    "description={}",
+                    value.as_deref().unwrap_or("NULL")
+                ));
+            }
+            if change.old_rounding_mode != change.new_rounding_mode {
+                let value = change
+                    .new_rounding_mode
+                    .map(|m| format!("'{}'", m.sql_name()));
+                options.push(format!(
+                    "rounding_mode={}",
+                    value.as_deref().unwrap_or("NULL")
+                ));
+            }
+            if !options.is_empty() {
+                statements.push(AlterStatement::Ddl(format!(
+                    "{} SET OPTIONS({})",
+                    alter,
+                    options.join(", ")
+                )));
+            }
+
+            if change.old_policy_tags != change.new_policy_tags {
+                statements.push(rebuild(
+                    &change.name,
+                    "changes policy tags, which ALTER TABLE cannot set".to_string(),
+                ));
+            }
+        }
+
+        statements
+    }
+}
+
+/// Precision and scale changes BigQuery applies in place: neither the scale
+/// nor the integer digits shrink, and dropping the parameters entirely.
+fn widens_parameters(change: &FieldChange) -> bool {
+    let (Some(old_precision), Some(new_precision)) = (change.old_precision, change.new_precision)
+    else {
+        return change.new_precision.is_none();
+    };
+    let old_scale = change.old_scale.unwrap_or(0);
+    let new_scale = change.new_scale.unwrap_or(0);
+    new_scale >= old_scale && new_precision - new_scale >= old_precision - old_scale
+}
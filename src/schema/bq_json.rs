@@ -0,0 +1,159 @@
+use super::field::{BqType, Field, FieldMode};
+use super::table::Schema;
+use crate::error::{BqDriftError, Result};
+use serde_json::{json, Map, Value};
+
+impl Schema {
+    /// Parse a schema exported by BigQuery: the table JSON from
+    /// `bq show --format=json`, a `{"fields": [...]}` object, or the bare
+    /// field array from `bq show --schema`.
+    pub fn from_bq_json(json: &str) -> Result<Schema> {
+        let value: Value = serde_json::from_str(json)?;
+        let fields = match &value {
+            Value::Array(fields) => fields,
+            Value::Object(obj) => obj
+                .get("schema")
+                .and_then(|schema| schema.get("fields"))
+                .or_else(|| obj.get("fields"))
+                .and_then(Value::as_array)
+                .ok_or_else(|| {
+                    BqDriftError::Schema(
+                        "BigQuery schema JSON has no 'fields' or 'schema.fields' array".to_string(),
+                    )
+                })?,
+            _ => {
+                return Err(BqDriftError::Schema(
+                    "BigQuery schema JSON must be an object or an array of fields".to_string(),
+                ))
+            }
+        };
+
+        Ok(Schema::from_fields(parse_fields(fields, "")?))
+    }
+
+    /// The field array BigQuery's REST API takes as a table's
+    /// `schema.fields`, the inverse of `from_bq_json`.
+    pub fn to_bq_json(&self) -> String {
+        let fields: Vec<Value> = self.fields.iter().map(field_to_json).collect();
+        Value::Array(fields).to_string()
+    }
+}
+
+fn field_to_json(field: &Field) -> Value {
+    let type_name = match field.field_type {
+        BqType::Record => "RECORD",
+        ref other => other.sql_name(),
+    };
+    let mut obj = Map::new();
+    obj.insert("name".to_string(), json!(field.name));
+    obj.insert("type".to_string(), json!(type_name));
+    obj.insert("mode".to_string(), json!(field.mode.sql_name()));
+    if let Some(description) = &field.description {
+        obj.insert("description".to_string(), json!(description));
+    }
+    if let Some(expr) = &field.default_expression {
+        obj.insert("defaultValueExpression".to_string(), json!(expr));
+    }
+    if !field.policy_tags.is_empty() {
+        obj.insert(
+            "policyTags".to_string(),
+            json!({ "names": field.policy_tags }),
+        );
+    }
+    if let Some(precision) = field.precision {
+        obj.insert("precision".to_string(), json!(precision.to_string()));
+    }
+    if let Some(scale) = field.scale {
+        obj.insert("scale".to_string(), json!(scale.to_string()));
+    }
+    if let Some(mode) = &field.rounding_mode {
+        obj.insert("roundingMode".to_string(), json!(mode.sql_name()));
+    }
+    if let Some(nested) = &field.fields {
+        obj.insert(
+            "fields".to_string(),
+            Value::Array(nested.iter().map(field_to_json).collect()),
+        );
+    }
+    Value::Object(obj)
+}
+
+fn parse_fields(fields: &[Value], prefix: &str) -> Result<Vec<Field>> {
+    fields.iter().map(|f| parse_field(f, prefix)).collect()
+}
+
+fn parse_field(value: &Value, prefix: &str) -> Result<Field> {
+    let name = value
+        .get("name")
+        .and_then(Value::as_str)
+        .ok_or_else(|| BqDriftError::Schema(format!("Field in '{}' has no name", prefix)))?;
+    let path = format!("{}{}", prefix, name);
+
+    let type_name = value
+        .get("type")
+        .and_then(Value::as_str)
+        .ok_or_else(|| BqDriftError::Schema(format!("Field '{}' has no type", path)))?;
+    let field_type = BqType::from_sql_name(type_name).ok_or_else(|| {
+        BqDriftError::Schema(format!(
+            "Field '{}' has unsupported type '{}'",
+            path, type_name
+        ))
+    })?;
+
+    let mode = match value.get("mode").and_then(Value::as_str) {
+        None => FieldMode::Nullable,
+        Some(mode) => match mode.to_ascii_uppercase().as_str() {
+            "NULLABLE" => FieldMode::Nullable,
+            "REQUIRED" => FieldMode::Required,
+            "REPEATED" => FieldMode::Repeated,
+            _ => {
+                return Err(BqDriftError::Schema(format!(
+                    "Field '{}' has unsupported mode '{}'",
+                    path, mode
+                )))
+            }
+        },
+    };
+
+    let mut field = Field::new(name, field_type);
+    field.mode = mode;
+    field.description = value
+        .get("description")
+        .and_then(Value::as_str)
+        .map(str::to_string);
+    field.default_expression = value
+        .get("defaultValueExpression")
+        .and_then(Value::as_str)
+        .map(str::to_string);
+    field.policy_tags = value
+        .get("policyTags")
+        .and_then(|tags| tags.get("names"))
+        .and_then(Value::as_array)
+        .map(|names| {
+            names
+                .iter()
+                .filter_map(Value::as_str)
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default();
+
+    // BigQuery reports INT64 properties as JSON strings.
+    let parameter = |key: &str| {
+        value.get(key).and_then(|v| match v {
+            Value::String(s) => s.parse().ok(),
+            other => other.as_u64().and_then(|n| u32::try_from(n).ok()),
+        })
+    };
+    field.precision = parameter("precision");
+    field.scale = parameter("scale");
+    field.rounding_mode = value
+        .get("roundingMode")
+        .and_then(Value::as_str)
+        .and_then(|mode| serde_json::from_value(Value::String(mode.to_string())).ok());
+
+    if let Some(nested) = value.get("fields").and_then(Value::as_array) {
+        field.fields = Some(parse_fields(nested, &format!("{}.", path))?);
+    }
+    Ok(field)
+}
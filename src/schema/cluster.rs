@@ -2,6 +2,8 @@ use crate::error::{BqDriftError, Result};
 use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
 
+pub const MAX_CLUSTER_FIELDS: usize = 4;
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct ClusterConfig {
     pub fields: Vec<String>,
@@ -9,7 +11,7 @@ pub struct ClusterConfig {
 
 impl ClusterConfig {
     pub fn new(fields: Vec<String>) -> Result<Self> {
-        if fields.len() > 4 {
+        if fields.len() > MAX_CLUSTER_FIELDS {
             return Err(BqDriftError::Cluster(
                 "BigQuery supports maximum 4 clustering fields".into(),
             ));
@@ -0,0 +1,196 @@
+use super::field::{BqType, Field, FieldMode, RoundingMode};
+use super::table::{parameterized_type, Schema};
+use std::fmt;
+
+/// A field present in both schemas with any attribute covered by the schema
+/// checksum changed, other than its nested fields.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldChange {
+    /// Dotted path for fields nested in RECORDs.
+    pub name: String,
+    pub old_type: BqType,
+    pub new_type: BqType,
+    pub old_mode: FieldMode,
+    pub new_mode: FieldMode,
+    pub old_default: Option<String>,
+    pub new_default: Option<String>,
+    pub old_description: Option<String>,
+    pub new_description: Option<String>,
+    pub old_policy_tags: Vec<String>,
+    pub new_policy_tags: Vec<String>,
+    pub old_precision: Option<u32>,
+    pub new_precision: Option<u32>,
+    pub old_scale: Option<u32>,
+    pub new_scale: Option<u32>,
+    pub old_rounding_mode: Option<RoundingMode>,
+    pub new_rounding_mode: Option<RoundingMode>,
+}
+
+impl FieldChange {
+    fn between(name: String, old: &Field, new: &Field) -> Self {
+        Self {
+            name,
+            old_type: old.field_type.clone(),
+            new_type: new.field_type.clone(),
+            old_mode: old.mode.clone(),
+            new_mode: new.mode.clone(),
+            old_default: old.default_expression.clone(),
+            new_default: new.default_expression.clone(),
+            old_description: old.description.clone(),
+            new_description: new.description.clone(),
+            old_policy_tags: old.policy_tags.clone(),
+            new_policy_tags: new.policy_tags.clone(),
+            old_precision: old.precision,
+            new_precision: new.precision,
+            old_scale: old.scale,
+            new_scale: new.scale,
+            old_rounding_mode: old.rounding_mode,
+            new_rounding_mode: new.rounding_mode,
+        }
+    }
+
+    /// Whether the NUMERIC/BIGNUMERIC precision, scale or rounding mode
+    /// changed.
+    pub fn parameters_changed(&self) -> bool {
+        self.old_precision != self.new_precision
+            || self.old_scale != self.new_scale
+            || self.old_rounding_mode != self.new_rounding_mode
+    }
+}
+
+/// Field-level differences between two schemas, matched by name so that
+/// reordering alone is not a change. Paths are dotted for nested fields.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SchemaDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub modified: Vec<FieldChange>,
+}
+
+impl SchemaDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.modified.is_empty()
+    }
+
+    fn compare(&mut self, prefix: &str, old: &[Field], new: &[Field]) {
+        let path = |name: &str| {
+            if prefix.is_empty() {
+                name.to_string()
+            } else {
+                format!("{}.{}", prefix, name)
+            }
+        };
+
+        for old_field in old {
+            if !new.iter().any(|f| f.name == old_field.name) {
+                self.removed.push(path(&old_field.name));
+            }
+        }
+
+        for new_field in new {
+            let Some(old_field) = old.iter().find(|f| f.name == new_field.name) else {
+                self.added.push(path(&new_field.name));
+                continue;
+            };
+
+            let change = FieldChange::between(path(&new_field.name), old_field, new_field);
+            if change.old_type != change.new_type
+                || change.old_mode != change.new_mode
+                || change.old_default != change.new_default
+                || change.old_description != change.new_description
+                || change.old_policy_tags != change.new_policy_tags
+                || change.parameters_changed()
+            {
+                self.modified.push(change);
+            }
+
+            if old_field.field_type == BqType::Record && new_field.field_type == BqType::Record {
+                self.compare(
+                    &path(&new_field.name),
+                    old_field.fields.as_deref().unwrap_or_default(),
+                    new_field.fields.as_deref().unwrap_or_default(),
+                );
+            }
+        }
+    }
+}
+
+impl Schema {
+    /// Changes needed to go from `self` to `other`.
+    pub fn diff(&self, other: &Schema) -> SchemaDiff {
+        let mut diff = SchemaDiff::default();
+        diff.compare("", &self.fields, &other.fields);
+        diff
+    }
+}
+
+fn column_label(
+    field_type: &BqType,
+    (precision, scale): (Option<u32>, Option<u32>),
+    rounding_mode: Option<&RoundingMode>,
+    mode: &FieldMode,
+    default: Option<&str>,
+) -> String {
+    let mut label = parameterized_type(field_type, precision, scale);
+    if let Some(rounding_mode) = rounding_mode {
+        label.push_str(&format!(" {}", rounding_mode.sql_name()));
+    }
+    match mode {
+        FieldMode::Nullable => {}
+        FieldMode::Required => label.push_str(" REQUIRED"),
+        FieldMode::Repeated => label.push_str(" REPEATED"),
+    }
+    if let Some(expr) = default {
+        label.push_str(&format!(" DEFAULT {}", expr));
+    }
+    label
+}
+
+impl fmt::Display for FieldChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "~ {}: {} -> {}",
+            self.name,
+            column_label(
+                &self.old_type,
+                (self.old_precision, self.old_scale),
+                self.old_rounding_mode.as_ref(),
+                &self.old_mode,
+                self.old_default.as_deref()
+            ),
+            column_label(
+                &self.new_type,
+                (self.new_precision, self.new_scale),
+                self.new_rounding_mode.as_ref(),
+                &self.new_mode,
+                self.new_default.as_deref()
+            )
+        )?;
+        if self.old_description != self.new_description {
+            write!(f, " (description changed)")?;
+        }
+        if self.old_policy_tags != self.new_policy_tags {
+            write!(f, " (policy tags changed)")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for SchemaDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let lines = self
+            .added
+            .iter()
+            .map(|name| format!("+ {}", name))
+            .chain(self.removed.iter().map(|name| format!("- {}", name)))
+            .chain(self.modified.iter().map(|change| change.to_string()));
+        for (i, line) in lines.enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", line)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,111 @@
+use super::field::{BqType, FieldMode};
+use super::table::Schema;
+use crate::error::{BqDriftError, Result};
+use std::fmt;
+
+/// A change between schema versions that BigQuery accepts but that loses or
+/// reinterprets existing data.
+#[derive(Debug, Clone, PartialEq)]
+pub enum EvolutionWarning {
+    /// The column is dropped; its existing values are lost.
+    ColumnRemoved(String),
+    /// Existing values are cast to the wider type.
+    TypeWidened {
+        field: String,
+        from: BqType,
+        to: BqType,
+    },
+}
+
+impl fmt::Display for EvolutionWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EvolutionWarning::ColumnRemoved(field) => write!(f, "column '{}' is removed", field),
+            EvolutionWarning::TypeWidened { field, from, to } => write!(
+                f,
+                "column '{}' is widened from {} to {}",
+                field,
+                from.sql_name(),
+                to.sql_name()
+            ),
+        }
+    }
+}
+
+/// Type changes BigQuery applies in place with `ALTER COLUMN SET DATA TYPE`.
+pub(super) fn is_widening(from: &BqType, to: &BqType) -> bool {
+    matches!(
+        (from, to),
+        (
+            BqType::Int64,
+            BqType::Numeric | BqType::Bignumeric | BqType::Float64
+        ) | (BqType::Numeric, BqType::Bignumeric | BqType::Float64)
+    )
+}
+
+impl Schema {
+    /// Check that `next` can replace `prev` on an existing table. Narrowing
+    /// a type, tightening a mode to `REQUIRED`, changing to or from
+    /// `REPEATED` and adding a `REQUIRED` column are rejected with a
+    /// `Schema` error listing every offending column. Adding nullable or
+    /// repeated columns and relaxing `REQUIRED` to `NULLABLE` pass silently.
+    pub fn check_evolution(prev: &Schema, next: &Schema) -> Result<Vec<EvolutionWarning>> {
+        let diff = prev.diff(next);
+        let mut incompatible = Vec::new();
+        let mut warnings = Vec::new();
+
+        for name in &diff.added {
+            if next
+                .get_field_path(name)
+                .is_some_and(|f| f.mode == FieldMode::Required)
+            {
+                incompatible.push(format!("new column '{}' is REQUIRED", name));
+            }
+        }
+
+        for change in &diff.modified {
+            if change.old_type != change.new_type {
+                if is_widening(&change.old_type, &change.new_type) {
+                    warnings.push(EvolutionWarning::TypeWidened {
+                        field: change.name.clone(),
+                        from: change.old_type.clone(),
+                        to: change.new_type.clone(),
+                    });
+                } else {
+                    incompatible.push(format!(
+                        "column '{}' changes type from {} to {}",
+                        change.name,
+                        change.old_type.sql_name(),
+                        change.new_type.sql_name()
+                    ));
+                }
+            }
+
+            let mode_compatible = change.old_mode == change.new_mode
+                || (change.old_mode == FieldMode::Required
+                    && change.new_mode == FieldMode::Nullable);
+            if !mode_compatible {
+                incompatible.push(format!(
+                    "column '{}' changes mode from {} to {}",
+                    change.name,
+                    change.old_mode.sql_name(),
+                    change.new_mode.sql_name()
+                ));
+            }
+        }
+
+        if !incompatible.is_empty() {
+            return Err(BqDriftError::Schema(format!(
+                "incompatible schema change: {}",
+                incompatible.join("; ")
+            )));
+        }
+
+        warnings.extend(
+            diff.removed
+                .into_iter()
+                .map(EvolutionWarning::ColumnRemoved),
+        );
+        Ok(warnings)
+    }
+}
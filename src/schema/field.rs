@@ -1,6 +1,7 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
 #[serde(rename_all = "UPPERCASE")]
 pub enum BqType {
     String,
@@ -16,19 +17,97 @@ pub enum BqType {
     Timestamp,
     Geography,
     Json,
+    Interval,
     Record,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+impl BqType {
+    /// Parse a type name as BigQuery reports it, accepting the legacy and
+    /// alias spellings (`INTEGER`, `FLOAT`, `BOOLEAN`, `STRUCT`, `DECIMAL`, ...).
+    pub fn from_sql_name(name: &str) -> Option<BqType> {
+        Some(match name.to_ascii_uppercase().as_str() {
+            "STRING" => BqType::String,
+            "BYTES" => BqType::Bytes,
+            "INT64" | "INTEGER" => BqType::Int64,
+            "FLOAT64" | "FLOAT" => BqType::Float64,
+            "NUMERIC" | "DECIMAL" => BqType::Numeric,
+            "BIGNUMERIC" | "BIGDECIMAL" => BqType::Bignumeric,
+            "BOOL" | "BOOLEAN" => BqType::Bool,
+            "DATE" => BqType::Date,
+            "DATETIME" => BqType::Datetime,
+            "TIME" => BqType::Time,
+            "TIMESTAMP" => BqType::Timestamp,
+            "GEOGRAPHY" => BqType::Geography,
+            "JSON" => BqType::Json,
+            "INTERVAL" => BqType::Interval,
+            "RECORD" | "STRUCT" => BqType::Record,
+            _ => return None,
+        })
+    }
+
+    /// Standard SQL type name. `Record` is `STRUCT`; its sub-fields are
+    /// rendered by the caller.
+    pub fn sql_name(&self) -> &'static str {
+        match self {
+            BqType::String => "STRING",
+            BqType::Bytes => "BYTES",
+            BqType::Int64 => "INT64",
+            BqType::Float64 => "FLOAT64",
+            BqType::Numeric => "NUMERIC",
+            BqType::Bignumeric => "BIGNUMERIC",
+            BqType::Bool => "BOOL",
+            BqType::Date => "DATE",
+            BqType::Datetime => "DATETIME",
+            BqType::Time => "TIME",
+            BqType::Timestamp => "TIMESTAMP",
+            BqType::Geography => "GEOGRAPHY",
+            BqType::Json => "JSON",
+            BqType::Interval => "INTERVAL",
+            BqType::Record => "STRUCT",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Default)]
 #[serde(rename_all = "UPPERCASE")]
 pub enum FieldMode {
     #[default]
+    #[serde(alias = "nullable")]
     Nullable,
+    #[serde(alias = "required")]
     Required,
+    #[serde(alias = "repeated")]
     Repeated,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl FieldMode {
+    pub fn sql_name(&self) -> &'static str {
+        match self {
+            FieldMode::Nullable => "NULLABLE",
+            FieldMode::Required => "REQUIRED",
+            FieldMode::Repeated => "REPEATED",
+        }
+    }
+}
+
+/// How NUMERIC and BIGNUMERIC values are rounded to the column's scale.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RoundingMode {
+    RoundHalfAwayFromZero,
+    RoundHalfEven,
+}
+
+impl RoundingMode {
+    pub fn sql_name(&self) -> &'static str {
+        match self {
+            RoundingMode::RoundHalfAwayFromZero => "ROUND_HALF_AWAY_FROM_ZERO",
+            RoundingMode::RoundHalfEven => "ROUND_HALF_EVEN",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
 pub struct Field {
     pub name: String,
     #[serde(rename = "type")]
@@ -39,6 +118,21 @@ pub struct Field {
     pub description: Option<String>,
     #[serde(default)]
     pub fields: Option<Vec<Field>>,
+    /// Column `DEFAULT` expression, e.g. `CURRENT_TIMESTAMP()`. Also accepted
+    /// as `default` in YAML.
+    #[serde(default, alias = "default", skip_serializing_if = "Option::is_none")]
+    pub default_expression: Option<String>,
+    /// Policy tag resource names, e.g.
+    /// `projects/p/locations/us/taxonomies/1/policyTags/2`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub policy_tags: Vec<String>,
+    /// Parameterized `NUMERIC(P, S)` / `BIGNUMERIC(P, S)`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub precision: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scale: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rounding_mode: Option<RoundingMode>,
 }
 
 impl Field {
@@ -49,6 +143,11 @@ impl Field {
             mode: FieldMode::default(),
             description: None,
             fields: None,
+            default_expression: None,
+            policy_tags: Vec::new(),
+            precision: None,
+            scale: None,
+            rounding_mode: None,
         }
     }
 
@@ -71,4 +170,49 @@ impl Field {
         self.fields = Some(fields);
         self
     }
+
+    pub fn with_default_expression(mut self, expr: impl Into<String>) -> Self {
+        self.default_expression = Some(expr.into());
+        self
+    }
+
+    pub fn with_policy_tag(mut self, tag: impl Into<String>) -> Self {
+        self.policy_tags.push(tag.into());
+        self
+    }
+
+    pub fn with_precision(mut self, precision: u32, scale: u32) -> Self {
+        self.precision = Some(precision);
+        self.scale = Some(scale);
+        self
+    }
+
+    pub fn with_rounding_mode(mut self, rounding_mode: RoundingMode) -> Self {
+        self.rounding_mode = Some(rounding_mode);
+        self
+    }
+
+    /// `precision`, `scale` and `rounding_mode` only apply to NUMERIC and
+    /// BIGNUMERIC, and the scale cannot exceed the precision.
+    pub(crate) fn numeric_parameter_problem(&self) -> Option<String> {
+        let has_parameters =
+            self.precision.is_some() || self.scale.is_some() || self.rounding_mode.is_some();
+        if !has_parameters {
+            return None;
+        }
+        if !matches!(self.field_type, BqType::Numeric | BqType::Bignumeric) {
+            return Some(format!(
+                "sets precision, scale or rounding_mode but is {}, not NUMERIC or BIGNUMERIC",
+                self.field_type.sql_name()
+            ));
+        }
+        match (self.precision, self.scale) {
+            (None, Some(_)) => Some("sets scale without precision".to_string()),
+            (Some(precision), Some(scale)) if scale > precision => Some(format!(
+                "has scale {} greater than precision {}",
+                scale, precision
+            )),
+            _ => None,
+        }
+    }
 }
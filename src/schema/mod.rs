@@ -1,9 +1,20 @@
+mod alter;
+mod bq_json;
 mod cluster;
+mod diff;
+mod evolution;
 mod field;
 mod partition;
+mod provenance;
 mod table;
 
-pub use cluster::ClusterConfig;
-pub use field::{BqType, Field, FieldMode};
-pub use partition::{PartitionConfig, PartitionKey, PartitionType};
+pub use alter::AlterStatement;
+pub use cluster::{ClusterConfig, MAX_CLUSTER_FIELDS};
+pub use diff::{FieldChange, SchemaDiff};
+pub use evolution::EvolutionWarning;
+pub use field::{BqType, Field, FieldMode, RoundingMode};
+pub use partition::{PartitionColumnType, PartitionConfig, PartitionKey, PartitionType, RangeSpec};
+pub use provenance::{
+    provenance_fields, PROVENANCE_QUERY_COLUMN, PROVENANCE_RUN_AT_COLUMN, PROVENANCE_VERSION_COLUMN,
+};
 pub use table::Schema;
@@ -1,8 +1,10 @@
-use chrono::{NaiveDate, NaiveDateTime};
+use super::field::BqType;
+use chrono::{Days, NaiveDate, NaiveDateTime, Timelike};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::fmt;
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Default)]
 #[serde(rename_all = "UPPERCASE")]
 pub enum PartitionType {
     Hour,
@@ -11,7 +13,13 @@ pub enum PartitionType {
     Month,
     Year,
     Range,
+    /// Partitioned on BigQuery's `_PARTITIONTIME` pseudo-column rather than
+    /// a user column.
+    #[serde(rename = "INGESTION_TIME", alias = "INGESTIONTIME")]
     IngestionTime,
+    /// No partitioning: the whole table is one logical partition, addressed
+    /// by the run date.
+    Unpartitioned,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -36,26 +44,41 @@ impl PartitionKey {
         PartitionKey::Year(year)
     }
 
+    /// Parse the canonical string form of a partition: `YYYY-MM-DDTHH`
+    /// (or `YYYY-MM-DDTHH:00:00`) for hour, `YYYY-MM-DD` for day, `YYYY-MM`
+    /// for month, `YYYY` for year and a plain integer for range. Fields must
+    /// be zero-padded; anything that could name more than one partition is
+    /// rejected.
     pub fn parse(s: &str, partition_type: &PartitionType) -> Result<Self, String> {
         match partition_type {
             PartitionType::Hour => {
-                if let Ok(dt) = NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S") {
-                    Ok(PartitionKey::Hour(dt))
-                } else if let Ok(dt) =
-                    NaiveDateTime::parse_from_str(&format!("{}:00:00", s), "%Y-%m-%dT%H:%M:%S")
-                {
-                    Ok(PartitionKey::Hour(dt))
+                let dt = if matches_shape(s, "dddd-dd-ddTdd:dd:dd") {
+                    NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S").ok()
+                } else if matches_shape(s, "dddd-dd-ddTdd") {
+                    NaiveDateTime::parse_from_str(&format!("{}:00:00", s), "%Y-%m-%dT%H:%M:%S").ok()
                 } else {
-                    Err(format!(
+                    None
+                };
+                match dt {
+                    Some(dt) if dt.time().minute() == 0 && dt.time().second() == 0 => {
+                        Ok(PartitionKey::Hour(dt))
+                    }
+                    Some(_) => Err(format!(
+                        "Ambiguous hour partition: '{}'. Minutes and seconds must be zero",
+                        s
+                    )),
+                    None => Err(format!(
                         "Invalid hour partition: '{}'. Expected format: YYYY-MM-DDTHH",
                         s
-                    ))
+                    )),
                 }
             }
-            PartitionType::Day | PartitionType::IngestionTime => {
-                NaiveDate::parse_from_str(s, "%Y-%m-%d")
+            PartitionType::Day | PartitionType::IngestionTime | PartitionType::Unpartitioned => {
+                Some(s)
+                    .filter(|s| matches_shape(s, "dddd-dd-dd"))
+                    .and_then(|s| NaiveDate::parse_from_str(s, "%Y-%m-%d").ok())
                     .map(PartitionKey::Day)
-                    .map_err(|_| {
+                    .ok_or_else(|| {
                         format!(
                             "Invalid day partition: '{}'. Expected format: YYYY-MM-DD",
                             s
@@ -63,31 +86,30 @@ impl PartitionKey {
                     })
             }
             PartitionType::Month => {
-                let parts: Vec<&str> = s.split('-').collect();
-                if parts.len() == 2 {
-                    let year = parts[0]
-                        .parse::<i32>()
-                        .map_err(|_| format!("Invalid year in month partition: '{}'", s))?;
-                    let month = parts[1]
-                        .parse::<u32>()
-                        .map_err(|_| format!("Invalid month in month partition: '{}'", s))?;
-                    if year <= 0 {
-                        Err(format!("Year must be positive, got: {}", year))
-                    } else if !(1..=12).contains(&month) {
-                        Err(format!("Month must be 1-12, got: {}", month))
-                    } else {
-                        Ok(Self::month_unchecked(year, month))
-                    }
-                } else {
-                    Err(format!(
+                if !matches_shape(s, "dddd-dd") {
+                    return Err(format!(
                         "Invalid month partition: '{}'. Expected format: YYYY-MM",
                         s
-                    ))
+                    ));
+                }
+                let year = s[..4]
+                    .parse::<i32>()
+                    .map_err(|_| format!("Invalid year in month partition: '{}'", s))?;
+                let month = s[5..]
+                    .parse::<u32>()
+                    .map_err(|_| format!("Invalid month in month partition: '{}'", s))?;
+                if year <= 0 {
+                    Err(format!("Year must be positive, got: {}", year))
+                } else if !(1..=12).contains(&month) {
+                    Err(format!("Month must be 1-12, got: {}", month))
+                } else {
+                    Ok(Self::month_unchecked(year, month))
                 }
             }
-            PartitionType::Year => s
-                .parse::<i32>()
-                .map_err(|_| format!("Invalid year partition: '{}'. Expected format: YYYY", s))
+            PartitionType::Year => Some(s)
+                .filter(|s| matches_shape(s, "dddd"))
+                .and_then(|s| s.parse::<i32>().ok())
+                .ok_or_else(|| format!("Invalid year partition: '{}'. Expected format: YYYY", s))
                 .and_then(|year| {
                     if year <= 0 {
                         Err(format!("Year must be positive, got: {}", year))
@@ -95,10 +117,11 @@ impl PartitionKey {
                         Ok(Self::year_unchecked(year))
                     }
                 }),
-            PartitionType::Range => s
-                .parse::<i64>()
+            PartitionType::Range => Some(s)
+                .filter(|s| !s.starts_with('+'))
+                .and_then(|s| s.parse::<i64>().ok())
                 .map(PartitionKey::Range)
-                .map_err(|_| format!("Invalid range partition: '{}'. Expected integer", s)),
+                .ok_or_else(|| format!("Invalid range partition: '{}'. Expected integer", s)),
         }
     }
 
@@ -155,6 +178,87 @@ impl PartitionKey {
         }
     }
 
+    pub fn previous(&self) -> Self {
+        self.offset(-1)
+    }
+
+    /// The partition `n` steps away of the same kind (hours, days, months,
+    /// years or range values); negative `n` moves backwards. Saturates at the
+    /// representable bounds like `next`.
+    pub fn offset(&self, n: i64) -> Self {
+        match self {
+            PartitionKey::Hour(dt) => PartitionKey::Hour(
+                chrono::Duration::try_hours(n)
+                    .and_then(|d| dt.checked_add_signed(d))
+                    .unwrap_or(if n < 0 {
+                        NaiveDateTime::MIN
+                    } else {
+                        NaiveDateTime::MAX
+                    }),
+            ),
+            PartitionKey::Day(d) => {
+                let days = Days::new(n.unsigned_abs());
+                let shifted = if n < 0 {
+                    d.checked_sub_days(days)
+                } else {
+                    d.checked_add_days(days)
+                };
+                PartitionKey::Day(shifted.unwrap_or(if n < 0 {
+                    NaiveDate::MIN
+                } else {
+                    NaiveDate::MAX
+                }))
+            }
+            PartitionKey::Month { year, month } => {
+                let index = (*year as i64 * 12 + (*month as i64 - 1)).saturating_add(n);
+                let year = index.div_euclid(12).clamp(1, i32::MAX as i64) as i32;
+                let month = if index < 12 {
+                    1
+                } else {
+                    index.rem_euclid(12) as u32 + 1
+                };
+                Self::month_unchecked(year, month)
+            }
+            PartitionKey::Year(y) => {
+                let year = (*y as i64).saturating_add(n).clamp(1, i32::MAX as i64);
+                Self::year_unchecked(year as i32)
+            }
+            PartitionKey::Range(v) => PartitionKey::Range(v.saturating_add(n)),
+        }
+    }
+
+    /// Number of partitions from `other` to `self`, so that
+    /// `other.offset(k) == self`. `None` when the keys are of different kinds
+    /// or the count overflows.
+    pub fn duration_since(&self, other: &PartitionKey) -> Option<i64> {
+        match (self, other) {
+            (PartitionKey::Hour(a), PartitionKey::Hour(b)) => Some((*a - *b).num_hours()),
+            (PartitionKey::Day(a), PartitionKey::Day(b)) => Some((*a - *b).num_days()),
+            (
+                PartitionKey::Month { year, month },
+                PartitionKey::Month {
+                    year: other_year,
+                    month: other_month,
+                },
+            ) => Some(
+                (*year as i64 - *other_year as i64) * 12 + (*month as i64 - *other_month as i64),
+            ),
+            (PartitionKey::Year(a), PartitionKey::Year(b)) => Some(*a as i64 - *b as i64),
+            (PartitionKey::Range(a), PartitionKey::Range(b)) => a.checked_sub(*b),
+            _ => None,
+        }
+    }
+
+    /// Whether the date this partition starts on falls in `start..=end`.
+    /// `Range` keys carry no date and are never within.
+    pub fn is_within(&self, start: NaiveDate, end: NaiveDate) -> bool {
+        if matches!(self, PartitionKey::Range(_)) {
+            return false;
+        }
+        let date = self.to_naive_date();
+        start <= date && date <= end
+    }
+
     pub fn to_naive_date(&self) -> NaiveDate {
         match self {
             PartitionKey::Hour(dt) => dt.date(),
@@ -177,8 +281,23 @@ impl PartitionKey {
         }
     }
 
+    /// The partition of `partition_type` containing `date`; the first hour of
+    /// the day for hourly tables. `None` for `Range`, which isn't date based.
+    pub fn for_date(date: NaiveDate, partition_type: &PartitionType) -> Option<Self> {
+        use chrono::Datelike;
+        match partition_type {
+            PartitionType::Hour => Some(PartitionKey::Hour(date.and_time(chrono::NaiveTime::MIN))),
+            PartitionType::Day | PartitionType::IngestionTime | PartitionType::Unpartitioned => {
+                Some(PartitionKey::Day(date))
+            }
+            PartitionType::Month => Some(Self::month_unchecked(date.year(), date.month())),
+            PartitionType::Year => Some(Self::year_unchecked(date.year())),
+            PartitionType::Range => None,
+        }
+    }
+
     pub fn default_for_type(partition_type: &PartitionType) -> Self {
-        use chrono::{Datelike, Timelike, Utc};
+        use chrono::{Datelike, Utc};
         let today = Utc::now().date_naive();
         match partition_type {
             PartitionType::Hour => {
@@ -189,7 +308,9 @@ impl PartitionKey {
                     .unwrap_or(now);
                 PartitionKey::Hour(hour_dt)
             }
-            PartitionType::Day | PartitionType::IngestionTime => PartitionKey::Day(today),
+            PartitionType::Day | PartitionType::IngestionTime | PartitionType::Unpartitioned => {
+                PartitionKey::Day(today)
+            }
             PartitionType::Month => Self::month_unchecked(today.year(), today.month()),
             PartitionType::Year => Self::year_unchecked(today.year()),
             PartitionType::Range => PartitionKey::Range(0),
@@ -197,6 +318,18 @@ impl PartitionKey {
     }
 }
 
+/// Whether `s` has exactly the layout of `shape`, where `d` stands for an
+/// ASCII digit and every other character must match literally.
+fn matches_shape(s: &str, shape: &str) -> bool {
+    s.len() == shape.len()
+        && s.bytes()
+            .zip(shape.bytes())
+            .all(|(c, expected)| match expected {
+                b'd' => c.is_ascii_digit(),
+                _ => c == expected,
+            })
+}
+
 impl fmt::Display for PartitionKey {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -253,7 +386,66 @@ impl From<NaiveDate> for PartitionKey {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// SQL type of a time-partitioning column, which decides how a partition is
+/// matched: `DATE` columns compare directly, `TIMESTAMP` and `DATETIME`
+/// columns are truncated to the partition unit first.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema, PartialEq, Eq, Default)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum PartitionColumnType {
+    #[default]
+    Date,
+    Timestamp,
+    Datetime,
+}
+
+impl PartitionColumnType {
+    pub fn sql_name(&self) -> &'static str {
+        match self {
+            PartitionColumnType::Date => "DATE",
+            PartitionColumnType::Timestamp => "TIMESTAMP",
+            PartitionColumnType::Datetime => "DATETIME",
+        }
+    }
+
+    /// Column type for a partition field of `bq_type`, if it can be one.
+    pub fn from_bq_type(bq_type: &BqType) -> Option<Self> {
+        match bq_type {
+            BqType::Date => Some(PartitionColumnType::Date),
+            BqType::Timestamp => Some(PartitionColumnType::Timestamp),
+            BqType::Datetime => Some(PartitionColumnType::Datetime),
+            _ => None,
+        }
+    }
+}
+
+/// Integer bounds of a `RANGE` partitioned table, as passed to
+/// `RANGE_BUCKET(field, GENERATE_ARRAY(start, end, interval))`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RangeSpec {
+    pub start: i64,
+    pub end: i64,
+    pub interval: i64,
+}
+
+impl RangeSpec {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.interval <= 0 {
+            return Err(format!(
+                "RANGE partition interval must be positive, got {}",
+                self.interval
+            ));
+        }
+        if self.start >= self.end {
+            return Err(format!(
+                "RANGE partition start ({}) must be less than end ({})",
+                self.start, self.end
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct PartitionConfig {
     #[serde(default)]
     pub field: Option<String>,
@@ -267,6 +459,18 @@ pub struct PartitionConfig {
     pub end: Option<i64>,
     #[serde(default)]
     pub interval: Option<i64>,
+    /// Emit `require_partition_filter`, so queries against the table must
+    /// filter on the partition column.
+    #[serde(default)]
+    pub require_filter: bool,
+    /// Days after which BigQuery drops a partition. Not supported for
+    /// `RANGE` partitioning.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expiration_days: Option<u32>,
+    /// Type of `field`. Inferred from the schema when not set; `DATE` when
+    /// neither says otherwise.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub column_type: Option<PartitionColumnType>,
 }
 
 impl PartitionConfig {
@@ -278,6 +482,9 @@ impl PartitionConfig {
             start: None,
             end: None,
             interval: None,
+            require_filter: false,
+            expiration_days: None,
+            column_type: None,
         }
     }
 
@@ -289,6 +496,9 @@ impl PartitionConfig {
             start: None,
             end: None,
             interval: None,
+            require_filter: false,
+            expiration_days: None,
+            column_type: None,
         }
     }
 
@@ -300,6 +510,9 @@ impl PartitionConfig {
             start: None,
             end: None,
             interval: None,
+            require_filter: false,
+            expiration_days: None,
+            column_type: None,
         }
     }
 
@@ -311,6 +524,9 @@ impl PartitionConfig {
             start: None,
             end: None,
             interval: None,
+            require_filter: false,
+            expiration_days: None,
+            column_type: None,
         }
     }
 
@@ -322,6 +538,9 @@ impl PartitionConfig {
             start: Some(start),
             end: Some(end),
             interval: Some(interval),
+            require_filter: false,
+            expiration_days: None,
+            column_type: None,
         }
     }
 
@@ -333,21 +552,109 @@ impl PartitionConfig {
             start: None,
             end: None,
             interval: None,
+            require_filter: false,
+            expiration_days: None,
+            column_type: None,
         }
     }
 
+    /// No partitioning, e.g. a clustered dimension table. This is the
+    /// default when a query's destination has no `partition` block.
+    pub fn unpartitioned() -> Self {
+        Self {
+            field: None,
+            partition_type: PartitionType::Unpartitioned,
+            granularity: None,
+            start: None,
+            end: None,
+            interval: None,
+            require_filter: false,
+            expiration_days: None,
+            column_type: None,
+        }
+    }
+
+    pub fn is_partitioned(&self) -> bool {
+        self.partition_type != PartitionType::Unpartitioned
+    }
+
+    pub fn with_require_filter(mut self, require_filter: bool) -> Self {
+        self.require_filter = require_filter;
+        self
+    }
+
+    pub fn with_column_type(mut self, column_type: PartitionColumnType) -> Self {
+        self.column_type = Some(column_type);
+        self
+    }
+
+    /// Type of the partition column, `DATE` unless set or inferred.
+    pub fn column_type(&self) -> PartitionColumnType {
+        self.column_type.unwrap_or_default()
+    }
+
+    pub fn with_expiration_days(mut self, days: u32) -> Self {
+        self.expiration_days = Some(days);
+        self
+    }
+
+    /// The user column the table is partitioned on; `None` for
+    /// ingestion-time and unpartitioned tables.
     pub fn field_name(&self) -> Option<&str> {
         match self.partition_type {
-            PartitionType::IngestionTime => Some("_PARTITIONDATE"),
+            PartitionType::IngestionTime | PartitionType::Unpartitioned => None,
             _ => self.field.as_deref(),
         }
     }
+
+    /// Column and type that select a partition in SQL. Ingestion-time tables
+    /// use the `_PARTITIONDATE` pseudo-column when daily and `_PARTITIONTIME`
+    /// otherwise.
+    pub fn predicate_column(&self) -> Option<(&str, PartitionColumnType)> {
+        match self.partition_type {
+            PartitionType::IngestionTime => match self.granularity {
+                None | Some(PartitionType::Day) => {
+                    Some(("_PARTITIONDATE", PartitionColumnType::Date))
+                }
+                Some(_) => Some(("_PARTITIONTIME", PartitionColumnType::Timestamp)),
+            },
+            _ => self.field_name().map(|field| (field, self.column_type())),
+        }
+    }
+
+    /// The `start`/`end`/`interval` bounds, when all three are set.
+    pub fn range_spec(&self) -> Option<RangeSpec> {
+        Some(RangeSpec {
+            start: self.start?,
+            end: self.end?,
+            interval: self.interval?,
+        })
+    }
+
+    /// `RANGE` partitioning needs all three bounds with a positive interval
+    /// and `start < end`; other partition types are not checked.
+    pub fn validate_range(&self) -> Result<(), String> {
+        if self.partition_type != PartitionType::Range {
+            return Ok(());
+        }
+        self.range_spec()
+            .ok_or_else(|| "RANGE partitioning requires start, end and interval".to_string())?
+            .validate()
+    }
+
+    /// Step between consecutive partitions when iterating a backfill: the
+    /// range interval for `RANGE` tables, otherwise one time unit.
+    pub fn step(&self) -> Option<i64> {
+        match self.partition_type {
+            PartitionType::Range => self.interval,
+            _ => None,
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use chrono::Timelike;
 
     #[test]
     fn test_partition_key_parse_day() {
@@ -393,6 +700,91 @@ mod tests {
         assert_eq!(key, PartitionKey::Range(1000));
     }
 
+    #[test]
+    fn test_partition_key_offset_and_previous() {
+        let day = PartitionKey::Day(NaiveDate::from_ymd_opt(2024, 3, 1).unwrap());
+        assert_eq!(
+            day.previous(),
+            PartitionKey::Day(NaiveDate::from_ymd_opt(2024, 2, 29).unwrap())
+        );
+        assert_eq!(day.offset(-7).offset(7), day);
+
+        let hour = PartitionKey::parse("2024-01-01T01", &PartitionType::Hour).unwrap();
+        assert_eq!(
+            hour.offset(-2),
+            PartitionKey::parse("2023-12-31T23", &PartitionType::Hour).unwrap()
+        );
+
+        let month = PartitionKey::Month {
+            year: 2024,
+            month: 2,
+        };
+        assert_eq!(
+            month.offset(-14),
+            PartitionKey::Month {
+                year: 2022,
+                month: 12
+            }
+        );
+        assert_eq!(
+            month.offset(11),
+            PartitionKey::Month {
+                year: 2025,
+                month: 1
+            }
+        );
+
+        assert_eq!(PartitionKey::Year(2).offset(-5), PartitionKey::Year(1));
+        assert_eq!(
+            PartitionKey::Range(10).offset(-20),
+            PartitionKey::Range(-10)
+        );
+    }
+
+    #[test]
+    fn test_partition_key_duration_since() {
+        let from = PartitionKey::Month {
+            year: 2023,
+            month: 11,
+        };
+        let to = PartitionKey::Month {
+            year: 2024,
+            month: 2,
+        };
+        assert_eq!(to.duration_since(&from), Some(3));
+        assert_eq!(from.duration_since(&to), Some(-3));
+        assert_eq!(from.offset(3), to);
+
+        let day = PartitionKey::Day(NaiveDate::from_ymd_opt(2024, 1, 1).unwrap());
+        let hour = PartitionKey::parse("2024-01-02T06", &PartitionType::Hour).unwrap();
+        assert_eq!(
+            hour.duration_since(
+                &PartitionKey::parse("2024-01-01T00", &PartitionType::Hour).unwrap()
+            ),
+            Some(30)
+        );
+        assert_eq!(day.duration_since(&hour), None);
+    }
+
+    #[test]
+    fn test_partition_key_is_within() {
+        let start = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let end = NaiveDate::from_ymd_opt(2024, 1, 31).unwrap();
+
+        assert!(PartitionKey::Day(start).is_within(start, end));
+        assert!(PartitionKey::Day(end).is_within(start, end));
+        assert!(!PartitionKey::Day(end.succ_opt().unwrap()).is_within(start, end));
+        assert!(PartitionKey::parse("2024-01-31T23", &PartitionType::Hour)
+            .unwrap()
+            .is_within(start, end));
+        assert!(PartitionKey::Month {
+            year: 2024,
+            month: 1
+        }
+        .is_within(start, end));
+        assert!(!PartitionKey::Range(5).is_within(start, end));
+    }
+
     #[test]
     fn test_partition_key_decorator_day() {
         let key = PartitionKey::Day(NaiveDate::from_ymd_opt(2024, 1, 15).unwrap());
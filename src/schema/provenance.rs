@@ -0,0 +1,14 @@
+use super::field::{BqType, Field};
+
+pub const PROVENANCE_QUERY_COLUMN: &str = "_bqdrift_query";
+pub const PROVENANCE_VERSION_COLUMN: &str = "_bqdrift_version";
+pub const PROVENANCE_RUN_AT_COLUMN: &str = "_bqdrift_run_at";
+
+/// Schema fields for the columns added by `with_provenance_columns`.
+pub fn provenance_fields() -> Vec<Field> {
+    vec![
+        Field::new(PROVENANCE_QUERY_COLUMN, BqType::String).required(),
+        Field::new(PROVENANCE_VERSION_COLUMN, BqType::Int64).required(),
+        Field::new(PROVENANCE_RUN_AT_COLUMN, BqType::Timestamp).required(),
+    ]
+}
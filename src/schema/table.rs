@@ -1,7 +1,24 @@
-use super::field::Field;
+use super::cluster::ClusterConfig;
+use super::field::{BqType, Field, FieldMode};
+use super::partition::{PartitionConfig, PartitionType};
+use crate::error::{BqDriftError, Result};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+const MAX_COLUMN_NAME_LEN: usize = 300;
+
+/// Column name prefixes BigQuery reserves for pseudo-columns, compared
+/// case-insensitively.
+const RESERVED_COLUMN_PREFIXES: [&str; 6] = [
+    "_TABLE_",
+    "_FILE_",
+    "_PARTITION",
+    "_ROW_TIMESTAMP",
+    "__ROOT__",
+    "_COLIDENTIFIER",
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
 pub struct Schema {
     #[serde(default)]
     pub fields: Vec<Field>,
@@ -26,6 +43,40 @@ impl Schema {
         self
     }
 
+    /// SHA-256 of the schema with columns, including RECORD sub-fields,
+    /// sorted by name, so that reordering fields does not change it.
+    pub fn fingerprint_unordered(&self) -> String {
+        let json = serde_json::to_string(&self.canonical_order().fields)
+            .expect("Schema serialization should never fail - all field types are serializable");
+        crate::drift::Checksums::sha256(&json)
+    }
+
+    /// Copy of the schema with fields sorted by name at every level.
+    pub(crate) fn canonical_order(&self) -> Schema {
+        fn sorted(fields: &[Field]) -> Vec<Field> {
+            let mut fields = fields.to_vec();
+            fields.sort_by(|a, b| a.name.cmp(&b.name));
+            for field in &mut fields {
+                if let Some(nested) = &field.fields {
+                    field.fields = Some(sorted(nested));
+                }
+            }
+            fields
+        }
+        Schema::from_fields(sorted(&self.fields))
+    }
+
+    /// Union of both schemas' columns in first-seen order. A column in both
+    /// must have the same type and mode, or `SchemaParse` names it; RECORD
+    /// sub-fields are merged the same way.
+    pub fn merge(&self, other: &Schema) -> Result<Schema> {
+        Ok(Schema::from_fields(merge_fields(
+            "",
+            &self.fields,
+            &other.fields,
+        )?))
+    }
+
     pub fn remove_field(mut self, name: &str) -> Self {
         self.fields.retain(|f| f.name != name);
         self
@@ -38,4 +89,288 @@ impl Schema {
     pub fn has_field(&self, name: &str) -> bool {
         self.fields.iter().any(|f| f.name == name)
     }
+
+    /// Top-level column by name.
+    pub fn column(&self, name: &str) -> Option<&Field> {
+        self.index_of(name).map(|i| &self.fields[i])
+    }
+
+    /// Ordinal position of a top-level column, as in `SELECT *`.
+    pub fn index_of(&self, name: &str) -> Option<usize> {
+        self.fields.iter().position(|f| f.name == name)
+    }
+
+    pub fn len(&self) -> usize {
+        self.fields.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    /// Look up a field by dotted path through RECORD sub-fields, e.g.
+    /// `user.address.zip`.
+    pub fn get_field_path(&self, path: &str) -> Option<&Field> {
+        let mut parts = path.split('.');
+        let mut field = self.get_field(parts.next()?)?;
+        for part in parts {
+            field = field.fields.as_ref()?.iter().find(|f| f.name == part)?;
+        }
+        Some(field)
+    }
+
+    /// Every field including RECORD sub-fields, depth first, with its dotted
+    /// path.
+    pub fn all_fields(&self) -> Vec<(String, &Field)> {
+        fn walk<'f>(fields: &'f [Field], prefix: &str, out: &mut Vec<(String, &'f Field)>) {
+            for field in fields {
+                let path = format!("{}{}", prefix, field.name);
+                out.push((path.clone(), field));
+                if let Some(nested) = &field.fields {
+                    walk(nested, &format!("{}.", path), out);
+                }
+            }
+        }
+
+        let mut out = Vec::with_capacity(self.fields.len());
+        walk(&self.fields, "", &mut out);
+        out
+    }
+
+    /// Reject column names BigQuery would refuse: names must start with a
+    /// letter or underscore, be at most 300 characters and not use a
+    /// reserved pseudo-column prefix. Nested fields are reported by dotted
+    /// path.
+    pub fn validate_names(&self) -> Result<()> {
+        for (path, field) in self.all_fields() {
+            let name = field.name.as_str();
+            let problem = if !name
+                .chars()
+                .next()
+                .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            {
+                "must start with a letter or underscore".to_string()
+            } else if name.chars().count() > MAX_COLUMN_NAME_LEN {
+                format!("is longer than {} characters", MAX_COLUMN_NAME_LEN)
+            } else if let Some(prefix) = RESERVED_COLUMN_PREFIXES
+                .iter()
+                .find(|p| name.to_ascii_uppercase().starts_with(*p))
+            {
+                format!("uses the reserved prefix '{}'", prefix)
+            } else {
+                continue;
+            };
+            return Err(BqDriftError::Schema(format!(
+                "column '{}' {}",
+                path, problem
+            )));
+        }
+        Ok(())
+    }
+
+    /// Reject NUMERIC parameters BigQuery would refuse, see
+    /// `Field::with_precision`.
+    pub fn validate_numeric_parameters(&self) -> Result<()> {
+        for (path, field) in self.all_fields() {
+            if let Some(problem) = field.numeric_parameter_problem() {
+                return Err(BqDriftError::Schema(format!(
+                    "column '{}' {}",
+                    path, problem
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether any column, nested ones included, sets a NUMERIC precision,
+    /// scale or rounding mode. The table API has no fields for these, so
+    /// such tables are created through DDL.
+    pub fn has_column_parameters(&self) -> bool {
+        self.all_fields()
+            .iter()
+            .any(|(_, f)| f.precision.is_some() || f.scale.is_some() || f.rounding_mode.is_some())
+    }
+
+    /// `CREATE TABLE` statement for this schema with partitioning and
+    /// clustering. `table` is used verbatim inside backticks. Policy tags
+    /// have no DDL form; they are attached when the table is created through
+    /// the API. Tables with NUMERIC parameters are created from this DDL.
+    pub fn to_ddl(
+        &self,
+        table: &str,
+        partition: &PartitionConfig,
+        cluster: Option<&ClusterConfig>,
+    ) -> String {
+        self.to_ddl_with_labels(table, partition, cluster, &BTreeMap::new())
+    }
+
+    /// `to_ddl` with table `labels` in the `OPTIONS` clause.
+    pub fn to_ddl_with_labels(
+        &self,
+        table: &str,
+        partition: &PartitionConfig,
+        cluster: Option<&ClusterConfig>,
+        labels: &BTreeMap<String, String>,
+    ) -> String {
+        let columns: Vec<String> = self
+            .fields
+            .iter()
+            .map(|f| format!("  {}", column_ddl(f)))
+            .collect();
+
+        let mut ddl = format!("CREATE TABLE `{}` (\n{}\n)", table, columns.join(",\n"));
+        let partition_expr = self.partition_expr(partition);
+        if let Some(expr) = &partition_expr {
+            ddl.push_str(&format!("\nPARTITION BY {}", expr));
+        }
+        if let Some(cluster) = cluster.filter(|c| !c.is_empty()) {
+            ddl.push_str(&format!("\nCLUSTER BY {}", cluster.fields.join(", ")));
+        }
+        let mut options = Vec::new();
+        if partition_expr.is_some() {
+            if let Some(days) = partition
+                .expiration_days
+                .filter(|_| partition.partition_type != PartitionType::Range)
+            {
+                options.push(format!("partition_expiration_days = {}", days));
+            }
+            if partition.require_filter {
+                options.push("require_partition_filter = TRUE".to_string());
+            }
+        }
+        if !labels.is_empty() {
+            let pairs: Vec<String> = labels
+                .iter()
+                .map(|(key, value)| format!("(\"{}\", \"{}\")", key, value))
+                .collect();
+            options.push(format!("labels = [{}]", pairs.join(", ")));
+        }
+        if !options.is_empty() {
+            ddl.push_str(&format!("\nOPTIONS({})", options.join(", ")));
+        }
+        ddl
+    }
+
+    fn partition_expr(&self, partition: &PartitionConfig) -> Option<String> {
+        if partition.partition_type == PartitionType::IngestionTime {
+            return Some(
+                match partition
+                    .granularity
+                    .as_ref()
+                    .unwrap_or(&PartitionType::Day)
+                {
+                    PartitionType::Hour => "TIMESTAMP_TRUNC(_PARTITIONTIME, HOUR)".to_string(),
+                    PartitionType::Month => "TIMESTAMP_TRUNC(_PARTITIONTIME, MONTH)".to_string(),
+                    PartitionType::Year => "TIMESTAMP_TRUNC(_PARTITIONTIME, YEAR)".to_string(),
+                    _ => "_PARTITIONDATE".to_string(),
+                },
+            );
+        }
+
+        let field = partition.field.as_deref()?;
+        if partition.partition_type == PartitionType::Range {
+            return Some(format!(
+                "RANGE_BUCKET({}, GENERATE_ARRAY({}, {}, {}))",
+                field,
+                partition.start.unwrap_or(0),
+                partition.end.unwrap_or(0),
+                partition.interval.unwrap_or(1)
+            ));
+        }
+
+        let unit = match partition.partition_type {
+            PartitionType::Hour => "HOUR",
+            PartitionType::Month => "MONTH",
+            PartitionType::Year => "YEAR",
+            _ => "DAY",
+        };
+        let expr = match self.get_field(field).map(|f| &f.field_type) {
+            Some(BqType::Timestamp) => format!("TIMESTAMP_TRUNC({}, {})", field, unit),
+            Some(BqType::Datetime) => format!("DATETIME_TRUNC({}, {})", field, unit),
+            _ if unit == "DAY" => field.to_string(),
+            _ => format!("DATE_TRUNC({}, {})", field, unit),
+        };
+        Some(expr)
+    }
+}
+
+pub(super) fn column_ddl(field: &Field) -> String {
+    let mut column = format!("`{}` {}", field.name, type_ddl(field));
+    if let Some(expr) = &field.default_expression {
+        column.push_str(&format!(" DEFAULT {}", expr));
+    }
+    if field.mode == FieldMode::Required {
+        column.push_str(" NOT NULL");
+    }
+    let mut options = Vec::new();
+    if let Some(description) = &field.description {
+        options.push(format!("description={}", string_literal(description)));
+    }
+    if let Some(mode) = &field.rounding_mode {
+        options.push(format!("rounding_mode='{}'", mode.sql_name()));
+    }
+    if !options.is_empty() {
+        column.push_str(&format!(" OPTIONS({})", options.join(", ")));
+    }
+    column
+}
+
+fn type_ddl(field: &Field) -> String {
+    let base = match field.field_type {
+        BqType::Record => {
+            let sub_fields: Vec<String> = field.fields.iter().flatten().map(column_ddl).collect();
+            format!("STRUCT<{}>", sub_fields.join(", "))
+        }
+        ref other => parameterized_type(other, field.precision, field.scale),
+    };
+    if field.mode == FieldMode::Repeated {
+        format!("ARRAY<{}>", base)
+    } else {
+        base
+    }
+}
+
+/// `NUMERIC(P, S)`-style type name, or the plain name without parameters.
+pub(super) fn parameterized_type(
+    field_type: &BqType,
+    precision: Option<u32>,
+    scale: Option<u32>,
+) -> String {
+    match (precision, scale) {
+        (Some(precision), Some(scale)) => {
+            format!("{}({}, {})", field_type.sql_name(), precision, scale)
+        }
+        (Some(precision), None) => format!("{}({})", field_type.sql_name(), precision),
+        _ => field_type.sql_name().to_string(),
+    }
+}
+
+/// Double-quoted string literal for DDL options.
+pub(super) fn string_literal(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn merge_fields(prefix: &str, left: &[Field], right: &[Field]) -> Result<Vec<Field>> {
+    let mut merged = left.to_vec();
+    for field in right {
+        let path = format!("{}{}", prefix, field.name);
+        let Some(existing) = merged.iter_mut().find(|f| f.name == field.name) else {
+            merged.push(field.clone());
+            continue;
+        };
+        if existing.field_type != field.field_type || existing.mode != field.mode {
+            return Err(BqDriftError::SchemaParse(format!(
+                "cannot merge schemas: column '{}' is {} {} in one and {} {} in the other",
+                path,
+                existing.field_type.sql_name(),
+                existing.mode.sql_name(),
+                field.field_type.sql_name(),
+                field.mode.sql_name()
+            )));
+        }
+        if let (Some(ours), Some(theirs)) = (&existing.fields, &field.fields) {
+            existing.fields = Some(merge_fields(&format!("{}.", path), ours, theirs)?);
+        }
+    }
+    Ok(merged)
 }
@@ -322,3 +322,39 @@ fn test_ingestion_time_uses_day_parsing() {
         PartitionKey::Day(NaiveDate::from_ymd_opt(2024, 6, 15).unwrap())
     );
 }
+
+#[test]
+fn test_parse_rejects_ambiguous_partitions() {
+    let err = PartitionKey::parse("2024-6-5", &PartitionType::Day).unwrap_err();
+    assert!(err.contains("Invalid day partition"));
+
+    let err = PartitionKey::parse("2024-06-15T10:30:00", &PartitionType::Hour).unwrap_err();
+    assert!(err.contains("Ambiguous hour partition"));
+
+    let err = PartitionKey::parse("2024-6", &PartitionType::Month).unwrap_err();
+    assert!(err.contains("Invalid month partition"));
+
+    let err = PartitionKey::parse("+2024", &PartitionType::Year).unwrap_err();
+    assert!(err.contains("Invalid year partition"));
+
+    let err = PartitionKey::parse(" 2024-06-15", &PartitionType::Day).unwrap_err();
+    assert!(err.contains("Invalid day partition"));
+
+    let err = PartitionKey::parse("+5", &PartitionType::Range).unwrap_err();
+    assert!(err.contains("Invalid range partition"));
+}
+
+#[test]
+fn test_parse_round_trips_display() {
+    let keys = [
+        PartitionKey::parse("2024-06-15T10", &PartitionType::Hour).unwrap(),
+        PartitionKey::parse("2024-06-15", &PartitionType::Day).unwrap(),
+        PartitionKey::parse("2024-06", &PartitionType::Month).unwrap(),
+        PartitionKey::parse("2024", &PartitionType::Year).unwrap(),
+        PartitionKey::parse("-100", &PartitionType::Range).unwrap(),
+    ];
+    for key in keys {
+        let reparsed = PartitionKey::parse(&key.to_string(), &key.partition_type()).unwrap();
+        assert_eq!(reparsed, key);
+    }
+}
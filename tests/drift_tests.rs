@@ -1,13 +1,12 @@
 use bqdrift::diff::{decode_sql, format_sql_diff, has_changes};
 use bqdrift::dsl::QueryLoader;
-use bqdrift::schema::Schema;
+use bqdrift::schema::{ClusterConfig, Schema};
 use bqdrift::ImmutabilityChecker;
 use bqdrift::{
-    compress_to_base64, decompress_from_base64, Checksums, DriftDetector, DriftState,
-    ExecutionStatus, PartitionState,
+    compress_to_base64, decompress_from_base64, run_drift_check, Checksums, DriftDetector,
+    DriftState, ExecutionStatus, PartitionState, RerunPolicy, StateStore,
 };
 use chrono::{NaiveDate, Utc};
-use std::collections::HashMap;
 use std::path::Path;
 
 fn fixtures_path() -> &'static Path {
@@ -41,24 +40,18 @@ fn create_stored_state_with_version(
     yaml_content: &str,
     schema: &Schema,
 ) -> PartitionState {
-    let checksums = Checksums::compute(sql_content, schema, yaml_content);
-    PartitionState {
-        query_name: query_name.to_string(),
-        partition_date,
-        version,
-        sql_revision: revision,
-        effective_from: NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
-        sql_checksum: checksums.sql,
-        schema_checksum: checksums.schema,
-        yaml_checksum: checksums.yaml,
-        executed_sql_b64: Some(compress_to_base64(sql_content)),
-        upstream_states: HashMap::new(),
-        executed_at: Utc::now(),
-        execution_time_ms: Some(100),
-        rows_written: Some(1000),
-        bytes_processed: Some(10000),
-        status: ExecutionStatus::Success,
+    let mut builder = PartitionState::builder(query_name, partition_date)
+        .with_version(version)
+        .with_effective_from(NaiveDate::from_ymd_opt(2024, 1, 1).unwrap())
+        .with_checksums(Checksums::compute(sql_content, schema, yaml_content))
+        .with_executed_sql(sql_content)
+        .with_execution_time_ms(100)
+        .with_rows_written(1000)
+        .with_bytes_processed(10000);
+    if let Some(revision) = revision {
+        builder = builder.with_sql_revision(revision);
     }
+    builder.build()
 }
 
 #[test]
@@ -287,6 +280,223 @@ fn test_needs_rerun_filters_correctly() {
     assert_eq!(needs_rerun[0].state, DriftState::NeverRun);
 }
 
+#[test]
+fn test_partition_state_builder_defaults() {
+    let date = NaiveDate::from_ymd_opt(2024, 6, 15).unwrap();
+    let before = Utc::now();
+    let state = PartitionState::builder("simple_query", date).build();
+
+    assert_eq!(state.query_name, "simple_query");
+    assert_eq!(state.partition_date, date);
+    assert_eq!(state.version, 1);
+    assert_eq!(state.effective_from, date);
+    assert_eq!(state.status, ExecutionStatus::Success);
+    assert!(state.upstream_states.is_empty());
+    assert!(state.upstream_checksums.is_empty());
+    assert!(state.executed_sql_b64.is_none());
+    assert!(state.executed_at >= before);
+}
+
+#[test]
+fn test_partition_state_builder_setters() {
+    let date = NaiveDate::from_ymd_opt(2024, 6, 15).unwrap();
+    let upstream_at = Utc::now();
+    let state = PartitionState::builder("simple_query", date)
+        .with_version(2)
+        .with_sql_revision(3)
+        .with_executed_sql("SELECT 1")
+        .with_upstream_state("raw.events", upstream_at)
+        .with_upstream_checksum("staging_events", "abc")
+        .with_status(ExecutionStatus::Failed)
+        .build();
+
+    assert_eq!(state.version, 2);
+    assert_eq!(state.sql_revision, Some(3));
+    assert_eq!(
+        decompress_from_base64(state.executed_sql_b64.as_deref().unwrap()).as_deref(),
+        Some("SELECT 1")
+    );
+    assert_eq!(state.upstream_states.get("raw.events"), Some(&upstream_at));
+    assert_eq!(
+        state
+            .upstream_checksums
+            .get("staging_events")
+            .map(String::as_str),
+        Some("abc")
+    );
+    assert_eq!(state.status, ExecutionStatus::Failed);
+}
+
+#[test]
+fn test_partition_state_json_roundtrip() {
+    let schema = Schema::default();
+    let mut state = create_stored_state_for_query(
+        "simple_query",
+        NaiveDate::from_ymd_opt(2024, 6, 15).unwrap(),
+        "SELECT 1",
+        "name: simple_query",
+        &schema,
+    );
+    let upstream_at = chrono::DateTime::parse_from_rfc3339("2024-06-15T08:30:00Z")
+        .unwrap()
+        .with_timezone(&Utc);
+    state
+        .upstream_states
+        .insert("raw.events".to_string(), upstream_at);
+    state.executed_at = upstream_at;
+    state.status = ExecutionStatus::Failed;
+
+    let json = serde_json::to_value(&state).unwrap();
+    assert_eq!(json["partition_date"], "2024-06-15");
+    assert_eq!(json["effective_from"], "2024-01-01");
+    assert_eq!(json["executed_at"], "2024-06-15T08:30:00Z");
+    assert_eq!(
+        json["upstream_states"]["raw.events"],
+        "2024-06-15T08:30:00Z"
+    );
+    assert_eq!(json["status"], "FAILED");
+
+    let restored: PartitionState = serde_json::from_value(json).unwrap();
+    assert_eq!(restored.partition_date, state.partition_date);
+    assert_eq!(restored.sql_checksum, state.sql_checksum);
+    assert_eq!(restored.upstream_states, state.upstream_states);
+    assert_eq!(restored.executed_at, state.executed_at);
+    assert_eq!(restored.status, ExecutionStatus::Failed);
+}
+
+#[test]
+fn test_partition_state_deserializes_without_optional_fields() {
+    let json = serde_json::json!({
+        "query_name": "simple_query",
+        "partition_date": "2024-06-15",
+        "version": 1,
+        "effective_from": "2024-01-01",
+        "sql_checksum": "a",
+        "schema_checksum": "b",
+        "yaml_checksum": "c",
+        "executed_at": "2024-06-15T08:30:00+00:00",
+        "status": "SUCCESS"
+    });
+
+    let state: PartitionState = serde_json::from_value(json).unwrap();
+    assert!(state.sql_revision.is_none());
+    assert!(state.upstream_states.is_empty());
+    assert!(state.upstream_checksums.is_empty());
+    assert_eq!(state.status, ExecutionStatus::Success);
+}
+
+#[test]
+fn test_needs_rerun_policy_controls_failed_partitions() {
+    let loader = QueryLoader::new();
+    let queries = loader.load_dir(fixtures_path()).unwrap();
+    let yaml_contents = loader.load_yaml_contents(fixtures_path()).unwrap();
+
+    let simple_query = queries.iter().find(|q| q.name == "simple_query").unwrap();
+    let yaml_content = yaml_contents.get("simple_query").unwrap();
+
+    let date1 = NaiveDate::from_ymd_opt(2024, 6, 15).unwrap();
+    let date2 = NaiveDate::from_ymd_opt(2024, 6, 16).unwrap();
+    let version = simple_query.get_version_for_date(date1).unwrap();
+
+    let mut stored_failed = create_stored_state_for_query(
+        "simple_query",
+        date1,
+        version.get_sql_for_date(date1),
+        yaml_content,
+        &version.schema,
+    );
+    stored_failed.status = ExecutionStatus::Failed;
+
+    let queries_vec = vec![simple_query.clone()];
+    let detector = DriftDetector::new(&queries_vec, &yaml_contents);
+    let report = detector.detect(&[stored_failed], date1, date2).unwrap();
+
+    assert_eq!(report.needs_rerun().len(), 2);
+
+    let review_failures = RerunPolicy::new().with_include_failed(false);
+    let needs_rerun = report.needs_rerun_with(&review_failures);
+    assert_eq!(needs_rerun.len(), 1);
+    assert_eq!(needs_rerun[0].state, DriftState::NeverRun);
+}
+
+#[test]
+fn test_drift_report_render_table() {
+    let loader = QueryLoader::new();
+    let queries = loader.load_dir(fixtures_path()).unwrap();
+    let yaml_contents = loader.load_yaml_contents(fixtures_path()).unwrap();
+
+    let simple_query = queries.iter().find(|q| q.name == "simple_query").unwrap();
+    let yaml_content = yaml_contents.get("simple_query").unwrap();
+
+    let date1 = NaiveDate::from_ymd_opt(2024, 6, 15).unwrap();
+    let date2 = NaiveDate::from_ymd_opt(2024, 6, 16).unwrap();
+
+    let stored_changed = create_stored_state_for_query(
+        "simple_query",
+        date1,
+        "SELECT 'old' FROM x",
+        yaml_content,
+        &simple_query.get_version_for_date(date1).unwrap().schema,
+    );
+
+    let queries_vec = vec![simple_query.clone()];
+    let detector = DriftDetector::new(&queries_vec, &yaml_contents);
+    let report = detector.detect(&[stored_changed], date1, date2).unwrap();
+
+    let plain = report.render_table(false);
+    let lines: Vec<&str> = plain.lines().collect();
+    assert!(lines[0].starts_with("QUERY"));
+    assert!(lines[1].starts_with("simple_query  2024-06-15  sql_changed"));
+    assert!(lines[2].contains("2024-06-16  never_run"));
+    assert!(!plain.contains("\x1b["));
+    assert!(plain.ends_with("2 partitions: 1 sql_changed, 1 never_run"));
+
+    let colored = report.render_table(true);
+    assert!(colored.contains("\x1b[31msql_changed"));
+    assert!(colored.contains("\x1b[36mnever_run"));
+}
+
+#[test]
+fn test_drift_report_render_table_capped_prioritizes_drifted() {
+    let loader = QueryLoader::new();
+    let queries = loader.load_dir(fixtures_path()).unwrap();
+    let yaml_contents = loader.load_yaml_contents(fixtures_path()).unwrap();
+
+    let simple_query = queries.iter().find(|q| q.name == "simple_query").unwrap();
+    let yaml_content = yaml_contents.get("simple_query").unwrap();
+
+    let from = NaiveDate::from_ymd_opt(2024, 6, 1).unwrap();
+    let to = NaiveDate::from_ymd_opt(2024, 6, 10).unwrap();
+    let version = simple_query.get_version_for_date(from).unwrap();
+
+    let mut stored = Vec::new();
+    let mut date = from;
+    while date <= NaiveDate::from_ymd_opt(2024, 6, 9).unwrap() {
+        stored.push(create_stored_state_for_query(
+            "simple_query",
+            date,
+            version.get_sql_for_date(date),
+            yaml_content,
+            &version.schema,
+        ));
+        date = date.succ_opt().unwrap();
+    }
+
+    let queries_vec = vec![simple_query.clone()];
+    let detector = DriftDetector::new(&queries_vec, &yaml_contents);
+    let report = detector.detect(&stored, from, to).unwrap();
+    assert_eq!(report.partitions.len(), 10);
+
+    let table = report.render_table_capped(2, false);
+    assert!(table.contains("2024-06-10  never_run"));
+    assert!(table.contains("... 8 more"));
+    assert_eq!(table.matches("2024-06-").count(), 2);
+    assert!(table.ends_with("10 partitions: 9 current, 1 never_run"));
+
+    let uncapped = report.render_table_capped(20, false);
+    assert_eq!(uncapped, report.render_table(false));
+}
+
 // ============================================================================
 // Immutability Checker Integration Tests
 // ============================================================================
@@ -662,3 +872,267 @@ fn test_immutability_check_state_without_executed_sql_skipped() {
 
     assert!(report.is_clean());
 }
+
+#[test]
+fn test_run_drift_check_with_empty_store() {
+    let date = NaiveDate::from_ymd_opt(2024, 6, 15).unwrap();
+    let store: Vec<PartitionState> = Vec::new();
+
+    let outcome = run_drift_check(fixtures_path(), &store, date, date).unwrap();
+
+    assert!(outcome.query_count > 0);
+    assert!(outcome.has_drift());
+    assert_eq!(outcome.exit_code(), 1);
+    assert!(outcome
+        .report
+        .partitions
+        .iter()
+        .all(|p| p.state == DriftState::NeverRun));
+}
+
+#[test]
+fn test_state_store_filters_by_date_range() {
+    let date1 = NaiveDate::from_ymd_opt(2024, 6, 15).unwrap();
+    let date2 = NaiveDate::from_ymd_opt(2024, 6, 16).unwrap();
+    let store = vec![
+        PartitionState::builder("simple_query", date1).build(),
+        PartitionState::builder("simple_query", date2).build(),
+    ];
+
+    let states = store.load_states(date2, date2).unwrap();
+    assert_eq!(states.len(), 1);
+    assert_eq!(states[0].partition_date, date2);
+}
+
+#[test]
+fn test_run_drift_check_with_empty_catalog() {
+    let dir = tempfile::tempdir().unwrap();
+    let date = NaiveDate::from_ymd_opt(2024, 6, 15).unwrap();
+    let store: Vec<PartitionState> = Vec::new();
+
+    let outcome = run_drift_check(dir.path(), &store, date, date).unwrap();
+
+    assert_eq!(outcome.query_count, 0);
+    assert!(outcome.report.partitions.is_empty());
+    assert!(!outcome.has_drift());
+    assert_eq!(outcome.exit_code(), 0);
+}
+
+#[test]
+fn test_schema_checksum_detects_nested_field_changes() {
+    use bqdrift::schema::{BqType, Field};
+
+    let nested = |zip: Field| {
+        Schema::from_fields(vec![Field::new("user", BqType::Record).with_fields(vec![
+            Field::new("address", BqType::Record).with_fields(vec![zip]),
+        ])])
+    };
+    let base = nested(Field::new("zip", BqType::String));
+    let required = nested(Field::new("zip", BqType::String).required());
+    let mut added = base.clone();
+    added.fields[0].fields.as_mut().unwrap()[0]
+        .fields
+        .as_mut()
+        .unwrap()
+        .push(Field::new("plus4", BqType::String));
+
+    let checksum = |schema: &Schema| Checksums::compute("SELECT 1", schema, "").schema;
+    assert_ne!(checksum(&base), checksum(&required));
+    assert_ne!(checksum(&base), checksum(&added));
+}
+
+#[test]
+fn test_schema_checksum_detects_policy_tag_removal() {
+    use bqdrift::schema::{BqType, Field};
+
+    let email = Field::new("email", BqType::String).with_description("Contact email");
+    let tagged = Schema::from_fields(vec![email
+        .clone()
+        .with_policy_tag("projects/p/locations/us/taxonomies/1/policyTags/2")]);
+    let untagged = Schema::from_fields(vec![email]);
+
+    let checksum = |schema: &Schema| Checksums::compute("SELECT 1", schema, "").schema;
+    assert_ne!(checksum(&tagged), checksum(&untagged));
+}
+
+#[test]
+fn test_schema_checksum_detects_default_change() {
+    use bqdrift::schema::{BqType, Field};
+
+    let with_default = |expr: &str| {
+        Schema::from_fields(vec![
+            Field::new("loaded_at", BqType::Timestamp).with_default_expression(expr)
+        ])
+    };
+
+    let checksum = |schema: &Schema| Checksums::compute("SELECT 1", schema, "").schema;
+    assert_ne!(
+        checksum(&with_default("CURRENT_TIMESTAMP()")),
+        checksum(&with_default("TIMESTAMP '2024-01-01'"))
+    );
+}
+
+#[test]
+fn test_drift_report_gaps_coalesce_consecutive_partitions() {
+    use bqdrift::{DriftReport, PartitionDrift, PartitionKey};
+
+    let day = |d: u32| PartitionKey::Day(NaiveDate::from_ymd_opt(2024, 3, d).unwrap());
+    let drift = |query: &str, d: u32, state: DriftState| PartitionDrift {
+        query_name: query.to_string(),
+        partition_key: day(d),
+        state,
+        current_version: 1,
+        executed_version: None,
+        caused_by: None,
+        executed_sql_b64: None,
+        current_sql: None,
+    };
+
+    let mut report = DriftReport::new();
+    for d in 5..=8 {
+        report.add(drift("daily_stats", d, DriftState::NeverRun));
+    }
+    report.add(drift("daily_stats", 9, DriftState::Failed));
+    report.add(drift("daily_stats", 10, DriftState::Current));
+    report.add(drift("daily_stats", 11, DriftState::NeverRun));
+    report.add(drift("daily_stats", 12, DriftState::SqlChanged));
+    report.add(drift("agg", 2, DriftState::Failed));
+    report.add(drift("agg", 1, DriftState::NeverRun));
+
+    assert_eq!(
+        report.gaps(),
+        vec![
+            ("agg".to_string(), day(1), day(2)),
+            ("daily_stats".to_string(), day(5), day(9)),
+            ("daily_stats".to_string(), day(11), day(11)),
+        ]
+    );
+    assert!(DriftReport::new().gaps().is_empty());
+}
+
+#[test]
+fn test_partition_expiration_change_is_schema_drift() {
+    let loader = QueryLoader::new();
+    let queries = loader.load_dir(fixtures_path()).unwrap();
+    let yaml_contents = loader.load_yaml_contents(fixtures_path()).unwrap();
+
+    let mut simple_query = queries
+        .iter()
+        .find(|q| q.name == "simple_query")
+        .unwrap()
+        .clone();
+    let yaml_content = yaml_contents.get("simple_query").unwrap();
+
+    let date = NaiveDate::from_ymd_opt(2024, 6, 15).unwrap();
+    let version = simple_query.get_version_for_date(date).unwrap().clone();
+    let sql = version.get_sql_for_date(date);
+    let stored =
+        create_stored_state_for_query("simple_query", date, sql, yaml_content, &version.schema);
+
+    let without_expiration =
+        Checksums::from_query_version(&simple_query, &version, yaml_content, date);
+    assert_eq!(
+        without_expiration,
+        Checksums::from_version(&version, yaml_content, date)
+    );
+
+    simple_query.destination.partition.expiration_days = Some(30);
+    let with_expiration =
+        Checksums::from_query_version(&simple_query, &version, yaml_content, date);
+    assert_eq!(with_expiration.sql, without_expiration.sql);
+    assert_ne!(with_expiration.schema, without_expiration.schema);
+
+    let queries_vec = vec![simple_query];
+    let detector = DriftDetector::new(&queries_vec, &yaml_contents);
+    let report = detector.detect(&[stored], date, date).unwrap();
+    assert_eq!(report.partitions[0].state, DriftState::SchemaChanged);
+}
+
+#[test]
+fn test_cluster_change_is_schema_drift() {
+    let loader = QueryLoader::new();
+    let queries = loader.load_dir(fixtures_path()).unwrap();
+    let yaml_contents = loader.load_yaml_contents(fixtures_path()).unwrap();
+
+    let mut simple_query = queries
+        .iter()
+        .find(|q| q.name == "simple_query")
+        .unwrap()
+        .clone();
+    let yaml_content = yaml_contents.get("simple_query").unwrap();
+
+    let date = NaiveDate::from_ymd_opt(2024, 6, 15).unwrap();
+    let version = simple_query.get_version_for_date(date).unwrap().clone();
+    let sql = version.get_sql_for_date(date);
+    let legacy =
+        create_stored_state_for_query("simple_query", date, sql, yaml_content, &version.schema);
+    let mut stored = legacy.clone();
+    stored.cluster_checksum = Some(Checksums::cluster(simple_query.cluster.as_ref()));
+
+    let queries_vec = vec![simple_query.clone()];
+    let detector = DriftDetector::new(&queries_vec, &yaml_contents);
+    let report = detector.detect(&[stored.clone()], date, date).unwrap();
+    assert_eq!(report.partitions[0].state, DriftState::Current);
+
+    simple_query.cluster = Some(ClusterConfig::new(vec!["date".to_string()]).unwrap());
+    let queries_vec = vec![simple_query];
+    let detector = DriftDetector::new(&queries_vec, &yaml_contents);
+    let report = detector.detect(&[stored], date, date).unwrap();
+    assert_eq!(report.partitions[0].state, DriftState::SchemaChanged);
+
+    // States recorded before clustering was tracked are not compared.
+    let report = detector.detect(&[legacy], date, date).unwrap();
+    assert_eq!(report.partitions[0].state, DriftState::Current);
+}
+
+#[test]
+fn test_unordered_schema_ignores_column_reorder() {
+    let loader = QueryLoader::new();
+    let queries = loader.load_dir(fixtures_path()).unwrap();
+    let yaml_contents = loader.load_yaml_contents(fixtures_path()).unwrap();
+
+    let simple_query = queries
+        .iter()
+        .find(|q| q.name == "simple_query")
+        .unwrap()
+        .clone();
+    let yaml_content = yaml_contents.get("simple_query").unwrap();
+
+    let date = NaiveDate::from_ymd_opt(2024, 6, 15).unwrap();
+    let version = simple_query.get_version_for_date(date).unwrap();
+    let mut stored = create_stored_state_for_query(
+        "simple_query",
+        date,
+        version.get_sql_for_date(date),
+        yaml_content,
+        &version.schema,
+    );
+    stored.unordered_schema_checksum = Some(Checksums::unordered_schema(&simple_query, version));
+    let legacy = PartitionState {
+        unordered_schema_checksum: None,
+        ..stored.clone()
+    };
+
+    let mut reordered = simple_query.clone();
+    reordered.versions[0].schema.fields.reverse();
+    let queries_vec = vec![reordered];
+
+    let strict = DriftDetector::new(&queries_vec, &yaml_contents);
+    let report = strict.detect(&[stored.clone()], date, date).unwrap();
+    assert_eq!(report.partitions[0].state, DriftState::SchemaChanged);
+
+    let relaxed = DriftDetector::new(&queries_vec, &yaml_contents).with_unordered_schema(true);
+    let report = relaxed.detect(&[stored.clone()], date, date).unwrap();
+    assert_eq!(report.partitions[0].state, DriftState::Current);
+
+    // States recorded without the unordered checksum still see the reorder.
+    let report = relaxed.detect(&[legacy], date, date).unwrap();
+    assert_eq!(report.partitions[0].state, DriftState::SchemaChanged);
+
+    let mut retyped = queries_vec[0].clone();
+    retyped.versions[0].schema.fields[0].field_type = bqdrift::BqType::String;
+    let queries_vec = vec![retyped];
+    let relaxed = DriftDetector::new(&queries_vec, &yaml_contents).with_unordered_schema(true);
+    let report = relaxed.detect(&[stored], date, date).unwrap();
+    assert_eq!(report.partitions[0].state, DriftState::SchemaChanged);
+}
@@ -163,6 +163,45 @@ fn test_get_version_for_date_before_first() {
     assert!(version.is_none());
 }
 
+#[test]
+fn test_get_version_for_date_at_effective_from_boundary() {
+    let loader = QueryLoader::new();
+    let query = loader
+        .load_query(fixtures_path().join("analytics/versioned_query.yaml"))
+        .unwrap();
+    let version_on = |y, m, d| {
+        query
+            .get_version_for_date(NaiveDate::from_ymd_opt(y, m, d).unwrap())
+            .map(|v| v.version)
+    };
+
+    assert_eq!(version_on(2023, 12, 31), None);
+    assert_eq!(version_on(2024, 1, 1), Some(1));
+    assert_eq!(version_on(2024, 2, 29), Some(1));
+    assert_eq!(version_on(2024, 3, 1), Some(2));
+    assert_eq!(version_on(2024, 5, 31), Some(2));
+    assert_eq!(version_on(2024, 6, 1), Some(3));
+    assert_eq!(version_on(2024, 9, 1), Some(4));
+}
+
+#[test]
+fn test_version_by_number() {
+    let loader = QueryLoader::new();
+    let query = loader
+        .load_query(fixtures_path().join("analytics/versioned_query.yaml"))
+        .unwrap();
+
+    let v2 = query.version(2).unwrap();
+    assert_eq!(v2.version, 2);
+    assert_eq!(
+        v2.effective_from,
+        NaiveDate::from_ymd_opt(2024, 3, 1).unwrap()
+    );
+    assert_eq!(query.version(4).unwrap().version, 4);
+    assert!(query.version(0).is_none());
+    assert!(query.version(5).is_none());
+}
+
 #[test]
 fn test_get_sql_for_date_with_revision() {
     let loader = QueryLoader::new();
@@ -181,6 +220,24 @@ fn test_get_sql_for_date_with_revision() {
     assert!(sql.contains("COALESCE"));
 }
 
+#[test]
+fn test_revision_for_date() {
+    let loader = QueryLoader::new();
+    let query = loader
+        .load_query(fixtures_path().join("analytics/versioned_query.yaml"))
+        .unwrap();
+    let v2 = query.version(2).unwrap();
+    let revision_on = |d| {
+        v2.revision_for_date(NaiveDate::from_ymd_opt(2024, 3, d).unwrap())
+            .map(|r| r.revision)
+    };
+
+    assert_eq!(revision_on(14), None);
+    assert_eq!(revision_on(15), Some(1));
+    assert_eq!(revision_on(31), Some(1));
+    assert!(query.version(1).unwrap().revisions.is_empty());
+}
+
 #[test]
 fn test_latest_version() {
     let loader = QueryLoader::new();
@@ -509,3 +566,1075 @@ fn test_invariants_v2_added_check() {
         _ => panic!("Expected RowCount check"),
     }
 }
+
+const DEFAULT_INVARIANTS_YAML: &str = r#"
+name: default_invariants_query
+destination:
+  dataset: test_dataset
+  table: default_invariants
+  partition:
+    field: date
+    type: DAY
+
+default_invariants:
+  after:
+    - name: has_rows
+      type: row_count
+      min: 1
+    - name: region_not_null
+      type: null_percentage
+      column: region
+      max_percentage: 0.0
+
+versions:
+  - version: 1
+    effective_from: 2024-01-01
+    source: SELECT @partition_date AS date, 'us' AS region
+    schema:
+      - name: date
+        type: DATE
+      - name: region
+        type: STRING
+    invariants:
+      after:
+        - name: has_rows
+          type: row_count
+          min: 100
+"#;
+
+fn load_yaml_str(yaml: &str) -> bqdrift::Result<bqdrift::QueryDef> {
+    QueryLoader::new().load_from_str(yaml, Path::new("."))
+}
+
+#[test]
+fn test_default_invariants_merged_into_versions() {
+    let query = load_yaml_str(DEFAULT_INVARIANTS_YAML).unwrap();
+
+    assert_eq!(query.default_invariants.after.len(), 2);
+
+    let after = &query.versions[0].invariants.after;
+    let names: Vec<_> = after.iter().map(|i| i.name.as_str()).collect();
+    assert_eq!(names, vec!["region_not_null", "has_rows"]);
+
+    match &after[1].check {
+        InvariantCheck::RowCount { min, .. } => assert_eq!(*min, Some(100)),
+        other => panic!("expected row_count, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_default_invariants_reject_duplicate_names() {
+    let yaml = DEFAULT_INVARIANTS_YAML.replace("name: region_not_null", "name: has_rows");
+    let err = load_yaml_str(&yaml).unwrap_err();
+
+    assert!(err
+        .to_string()
+        .contains("Duplicate default invariant 'has_rows'"));
+}
+
+#[test]
+fn test_resolve_executable_sql_selects_revision_and_wraps_merge() {
+    let loader = QueryLoader::new();
+    let query = loader
+        .load_query(fixtures_path().join("analytics/versioned_query.yaml"))
+        .unwrap();
+    let partition = bqdrift::PartitionKey::Day(NaiveDate::from_ymd_opt(2024, 3, 10).unwrap());
+
+    let before_revision = query
+        .resolve_executable_sql(partition, NaiveDate::from_ymd_opt(2024, 3, 12).unwrap())
+        .unwrap();
+    assert!(before_revision.contains("MERGE `test_dataset.versioned_table` AS target"));
+    assert!(before_revision.contains("DATE(e.created_at) = '2024-03-10'"));
+    assert!(before_revision.contains("target.date = DATE '2024-03-10'"));
+    assert!(!before_revision.contains("COALESCE"));
+    assert!(!before_revision.contains("@partition_date"));
+
+    let after_revision = query
+        .resolve_executable_sql(partition, NaiveDate::from_ymd_opt(2024, 3, 20).unwrap())
+        .unwrap();
+    assert!(after_revision.contains("COALESCE(e.user_id, 'unknown')"));
+}
+
+#[test]
+fn test_resolve_executable_sql_before_first_version_errors() {
+    let loader = QueryLoader::new();
+    let query = loader
+        .load_query(fixtures_path().join("analytics/versioned_query.yaml"))
+        .unwrap();
+    let partition = bqdrift::PartitionKey::Day(NaiveDate::from_ymd_opt(2023, 12, 31).unwrap());
+
+    assert!(query
+        .resolve_executable_sql(partition, NaiveDate::from_ymd_opt(2024, 1, 1).unwrap())
+        .is_err());
+}
+
+#[test]
+fn test_load_from_str_resolves_includes_against_base_dir() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(
+        dir.path().join("query.v1.sql"),
+        "SELECT @partition_date AS date FROM raw.events",
+    )
+    .unwrap();
+    let yaml = OUT_OF_ORDER_VERSIONS_YAML.replace(
+        "source: SELECT @partition_date AS date FROM raw.events",
+        "source: ${{ file: query.v1.sql }}",
+    );
+
+    let loader = QueryLoader::new();
+    let query = loader.load_from_str(&yaml, dir.path()).unwrap();
+    assert_eq!(
+        query.versions[0].sql_content,
+        "SELECT @partition_date AS date FROM raw.events"
+    );
+
+    let err = loader
+        .load_from_str(&yaml, &dir.path().join("missing"))
+        .unwrap_err();
+    assert!(err.to_string().contains("Base directory not found"));
+}
+
+#[test]
+fn test_load_dir_sorts_by_name_and_reports_failing_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let named = |name: &str| {
+        OUT_OF_ORDER_VERSIONS_YAML.replace("name: out_of_order", &format!("name: {}", name))
+    };
+    std::fs::write(dir.path().join("a.yaml"), named("zeta")).unwrap();
+    std::fs::write(dir.path().join("b.yaml"), named("alpha")).unwrap();
+
+    let loader = QueryLoader::new();
+    let (queries, contents) = loader.load_dir_with_contents(dir.path()).unwrap();
+    let names: Vec<&str> = queries.iter().map(|q| q.name.as_str()).collect();
+    assert_eq!(names, vec!["alpha", "zeta"]);
+    assert!(contents["zeta"].contains("name: zeta"));
+
+    let broken = OUT_OF_ORDER_VERSIONS_YAML.replace("    field: date\n", "    field: missing\n");
+    std::fs::write(dir.path().join("c.yaml"), broken).unwrap();
+    let err = loader.load_dir(dir.path()).unwrap_err();
+    assert!(matches!(err, bqdrift::BqDriftError::Validation(_)));
+    let msg = err.to_string();
+    assert!(msg.contains("c.yaml: Query 'out_of_order' v1: partition field 'missing'"));
+}
+
+#[test]
+fn test_load_dir_skips_ignored_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let named = |name: &str| {
+        OUT_OF_ORDER_VERSIONS_YAML.replace("name: out_of_order", &format!("name: {}", name))
+    };
+    std::fs::create_dir_all(dir.path().join("reports/_archive")).unwrap();
+    std::fs::write(dir.path().join("reports/live.yaml"), named("live")).unwrap();
+    std::fs::write(dir.path().join("reports/_archive/old.yaml"), "not: [valid").unwrap();
+    std::fs::write(dir.path().join("next.draft.yaml"), named("draft")).unwrap();
+
+    let loader = QueryLoader::new().with_ignore(["**/_archive/**", "**/*.draft.yaml"]);
+    let names: Vec<String> = loader
+        .load_dir(dir.path())
+        .unwrap()
+        .into_iter()
+        .map(|q| q.name)
+        .collect();
+    assert_eq!(names, vec!["live"]);
+
+    assert!(QueryLoader::new().load_dir(dir.path()).is_err());
+    let err = QueryLoader::new()
+        .with_ignore(["[unclosed"])
+        .load_dir(dir.path())
+        .unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("Invalid ignore pattern '[unclosed'"));
+}
+
+#[test]
+fn test_load_dir_reads_every_document_of_a_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let named = |name: &str| {
+        OUT_OF_ORDER_VERSIONS_YAML.replace("name: out_of_order", &format!("name: {}", name))
+    };
+    let path = dir.path().join("bundle.yaml");
+    std::fs::write(
+        &path,
+        format!("---{}---\n{}", named("first"), named("second")),
+    )
+    .unwrap();
+
+    let (queries, contents) = QueryLoader::new()
+        .load_dir_with_contents(dir.path())
+        .unwrap();
+    let names: Vec<&str> = queries.iter().map(|q| q.name.as_str()).collect();
+    assert_eq!(names, vec!["first", "second"]);
+    assert!(contents["first"].contains("name: first"));
+    assert!(!contents["first"].contains("name: second"));
+
+    let broken = named("third").replace("    field: date\n", "    field: missing\n");
+    std::fs::write(
+        &path,
+        format!("{}---\n{}---\n{}", named("first"), broken, named("second")),
+    )
+    .unwrap();
+    let err = QueryLoader::new().load_dir(dir.path()).unwrap_err();
+    assert!(matches!(err, bqdrift::BqDriftError::Validation(_)));
+    assert!(err
+        .to_string()
+        .contains("bundle.yaml: document 2: Query 'third' v1: partition field 'missing'"));
+}
+
+#[test]
+fn test_load_dir_filtered_by_tags() {
+    let dir = tempfile::tempdir().unwrap();
+    let tagged = |name: &str, tags: &str| {
+        OUT_OF_ORDER_VERSIONS_YAML.replace(
+            "name: out_of_order\n",
+            &format!("name: {}\ntags: [{}]\n", name, tags),
+        )
+    };
+    std::fs::write(dir.path().join("a.yaml"), tagged("hourly_a", "hourly")).unwrap();
+    std::fs::write(
+        dir.path().join("b.yaml"),
+        tagged("hourly_b", "hourly, experimental"),
+    )
+    .unwrap();
+    std::fs::write(dir.path().join("c.yaml"), tagged("daily_c", "daily")).unwrap();
+    std::fs::write(dir.path().join("d.yaml"), tagged("untagged_d", "")).unwrap();
+
+    let loader = QueryLoader::new();
+    let names = |include: &[&str], exclude: &[&str]| -> Vec<String> {
+        loader
+            .load_dir_filtered(dir.path(), include, exclude)
+            .unwrap()
+            .into_iter()
+            .map(|q| q.name)
+            .collect()
+    };
+
+    assert_eq!(names(&[], &[]).len(), 4);
+    assert_eq!(names(&["hourly"], &[]), vec!["hourly_a", "hourly_b"]);
+    assert_eq!(names(&["hourly"], &["experimental"]), vec!["hourly_a"]);
+    assert_eq!(names(&["daily", "hourly"], &[]).len(), 3);
+    assert_eq!(names(&[], &["hourly"]), vec!["daily_c", "untagged_d"]);
+
+    let (queries, contents) = loader
+        .load_dir_with_contents_filtered(dir.path(), &["daily"], &[])
+        .unwrap();
+    assert_eq!(queries.len(), 1);
+    assert_eq!(contents.len(), 1);
+    assert!(contents["daily_c"].contains("tags: [daily]"));
+}
+
+#[test]
+fn test_load_dir_rejects_duplicate_query_names() {
+    let dir = tempfile::tempdir().unwrap();
+    let named = |name: &str, table: &str| {
+        OUT_OF_ORDER_VERSIONS_YAML
+            .replace("name: out_of_order", &format!("name: {}", name))
+            .replace("table: out_of_order", &format!("table: {}", table))
+    };
+    std::fs::write(dir.path().join("a.yaml"), named("sessions", "sessions")).unwrap();
+    std::fs::write(dir.path().join("b.yaml"), named("sessions", "sessions_v2")).unwrap();
+    std::fs::write(dir.path().join("c.yaml"), named("users", "users")).unwrap();
+
+    let err = QueryLoader::new().load_dir(dir.path()).unwrap_err();
+    assert!(matches!(err, bqdrift::BqDriftError::Validation(_)));
+    let message = err.to_string();
+    assert!(message.contains("Duplicate query names: 'sessions' in "));
+    assert!(message.contains(&dir.path().join("a.yaml").display().to_string()));
+    assert!(message.contains(&dir.path().join("b.yaml").display().to_string()));
+    assert!(!message.contains("users"));
+
+    // Sharing a destination only warns
+    std::fs::write(
+        dir.path().join("b.yaml"),
+        named("sessions_copy", "sessions"),
+    )
+    .unwrap();
+    let queries = QueryLoader::new().load_dir(dir.path()).unwrap();
+    assert_eq!(queries.len(), 3);
+}
+
+#[test]
+fn test_load_dir_without_yaml_returns_empty_catalog() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("notes.txt"), "not a query").unwrap();
+
+    let loader = QueryLoader::new();
+    let (queries, contents) = loader.load_dir_with_contents(dir.path()).unwrap();
+
+    assert!(queries.is_empty());
+    assert!(contents.is_empty());
+}
+
+#[test]
+fn test_destination_project_override() {
+    let yaml = DEFAULT_INVARIANTS_YAML.replace(
+        "destination:\n  dataset: test_dataset",
+        "destination:\n  project: reporting-prod\n  dataset: test_dataset",
+    );
+    let query = load_yaml_str(&yaml).unwrap();
+
+    assert_eq!(query.destination.project.as_deref(), Some("reporting-prod"));
+    assert_eq!(
+        query.destination.qualified_table(),
+        "reporting-prod.test_dataset.default_invariants"
+    );
+
+    let partition = bqdrift::PartitionKey::Day(NaiveDate::from_ymd_opt(2024, 6, 15).unwrap());
+    let sql = query
+        .resolve_executable_sql(partition, NaiveDate::from_ymd_opt(2024, 6, 15).unwrap())
+        .unwrap();
+    assert!(sql.contains("MERGE `reporting-prod.test_dataset.default_invariants` AS target"));
+}
+
+#[test]
+fn test_destination_without_project_uses_dataset_table() {
+    let query = load_yaml_str(DEFAULT_INVARIANTS_YAML).unwrap();
+
+    assert!(query.destination.project.is_none());
+    assert_eq!(
+        query.destination.qualified_table(),
+        "test_dataset.default_invariants"
+    );
+}
+
+#[test]
+fn test_provenance_columns_added_to_schema_and_sql() {
+    let yaml = DEFAULT_INVARIANTS_YAML.replace(
+        "name: default_invariants_query\n",
+        "name: default_invariants_query\nprovenance: true\n",
+    );
+    let query = load_yaml_str(&yaml).unwrap();
+    assert!(query.provenance);
+
+    let schema = &query.versions[0].schema;
+    assert_eq!(schema.fields.len(), 5);
+    assert!(schema.has_field("_bqdrift_query"));
+    assert!(schema.has_field("_bqdrift_version"));
+    assert!(schema.has_field("_bqdrift_run_at"));
+
+    let partition = bqdrift::PartitionKey::Day(NaiveDate::from_ymd_opt(2024, 6, 15).unwrap());
+    let sql = query
+        .resolve_executable_sql(partition, NaiveDate::from_ymd_opt(2024, 6, 15).unwrap())
+        .unwrap();
+    assert!(sql.contains("'default_invariants_query' AS _bqdrift_query"));
+    assert!(sql.contains("1 AS _bqdrift_version"));
+    assert!(sql.contains("SELECT '2024-06-15' AS date, 'us' AS region"));
+}
+
+#[test]
+fn test_provenance_disabled_by_default() {
+    let query = load_yaml_str(DEFAULT_INVARIANTS_YAML).unwrap();
+    assert!(!query.provenance);
+    assert!(!query.versions[0].schema.has_field("_bqdrift_query"));
+
+    let partition = bqdrift::PartitionKey::Day(NaiveDate::from_ymd_opt(2024, 6, 15).unwrap());
+    let sql = query
+        .resolve_executable_sql(partition, NaiveDate::from_ymd_opt(2024, 6, 15).unwrap())
+        .unwrap();
+    assert!(!sql.contains("_bqdrift_query"));
+}
+
+const NESTED_SCHEMA_YAML: &str = r#"
+name: nested_query
+destination:
+  dataset: test_dataset
+  table: nested
+  partition:
+    field: date
+    type: DAY
+
+versions:
+  - version: 1
+    effective_from: 2024-01-01
+    source: SELECT @partition_date AS date
+    schema:
+      - name: date
+        type: DATE
+      - name: user
+        type: RECORD
+        fields:
+          - name: name
+            type: STRING
+          - name: address
+            type: RECORD
+            fields:
+              - name: zip
+                type: STRING
+                mode: REQUIRED
+              - name: street
+                type: STRING
+  - version: 2
+    effective_from: 2024-06-01
+    source: SELECT @partition_date AS date
+    schema:
+      base: ${{ versions.1.schema }}
+      add:
+        - name: user.address.plus4
+          type: STRING
+      modify:
+        - name: user.address.zip
+          type: STRING
+          mode: NULLABLE
+      remove:
+        - user.address.street
+"#;
+
+#[test]
+fn test_extended_schema_nested_paths() {
+    let query = load_yaml_str(NESTED_SCHEMA_YAML).unwrap();
+    let v1 = &query.versions[0].schema;
+    let v2 = &query.versions[1].schema;
+
+    assert!(v1.get_field_path("user.address.street").is_some());
+    assert!(v2.get_field_path("user.address.street").is_none());
+    assert!(v2.get_field_path("user.address.plus4").is_some());
+    assert!(v2.get_field_path("user.name").is_some());
+
+    let zip = v2.get_field_path("user.address.zip").unwrap();
+    assert_eq!(zip.name, "zip");
+    assert_eq!(
+        v1.get_field_path("user.address.zip").unwrap().mode,
+        bqdrift::FieldMode::Required
+    );
+    assert_eq!(zip.mode, bqdrift::FieldMode::Nullable);
+}
+
+#[test]
+fn test_extended_schema_nested_path_unknown_parent() {
+    let yaml = NESTED_SCHEMA_YAML.replace("- name: user.address.plus4", "- name: account.plus4");
+    let err = load_yaml_str(&yaml).unwrap_err();
+    assert!(err.to_string().contains("'account' not found"));
+}
+
+const OUT_OF_ORDER_VERSIONS_YAML: &str = r#"
+name: out_of_order
+destination:
+  dataset: test_dataset
+  table: out_of_order
+  partition:
+    field: date
+    type: DAY
+
+versions:
+  - version: 3
+    effective_from: 2024-09-01
+    source: ${{ versions.2.sql }}
+    schema: ${{ versions.2.schema }}
+  - version: 1
+    effective_from: 2024-01-01
+    source: SELECT @partition_date AS date FROM raw.events
+    schema:
+      - name: date
+        type: DATE
+  - version: 2
+    effective_from: 2024-06-01
+    source: SELECT @partition_date AS date, 1 AS n FROM raw.events
+    schema:
+      base: ${{ versions.1.schema }}
+      add:
+        - name: n
+          type: INT64
+"#;
+
+#[test]
+fn test_version_refs_resolve_regardless_of_listing_order() {
+    let query = load_yaml_str(OUT_OF_ORDER_VERSIONS_YAML).unwrap();
+    let versions: Vec<u32> = query.versions.iter().map(|v| v.version).collect();
+    assert_eq!(versions, vec![1, 2, 3]);
+
+    let v2 = &query.versions[1];
+    let v3 = &query.versions[2];
+    assert!(v3.schema.has_field("n"));
+    assert_eq!(v3.sql_content, v2.sql_content);
+    assert!(v3.dependencies.contains("raw.events"));
+}
+
+#[test]
+fn test_schema_migration_plan() {
+    let query = load_yaml_str(OUT_OF_ORDER_VERSIONS_YAML).unwrap();
+
+    let plan = query.schema_migration_plan(1, 2).unwrap();
+    let sql: Vec<String> = plan.iter().map(|s| s.to_string()).collect();
+    assert_eq!(
+        sql,
+        vec!["ALTER TABLE `test_dataset.out_of_order` ADD COLUMN `n` INT64;"]
+    );
+    assert!(query.schema_migration_plan(2, 3).unwrap().is_empty());
+
+    let err = query.schema_migration_plan(1, 4).unwrap_err();
+    assert!(err.to_string().contains("has no version 4"));
+}
+
+#[test]
+fn test_version_ref_to_higher_numbered_version_rejected() {
+    let yaml = OUT_OF_ORDER_VERSIONS_YAML.replace(
+        "base: ${{ versions.1.schema }}",
+        "base: ${{ versions.3.schema }}",
+    );
+    let err = load_yaml_str(&yaml).unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("must reference a lower-numbered version"));
+}
+
+#[test]
+fn test_version_ref_to_later_effective_version_rejected() {
+    let yaml = OUT_OF_ORDER_VERSIONS_YAML
+        .replace("effective_from: 2024-06-01", "effective_from: 2024-12-01");
+    let err = load_yaml_str(&yaml).unwrap_err();
+    assert!(err.to_string().contains("v3 schema reference"));
+    assert!(err.to_string().contains("v2 takes effect later"));
+}
+
+#[test]
+fn test_version_ref_field_mismatch_rejected() {
+    let yaml = OUT_OF_ORDER_VERSIONS_YAML.replace(
+        "schema: ${{ versions.2.schema }}",
+        "schema: ${{ versions.2.sql }}",
+    );
+    let err = load_yaml_str(&yaml).unwrap_err();
+    assert!(err.to_string().contains("expected 'schema' field"));
+}
+
+#[test]
+fn test_require_filter_merge_keeps_partition_predicate() {
+    let yaml = NESTED_SCHEMA_YAML.replace(
+        "    type: DAY\n",
+        "    type: DAY\n    require_filter: true\n",
+    );
+    let query = load_yaml_str(&yaml).unwrap();
+    assert!(query.destination.partition.require_filter);
+
+    let date = NaiveDate::from_ymd_opt(2024, 7, 1).unwrap();
+    let sql = query
+        .resolve_executable_sql(bqdrift::schema::PartitionKey::Day(date), date)
+        .unwrap();
+    assert!(sql.contains("WHEN NOT MATCHED BY SOURCE AND target.date = DATE '2024-07-01'"));
+}
+
+#[test]
+fn test_blocked_version_refuses_writes() {
+    let yaml = OUT_OF_ORDER_VERSIONS_YAML.replace(
+        "    effective_from: 2024-06-01\n",
+        "    effective_from: 2024-06-01\n    blocked_reason: double-counts refunds\n",
+    );
+    let query = load_yaml_str(&yaml).unwrap();
+    assert!(query.versions[1].is_blocked());
+
+    let blocked = bqdrift::PartitionKey::Day(NaiveDate::from_ymd_opt(2024, 7, 1).unwrap());
+    let err = query.writable_version(&blocked).unwrap_err();
+    assert!(matches!(err, bqdrift::BqDriftError::VersionBlocked(_)));
+    assert!(err.to_string().contains("'out_of_order' v2"));
+    assert!(err.to_string().contains("double-counts refunds"));
+
+    let open = bqdrift::PartitionKey::Day(NaiveDate::from_ymd_opt(2024, 9, 1).unwrap());
+    assert_eq!(query.writable_version(&open).unwrap().version, 3);
+}
+
+#[test]
+fn test_cluster_field_missing_from_schema_rejected() {
+    let yaml = OUT_OF_ORDER_VERSIONS_YAML.replace(
+        "    type: DAY\n",
+        "    type: DAY\n  cluster:\n    - date\n    - n\n",
+    );
+    let err = load_yaml_str(&yaml).unwrap_err();
+    assert!(matches!(err, bqdrift::BqDriftError::Validation(_)));
+    assert!(err
+        .to_string()
+        .contains("cluster field 'n' not found in v1 schema"));
+
+    let yaml = OUT_OF_ORDER_VERSIONS_YAML
+        .replace("    type: DAY\n", "    type: DAY\n  cluster:\n    - date\n");
+    assert!(load_yaml_str(&yaml).unwrap().cluster.is_some());
+}
+
+#[test]
+fn test_more_than_four_cluster_fields_rejected() {
+    let yaml = OUT_OF_ORDER_VERSIONS_YAML.replace(
+        "    type: DAY\n",
+        "    type: DAY\n  cluster: [date, a, b, c, d]\n",
+    );
+    let err = load_yaml_str(&yaml).unwrap_err();
+    assert!(matches!(err, bqdrift::BqDriftError::Validation(_)));
+    assert!(err.to_string().contains("cluster field 'd' exceeds"));
+}
+
+#[test]
+fn test_field_default_alias_and_blank_default_rejected() {
+    let with_default = |default: &str| {
+        OUT_OF_ORDER_VERSIONS_YAML.replace(
+            "      - name: date\n        type: DATE\n",
+            &format!(
+                "      - name: date\n        type: DATE\n      - name: loaded_at\n        type: TIMESTAMP\n        default: {}\n",
+                default
+            ),
+        )
+    };
+
+    let query = load_yaml_str(&with_default("CURRENT_TIMESTAMP()")).unwrap();
+    let loaded_at = query.versions[0].schema.get_field("loaded_at").unwrap();
+    assert_eq!(
+        loaded_at.default_expression.as_deref(),
+        Some("CURRENT_TIMESTAMP()")
+    );
+
+    let err = load_yaml_str(&with_default("\"  \"")).unwrap_err();
+    assert!(matches!(err, bqdrift::BqDriftError::Validation(_)));
+    assert!(err
+        .to_string()
+        .contains("v1: field 'loaded_at' has an empty default"));
+}
+
+#[test]
+fn test_range_partition_bounds_validated() {
+    let range = |bounds: &str| {
+        OUT_OF_ORDER_VERSIONS_YAML
+            .replace(
+                "    field: date\n    type: DAY\n",
+                &format!("    field: bucket\n    type: RANGE\n{}", bounds),
+            )
+            .replace(
+                "      - name: date\n        type: DATE\n",
+                "      - name: date\n        type: DATE\n      - name: bucket\n        type: INT64\n",
+            )
+    };
+
+    let query = load_yaml_str(&range("    start: 0\n    end: 1000\n    interval: 100\n")).unwrap();
+    let spec = query.destination.partition.range_spec().unwrap();
+    assert_eq!((spec.start, spec.end, spec.interval), (0, 1000, 100));
+
+    let err = load_yaml_str(&range("    start: 0\n    end: 1000\n")).unwrap_err();
+    assert!(matches!(err, bqdrift::BqDriftError::Validation(_)));
+    assert!(err.to_string().contains("requires start, end and interval"));
+
+    let err = load_yaml_str(&range("    start: 0\n    end: 1000\n    interval: 0\n")).unwrap_err();
+    assert!(err.to_string().contains("interval must be positive"));
+
+    let err =
+        load_yaml_str(&range("    start: 500\n    end: 500\n    interval: 10\n")).unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("start (500) must be less than end (500)"));
+}
+
+#[test]
+fn test_destination_without_partition_is_unpartitioned() {
+    let yaml = r#"
+name: customers
+destination:
+  dataset: dims
+  table: customers
+  cluster: [region]
+
+versions:
+  - version: 1
+    effective_from: 2024-01-01
+    source: SELECT id, region FROM raw.customers
+    schema:
+      - name: id
+        type: INT64
+      - name: region
+        type: STRING
+"#;
+    let query = load_yaml_str(yaml).unwrap();
+    assert!(!query.destination.partition.is_partitioned());
+    assert!(query.cluster.is_some());
+
+    let day = bqdrift::PartitionKey::Day(NaiveDate::from_ymd_opt(2024, 6, 1).unwrap());
+    let plan = query
+        .merge_plan(day, NaiveDate::from_ymd_opt(2024, 6, 1).unwrap())
+        .unwrap();
+    assert_eq!(
+        plan.strategy,
+        bqdrift::executor::MergeStrategy::ReplaceTable
+    );
+
+    let ddl = query.versions[0].schema.to_ddl(
+        "dims.customers",
+        &query.destination.partition,
+        query.cluster.as_ref(),
+    );
+    assert!(!ddl.contains("PARTITION BY"));
+    assert!(ddl.ends_with("CLUSTER BY region"));
+
+    let result = bqdrift::QueryValidator::validate(&query);
+    assert!(result.warnings.is_empty());
+}
+
+#[test]
+fn test_invalid_column_name_rejected_at_load() {
+    let yaml = OUT_OF_ORDER_VERSIONS_YAML.replace(
+        "      add:\n",
+        "      add:\n        - name: 7day_total\n          type: INT64\n",
+    );
+    let err = load_yaml_str(&yaml).unwrap_err();
+    assert!(matches!(err, bqdrift::BqDriftError::Validation(_)));
+    assert!(err.to_string().contains(
+        "Query 'out_of_order' v2: column '7day_total' must start with a letter or underscore"
+    ));
+}
+
+#[test]
+fn test_narrowing_schema_change_rejected_at_load() {
+    let yaml = OUT_OF_ORDER_VERSIONS_YAML.replace(
+        "      add:\n        - name: n\n          type: INT64\n",
+        "      modify:\n        - name: date\n          type: STRING\n      add:\n        - name: n\n          type: INT64\n          mode: REQUIRED\n",
+    );
+    let err = load_yaml_str(&yaml).unwrap_err();
+    assert!(matches!(err, bqdrift::BqDriftError::Validation(_)));
+    let msg = err.to_string();
+    assert!(msg.contains("Query 'out_of_order' v2 (from v1): incompatible schema change"));
+    assert!(msg.contains("new column 'n' is REQUIRED"));
+    assert!(msg.contains("column 'date' changes type from DATE to STRING"));
+}
+
+#[test]
+fn test_partition_column_type_inferred_and_checked() {
+    let with_column = |column: &str| {
+        OUT_OF_ORDER_VERSIONS_YAML
+            .replace("    field: date\n", "    field: event_ts\n")
+            .replace(
+                "      - name: date\n        type: DATE\n",
+                "      - name: date\n        type: DATE\n      - name: event_ts\n        type: TIMESTAMP\n",
+            )
+            .replace("    type: DAY\n", &format!("    type: DAY\n{}", column))
+    };
+
+    let query = load_yaml_str(&with_column("")).unwrap();
+    assert_eq!(
+        query.destination.partition.column_type(),
+        bqdrift::PartitionColumnType::Timestamp
+    );
+
+    let date = NaiveDate::from_ymd_opt(2024, 7, 1).unwrap();
+    let plan = query
+        .merge_plan(bqdrift::PartitionKey::Day(date), date)
+        .unwrap();
+    assert_eq!(
+        plan.partition_condition,
+        "DATE(target.event_ts) = DATE '2024-07-01'"
+    );
+
+    let err = load_yaml_str(&with_column("    column_type: DATETIME\n")).unwrap_err();
+    assert!(matches!(err, bqdrift::BqDriftError::Validation(_)));
+    assert!(err.to_string().contains(
+        "partition column_type DATETIME does not match field 'event_ts' of type TIMESTAMP"
+    ));
+
+    let err = load_yaml_str(&with_column("    column_type: DATE\n")).unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("partition column_type DATE does not match field 'event_ts' of type TIMESTAMP"));
+
+    let query = load_yaml_str(&with_column("    column_type: TIMESTAMP\n")).unwrap();
+    assert_eq!(
+        query.destination.partition.column_type,
+        Some(bqdrift::PartitionColumnType::Timestamp)
+    );
+}
+
+#[test]
+fn test_ingestion_time_partition_uses_pseudo_columns() {
+    let ingestion = |granularity: &str| {
+        OUT_OF_ORDER_VERSIONS_YAML.replace(
+            "    field: date\n    type: DAY\n",
+            &format!(
+                "    type: INGESTION_TIME\n    granularity: {}\n",
+                granularity
+            ),
+        )
+    };
+    let date = NaiveDate::from_ymd_opt(2024, 7, 1).unwrap();
+    let key = bqdrift::PartitionKey::Day(date);
+
+    let query = load_yaml_str(&ingestion("DAY")).unwrap();
+    assert_eq!(query.destination.partition.field_name(), None);
+    let plan = query.merge_plan(key, date).unwrap();
+    assert_eq!(
+        plan.partition_condition,
+        "target._PARTITIONDATE = DATE '2024-07-01'"
+    );
+    assert_eq!(key.decorator(), "$20240701");
+    let sql = plan.render();
+    assert!(sql.contains("THEN INSERT (_PARTITIONTIME, `date`"));
+    assert!(sql.contains("VALUES (TIMESTAMP '2024-07-01', `date`"));
+    assert!(!sql.contains("INSERT ROW"));
+
+    let query = load_yaml_str(&ingestion("MONTH")).unwrap();
+    let month = bqdrift::PartitionKey::Month {
+        year: 2024,
+        month: 7,
+    };
+    let plan = query.merge_plan(month, date).unwrap();
+    assert_eq!(
+        plan.partition_condition,
+        "TIMESTAMP_TRUNC(target._PARTITIONTIME, MONTH) = TIMESTAMP '2024-07-01'"
+    );
+}
+
+#[test]
+fn test_numeric_scale_above_precision_rejected() {
+    let yaml = OUT_OF_ORDER_VERSIONS_YAML.replace(
+        "      - name: date\n        type: DATE\n",
+        "      - name: date\n        type: DATE\n      - name: amount\n        type: NUMERIC\n        precision: 5\n        scale: 9\n",
+    );
+    let err = load_yaml_str(&yaml).unwrap_err();
+    assert!(matches!(err, bqdrift::BqDriftError::Validation(_)));
+    assert!(err
+        .to_string()
+        .contains("v1: column 'amount' has scale 9 greater than precision 5"));
+}
+
+#[test]
+fn test_partition_field_checked_against_schema() {
+    let yaml = OUT_OF_ORDER_VERSIONS_YAML.replace("    field: date\n", "    field: event_date\n");
+    let err = load_yaml_str(&yaml).unwrap_err();
+    assert!(matches!(err, bqdrift::BqDriftError::Validation(_)));
+    assert!(err
+        .to_string()
+        .contains("v1: partition field 'event_date' not found in schema (fields: date)"));
+
+    let yaml = OUT_OF_ORDER_VERSIONS_YAML.replace("    type: DAY\n", "    type: HOUR\n");
+    let err = load_yaml_str(&yaml).unwrap_err();
+    assert!(err.to_string().contains(
+        "partition field 'date' is DATE, but HOUR partitioning needs TIMESTAMP or DATETIME"
+    ));
+
+    let yaml = OUT_OF_ORDER_VERSIONS_YAML.replace(
+        "    field: date\n    type: DAY\n",
+        "    field: date\n    type: RANGE\n    start: 0\n    end: 10\n    interval: 1\n",
+    );
+    let err = load_yaml_str(&yaml).unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("is DATE, but RANGE partitioning needs INT64"));
+}
+
+#[test]
+fn test_repeated_columns_stay_out_of_merge_predicate() {
+    let yaml = OUT_OF_ORDER_VERSIONS_YAML.replace(
+        "      - name: date\n        type: DATE\n",
+        "      - name: date\n        type: DATE\n      - name: tags\n        type: STRING\n        mode: REPEATED\n",
+    );
+    let query = load_yaml_str(&yaml).unwrap();
+    let date = NaiveDate::from_ymd_opt(2024, 7, 1).unwrap();
+    let sql = query
+        .merge_plan(bqdrift::PartitionKey::Day(date), date)
+        .unwrap()
+        .render();
+    assert!(sql.contains("ON FALSE"));
+    assert!(
+        sql.contains("WHEN NOT MATCHED BY SOURCE AND target.date = DATE '2024-07-01' THEN DELETE")
+    );
+    assert!(sql.contains("WHEN NOT MATCHED BY TARGET THEN INSERT ROW"));
+    assert!(!sql.contains("tags"));
+
+    let yaml = OUT_OF_ORDER_VERSIONS_YAML.replace(
+        "      - name: date\n        type: DATE\n",
+        "      - name: date\n        type: DATE\n        mode: REPEATED\n",
+    );
+    let err = load_yaml_str(&yaml).unwrap_err();
+    assert!(matches!(err, bqdrift::BqDriftError::Validation(_)));
+    assert!(err
+        .to_string()
+        .contains("v1: partition field 'date' is REPEATED"));
+}
+
+#[test]
+fn test_destination_labels_parsed_and_validated() {
+    let with_labels = |labels: &str| {
+        OUT_OF_ORDER_VERSIONS_YAML.replace(
+            "    type: DAY\n",
+            &format!("    type: DAY\n  labels:\n{}", labels),
+        )
+    };
+
+    let query = load_yaml_str(&with_labels("    team: growth\n    cost-center: \"42\"\n")).unwrap();
+    let labels: Vec<(&str, &str)> = query
+        .destination
+        .labels
+        .iter()
+        .map(|(k, v)| (k.as_str(), v.as_str()))
+        .collect();
+    assert_eq!(labels, vec![("cost-center", "42"), ("team", "growth")]);
+
+    let ddl = query.versions[0].schema.to_ddl_with_labels(
+        "test_dataset.out_of_order",
+        &query.destination.partition,
+        None,
+        &query.destination.labels,
+    );
+    assert!(ddl.ends_with("OPTIONS(labels = [(\"cost-center\", \"42\"), (\"team\", \"growth\")])"));
+
+    let err = load_yaml_str(&with_labels("    Team: growth\n")).unwrap_err();
+    assert!(matches!(err, bqdrift::BqDriftError::Validation(_)));
+    assert!(err.to_string().contains("label key 'Team' must be"));
+
+    let err = load_yaml_str(&with_labels("    team: Growth Team\n")).unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("label 'team' value 'Growth Team' must be"));
+
+    let err = load_yaml_str(&with_labels("    1team: growth\n")).unwrap_err();
+    assert!(err.to_string().contains("starting with a letter"));
+}
+
+fn dependent_query(name: &str, reads: &str) -> bqdrift::QueryDef {
+    load_yaml_str(&format!(
+        r#"
+name: {name}
+destination:
+  dataset: analytics
+  table: {name}
+  partition:
+    field: date
+    type: DAY
+versions:
+  - version: 1
+    effective_from: 2024-01-01
+    source: SELECT date FROM {reads} WHERE date = @partition_date
+    schema:
+      - name: date
+        type: DATE
+"#
+    ))
+    .unwrap()
+}
+
+#[test]
+fn test_topo_sort_orders_producers_first() {
+    let queries = vec![
+        dependent_query("monthly", "analytics.weekly"),
+        dependent_query("weekly", "analytics.daily"),
+        dependent_query("daily", "raw.events"),
+        dependent_query("audit", "analytics.audit"),
+    ];
+    let order: Vec<&str> = QueryLoader::topo_sort(&queries)
+        .unwrap()
+        .into_iter()
+        .map(|i| queries[i].name.as_str())
+        .collect();
+    assert_eq!(order, vec!["daily", "audit", "weekly", "monthly"]);
+
+    let cyclic = vec![
+        dependent_query("a", "analytics.c"),
+        dependent_query("b", "analytics.a"),
+        dependent_query("c", "analytics.b"),
+    ];
+    let err = QueryLoader::topo_sort(&cyclic).unwrap_err();
+    assert!(matches!(err, bqdrift::BqDriftError::Validation(_)));
+    assert!(err
+        .to_string()
+        .contains("Dependency cycle between queries: b -> c -> a -> b"));
+}
+
+#[test]
+fn test_dependency_graph_uses_destination_project() {
+    let mut daily = dependent_query("daily", "raw.events");
+    daily.destination.project = Some("other-proj".to_string());
+    let queries = vec![
+        dependent_query("weekly", "other-proj.analytics.daily"),
+        daily,
+    ];
+    let graph = bqdrift::DependencyGraph::from_queries(&queries);
+
+    let json: serde_json::Value = serde_json::from_str(&graph.to_json()).unwrap();
+    assert_eq!(
+        json["edges"],
+        serde_json::json!([
+            {"from": "other-proj.analytics.daily", "to": "analytics.weekly"},
+            {"from": "raw.events", "to": "other-proj.analytics.daily"},
+        ])
+    );
+}
+
+#[test]
+fn test_dependency_graph_export() {
+    let queries = vec![
+        dependent_query("weekly", "analytics.daily"),
+        dependent_query("daily", "raw.events"),
+    ];
+    let graph = bqdrift::DependencyGraph::from_queries(&queries);
+
+    let dot = graph.to_dot();
+    assert!(dot.starts_with("digraph bqdrift {"));
+    assert!(dot.contains("\"raw.events\" [shape=ellipse, style=dashed];"));
+    assert!(dot.contains("\"analytics.daily\" [shape=box, label=\"analytics.daily\\n(daily)\"];"));
+    assert!(dot.contains("\"raw.events\" -> \"analytics.daily\";"));
+    assert!(dot.contains("\"analytics.daily\" -> \"analytics.weekly\";"));
+
+    let json: serde_json::Value = serde_json::from_str(&graph.to_json()).unwrap();
+    assert_eq!(
+        json["nodes"],
+        serde_json::json!([
+            {"id": "analytics.daily", "kind": "query", "query": "daily"},
+            {"id": "analytics.weekly", "kind": "query", "query": "weekly"},
+            {"id": "raw.events", "kind": "source"},
+        ])
+    );
+    assert_eq!(
+        json["edges"],
+        serde_json::json!([
+            {"from": "analytics.daily", "to": "analytics.weekly"},
+            {"from": "raw.events", "to": "analytics.daily"},
+        ])
+    );
+}
+
+#[test]
+fn test_unresolved_reference_in_sql_rejected() {
+    let yaml = OUT_OF_ORDER_VERSIONS_YAML.replace(
+        "source: SELECT @partition_date AS date, 1 AS n FROM raw.events",
+        "source: SELECT *, 1 AS n FROM (${{ versions.1.sql }})",
+    );
+    let err = load_yaml_str(&yaml).unwrap_err();
+    assert!(matches!(err, bqdrift::BqDriftError::VariableResolution(_)));
+    assert!(err
+        .to_string()
+        .contains("Query 'out_of_order' v2: unresolved reference '${{ versions.1.sql }}' in SQL"));
+
+    let yaml = OUT_OF_ORDER_VERSIONS_YAML.replace(
+        "source: SELECT @partition_date AS date FROM raw.events",
+        "source: SELECT @partition_date AS date FROM ${{ versions.0.sql",
+    );
+    let err = load_yaml_str(&yaml).unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("v1: unresolved reference '${{ versions.0.sql'"));
+}
+
+#[test]
+fn test_loader_vars_select_conditional_branch() {
+    let yaml = OUT_OF_ORDER_VERSIONS_YAML.replace(
+        "source: SELECT @partition_date AS date FROM raw.events",
+        "source: SELECT @partition_date AS date FROM {% if env == \"prod\" %}raw.events{% else %}raw.events_sample{% endif %}",
+    );
+    let load = |env: &str| {
+        QueryLoader::new()
+            .with_vars([("env", env)])
+            .load_from_str(&yaml, Path::new("."))
+            .unwrap()
+    };
+
+    assert!(load("prod").versions[0].dependencies.contains("raw.events"));
+    assert!(load("dev").versions[0]
+        .dependencies
+        .contains("raw.events_sample"));
+
+    let err = load_yaml_str(&yaml).unwrap_err();
+    assert!(err.to_string().contains("Undefined variable 'env'"));
+}
+
+#[test]
+fn test_previous_names_parsed() {
+    let query = load_yaml_str(OUT_OF_ORDER_VERSIONS_YAML).unwrap();
+    assert!(query.previous_names.is_empty());
+
+    let yaml = OUT_OF_ORDER_VERSIONS_YAML.replacen(
+        "name: out_of_order\n",
+        "name: out_of_order\nprevious_names:\n  - unordered\n  - legacy_order\n",
+        1,
+    );
+    let query = load_yaml_str(&yaml).unwrap();
+    assert_eq!(query.previous_names, vec!["unordered", "legacy_order"]);
+}
@@ -1,5 +1,6 @@
 use bqdrift::schema::{
-    BqType, ClusterConfig, Field, FieldMode, PartitionConfig, PartitionType, Schema,
+    AlterStatement, BqType, ClusterConfig, EvolutionWarning, Field, FieldMode, PartitionConfig,
+    PartitionType, RoundingMode, Schema,
 };
 
 #[test]
@@ -70,6 +71,22 @@ fn test_schema_get_field() {
     assert!(schema.get_field("nonexistent").is_none());
 }
 
+#[test]
+fn test_schema_column_and_index_of() {
+    let schema = Schema::new()
+        .add_field(Field::new("date", BqType::Date))
+        .add_field(Field::new("count", BqType::Int64));
+
+    assert_eq!(schema.len(), 2);
+    assert!(!schema.is_empty());
+    assert!(Schema::new().is_empty());
+
+    assert_eq!(schema.index_of("count"), Some(1));
+    assert_eq!(schema.column("count").unwrap().field_type, BqType::Int64);
+    assert_eq!(schema.index_of("nonexistent"), None);
+    assert!(schema.column("nonexistent").is_none());
+}
+
 #[test]
 fn test_schema_has_field() {
     let schema = Schema::new().add_field(Field::new("date", BqType::Date));
@@ -157,3 +174,700 @@ fn test_bq_types() {
     assert_eq!(BqType::Numeric, BqType::Numeric);
     assert_eq!(BqType::Record, BqType::Record);
 }
+
+#[test]
+fn test_schema_get_field_path_nested() {
+    let schema = Schema::from_fields(vec![
+        Field::new("id", BqType::Int64),
+        Field::new("user", BqType::Record).with_fields(vec![
+            Field::new("name", BqType::String),
+            Field::new("address", BqType::Record)
+                .with_fields(vec![Field::new("zip", BqType::String)]),
+        ]),
+    ]);
+
+    assert_eq!(
+        schema
+            .get_field_path("user.address.zip")
+            .unwrap()
+            .field_type,
+        BqType::String
+    );
+    assert!(schema.get_field_path("id").is_some());
+    assert!(schema.get_field_path("user.missing").is_none());
+    assert!(schema.get_field_path("id.nested").is_none());
+}
+
+#[test]
+fn test_schema_to_ddl_day_partitioned_clustered() {
+    let schema = Schema::from_fields(vec![
+        Field::new("date", BqType::Date).required(),
+        Field::new("region", BqType::String),
+        Field::new("user_count", BqType::Int64).with_description("Distinct \"active\" users"),
+        Field::new("tags", BqType::String).repeated(),
+        Field::new("user", BqType::Record).with_fields(vec![
+            Field::new("id", BqType::String).required(),
+            Field::new("scores", BqType::Float64).repeated(),
+        ]),
+        Field::new("events", BqType::Record)
+            .repeated()
+            .with_fields(vec![Field::new("name", BqType::String)]),
+    ]);
+    let cluster = ClusterConfig::from_fields(["region"]).unwrap();
+
+    let ddl = schema.to_ddl(
+        "my-project.analytics.daily_stats",
+        &PartitionConfig::day("date"),
+        Some(&cluster),
+    );
+
+    assert_eq!(
+        ddl,
+        "CREATE TABLE `my-project.analytics.daily_stats` (\n\
+         \x20 `date` DATE NOT NULL,\n\
+         \x20 `region` STRING,\n\
+         \x20 `user_count` INT64 OPTIONS(description=\"Distinct \\\"active\\\" users\"),\n\
+         \x20 `tags` ARRAY<STRING>,\n\
+         \x20 `user` STRUCT<`id` STRING NOT NULL, `scores` ARRAY<FLOAT64>>,\n\
+         \x20 `events` ARRAY<STRUCT<`name` STRING>>\n\
+         )\n\
+         PARTITION BY date\n\
+         CLUSTER BY region"
+    );
+}
+
+#[test]
+fn test_schema_to_ddl_partition_expressions() {
+    let schema = Schema::from_fields(vec![
+        Field::new("event_ts", BqType::Timestamp),
+        Field::new("month", BqType::Date),
+        Field::new("bucket", BqType::Int64),
+    ]);
+    let last_line = |partition: PartitionConfig| {
+        schema
+            .to_ddl("analytics.t", &partition, None)
+            .lines()
+            .last()
+            .unwrap()
+            .to_string()
+    };
+
+    assert_eq!(
+        last_line(PartitionConfig::hour("event_ts")),
+        "PARTITION BY TIMESTAMP_TRUNC(event_ts, HOUR)"
+    );
+    assert_eq!(
+        last_line(PartitionConfig::day("event_ts")),
+        "PARTITION BY TIMESTAMP_TRUNC(event_ts, DAY)"
+    );
+    assert_eq!(
+        last_line(PartitionConfig::month("month")),
+        "PARTITION BY DATE_TRUNC(month, MONTH)"
+    );
+    assert_eq!(
+        last_line(PartitionConfig::range("bucket", 0, 100, 10)),
+        "PARTITION BY RANGE_BUCKET(bucket, GENERATE_ARRAY(0, 100, 10))"
+    );
+    assert_eq!(
+        last_line(PartitionConfig::ingestion_time(PartitionType::Day)),
+        "PARTITION BY _PARTITIONDATE"
+    );
+}
+
+#[test]
+fn test_field_mode_accepts_either_case() {
+    let cases = [
+        ("NULLABLE", FieldMode::Nullable),
+        ("nullable", FieldMode::Nullable),
+        ("REQUIRED", FieldMode::Required),
+        ("required", FieldMode::Required),
+        ("REPEATED", FieldMode::Repeated),
+        ("repeated", FieldMode::Repeated),
+    ];
+    for (yaml, expected) in cases {
+        let field: Field = serde_yaml::from_str(&format!(
+            "name: x
+type: STRING
+mode: {}
+",
+            yaml
+        ))
+        .unwrap();
+        assert_eq!(field.mode, expected, "mode: {}", yaml);
+
+        let written = serde_yaml::to_string(&field).unwrap();
+        assert!(written.contains(&format!("mode: {}", yaml.to_uppercase())));
+        let reparsed: Field = serde_yaml::from_str(&written).unwrap();
+        assert_eq!(reparsed.mode, expected);
+    }
+}
+
+#[test]
+fn test_partition_require_filter_yaml_round_trip() {
+    let config: PartitionConfig =
+        serde_yaml::from_str("field: date\ntype: DAY\nrequire_filter: true\n").unwrap();
+    assert!(config.require_filter);
+
+    let yaml = serde_yaml::to_string(&config).unwrap();
+    let reparsed: PartitionConfig = serde_yaml::from_str(&yaml).unwrap();
+    assert!(reparsed.require_filter);
+
+    let default: PartitionConfig = serde_yaml::from_str("field: date\ntype: DAY\n").unwrap();
+    assert!(!default.require_filter);
+}
+
+#[test]
+fn test_schema_to_ddl_require_partition_filter() {
+    let schema = Schema::from_fields(vec![Field::new("date", BqType::Date)]);
+    let partition = PartitionConfig::day("date").with_require_filter(true);
+
+    let ddl = schema.to_ddl("analytics.t", &partition, None);
+    assert!(ddl.ends_with("PARTITION BY date\nOPTIONS(require_partition_filter = TRUE)"));
+}
+
+#[test]
+fn test_field_default_expression_in_ddl() {
+    let field: Field = serde_yaml::from_str(
+        "name: loaded_at\ntype: TIMESTAMP\nmode: REQUIRED\ndefault_expression: CURRENT_TIMESTAMP()\n",
+    )
+    .unwrap();
+    assert_eq!(
+        field.default_expression.as_deref(),
+        Some("CURRENT_TIMESTAMP()")
+    );
+
+    let schema = Schema::from_fields(vec![
+        Field::new("date", BqType::Date),
+        field,
+        Field::new("source", BqType::String).with_default_expression("'batch'"),
+    ]);
+    let ddl = schema.to_ddl("analytics.t", &PartitionConfig::day("date"), None);
+    assert!(ddl.contains("  `loaded_at` TIMESTAMP DEFAULT CURRENT_TIMESTAMP() NOT NULL,\n"));
+    assert!(ddl.contains("  `source` STRING DEFAULT 'batch'\n"));
+}
+
+#[test]
+fn test_field_without_default_serializes_unchanged() {
+    let json = serde_json::to_string(&Field::new("id", BqType::String)).unwrap();
+    assert!(!json.contains("default_expression"));
+    assert!(!json.contains("policy_tags"));
+}
+
+#[test]
+fn test_field_policy_tags_from_yaml() {
+    let field: Field = serde_yaml::from_str(
+        "name: email\ntype: STRING\ndescription: Contact email\npolicy_tags:\n  - projects/p/locations/us/taxonomies/1/policyTags/2\n",
+    )
+    .unwrap();
+    assert_eq!(
+        field.policy_tags,
+        vec!["projects/p/locations/us/taxonomies/1/policyTags/2"]
+    );
+
+    let ddl = Schema::from_fields(vec![field]).to_ddl("t", &PartitionConfig::day("date"), None);
+    assert!(ddl.contains("`email` STRING OPTIONS(description=\"Contact email\")"));
+}
+
+#[test]
+fn test_partition_expiration_days_in_ddl() {
+    let config: PartitionConfig =
+        serde_yaml::from_str("field: date\ntype: DAY\nexpiration_days: 14\n").unwrap();
+    assert_eq!(config.expiration_days, Some(14));
+
+    let schema = Schema::from_fields(vec![Field::new("date", BqType::Date)]);
+    let ddl = schema.to_ddl(
+        "staging.t",
+        &config.with_require_filter(true),
+        Some(&ClusterConfig::from_fields(["date"]).unwrap()),
+    );
+    assert!(ddl.ends_with(
+        "CLUSTER BY date\nOPTIONS(partition_expiration_days = 14, require_partition_filter = TRUE)"
+    ));
+
+    let range = PartitionConfig::range("bucket", 0, 10, 1).with_expiration_days(14);
+    let schema = Schema::from_fields(vec![Field::new("bucket", BqType::Int64)]);
+    assert!(!schema
+        .to_ddl("staging.t", &range, None)
+        .contains("partition_expiration_days"));
+}
+
+#[test]
+fn test_schema_diff_reordered_fields_is_empty() {
+    let a = Schema::from_fields(vec![
+        Field::new("date", BqType::Date),
+        Field::new("count", BqType::Int64),
+    ]);
+    let b = Schema::from_fields(vec![
+        Field::new("count", BqType::Int64),
+        Field::new("date", BqType::Date),
+    ]);
+
+    let diff = a.diff(&b);
+    assert!(diff.is_empty());
+    assert_eq!(diff.to_string(), "");
+}
+
+#[test]
+fn test_schema_diff_reports_field_changes() {
+    let old = Schema::from_fields(vec![
+        Field::new("date", BqType::Date),
+        Field::new("count", BqType::Int64),
+        Field::new("legacy", BqType::String),
+        Field::new("user", BqType::Record).with_fields(vec![Field::new("id", BqType::String)]),
+    ]);
+    let new = Schema::from_fields(vec![
+        Field::new("date", BqType::Date).required(),
+        Field::new("count", BqType::Float64),
+        Field::new("region", BqType::String),
+        Field::new("user", BqType::Record).with_fields(vec![
+            Field::new("id", BqType::String),
+            Field::new("email", BqType::String),
+        ]),
+    ]);
+
+    let diff = old.diff(&new);
+    assert_eq!(diff.added, vec!["region", "user.email"]);
+    assert_eq!(diff.removed, vec!["legacy"]);
+    assert_eq!(diff.modified.len(), 2);
+    assert_eq!(diff.modified[1].old_type, BqType::Int64);
+    assert_eq!(diff.modified[1].new_type, BqType::Float64);
+    assert_eq!(
+        diff.to_string(),
+        "+ region\n+ user.email\n- legacy\n~ date: DATE -> DATE REQUIRED\n~ count: INT64 -> FLOAT64"
+    );
+}
+
+#[test]
+fn test_schema_diff_covers_every_checksummed_attribute() {
+    let old = Schema::from_fields(vec![
+        Field::new("amount", BqType::Numeric).with_precision(10, 2),
+        Field::new("email", BqType::String),
+        Field::new("note", BqType::String).with_description("Free text"),
+    ]);
+    let new = Schema::from_fields(vec![
+        Field::new("amount", BqType::Numeric)
+            .with_precision(12, 2)
+            .with_rounding_mode(RoundingMode::RoundHalfEven),
+        Field::new("email", BqType::String)
+            .with_policy_tag("projects/p/locations/us/taxonomies/1/policyTags/2"),
+        Field::new("note", BqType::String).with_description("Operator notes"),
+    ]);
+
+    let diff = old.diff(&new);
+    assert_eq!(diff.modified.len(), 3);
+    assert!(diff.modified[0].parameters_changed());
+    assert_eq!(
+        diff.to_string(),
+        "~ amount: NUMERIC(10, 2) -> NUMERIC(12, 2) ROUND_HALF_EVEN\n\
+         ~ email: STRING -> STRING (policy tags changed)\n\
+         ~ note: STRING -> STRING (description changed)"
+    );
+}
+
+#[test]
+fn test_schema_diff_detects_default_expression_change() {
+    let old = Schema::from_fields(vec![Field::new("loaded_at", BqType::Timestamp)]);
+    let new =
+        Schema::from_fields(vec![Field::new("loaded_at", BqType::Timestamp)
+            .with_default_expression("CURRENT_TIMESTAMP()")]);
+
+    assert_eq!(
+        old.diff(&new).to_string(),
+        "~ loaded_at: TIMESTAMP -> TIMESTAMP DEFAULT CURRENT_TIMESTAMP()"
+    );
+}
+
+const BQ_TABLE_JSON: &str = r#"{
+  "kind": "bigquery#table",
+  "schema": {
+    "fields": [
+      {"name": "date", "type": "DATE", "mode": "REQUIRED"},
+      {"name": "user_count", "type": "INTEGER"},
+      {"name": "ratio", "type": "FLOAT", "mode": "NULLABLE", "description": "Share of active users"},
+      {"name": "tags", "type": "STRING", "mode": "REPEATED"},
+      {
+        "name": "user",
+        "type": "STRUCT",
+        "fields": [
+          {"name": "id", "type": "STRING"},
+          {"name": "events", "type": "RECORD", "mode": "REPEATED", "fields": [
+            {"name": "at", "type": "TIMESTAMP"}
+          ]}
+        ]
+      }
+    ]
+  }
+}"#;
+
+#[test]
+fn test_schema_from_bq_table_json() {
+    let schema = Schema::from_bq_json(BQ_TABLE_JSON).unwrap();
+
+    assert_eq!(schema.fields.len(), 5);
+    let date = schema.get_field("date").unwrap();
+    assert_eq!(date.field_type, BqType::Date);
+    assert_eq!(date.mode, FieldMode::Required);
+    assert_eq!(
+        schema.get_field("user_count").unwrap().field_type,
+        BqType::Int64
+    );
+    let ratio = schema.get_field("ratio").unwrap();
+    assert_eq!(ratio.field_type, BqType::Float64);
+    assert_eq!(ratio.description.as_deref(), Some("Share of active users"));
+    assert_eq!(schema.get_field("tags").unwrap().mode, FieldMode::Repeated);
+
+    assert_eq!(schema.get_field("user").unwrap().field_type, BqType::Record);
+    let events = schema.get_field_path("user.events").unwrap();
+    assert_eq!(events.field_type, BqType::Record);
+    assert_eq!(events.mode, FieldMode::Repeated);
+    assert_eq!(
+        schema.get_field_path("user.events.at").unwrap().field_type,
+        BqType::Timestamp
+    );
+}
+
+#[test]
+fn test_schema_to_bq_json_round_trip() {
+    let schema = Schema::from_bq_json(BQ_TABLE_JSON).unwrap().add_fields([
+        Field::new("loaded_at", BqType::Timestamp)
+            .required()
+            .with_default_expression("CURRENT_TIMESTAMP()"),
+        Field::new("email", BqType::String)
+            .with_policy_tag("projects/p/locations/us/taxonomies/1/policyTags/2"),
+        Field::new("amount", BqType::Numeric)
+            .with_precision(18, 2)
+            .with_rounding_mode(RoundingMode::RoundHalfEven),
+        Field::new("session_length", BqType::Interval),
+    ]);
+
+    let json = schema.to_bq_json();
+    assert_eq!(Schema::from_bq_json(&json).unwrap(), schema);
+
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(value[0]["mode"], "REQUIRED");
+    assert_eq!(value[1]["mode"], "NULLABLE");
+    assert_eq!(value[4]["type"], "RECORD");
+    assert_eq!(value[4]["fields"][1]["fields"][0]["name"], "at");
+    assert_eq!(value[5]["defaultValueExpression"], "CURRENT_TIMESTAMP()");
+    assert_eq!(value[7]["precision"], "18");
+}
+
+#[test]
+fn test_schema_from_bq_json_field_array() {
+    let schema =
+        Schema::from_bq_json(r#"[{"name": "id", "type": "STRING", "mode": "REQUIRED"}]"#).unwrap();
+    assert_eq!(schema.fields.len(), 1);
+    assert_eq!(schema.fields[0].mode, FieldMode::Required);
+}
+
+#[test]
+fn test_schema_from_bq_json_rejects_unknown_type() {
+    let err = Schema::from_bq_json(
+        r#"{"fields": [{"name": "r", "type": "RECORD", "fields": [{"name": "span", "type": "RANGE"}]}]}"#,
+    )
+    .unwrap_err();
+    assert!(matches!(err, bqdrift::BqDriftError::Schema(_)));
+    assert!(err
+        .to_string()
+        .contains("Field 'r.span' has unsupported type 'RANGE'"));
+}
+
+#[test]
+fn test_schema_validate_names() {
+    let ok = Schema::from_fields(vec![
+        Field::new("_id", BqType::String),
+        Field::new("user", BqType::Record).with_fields(vec![Field::new("name", BqType::String)]),
+    ]);
+    assert!(ok.validate_names().is_ok());
+
+    let reject = |field: Field| {
+        Schema::from_fields(vec![field])
+            .validate_names()
+            .unwrap_err()
+    };
+
+    let err = reject(Field::new("1st", BqType::Int64));
+    assert!(err
+        .to_string()
+        .contains("column '1st' must start with a letter or underscore"));
+
+    let err = reject(Field::new("x".repeat(301), BqType::Int64));
+    assert!(err.to_string().contains("longer than 300 characters"));
+
+    let err = reject(Field::new("_partitiontime", BqType::Timestamp));
+    assert!(err.to_string().contains("reserved prefix '_PARTITION'"));
+
+    let err = reject(
+        Field::new("user", BqType::Record)
+            .with_fields(vec![Field::new("_FILE_NAME", BqType::String)]),
+    );
+    assert!(err.to_string().contains("column 'user._FILE_NAME'"));
+}
+
+#[test]
+fn test_check_evolution() {
+    let prev = Schema::from_fields(vec![
+        Field::new("id", BqType::String).required(),
+        Field::new("count", BqType::Int64),
+        Field::new("score", BqType::Float64),
+        Field::new("legacy", BqType::String),
+    ]);
+
+    let additive = Schema::from_fields(vec![
+        Field::new("id", BqType::String),
+        Field::new("count", BqType::Numeric),
+        Field::new("score", BqType::Float64),
+        Field::new("tags", BqType::String).repeated(),
+    ]);
+    let warnings = Schema::check_evolution(&prev, &additive).unwrap();
+    assert_eq!(
+        warnings,
+        vec![
+            EvolutionWarning::TypeWidened {
+                field: "count".to_string(),
+                from: BqType::Int64,
+                to: BqType::Numeric,
+            },
+            EvolutionWarning::ColumnRemoved("legacy".to_string()),
+        ]
+    );
+
+    let narrowing = Schema::from_fields(vec![
+        Field::new("id", BqType::String).required(),
+        Field::new("count", BqType::Int64).required(),
+        Field::new("score", BqType::Int64),
+    ]);
+    let err = Schema::check_evolution(&prev, &narrowing).unwrap_err();
+    assert!(matches!(err, bqdrift::BqDriftError::Schema(_)));
+    let msg = err.to_string();
+    assert!(msg.contains("column 'score' changes type from FLOAT64 to INT64"));
+    assert!(msg.contains("column 'count' changes mode from NULLABLE to REQUIRED"));
+}
+
+#[test]
+fn test_alter_statements() {
+    let prev = Schema::from_fields(vec![
+        Field::new("id", BqType::String).required(),
+        Field::new("count", BqType::Int64),
+        Field::new("score", BqType::Float64),
+        Field::new("legacy", BqType::String),
+        Field::new("user", BqType::Record).with_fields(vec![Field::new("name", BqType::String)]),
+    ]);
+    let next = Schema::from_fields(vec![
+        Field::new("id", BqType::String),
+        Field::new("count", BqType::Numeric).with_default_expression("0"),
+        Field::new("score", BqType::Int64),
+        Field::new("tags", BqType::String).repeated(),
+        Field::new("loaded_at", BqType::Timestamp).required(),
+        Field::new("user", BqType::Record).with_fields(vec![
+            Field::new("name", BqType::String),
+            Field::new("email", BqType::String),
+        ]),
+    ]);
+
+    let statements = prev.alter_statements(&next, "proj.ds.t");
+    let supported: Vec<String> = statements
+        .iter()
+        .filter(|s| s.is_supported())
+        .map(|s| s.to_string())
+        .collect();
+    assert_eq!(
+        supported,
+        vec![
+            "ALTER TABLE `proj.ds.t` ADD COLUMN `tags` ARRAY<STRING>;",
+            "ALTER TABLE `proj.ds.t` ALTER COLUMN `id` DROP NOT NULL;",
+            "ALTER TABLE `proj.ds.t` ALTER COLUMN `count` SET DATA TYPE NUMERIC;",
+            "ALTER TABLE `proj.ds.t` ALTER COLUMN `count` SET DEFAULT 0;",
+        ]
+    );
+    let destructive: Vec<String> = statements
+        .iter()
+        .filter(|s| s.is_destructive())
+        .map(|s| s.to_string())
+        .collect();
+    assert_eq!(
+        destructive,
+        vec!["-- destructive: ALTER TABLE `proj.ds.t` DROP COLUMN `legacy`;"]
+    );
+
+    let rebuilds: Vec<&str> = statements
+        .iter()
+        .filter_map(|s| match s {
+            AlterStatement::RequiresRebuild { column, .. } => Some(column.as_str()),
+            AlterStatement::Ddl(_) | AlterStatement::Destructive(_) => None,
+        })
+        .collect();
+    assert_eq!(rebuilds, vec!["loaded_at", "user.email", "score"]);
+    assert!(statements.iter().any(|s| s
+        .to_string()
+        .contains("column 'score' changes type from FLOAT64 to INT64")));
+
+    assert!(prev.alter_statements(&prev, "proj.ds.t").is_empty());
+}
+
+#[test]
+fn test_alter_statements_for_column_attributes() {
+    let prev = Schema::from_fields(vec![
+        Field::new("amount", BqType::Numeric).with_precision(10, 2),
+        Field::new("rate", BqType::Numeric).with_precision(10, 4),
+        Field::new("note", BqType::String),
+        Field::new("email", BqType::String),
+    ]);
+    let next = Schema::from_fields(vec![
+        Field::new("amount", BqType::Numeric)
+            .with_precision(12, 2)
+            .with_rounding_mode(RoundingMode::RoundHalfEven),
+        Field::new("rate", BqType::Numeric).with_precision(10, 2),
+        Field::new("note", BqType::String).with_description("Operator \"notes\""),
+        Field::new("email", BqType::String)
+            .with_policy_tag("projects/p/locations/us/taxonomies/1/policyTags/2"),
+    ]);
+
+    let statements: Vec<String> = prev
+        .alter_statements(&next, "proj.ds.t")
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+    assert_eq!(
+        statements,
+        vec![
+            "ALTER TABLE `proj.ds.t` ALTER COLUMN `amount` SET DATA TYPE NUMERIC(12, 2);",
+            "ALTER TABLE `proj.ds.t` ALTER COLUMN `amount` SET OPTIONS(rounding_mode='ROUND_HALF_EVEN');",
+            "-- requires rebuild: column 'rate' changes type from NUMERIC(10, 4) to NUMERIC(10, 2)",
+            "ALTER TABLE `proj.ds.t` ALTER COLUMN `note` SET OPTIONS(description=\"Operator \\\"notes\\\"\");",
+            "-- requires rebuild: column 'email' changes policy tags, which ALTER TABLE cannot set",
+        ]
+    );
+}
+
+#[test]
+fn test_schema_merge() {
+    let audit = Schema::new()
+        .add_field(Field::new("loaded_at", BqType::Timestamp).required())
+        .add_field(Field::new("date", BqType::Date));
+    let query = Schema::new()
+        .add_field(Field::new("date", BqType::Date))
+        .add_field(Field::new("count", BqType::Int64));
+
+    let merged = audit.merge(&query).unwrap();
+    let names: Vec<&str> = merged.fields.iter().map(|f| f.name.as_str()).collect();
+    assert_eq!(names, vec!["loaded_at", "date", "count"]);
+
+    let conflicting = Schema::new().add_field(Field::new("loaded_at", BqType::Timestamp));
+    let err = audit.merge(&conflicting).unwrap_err();
+    assert!(matches!(err, bqdrift::BqDriftError::SchemaParse(_)));
+    assert!(err.to_string().contains(
+        "column 'loaded_at' is TIMESTAMP REQUIRED in one and TIMESTAMP NULLABLE in the other"
+    ));
+
+    let user = |sub: Field| {
+        Schema::new().add_field(Field::new("user", BqType::Record).with_fields(vec![sub]))
+    };
+    let merged = user(Field::new("id", BqType::Int64))
+        .merge(&user(Field::new("email", BqType::String)))
+        .unwrap();
+    assert!(merged.get_field_path("user.email").is_some());
+
+    let err = user(Field::new("id", BqType::Int64))
+        .merge(&user(Field::new("id", BqType::String)))
+        .unwrap_err();
+    assert!(err.to_string().contains("column 'user.id' is INT64"));
+}
+
+#[test]
+fn test_numeric_precision_and_rounding_mode() {
+    let yaml = r#"
+- name: amount
+  type: NUMERIC
+  precision: 18
+  scale: 2
+  rounding_mode: ROUND_HALF_EVEN
+- name: rate
+  type: BIGNUMERIC
+  precision: 40
+"#;
+    let fields: Vec<Field> = serde_yaml::from_str(yaml).unwrap();
+    let schema = Schema::from_fields(fields);
+    assert!(schema.validate_numeric_parameters().is_ok());
+
+    let amount = schema.get_field("amount").unwrap();
+    assert_eq!((amount.precision, amount.scale), (Some(18), Some(2)));
+    assert_eq!(amount.rounding_mode, Some(RoundingMode::RoundHalfEven));
+
+    let ddl = schema.to_ddl("finance.ledger", &PartitionConfig::unpartitioned(), None);
+    assert!(ddl.contains("`amount` NUMERIC(18, 2) OPTIONS(rounding_mode='ROUND_HALF_EVEN')"));
+    assert!(ddl.contains("`rate` BIGNUMERIC(40)"));
+
+    let plain = Schema::new().add_field(Field::new("amount", BqType::Numeric));
+    let json = |s: &Schema| serde_json::to_string(&s.fields).unwrap();
+    assert!(!json(&plain).contains("precision"));
+    assert_ne!(
+        json(&plain),
+        json(&Schema::new().add_field(Field::new("amount", BqType::Numeric).with_precision(18, 2)))
+    );
+}
+
+#[test]
+fn test_numeric_parameters_validated() {
+    let check = |field: Field| {
+        Schema::new()
+            .add_field(field)
+            .validate_numeric_parameters()
+            .unwrap_err()
+            .to_string()
+    };
+
+    assert!(
+        check(Field::new("amount", BqType::Numeric).with_precision(5, 6))
+            .contains("column 'amount' has scale 6 greater than precision 5")
+    );
+    assert!(check(
+        Field::new("amount", BqType::Float64).with_rounding_mode(RoundingMode::RoundHalfEven)
+    )
+    .contains("is FLOAT64, not NUMERIC or BIGNUMERIC"));
+
+    let mut scale_only = Field::new("amount", BqType::Numeric);
+    scale_only.scale = Some(2);
+    assert!(check(scale_only).contains("sets scale without precision"));
+}
+
+#[test]
+fn test_schema_from_bq_json_numeric_parameters() {
+    let schema = Schema::from_bq_json(
+        r#"[{"name": "amount", "type": "NUMERIC", "precision": "18", "scale": "2", "roundingMode": "ROUND_HALF_AWAY_FROM_ZERO"}]"#,
+    )
+    .unwrap();
+    let amount = schema.get_field("amount").unwrap();
+    assert_eq!((amount.precision, amount.scale), (Some(18), Some(2)));
+    assert_eq!(
+        amount.rounding_mode,
+        Some(RoundingMode::RoundHalfAwayFromZero)
+    );
+}
+
+#[test]
+fn test_schema_fingerprint_unordered() {
+    let user = |fields: Vec<Field>| Field::new("user", BqType::Record).with_fields(fields);
+    let a = Schema::from_fields(vec![
+        Field::new("date", BqType::Date),
+        user(vec![
+            Field::new("id", BqType::Int64),
+            Field::new("email", BqType::String),
+        ]),
+    ]);
+    let b = Schema::from_fields(vec![
+        user(vec![
+            Field::new("email", BqType::String),
+            Field::new("id", BqType::Int64),
+        ]),
+        Field::new("date", BqType::Date),
+    ]);
+    assert_eq!(a.fingerprint_unordered(), b.fingerprint_unordered());
+
+    let retyped = Schema::from_fields(vec![Field::new("date", BqType::Timestamp)]);
+    let original = Schema::from_fields(vec![Field::new("date", BqType::Date)]);
+    assert_ne!(
+        retyped.fingerprint_unordered(),
+        original.fingerprint_unordered()
+    );
+}